@@ -1,64 +1,1172 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
-use timetable_core::config::Config;
-use timetable_core::parser::parse_pdf;
-use timetable_core::processor::{process_map, MapHighlight};
-use timetable_core::renderer::render_timetable;
-
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use timetable_core::config::{
+    room_prefix, starter_config_with, starter_mappings, Config, Mapping, Strings, TeacherNames,
+};
+use timetable_core::html::render_timetable_html;
+use timetable_core::icons::IconSet;
+use timetable_core::latex::render_timetable_latex;
+use timetable_core::layout::PERIOD_LABELS;
+use timetable_core::parser::{anonymise, parse_pdf, split_students, Lesson, Week, UNKNOWN};
+use timetable_core::processor::{
+    heatmap, list_map_ids, optimise_svg, raster_map, sanitise_map, HighlightMode, MapArea,
+    MapCache, MapDocument, MapElement, MapLabels, MapRoute, ProcessorError, SharedFill,
+    CROP_PADDING, DIM_OPACITY, HEATMAP_COLOR,
+};
+use timetable_core::renderer::{
+    combine_maps, find_overflows, render_day_to_string, render_duplex_pdf,
+    render_household_to_string, render_timetable, render_timetable_pdf, render_timetable_png,
+    render_timetable_with_template, render_timetables_pdf, render_weeks_to_string, svg_to_pdf,
+    svg_to_png, svg_to_svgz, MapPlacement, MultiWeekLayout, Orientation, PaperSize, RenderOptions,
+    OPTIMISE_PRECISION,
+};
+use timetable_core::typst::render_timetable_typst;
+
+/// Output file format for generated timetables
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Scalable vector graphics (default)
+    Svg,
+    /// Gzip-compressed SVG, much smaller for emailing sheets with a map
+    Svgz,
+    /// Print-ready PDF
+    Pdf,
+    /// Raster PNG image (see `--dpi`)
+    Png,
+    /// Interactive HTML page with hover tooltips
+    Html,
+    /// Typst source for further editing
+    Typst,
+    /// LaTeX tabularx fragment (without the map)
+    Latex,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "svg",
+            OutputFormat::Svgz => "svgz",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Png => "png",
+            OutputFormat::Html => "html",
+            OutputFormat::Typst => "typ",
+            OutputFormat::Latex => "tex",
+        }
+    }
+}
+
+/// Paper size for generated sheets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Paper {
+    A3,
+    A4,
+    A5,
+    Letter,
+}
+
+impl From<Paper> for PaperSize {
+    fn from(paper: Paper) -> Self {
+        match paper {
+            Paper::A3 => PaperSize::A3,
+            Paper::A4 => PaperSize::A4,
+            Paper::A5 => PaperSize::A5,
+            Paper::Letter => PaperSize::Letter,
+        }
+    }
+}
+
+/// How teacher names appear on generated sheets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Teachers {
+    /// Names as they appear in the timetable
+    Full,
+    /// Title and initials only, e.g. "Mr J S"
+    Initials,
+    /// No teacher names
+    Hidden,
+}
+
+impl From<Teachers> for TeacherNames {
+    fn from(teachers: Teachers) -> Self {
+        match teachers {
+            Teachers::Full => TeacherNames::Full,
+            Teachers::Initials => TeacherNames::Initials,
+            Teachers::Hidden => TeacherNames::Hidden,
+        }
+    }
+}
+
+/// How departments are coloured on the map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Highlighting {
+    /// Rewrite the fill of each highlighted shape
+    Attributes,
+    /// Add a stylesheet colouring the highlighted groups, for maps styled through classes
+    Stylesheet,
+}
+
+impl From<Highlighting> for HighlightMode {
+    fn from(highlighting: Highlighting) -> Self {
+        match highlighting {
+            Highlighting::Attributes => HighlightMode::Attributes,
+            Highlighting::Stylesheet => HighlightMode::Stylesheet,
+        }
+    }
+}
+
+/// How rooms shared between departments are coloured on the map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SharedRooms {
+    /// Diagonal stripes of each department's colour
+    Stripes,
+    /// A gradient from one department's colour to the next
+    Gradient,
+}
+
+impl From<SharedRooms> for SharedFill {
+    fn from(shared: SharedRooms) -> Self {
+        match shared {
+            SharedRooms::Stripes => SharedFill::Stripes,
+            SharedRooms::Gradient => SharedFill::Gradient,
+        }
+    }
+}
+
+/// Text drawn over highlighted rooms on the map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RoomLabels {
+    /// The room code
+    Codes,
+    /// The room code and the lessons held there, e.g. "Mon L1, Wed L3"
+    Periods,
+}
+
+impl From<RoomLabels> for MapLabels {
+    fn from(labels: RoomLabels) -> Self {
+        match labels {
+            RoomLabels::Codes => MapLabels::Codes,
+            RoomLabels::Periods => MapLabels::Periods,
+        }
+    }
+}
+
+/// A school day, for --route and --day
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Weekday {
+    #[value(alias = "monday")]
+    Mon,
+    #[value(alias = "tuesday")]
+    Tue,
+    #[value(alias = "wednesday")]
+    Wed,
+    #[value(alias = "thursday")]
+    Thu,
+    #[value(alias = "friday")]
+    Fri,
+}
+
+impl Weekday {
+    /// The column of this day in a parsed week
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// How --route marks the day's walk
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RouteStyle {
+    /// A dashed line through numbered stops
+    Line,
+    /// Numbered stops only
+    Markers,
+}
+
+/// Where the map goes on generated sheets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Placement {
+    /// Below the grid, or beside it with --landscape
+    Auto,
+    /// Below the grid
+    Below,
+    /// Above the grid
+    Above,
+    /// Right of the grid
+    Beside,
+    /// On its own page after each sheet (PDF output only)
+    Page,
+    /// No map
+    None,
+}
+
+/// Layout preset for generated timetables
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Layout {
+    /// Full sheet with the map (default)
+    Sheet,
+    /// A7 pocket card: abbreviated subjects, room codes only, no map
+    Pocket,
+    /// 9:19.5 phone lock screen: large fonts, no map
+    Phone,
+}
+
+/// Tools run instead of generating timetables
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Inspect a school map SVG
+    Map {
+        #[command(subcommand)]
+        command: MapCommand,
+    },
+    /// List the room codes in a timetable PDF, how often each is used and the
+    /// mapping it resolves to, as a start on a new school's config
+    Rooms {
+        /// Path to the Bromcom PDF timetable
+        input: PathBuf,
+
+        /// Configuration whose mappings and room aliases the rooms are resolved with
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// List the teachers in a timetable PDF with the rooms and subjects they
+    /// appear with, to check how their names were read
+    Teachers {
+        /// Path to the Bromcom PDF timetable
+        input: PathBuf,
+    },
+    /// Check a config against a timetable PDF and its map, reporting rooms
+    /// without a mapping, map_ids missing from the map, overrides that match no
+    /// lesson and empty periods; fails if any are found
+    Check {
+        /// Path to the Bromcom PDF timetable
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to the configuration TOML file
+        #[arg(short, long)]
+        config: PathBuf,
+
+        /// Path to the map SVG file, replacing any [[maps]] in the config
+        #[arg(short, long)]
+        map: Option<PathBuf>,
+    },
+    /// Write a commented starter config for a timetable PDF, with a mapping and
+    /// colour for each room prefix it uses
+    Init {
+        /// Path to the Bromcom PDF timetable
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path of the config file to write
+        #[arg(short, long, default_value = "config.toml")]
+        output: PathBuf,
+
+        /// Replace the config file if it already exists
+        #[arg(long)]
+        force: bool,
+
+        /// Map SVG file whose element names are offered as map_ids
+        #[arg(short, long)]
+        map: Option<PathBuf>,
+
+        /// Ask for each department's name, colour and map element on the terminal
+        #[arg(long)]
+        interactive: bool,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum MapCommand {
+    /// List the ids and data-names in a map that a department's `map_id` can use
+    Ids {
+        /// Path to the map SVG file
+        map: PathBuf,
+    },
+}
+
+#[derive(Clone, Parser)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
 struct Cli {
-    /// Path to the Bromcom PDF timetable
-    #[arg(short, long)]
-    input: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Options for generating sheets, present exactly when no subcommand is given
+    #[command(flatten)]
+    generate: Option<GenerateArgs>,
+}
+
+/// Options for generating sheets from Bromcom PDFs, the default command.
+#[derive(Clone, Args)]
+struct GenerateArgs {
+    /// Path to the Bromcom PDF timetable (repeat with --compare for several students).
+    /// A directory or a file name pattern such as `input/*.pdf` (or several --input
+    /// files without --compare) generates each PDF into a subdirectory of --output
+    /// named after its student
+    #[arg(short, long, required = true)]
+    input: Vec<PathBuf>,
 
     /// Path to the configuration TOML file
     #[arg(short, long)]
     config: PathBuf,
 
-    /// Path to the map SVG file
+    /// Path to the map SVG file, replacing any [[maps]] in the config
     #[arg(short, long)]
     map: Option<PathBuf>,
 
-    /// Output directory for generated SVGs
+    /// Output directory for generated timetables
     #[arg(short, long)]
     output: PathBuf,
 
+    /// Output file format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Svg)]
+    format: OutputFormat,
+
+    /// Only generate these weeks of each PDF, counted from 1, e.g. `--week 2` or
+    /// `--week 1-2,4` (repeatable); files keep the names a full run gives them
+    #[arg(long, value_name = "N[-M]", value_delimiter = ',', value_parser = parse_week_range)]
+    week: Vec<RangeInclusive<usize>>,
+
+    /// Resolution for PNG output, in dots per inch
+    #[arg(long, default_value_t = 150.0)]
+    dpi: f32,
+
+    /// Write SVG styling as attributes on each element instead of a `<style>` block,
+    /// for office tools and older viewers that ignore embedded CSS
+    #[arg(long)]
+    inline_styles: bool,
+
+    /// Shrink SVG output (and the map embedded in HTML) by dropping metadata and
+    /// whitespace, rounding coordinates and merging styles
+    #[arg(long)]
+    optimise: bool,
+
+    /// Layout preset (`--paper` and `--landscape` apply to `sheet` only)
+    #[arg(long, value_enum, default_value_t = Layout::Sheet)]
+    layout: Layout,
+
+    /// Render through a minijinja template instead of the built-in renderer
+    /// (the output extension comes from the template name, e.g. `sheet.html.j2`)
+    #[arg(long, conflicts_with_all = ["combine", "two_up", "duplex"])]
+    template: Option<PathBuf>,
+
+    /// Draw a legend of the departments used each week above the map
+    #[arg(long)]
+    legend: bool,
+
+    /// Shade today's column on the week that includes today (needs a `[[calendar]]`),
+    /// and mute the rest of the week's departments on its map
+    #[arg(long)]
+    today: bool,
+
+    /// Draw empty slots as labelled "Free / Study" cells
+    #[arg(long)]
+    free_periods: bool,
+
+    /// Date (and optionally time) shown in the footer instead of now, e.g. "2026-01-05"
+    /// or "2026-01-05 09:30", so repeated runs give identical files; also used as
+    /// today for the term calendar and --today
+    #[arg(long, value_parser = parse_generated_at)]
+    generated_at: Option<chrono::DateTime<chrono::Local>>,
+
+    /// Lay out a staff timetable, with the staff member and department in the
+    /// header and the class code in place of the teacher
+    #[arg(long)]
+    teacher_sheet: bool,
+
+    /// Use a dark background with light text
+    #[arg(long)]
+    dark: bool,
+
+    /// Use black and white with hatch patterns for departments, for e-ink screens and mono printers
+    #[arg(long, conflicts_with = "dark")]
+    mono: bool,
+
+    /// How teacher names appear, overriding `teacher_names` in the config
+    #[arg(long, value_enum)]
+    teachers: Option<Teachers>,
+
+    /// Paper size for generated sheets
+    #[arg(long, value_enum, default_value_t = Paper::A4)]
+    paper: Paper,
+
+    /// Render landscape sheets with the map to the right of the timetable
+    #[arg(long)]
+    landscape: bool,
+
+    /// Where the map goes relative to the timetable grid
+    #[arg(long, value_enum, default_value_t = Placement::Auto)]
+    map_placement: Placement,
+
+    /// How departments are coloured on the map (`stylesheet` needs a viewer that applies CSS)
+    #[arg(long, value_enum, default_value_t = Highlighting::Attributes)]
+    map_highlight: Highlighting,
+
+    /// How map areas used by several departments this week (such as a shared hall)
+    /// are coloured
+    #[arg(long, value_enum, default_value_t = SharedRooms::Stripes)]
+    shared_fill: SharedRooms,
+
+    /// Fade the map outside this week's departments so they stand out on busy site maps
+    #[arg(long)]
+    dim_map: bool,
+
+    /// Zoom the map to this week's departments (and route), so the relevant part of
+    /// a large campus map fills the space
+    #[arg(long)]
+    crop_map: bool,
+
+    /// Draw a legend of the highlighted departments inside the map itself, so the
+    /// map explains itself when used on its own
+    #[arg(long)]
+    map_legend: bool,
+
+    /// Colour the map by how many lessons this week are held in each department (or,
+    /// with --room-highlights, room), darker for more, instead of by department
+    #[arg(long)]
+    map_heatmap: bool,
+
+    /// Strip scripts, event handlers and external references from the map before
+    /// use, for maps supplied by third parties
+    #[arg(long)]
+    sanitise_map: bool,
+
+    /// Stop with an error, instead of a warning, when a mapping's map_id matches
+    /// nothing in the map
+    #[arg(long)]
+    strict_map: bool,
+
+    /// Highlight each room the student visits where the map has an element with the
+    /// room code as its id, falling back to the whole department elsewhere
+    #[arg(long)]
+    room_highlights: bool,
+
+    /// Label each highlighted room on the map with its code, or its code and periods
+    #[arg(long, value_enum, requires = "room_highlights")]
+    room_labels: Option<RoomLabels>,
+
+    /// Draw the route between the rooms of this day's lessons, in period order, on
+    /// each week's map. Rooms are placed from [room_positions] in the config, or the
+    /// centre of their own map element or their department's
+    #[arg(long, value_name = "DAY")]
+    route: Option<Weekday>,
+
+    /// Join the --route stops with a line, or just number them; either way a key
+    /// lists the periods spent in each room
+    #[arg(long, value_enum, default_value = "line", requires = "route")]
+    route_style: RouteStyle,
+
+    /// Write all weeks into a single multi-page PDF instead of one file per week
+    #[arg(long)]
+    combine: bool,
+
+    /// With --combine, append a page showing the whole map with every used department highlighted
+    #[arg(long, requires = "combine")]
+    map_page: bool,
+
+    /// Put every week on one page (stacked, or side by side with --landscape) with a shared map
+    #[arg(long, conflicts_with = "combine")]
+    two_up: bool,
+
+    /// Write a double-sided PDF (Week 1 front, Week 2 back) with mirrored margins
+    #[arg(long, conflicts_with_all = ["combine", "two_up"])]
+    duplex: bool,
+
+    /// Write a large-format sheet per school day instead of one per week, each with a
+    /// map highlighting only that day's departments, for students who find the
+    /// whole week's map overwhelming
+    #[arg(
+        long,
+        conflicts_with_all = ["combine", "two_up", "duplex", "compare", "household", "template"]
+    )]
+    daily: bool,
+
+    /// Only write the day sheets for these days, e.g. `--day monday` or `--day mon,wed`
+    /// (repeatable); implies --daily
+    #[arg(
+        long,
+        value_enum,
+        value_name = "DAY",
+        value_delimiter = ',',
+        conflicts_with_all = ["combine", "two_up", "duplex", "compare", "household", "template"]
+    )]
+    day: Vec<Weekday>,
+
+    /// Put the same week of every --input side by side on one page, e.g. siblings for the fridge
+    #[arg(long, conflicts_with_all = ["combine", "two_up", "duplex", "template", "student_name", "form", "year", "house"])]
+    compare: bool,
+
+    /// Merge every --input into one grid with a sub-row per student in each lesson slot
+    #[arg(long, conflicts_with_all = ["compare", "combine", "two_up", "duplex", "template", "student_name", "form", "year", "house"])]
+    household: bool,
+
+    /// Replace the student, form, year group, house and teacher names with placeholders, for sharing bug reports
+    #[arg(long, conflicts_with_all = ["student_name", "form", "year", "house"])]
+    anonymise: bool,
+
     /// Student name (optional, e.g., "Alex Testington")
     #[arg(short, long)]
     student_name: Option<String>,
 
+    /// From a PDF holding several students' timetables (e.g. a year-group export),
+    /// only generate the one with this name (ignoring case)
+    #[arg(long, value_name = "NAME")]
+    student: Option<String>,
+
+    /// Like --student, but picks the student by position in the PDF, counted from 1
+    #[arg(long, value_name = "N", conflicts_with = "student")]
+    student_index: Option<usize>,
+
     /// Student form/class (optional, e.g., "11XX")
     #[arg(short, long)]
     form: Option<String>,
+
+    /// Student year group (optional, e.g., "Year 11")
+    #[arg(long)]
+    year: Option<String>,
+
+    /// Student house (optional, e.g., "Austen")
+    #[arg(long)]
+    house: Option<String>,
+}
+
+impl GenerateArgs {
+    /// Whether the `number`th week of a PDF (from 1) is generated under `--week`.
+    fn selects_week(&self, number: usize) -> bool {
+        self.week.is_empty() || self.week.iter().any(|range| range.contains(&number))
+    }
+}
+
+/// Run a `map` subcommand.
+fn run_map_command(command: &MapCommand) -> Result<()> {
+    match command {
+        MapCommand::Ids { map } => {
+            let elements = list_map_ids(map)
+                .with_context(|| format!("Failed to read map {}", map.display()))?;
+            let width = |column: fn(&MapElement) -> Option<&str>, heading: &str| {
+                elements
+                    .iter()
+                    .filter_map(column)
+                    .map(str::len)
+                    .chain([heading.len()])
+                    .max()
+                    .unwrap_or_default()
+            };
+            let id_width = width(|e| e.id.as_deref(), "id");
+            let name_width = width(|e| e.data_name.as_deref(), "data-name");
+            println!("{:id_width$}  {:name_width$}  element", "id", "data-name");
+            for element in &elements {
+                println!(
+                    "{:id_width$}  {:name_width$}  {}",
+                    element.id.as_deref().unwrap_or("-"),
+                    element.data_name.as_deref().unwrap_or("-"),
+                    element.element
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the `rooms` subcommand, listing each room code in `input` with its
+/// number of lessons and the mapping `config` gives it.
+fn run_rooms_command(input: &Path, config: Option<&Path>) -> Result<()> {
+    let config = match config {
+        Some(path) => Config::load(path).context("Failed to load config")?,
+        None => Config::default(),
+    };
+    let weeks = parse_pdf(input).context("Failed to parse PDF")?;
+    let mut rooms: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
+    for lesson in weeks.iter().flat_map(|week| &week.lessons) {
+        if !lesson.room.is_empty() {
+            *rooms.entry(lesson.room.as_str()).or_default() += 1;
+        }
+    }
+
+    let room_width = rooms
+        .keys()
+        .map(|room| room.len())
+        .chain([4])
+        .max()
+        .unwrap_or_default();
+    println!("{:room_width$}  lessons  mapping", "room");
+    for (room, count) in &rooms {
+        let mapping = match config.get_style_for_room(room) {
+            Some(mapping) => format!(
+                "{} -> {}{}",
+                mapping.prefix,
+                mapping.map_id,
+                mapping
+                    .label
+                    .as_ref()
+                    .map(|label| format!(" ({})", label))
+                    .unwrap_or_default()
+            ),
+            None => "-".to_string(),
+        };
+        println!("{:room_width$}  {:>7}  {}", room, count, mapping);
+    }
+    let unmapped = rooms
+        .keys()
+        .filter(|room| config.get_style_for_room(room).is_none())
+        .count();
+    eprintln!("{} rooms, {} without a mapping", rooms.len(), unmapped);
+    Ok(())
+}
+
+/// Run the `teachers` subcommand, listing each teacher in `input` with their
+/// number of lessons, rooms and subjects.
+fn run_teachers_command(input: &Path) -> Result<()> {
+    let weeks = parse_pdf(input).context("Failed to parse PDF")?;
+    let mut teachers: std::collections::BTreeMap<&str, Vec<&Lesson>> =
+        std::collections::BTreeMap::new();
+    for lesson in weeks.iter().flat_map(|week| &week.lessons) {
+        teachers
+            .entry(lesson.teacher.as_str())
+            .or_default()
+            .push(lesson);
+    }
+
+    // Distinct values of one field of the lessons, in order
+    let distinct = |lessons: &[&Lesson], field: fn(&Lesson) -> &str| {
+        let mut values: Vec<&str> = lessons.iter().map(|l| field(l)).collect();
+        values.sort_unstable();
+        values.dedup();
+        values.join(", ")
+    };
+    let rows: Vec<(&str, usize, String, String)> = teachers
+        .iter()
+        .map(|(teacher, lessons)| {
+            (
+                *teacher,
+                lessons.len(),
+                distinct(lessons, |l| &l.room),
+                distinct(lessons, |l| &l.subject),
+            )
+        })
+        .collect();
+    let teacher_width = rows
+        .iter()
+        .map(|r| r.0.len())
+        .chain([7])
+        .max()
+        .unwrap_or_default();
+    let rooms_width = rows
+        .iter()
+        .map(|r| r.2.len())
+        .chain([5])
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:teacher_width$}  lessons  {:rooms_width$}  subjects",
+        "teacher", "rooms"
+    );
+    for (teacher, count, rooms, subjects) in &rows {
+        println!(
+            "{:teacher_width$}  {:>7}  {:rooms_width$}  {}",
+            teacher, count, rooms, subjects
+        );
+    }
+    Ok(())
+}
+
+/// Run the `check` subcommand, printing each problem found with `config`
+/// for the timetable `input` and the map, and failing if there are any.
+fn run_check_command(input: &Path, config: &Path, map: Option<&Path>) -> Result<()> {
+    let config = Config::load(config).context("Failed to load config")?;
+    let mut weeks = parse_pdf(input).context("Failed to parse PDF")?;
+    let mut problems: Vec<String> = config
+        .apply_overrides_quietly(&mut weeks)
+        .into_iter()
+        .map(|problem| format!("Unmatched override: {}", problem))
+        .collect();
+
+    let lessons: Vec<&Lesson> = weeks.iter().flat_map(|week| &week.lessons).collect();
+    let mut unmapped: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
+    for lesson in &lessons {
+        if config.get_style_for_room(&lesson.room).is_none() {
+            *unmapped.entry(lesson.room.as_str()).or_default() += 1;
+        }
+    }
+    for (room, count) in unmapped {
+        problems.push(match room {
+            UNKNOWN => format!("Unread room: {} lessons have no room in the PDF", count),
+            room => format!("Unmapped room: {} ({} lessons)", room, count),
+        });
+    }
+
+    let paths: Vec<(&Path, &[MapArea])> = match map {
+        Some(path) => vec![(path, &config.map_areas)],
+        None => config
+            .maps
+            .iter()
+            .map(|map| (map.path.as_path(), map.areas.as_slice()))
+            .collect(),
+    };
+    let maps = paths
+        .iter()
+        .map(|(path, areas)| read_map(path, areas, false))
+        .collect::<Result<Vec<_>>>()?;
+    for (id, prefixes) in config.unmatched_map_ids(&maps) {
+        problems.push(format!(
+            "Missing map_id: \"{}\" (for {}) matches nothing in the map",
+            id,
+            prefixes.join(", ")
+        ));
+    }
+
+    // Named as overrides name them, whatever the sheets' locale
+    let days = Strings::default().days;
+    for week in &weeks {
+        for (day, day_name) in days.iter().enumerate() {
+            for (period, period_name) in PERIOD_LABELS.iter().enumerate() {
+                if !week
+                    .lessons
+                    .iter()
+                    .any(|l| l.day_index == day && l.period_index == period)
+                {
+                    problems.push(format!(
+                        "Empty slot: {}, {}, {}",
+                        week.week_name, day_name, period_name
+                    ));
+                }
+            }
+        }
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("{} problems found", problems.len());
+    }
+    println!("No problems found");
+    Ok(())
+}
+
+/// Run the `init` subcommand, writing a starter config for `input` to `output`,
+/// with map_ids chosen from the elements of `map` and, if `interactive`, each
+/// department confirmed on the terminal.
+fn run_init_command(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    map: Option<&Path>,
+    interactive: bool,
+) -> Result<()> {
+    if output.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to replace it",
+            output.display()
+        );
+    }
+    let weeks = parse_pdf(input).context("Failed to parse PDF")?;
+    let elements = match map {
+        Some(path) => {
+            list_map_ids(path).with_context(|| format!("Failed to read map {}", path.display()))?
+        }
+        None => Vec::new(),
+    };
+    let mut names: Vec<&str> = Vec::new();
+    for name in elements
+        .iter()
+        .flat_map(|e| e.id.as_deref().into_iter().chain(e.data_name.as_deref()))
+    {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut mappings = starter_mappings(&weeks);
+    for mapping in &mut mappings {
+        if let Some(name) = closest_map_id(mapping, &names) {
+            mapping.map_id = name.to_string();
+        }
+    }
+    if interactive {
+        mappings = run_wizard(&mut io::stdin().lock(), &weeks, mappings, &names)?;
+    }
+    fs::write(output, starter_config_with(&weeks, &mappings))
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    println!("Wrote starter config to {}", output.display());
+    Ok(())
+}
+
+/// Walk through the suggested `mappings` on the terminal, asking for each
+/// department's name, colour and map element (one of `names`, by number or
+/// name) and reading the answers from `input`, and return the mappings as
+/// answered. Pressing Enter keeps a suggestion.
+fn run_wizard(
+    input: &mut impl BufRead,
+    weeks: &[Week],
+    mappings: Vec<Mapping>,
+    names: &[&str],
+) -> Result<Vec<Mapping>> {
+    println!(
+        "Setting up {} departments. Press Enter to keep a suggestion.",
+        mappings.len()
+    );
+    if !names.is_empty() {
+        println!("\nElements in the map:");
+        for (number, name) in names.iter().enumerate() {
+            println!("{:>4}. {}", number + 1, name);
+        }
+    }
+
+    let total = mappings.len();
+    let mut chosen = Vec::new();
+    for (index, suggested) in mappings.into_iter().enumerate() {
+        let mut rooms: Vec<&str> = weeks
+            .iter()
+            .flat_map(|week| &week.lessons)
+            .map(|lesson| lesson.room.as_str())
+            .filter(|room| room_prefix(room) == Some(suggested.prefix.as_str()))
+            .collect();
+        rooms.sort_unstable();
+        rooms.dedup();
+        println!(
+            "\nDepartment {} of {}: rooms {}",
+            index + 1,
+            total,
+            rooms.join(", ")
+        );
+
+        let label = suggested.label.as_deref().unwrap_or(&suggested.prefix);
+        let label = ask(input, "Name (- to leave these rooms out)", label)?;
+        if label == "-" {
+            continue;
+        }
+        let color = loop {
+            let color = ask(input, "Colour (e.g. #fcdcd8)", &suggested.bg_color)?;
+            if is_hex_color(&color) {
+                break color;
+            }
+            println!("Please enter a hex colour, like #fcdcd8");
+        };
+        let map_id = ask(input, "Map element (number or name)", &suggested.map_id)?;
+        let map_id = match map_id.parse::<usize>() {
+            Ok(number) if (1..=names.len()).contains(&number) => names[number - 1].to_string(),
+            _ => map_id,
+        };
+        if !names.is_empty() && !names.contains(&map_id.as_str()) {
+            println!(
+                "Note: \"{}\" is not in the map; fix it in the config later",
+                map_id
+            );
+        }
+        // The suggested text colour only suits the suggested background
+        let fg_color = if color == suggested.bg_color {
+            suggested.fg_color.clone()
+        } else {
+            "#231f20".to_string()
+        };
+        chosen.push(
+            Mapping::new(&suggested.prefix, color, map_id)
+                .fg_color(fg_color)
+                .label(label),
+        );
+    }
+    Ok(chosen)
+}
+
+/// Print `question` with its `default` answer and read the reply, which is
+/// `default` if empty or at the end of input.
+fn ask(input: &mut impl BufRead, question: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", question, default);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let answer = line.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Whether `color` is a `#rgb` or `#rrggbb` hex colour.
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// The map element name most like `mapping`'s label or prefix, judged by the
+/// longest shared start (at least two letters), earliest in the map on a tie.
+fn closest_map_id<'a>(mapping: &Mapping, names: &[&'a str]) -> Option<&'a str> {
+    let shared = |a: &str, b: &str| {
+        a.chars()
+            .zip(b.chars())
+            .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+            .count()
+    };
+    let words: Vec<&str> = mapping
+        .label
+        .iter()
+        .flat_map(|label| label.split_whitespace())
+        .chain([mapping.prefix.as_str()])
+        .collect();
+    names
+        .iter()
+        .map(|name| {
+            (
+                words.iter().map(|w| shared(name, w)).max().unwrap_or(0),
+                *name,
+            )
+        })
+        .filter(|(score, _)| *score >= 2)
+        .fold(
+            None,
+            |best: Option<(usize, &str)>, (score, name)| match best {
+                Some((best_score, _)) if best_score >= score => best,
+                _ => Some((score, name)),
+            },
+        )
+        .map(|(_, name)| name)
+}
+
+/// Run a subcommand.
+fn run_command(command: &Command) -> Result<()> {
+    match command {
+        Command::Map { command } => run_map_command(command),
+        Command::Rooms { input, config } => run_rooms_command(input, config.as_deref()),
+        Command::Teachers { input } => run_teachers_command(input),
+        Command::Check { input, config, map } => run_check_command(input, config, map.as_deref()),
+        Command::Init {
+            input,
+            output,
+            force,
+            map,
+            interactive,
+        } => run_init_command(input, output, *force, map.as_deref(), *interactive),
+    }
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let cli = match (cli.command, cli.generate) {
+        (Some(command), _) => return run_command(&command),
+        (None, Some(generate)) => generate,
+        // clap asks for --input, --config and --output when there is no subcommand
+        (None, None) => Cli::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "--input, --config and --output are required without a subcommand",
+            )
+            .exit(),
+    };
+    if cli.map_placement == Placement::Page && cli.format != OutputFormat::Pdf {
+        anyhow::bail!("--map-placement page adds map pages to PDF output only; use --format pdf");
+    }
+    // --combine and --duplex always write a PDF, so a different format asked
+    // for by name would be ignored
+    if matches.value_source("format") == Some(ValueSource::CommandLine)
+        && cli.format != OutputFormat::Pdf
+    {
+        for (set, flag) in [(cli.combine, "--combine"), (cli.duplex, "--duplex")] {
+            if set {
+                anyhow::bail!("{} writes PDF output only; use --format pdf", flag);
+            }
+        }
+    }
+
+    let inputs = expand_inputs(&cli.input)?;
+    let batch = inputs.len() > 1
+        || cli
+            .input
+            .iter()
+            .any(|input| input.is_dir() || file_pattern(input).is_some());
+
+    // 1. Load the config, icons and maps, once for every student
+    let config = load_config(&cli)?;
+    let icons = IconSet::load(&config).context("Failed to load icon_set")?;
+    // The maps are parsed once and highlighted afresh for every sheet
+    let maps = SchoolMaps::load(&cli, &config)?;
+    maps.warn_unmatched(&config, cli.strict_map)?;
+    if batch && !cli.compare && !cli.household {
+        return run_batch(&cli, inputs, &config, &icons, &maps);
+    }
+    generate(
+        GenerateArgs {
+            input: inputs,
+            ..cli
+        },
+        &config,
+        &icons,
+        &maps,
+        None,
+    )
+}
 
-    println!("Processing timetable from: {:?}", cli.input);
+/// Load the config with the theme and teacher names the flags choose.
+fn load_config(cli: &GenerateArgs) -> Result<Config> {
+    let mut config = Config::load(&cli.config).context("Failed to load config")?;
+    if cli.dark {
+        config.theme = config.theme.with_dark_colors();
+    }
+    if cli.mono {
+        config.theme = config.theme.with_mono_colors();
+    }
+    if let Some(teachers) = cli.teachers {
+        config.teacher_names = teachers.into();
+    }
+    Ok(config)
+}
 
-    // 1. Load Config
-    let config = Config::load(&cli.config).context("Failed to load config")?;
+/// Generate each of `inputs` on its own, into a subdirectory of the output
+/// directory per student, carrying on past PDFs that fail. The students share
+/// `maps`, so departments they have in common are highlighted only once.
+fn run_batch(
+    cli: &GenerateArgs,
+    inputs: Vec<PathBuf>,
+    config: &Config,
+    icons: &IconSet,
+    maps: &SchoolMaps,
+) -> Result<()> {
+    let total = inputs.len();
+    let mut used = HashSet::new();
+    let mut failed = 0;
+    for input in inputs {
+        let student = GenerateArgs {
+            input: vec![input.clone()],
+            ..cli.clone()
+        };
+        if let Err(err) = generate(student, config, icons, maps, Some(&mut used)) {
+            eprintln!("Error: {:?}: {:#}", input, err);
+            failed += 1;
+        }
+    }
+    println!("Generated {} of {} timetables.", total - failed, total);
+    if failed > 0 {
+        anyhow::bail!("{} of {} timetables failed", failed, total);
+    }
+    Ok(())
+}
 
-    // 2. Parse PDF
-    let mut weeks = parse_pdf(&cli.input).context("Failed to parse PDF")?;
-    println!("Found {} weeks.", weeks.len());
+/// Generate the sheets for `cli.input`. In a batch, `used` holds the output
+/// subdirectories taken so far, and the sheets go in a new one named after the
+/// student.
+fn generate(
+    mut cli: GenerateArgs,
+    config: &Config,
+    icons: &IconSet,
+    maps: &SchoolMaps,
+    used: Option<&mut HashSet<String>>,
+) -> Result<()> {
+    // 2. Parse each PDF, then apply overrides and aliases from config
+    let today = cli
+        .generated_at
+        .unwrap_or_else(chrono::Local::now)
+        .date_naive();
+    let mut weeks = Vec::new();
+    let mut week_counts = Vec::new();
+    // For each week kept: its number among all the PDFs' weeks, used in file
+    // names, and its number within its own PDF
+    let mut week_numbers = Vec::new();
+    let mut weeks_before = 0;
+    for input in &cli.input {
+        println!("Processing timetable from: {:?}", input);
+        let mut student_weeks = parse_pdf(input).context("Failed to parse PDF")?;
+        println!("Found {} weeks.", student_weeks.len());
+        if cli.student.is_some() || cli.student_index.is_some() {
+            student_weeks = select_student(student_weeks, &cli, input)?;
+        }
 
-    // 3. Apply overrides from config
-    config.apply_overrides(&mut weeks);
+        config.apply_overrides(&mut student_weeks);
+        config.apply_aliases(&mut student_weeks);
+        if cli.anonymise {
+            anonymise(&mut student_weeks);
+        }
+        config.apply_calendar(&mut student_weeks, today);
+
+        // Weeks are left out only now, so overrides and the calendar still
+        // count every week in the PDF
+        let numbers: Vec<usize> = (1..=student_weeks.len())
+            .filter(|number| cli.selects_week(*number))
+            .collect();
+        if numbers.is_empty() {
+            eprintln!(
+                "Warning: --week selects none of the {} weeks in {:?}",
+                student_weeks.len(),
+                input
+            );
+        }
+        let found = student_weeks.len();
+        let mut number = 0;
+        student_weeks.retain(|_| {
+            number += 1;
+            cli.selects_week(number)
+        });
+        week_numbers.extend(numbers.into_iter().map(|n| (weeks_before + n, n)));
+        weeks_before += found;
+        week_counts.push(student_weeks.len());
+        weeks.extend(student_weeks);
+    }
+
+    if let Some(used) = used {
+        let dir = student_dir(&weeks, &cli.input[0], used);
+        cli.output = cli.output.join(dir);
+    }
 
     // Ensure output directory exists
     fs::create_dir_all(&cli.output).context("Failed to create output directory")?;
 
+    let mut options = match cli.layout {
+        Layout::Sheet => RenderOptions::builder()
+            .paper_size(cli.paper.into())
+            .orientation(if cli.landscape {
+                Orientation::Landscape
+            } else {
+                Orientation::Portrait
+            })
+            .build(),
+        Layout::Pocket => RenderOptions::pocket_card(),
+        Layout::Phone => RenderOptions::phone_wallpaper(),
+    };
+    options.show_legend = cli.legend;
+    options.map_placement = match cli.map_placement {
+        Placement::Auto => MapPlacement::Auto,
+        Placement::Below => MapPlacement::Below,
+        Placement::Above => MapPlacement::Above,
+        Placement::Beside => MapPlacement::Beside,
+        Placement::Page => MapPlacement::Page,
+        Placement::None => {
+            options.show_map = false;
+            options.map_placement
+        }
+    };
+    options.show_free_periods = cli.free_periods;
+    options.inline_styles = cli.inline_styles;
+    options.optimise = cli.optimise;
+    options.teacher_sheet = cli.teacher_sheet;
+    options.generated_at = cli.generated_at;
+    let template = match &cli.template {
+        Some(path) => Some((
+            fs::read_to_string(path).context("Failed to read template")?,
+            template_extension(path),
+        )),
+        None => None,
+    };
+
+    // Sheets collected for --combine/--two-up/--duplex
+    let mut sheets = Vec::new();
+
     // 4. Process each week
     for (i, week) in weeks.iter().enumerate() {
         println!("Processing {}", week.week_name);
         println!("  Total lessons: {}", week.lessons.len());
 
-        // Override student name and form if provided via CLI
+        // Override student details if provided via CLI
         let mut week_with_info = week.clone();
         if let Some(name) = &cli.student_name {
             week_with_info.student_name = Some(name.clone());
@@ -66,6 +1174,29 @@ fn main() -> Result<()> {
         if let Some(form_code) = &cli.form {
             week_with_info.form = Some(form_code.clone());
         }
+        if let Some(year) = &cli.year {
+            week_with_info.year_group = Some(year.clone());
+        }
+        if let Some(house) = &cli.house {
+            week_with_info.house = Some(house.clone());
+        }
+
+        let options = RenderOptions {
+            highlight_day: week.weekday_on(today).filter(|_| cli.today),
+            ..options.clone()
+        };
+
+        // Warn about text that won't fit before anything is printed
+        for overflow in find_overflows(&week_with_info, config, icons, &options) {
+            eprintln!(
+                "Warning: {}, {}, {}: {} \"{}\" does not fit its cell",
+                week.week_name,
+                config.strings.day(overflow.day_index),
+                config.strings.period(overflow.period_index),
+                overflow.kind,
+                overflow.text
+            );
+        }
 
         // Debug: Show period distribution
         let mut period_counts = [0usize; 6];
@@ -92,45 +1223,978 @@ fn main() -> Result<()> {
             );
         }
 
-        // Identify highlights for this week
-        let mut highlights = Vec::new();
-        // We want to highlight departments used in this week.
-        // We can iterate over lessons, find the room, look up the mapping, and add to highlights.
-        // We should deduplicate.
-
-        let mut seen_ids = std::collections::HashSet::new();
-
-        for lesson in &week_with_info.lessons {
-            if let Some(mapping) = config.get_style_for_room(&lesson.room) {
-                if seen_ids.insert(mapping.map_id.clone()) {
-                    highlights.push(MapHighlight {
-                        id: mapping.map_id.clone(),
-                        color: mapping.bg_color.clone(),
-                    });
-                }
-            }
+        // Day sheets highlight a map of their own for each day
+        if cli.daily || !cli.day.is_empty() {
+            write_day_sheets(
+                &week_with_info,
+                week_numbers[i].0,
+                config,
+                icons,
+                maps,
+                &cli,
+                &options,
+            )?;
+            continue;
         }
 
-        // 4. Process Map (optional)
-        let map_svg = if let Some(map_path) = &cli.map {
-            process_map(map_path, &highlights).context("Failed to process map")?
+        // 4. Process Map (optional; without one the renderer skips embedding)
+        let today_index = options
+            .highlight_day
+            .map(|day| day.num_days_from_monday() as usize);
+        let map_svg = maps.highlight(&[&week_with_info], config, &cli, cli.route, today_index)?;
+        let map_svg = if cli.optimise && !map_svg.is_empty() {
+            optimise_svg(&map_svg, OPTIMISE_PRECISION).context("Failed to optimise map")?
         } else {
-            // No map provided — renderer will skip embedding
-            String::new()
+            map_svg
         };
 
+        if cli.combine || cli.two_up || cli.duplex || cli.compare || cli.household {
+            sheets.push((week_with_info, map_svg));
+            continue;
+        }
+
         // 5. Render
         // Use a safe filename
         let safe_name = week_with_info
             .week_name
             .replace(|c: char| !c.is_alphanumeric() && c != ' ', "_");
-        let filename = format!("{}_{}.svg", safe_name, i + 1);
+
+        if let Some((source, extension)) = &template {
+            let output_path = cli
+                .output
+                .join(format!("{}_{}.{}", safe_name, week_numbers[i].0, extension));
+            let rendered = render_timetable_with_template(
+                &week_with_info,
+                config,
+                icons,
+                &map_svg,
+                &options,
+                source,
+            )
+            .context("Failed to render template")?;
+            fs::write(&output_path, rendered).context("Failed to write output")?;
+            println!("Generated: {:?}", output_path);
+            continue;
+        }
+
+        let filename = format!(
+            "{}_{}.{}",
+            safe_name,
+            week_numbers[i].0,
+            cli.format.extension()
+        );
         let output_path = cli.output.join(filename);
 
-        render_timetable(&week_with_info, &config, &map_svg, &output_path)
-            .context("Failed to render timetable")?;
+        match cli.format {
+            // The .svgz extension selects compression
+            OutputFormat::Svg | OutputFormat::Svgz => render_timetable(
+                &week_with_info,
+                config,
+                icons,
+                &map_svg,
+                &options,
+                &output_path,
+            ),
+            OutputFormat::Pdf => render_timetable_pdf(
+                &week_with_info,
+                config,
+                icons,
+                &map_svg,
+                &options,
+                &output_path,
+            ),
+            OutputFormat::Png => render_timetable_png(
+                &week_with_info,
+                config,
+                icons,
+                &map_svg,
+                &options,
+                cli.dpi,
+                &output_path,
+            ),
+            OutputFormat::Html => {
+                render_timetable_html(&week_with_info, config, &map_svg, &options, &output_path)
+            }
+            OutputFormat::Typst => {
+                render_timetable_typst(&week_with_info, config, &map_svg, &options, &output_path)
+            }
+            OutputFormat::Latex => {
+                render_timetable_latex(&week_with_info, config, &options, &output_path)
+            }
+        }
+        .context("Failed to render timetable")?;
+        println!("Generated: {:?}", output_path);
+    }
+
+    if cli.combine {
+        let map_page = if cli.map_page && !maps.is_empty() {
+            let weeks: Vec<&Week> = sheets.iter().map(|(week, _)| week).collect();
+            Some(maps.highlight(&weeks, config, &cli, None, None)?)
+        } else {
+            None
+        };
+
+        let output_path = cli.output.join("timetable.pdf");
+        let sheet_refs: Vec<_> = sheets
+            .iter()
+            .map(|(week, map_svg)| (week, map_svg.as_str()))
+            .collect();
+        render_timetables_pdf(
+            &sheet_refs,
+            config,
+            icons,
+            &options,
+            map_page.as_deref(),
+            &output_path,
+        )
+        .context("Failed to render combined PDF")?;
+        println!("Generated: {:?}", output_path);
+    }
+
+    if cli.duplex {
+        let output_path = cli.output.join("timetable_duplex.pdf");
+        let sheet_refs: Vec<_> = sheets
+            .iter()
+            .map(|(week, map_svg)| (week, map_svg.as_str()))
+            .collect();
+        render_duplex_pdf(&sheet_refs, config, icons, &options, &output_path)
+            .context("Failed to render duplex PDF")?;
         println!("Generated: {:?}", output_path);
     }
 
+    if cli.two_up {
+        let weeks: Vec<&Week> = sheets.iter().map(|(week, _)| week).collect();
+        let shared_map = maps.highlight(&weeks, config, &cli, None, None)?;
+        let layout = if cli.landscape {
+            MultiWeekLayout::SideBySide
+        } else {
+            MultiWeekLayout::Stacked
+        };
+        let weeks: Vec<_> = sheets.iter().map(|(week, _)| week.clone()).collect();
+        let svg = render_weeks_to_string(&weeks, config, icons, &shared_map, &options, layout)
+            .context("Failed to render timetable")?;
+
+        let output_path = cli
+            .output
+            .join(format!("timetable_2up.{}", cli.format.extension()));
+        write_page(svg, &cli, "--two-up", &output_path)?;
+    }
+
+    if cli.compare || cli.household {
+        // Split the sheets back into students, then put week N of each on one page
+        let mut students = Vec::new();
+        let mut student_numbers = Vec::new();
+        let mut sheets = sheets.into_iter();
+        let mut numbers = week_numbers.iter();
+        for count in week_counts {
+            students.push(
+                sheets
+                    .by_ref()
+                    .take(count)
+                    .map(|(week, _)| week)
+                    .collect::<Vec<_>>(),
+            );
+            student_numbers.push(
+                numbers
+                    .by_ref()
+                    .take(count)
+                    .map(|(_, number)| *number)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let pages = students.iter().map(Vec::len).max().unwrap_or(0);
+
+        for i in 0..pages {
+            let weeks: Vec<_> = students
+                .iter()
+                .filter_map(|student| student.get(i).cloned())
+                .collect();
+            let week_refs: Vec<&Week> = weeks.iter().collect();
+            let shared_map = maps.highlight(&week_refs, config, &cli, None, None)?;
+            let (svg, suffix, flag) = if cli.household {
+                let svg = render_household_to_string(&weeks, config, icons, &shared_map, &options);
+                (svg, "household", "--household")
+            } else {
+                let svg = render_weeks_to_string(
+                    &weeks,
+                    config,
+                    icons,
+                    &shared_map,
+                    &options,
+                    MultiWeekLayout::SideBySide,
+                );
+                (svg, "compare", "--compare")
+            };
+            let svg = svg.context("Failed to render timetable")?;
+
+            let safe_name = weeks[0]
+                .week_name
+                .replace(|c: char| !c.is_alphanumeric() && c != ' ', "_");
+            let number = student_numbers
+                .iter()
+                .find_map(|numbers| numbers.get(i))
+                .copied()
+                .unwrap_or(i + 1);
+            let output_path = cli.output.join(format!(
+                "{}_{}_{}.{}",
+                safe_name,
+                number,
+                suffix,
+                cli.format.extension()
+            ));
+            write_page(svg, &cli, flag, &output_path)?;
+        }
+    }
+
     Ok(())
 }
+
+/// The maps highlighted on each sheet: the `--map` file, or else the config's
+/// site maps, each showing the rooms routed to it by prefix.
+struct SchoolMaps {
+    maps: Vec<MapDocument>,
+    /// Whether rooms are routed by the config's `[[maps]]` rather than all
+    /// shown on a single `--map`
+    sites: bool,
+    /// Maps already highlighted, shared by students with the same departments
+    cache: RefCell<MapCache>,
+}
+
+impl SchoolMaps {
+    /// Parse the `--map` file, or each of the config's site maps. PNG and JPEG
+    /// maps are wrapped with the areas the config marks on them.
+    fn load(cli: &GenerateArgs, config: &Config) -> Result<Self> {
+        let (paths, sites) = match &cli.map {
+            Some(path) => (vec![(path.clone(), &config.map_areas)], false),
+            None => (
+                config
+                    .maps
+                    .iter()
+                    .map(|map| (map.path.clone(), &map.areas))
+                    .collect(),
+                true,
+            ),
+        };
+        let maps = paths
+            .iter()
+            .map(|(path, areas)| {
+                let mut map = read_map(path, areas, cli.sanitise_map)?
+                    .with_mode(cli.map_highlight.into())
+                    .with_shared_fill(cli.shared_fill.into());
+                if cli.dim_map {
+                    map = map.with_dimming(DIM_OPACITY);
+                }
+                if cli.crop_map {
+                    map = map.with_crop(CROP_PADDING);
+                }
+                if cli.map_legend {
+                    map = map.with_legend();
+                }
+                Ok(map)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            maps,
+            sites,
+            cache: RefCell::default(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// Warn about config `map_id`s that match nothing on any map, and about
+    /// department-like groups on the maps that no mapping names, since either
+    /// leaves rooms unhighlighted without any other sign. With `strict`, an
+    /// unmatched `map_id` is an error.
+    fn warn_unmatched(&self, config: &Config, strict: bool) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        for (id, prefixes) in config.unmatched_map_ids(&self.maps) {
+            if strict {
+                return Err(ProcessorError::HighlightNotFound(id.to_string())).with_context(|| {
+                    format!("Mapping for {} has an unknown map_id", prefixes.join(", "))
+                });
+            }
+            eprintln!(
+                "Warning: map_id \"{}\" (for {}) matches nothing in the map",
+                id,
+                prefixes.join(", ")
+            );
+        }
+        let map_ids = config.map_ids();
+        for map in &self.maps {
+            for group in map.unmapped_groups(&map_ids) {
+                eprintln!(
+                    "Warning: Map group \"{}\" is not the map_id of any mapping",
+                    group
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Highlight the departments of `weeks` on each map, with the route of
+    /// `route` day, and combine the maps the weeks use side by side. With
+    /// `today` (a day index), departments without a lesson that day are muted.
+    /// Empty without maps.
+    fn highlight(
+        &self,
+        weeks: &[&Week],
+        config: &Config,
+        cli: &GenerateArgs,
+        route: Option<Weekday>,
+        today: Option<usize>,
+    ) -> Result<String> {
+        let mut svgs = Vec::new();
+        for (index, map) in self.maps.iter().enumerate() {
+            // Just the lessons in rooms on this map
+            let weeks: Vec<Week> = weeks
+                .iter()
+                .map(|week| Week {
+                    lessons: week
+                        .lessons
+                        .iter()
+                        .filter(|l| !self.sites || config.site_map_for_room(&l.room) == Some(index))
+                        .cloned()
+                        .collect(),
+                    ..(*week).clone()
+                })
+                .collect();
+            let rooms = Some(map).filter(|_| cli.room_highlights);
+            let mut highlights =
+                config.map_highlights(&weeks, rooms, cli.room_labels.map(Into::into));
+            if self.sites && highlights.is_empty() {
+                continue;
+            }
+            if cli.map_heatmap {
+                let lessons: Vec<&Lesson> = weeks.iter().flat_map(|week| &week.lessons).collect();
+                let visits: Vec<usize> = highlights
+                    .iter()
+                    .map(|highlight| {
+                        lessons
+                            .iter()
+                            .filter(|l| {
+                                config.highlight_id(l, rooms) == Some(highlight.id.as_str())
+                            })
+                            .count()
+                    })
+                    .collect();
+                highlights = heatmap(&highlights, &visits, HEATMAP_COLOR)
+                    .into_iter()
+                    .zip(&visits)
+                    .map(|(highlight, count)| {
+                        let name = highlight
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| highlight.id.clone());
+                        highlight.name(format!("{} ({})", name, count))
+                    })
+                    .collect();
+            }
+            if let Some(day) = today {
+                let days: Vec<Week> = weeks.iter().map(|week| week.day(day)).collect();
+                let todays = config.map_highlights(&days, rooms, None);
+                for highlight in &mut highlights {
+                    highlight.muted = !todays
+                        .iter()
+                        .any(|t| t.id == highlight.id && t.color == highlight.color);
+                }
+            }
+            let routes: Vec<MapRoute> = route
+                .zip(weeks.first())
+                .and_then(|(day, week)| config.day_route(week, day.index(), map))
+                .map(|route| route.line(cli.route_style == RouteStyle::Line))
+                .into_iter()
+                .collect();
+            svgs.push(if routes.is_empty() {
+                self.cache.borrow_mut().highlight(map, &highlights)
+            } else {
+                map.highlight_with_routes(&highlights, &routes)
+            });
+        }
+        combine_maps(&svgs).context("Failed to combine maps")
+    }
+}
+
+/// Read and parse the map at `path`, wrapping a PNG or JPEG map with the
+/// `areas` marked on it, and first making it safe to open if `sanitise` is set.
+fn read_map(path: &Path, areas: &[MapArea], sanitise: bool) -> Result<MapDocument> {
+    let raster = path.extension().is_some_and(|extension| {
+        ["png", "jpg", "jpeg"].contains(&extension.to_string_lossy().to_lowercase().as_str())
+    });
+    let map = if raster {
+        fs::read(path)
+            .map_err(Into::into)
+            .and_then(|image| raster_map(&image, areas))
+    } else {
+        fs::read_to_string(path).map_err(Into::into)
+    };
+    let map = if sanitise {
+        map.and_then(|content| sanitise_map(&content))
+    } else {
+        map
+    };
+    map.and_then(MapDocument::parse)
+        .with_context(|| format!("Failed to process map {}", path.display()))
+}
+
+/// The PDFs named by `inputs`: files as given, every PDF in a directory, and
+/// the files matching a `*` or `?` pattern in the last part of a path, each
+/// sorted by name.
+fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let pattern = file_pattern(input);
+        let dir = match &pattern {
+            Some(_) => input
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new(".")),
+            None if input.is_dir() => input.as_path(),
+            None => {
+                files.push(input.clone());
+                continue;
+            }
+        };
+        let matches = |path: &Path| match &pattern {
+            Some(pattern) => path
+                .file_name()
+                .is_some_and(|name| wildcard_match(pattern, &name.to_string_lossy())),
+            None => path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")),
+        };
+        let mut found: Vec<PathBuf> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {:?}", dir))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && matches(path))
+            .collect();
+        if found.is_empty() {
+            anyhow::bail!("No PDFs found for --input {:?}", input);
+        }
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+/// The last part of `input` if it is a file name pattern, such as `*.pdf`.
+fn file_pattern(input: &Path) -> Option<Cow<'_, str>> {
+    input
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .filter(|name| name.contains(['*', '?']))
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The output subdirectory for a batch student: their name, or the PDF's file
+/// name without one, with the PDF's name added if another student has it and
+/// then a number until no other student has it.
+fn student_dir(weeks: &[Week], input: &Path, used: &mut HashSet<String>) -> String {
+    let safe = |name: &str| name.replace(|c: char| !c.is_alphanumeric() && c != ' ', "_");
+    let stem = safe(
+        &input
+            .file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default(),
+    );
+    let mut dir = weeks
+        .iter()
+        .find_map(|week| week.student_name.as_deref())
+        .map(safe)
+        .unwrap_or_else(|| stem.clone());
+    if used.contains(&dir) && dir != stem {
+        dir = format!("{} {}", dir, stem);
+    }
+    let base = dir.clone();
+    let mut number = 2;
+    while used.contains(&dir) {
+        dir = format!("{} {}", base, number);
+        number += 1;
+    }
+    used.insert(dir.clone());
+    dir
+}
+
+/// The weeks of the student picked with --student or --student-index from
+/// `weeks`, parsed from a PDF that may hold several students' timetables.
+fn select_student(weeks: Vec<Week>, cli: &GenerateArgs, input: &Path) -> Result<Vec<Week>> {
+    let mut students = split_students(weeks);
+    let name = |weeks: &[Week]| {
+        weeks
+            .iter()
+            .find_map(|week| week.student_name.clone())
+            .unwrap_or_else(|| "(unnamed)".to_string())
+    };
+    let index = match (&cli.student, cli.student_index) {
+        (Some(wanted), _) => {
+            let wanted = wanted.split_whitespace().collect::<Vec<_>>().join(" ");
+            let index = students.iter().position(|weeks| {
+                weeks.iter().any(|week| {
+                    week.student_name.as_deref().is_some_and(|name| {
+                        name.split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                            .eq_ignore_ascii_case(&wanted)
+                    })
+                })
+            });
+            index.with_context(|| {
+                let names: Vec<String> = students.iter().map(|weeks| name(weeks)).collect();
+                format!(
+                    "No student named \"{}\" in {:?}; it holds {}",
+                    wanted,
+                    input,
+                    names.join(", ")
+                )
+            })?
+        }
+        (None, index) => {
+            let index = index.expect("--student or --student-index is given");
+            if index == 0 || index > students.len() {
+                anyhow::bail!(
+                    "--student-index {} is out of range; {:?} holds {} students",
+                    index,
+                    input,
+                    students.len()
+                );
+            }
+            index - 1
+        }
+    };
+    let weeks = students.swap_remove(index);
+    println!(
+        "Selected student {} of {} ({} weeks): {}",
+        index + 1,
+        students.len() + 1,
+        weeks.len(),
+        name(&weeks)
+    );
+    Ok(weeks)
+}
+
+/// Write a day sheet for each day of `week` with lessons, numbered `number`
+/// like the week's own sheet, each with a map highlighting only that day's
+/// departments (and its route, on the --route day). With --day, only those
+/// days are written.
+fn write_day_sheets(
+    week: &Week,
+    number: usize,
+    config: &Config,
+    icons: &IconSet,
+    maps: &SchoolMaps,
+    cli: &GenerateArgs,
+    options: &RenderOptions,
+) -> Result<()> {
+    for &day in Weekday::value_variants() {
+        if !cli.day.is_empty() && !cli.day.contains(&day) {
+            continue;
+        }
+        let day_week = week.day(day.index());
+        if day_week.lessons.is_empty() {
+            if cli.day.contains(&day) {
+                eprintln!(
+                    "Warning: {} has no lessons on {}",
+                    week.week_name,
+                    config.strings.day(day.index())
+                );
+            }
+            continue;
+        }
+        let route = cli.route.filter(|&route| route == day);
+        let map_svg = maps.highlight(&[&day_week], config, cli, route, None)?;
+        let map_svg = if cli.optimise && !map_svg.is_empty() {
+            optimise_svg(&map_svg, OPTIMISE_PRECISION).context("Failed to optimise map")?
+        } else {
+            map_svg
+        };
+
+        let svg = render_day_to_string(week, day.index(), config, icons, &map_svg, options)
+            .context("Failed to render timetable")?;
+        let safe_name = format!("{} {}", week.week_name, config.strings.day(day.index()))
+            .replace(|c: char| !c.is_alphanumeric() && c != ' ', "_");
+        let output_path = cli.output.join(format!(
+            "{}_{}.{}",
+            safe_name,
+            number,
+            cli.format.extension()
+        ));
+        write_page(svg, cli, "--daily", &output_path)?;
+    }
+    Ok(())
+}
+
+/// Write a page from `render_weeks_to_string` in the chosen output format.
+fn write_page(svg: String, cli: &GenerateArgs, flag: &str, output_path: &Path) -> Result<()> {
+    let bytes = match cli.format {
+        OutputFormat::Svg => svg.into_bytes(),
+        OutputFormat::Svgz => svg_to_svgz(&svg).context("Failed to compress SVG")?,
+        OutputFormat::Pdf => svg_to_pdf(&svg).context("Failed to convert to PDF")?,
+        OutputFormat::Png => svg_to_png(&svg, cli.dpi).context("Failed to convert to PNG")?,
+        OutputFormat::Html | OutputFormat::Typst | OutputFormat::Latex => {
+            anyhow::bail!("{} supports SVG, SVGZ, PDF, and PNG output only", flag)
+        }
+    };
+    fs::write(output_path, bytes).context("Failed to write output")?;
+    println!("Generated: {:?}", output_path);
+    Ok(())
+}
+
+/// Parse a `--week` value: a week number or an inclusive range such as `1-3`.
+fn parse_week_range(value: &str) -> Result<RangeInclusive<usize>, String> {
+    let number = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("expected a week number from 1, got \"{}\"", n.trim()))
+    };
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (number(start)?, number(end)?),
+        None => (number(value)?, number(value)?),
+    };
+    if start > end {
+        return Err(format!("week range \"{}\" ends before it starts", value));
+    }
+    Ok(start..=end)
+}
+
+/// Parse `--generated-at` as a local date, with an optional `HH:MM` time.
+fn parse_generated_at(value: &str) -> Result<chrono::DateTime<chrono::Local>, String> {
+    let value = value.trim();
+    let time = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_time(chrono::NaiveTime::MIN))
+        })
+        .map_err(|_| format!("expected YYYY-MM-DD or YYYY-MM-DD HH:MM, got \"{}\"", value))?;
+    time.and_local_timezone(chrono::Local)
+        .earliest()
+        .ok_or_else(|| format!("{} does not exist in the local time zone", value))
+}
+
+/// Output extension for a template file: `sheet.html.j2` gives `html`.
+fn template_extension(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name
+        .strip_suffix(".j2")
+        .or_else(|| name.strip_suffix(".jinja"))
+        .unwrap_or(&name);
+    Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "txt".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The flags for a sheet run plus `extra`.
+    fn parse_cli(extra: &[&str]) -> Result<GenerateArgs, clap::Error> {
+        let args = [
+            "timetable_cli",
+            "-i",
+            "in.pdf",
+            "-c",
+            "config.toml",
+            "-o",
+            "out",
+        ];
+        Cli::try_parse_from(args.iter().chain(extra))
+            .map(|cli| cli.generate.expect("sheet flags without a subcommand"))
+    }
+
+    #[test]
+    fn sheet_flags_are_present_exactly_without_a_subcommand() {
+        let cli = parse_cli(&[]).unwrap();
+        assert_eq!(cli.config, Path::new("config.toml"));
+        assert_eq!(cli.output, Path::new("out"));
+
+        let cli = Cli::try_parse_from(["timetable_cli", "map", "ids", "map.svg"]).unwrap();
+        assert!(cli.command.is_some() && cli.generate.is_none());
+        assert!(Cli::try_parse_from(["timetable_cli", "-i", "in.pdf", "-o", "out"]).is_err());
+        assert!(Cli::try_parse_from(["timetable_cli"]).is_err());
+    }
+
+    #[test]
+    fn day_sheets_conflict_with_pages_of_several_students() {
+        assert!(parse_cli(&["--daily"]).is_ok());
+        assert!(parse_cli(&["--day", "mon"]).is_ok());
+        for flags in [
+            &["--daily", "--compare"][..],
+            &["--daily", "--household"],
+            &["--day", "mon", "--compare"],
+            &["--day", "mon", "--household"],
+        ] {
+            assert!(parse_cli(flags).is_err(), "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn day_sheets_are_named_after_the_week_and_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().to_str().unwrap();
+        let lesson = |day_index| Lesson {
+            subject: "Maths".into(),
+            room: "MA3".into(),
+            day_index,
+            period_index: 1,
+            ..Default::default()
+        };
+        let week = Week {
+            week_name: "Week 2".into(),
+            lessons: vec![lesson(0), lesson(2)],
+            ..Default::default()
+        };
+        let config = Config::default();
+        let options = RenderOptions::default();
+        let written = |cli: &GenerateArgs| {
+            let maps = SchoolMaps::load(cli, &config).unwrap();
+            write_day_sheets(&week, 4, &config, &IconSet::default(), &maps, cli, &options).unwrap();
+            let mut names: Vec<String> = fs::read_dir(out)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            for name in &names {
+                fs::remove_file(dir.path().join(name)).unwrap();
+            }
+            names
+        };
+
+        let cli = Cli::try_parse_from([
+            "timetable_cli",
+            "-i",
+            "in.pdf",
+            "-c",
+            "c.toml",
+            "-o",
+            out,
+            "--daily",
+        ])
+        .unwrap()
+        .generate
+        .unwrap();
+        assert_eq!(
+            written(&cli),
+            ["Week 2 Monday_4.svg", "Week 2 Wednesday_4.svg"]
+        );
+        let cli = Cli::try_parse_from([
+            "timetable_cli",
+            "-i",
+            "in.pdf",
+            "-c",
+            "c.toml",
+            "-o",
+            out,
+            "--day",
+            "wed,thu",
+        ])
+        .unwrap()
+        .generate
+        .unwrap();
+        assert_eq!(written(&cli), ["Week 2 Wednesday_4.svg"]);
+    }
+
+    #[test]
+    fn parse_week_range_reads_numbers_and_ranges() {
+        assert_eq!(parse_week_range("2"), Ok(2..=2));
+        assert_eq!(parse_week_range(" 1 - 3 "), Ok(1..=3));
+        assert!(parse_week_range("0").unwrap_err().contains("from 1"));
+        assert!(parse_week_range("3-1").unwrap_err().contains("ends before"));
+        assert!(parse_week_range("two").is_err());
+        assert!(parse_week_range("1-").is_err());
+    }
+
+    #[test]
+    fn selects_week_follows_the_week_flags() {
+        let cli = parse_cli(&["--week", "1-2,4"]).unwrap();
+        let selected: Vec<usize> = (1..=5).filter(|n| cli.selects_week(*n)).collect();
+        assert_eq!(selected, [1, 2, 4]);
+
+        let cli = parse_cli(&["--week", "3", "--week", "1"]).unwrap();
+        let selected: Vec<usize> = (1..=5).filter(|n| cli.selects_week(*n)).collect();
+        assert_eq!(selected, [1, 3]);
+
+        let cli = parse_cli(&[]).unwrap();
+        assert!((1..=5).all(|n| cli.selects_week(n)));
+
+        assert!(parse_cli(&["--week", "0"]).is_err());
+    }
+
+    #[test]
+    fn closest_map_id_prefers_the_longest_shared_start() {
+        let names = [
+            "Music_Rooms",
+            "Maths_Rooms",
+            "Main_Hall",
+            "Science_Rooms",
+            "Sc_Lab",
+        ];
+        let maths = Mapping::new("MA", "#fcdcd8", "").label("Mathematics");
+        assert_eq!(closest_map_id(&maths, &names), Some("Maths_Rooms"));
+
+        // A tie goes to the element earliest in the map
+        let science = Mapping::new("SC", "#fad7e6", "");
+        assert_eq!(closest_map_id(&science, &names), Some("Science_Rooms"));
+
+        // One shared letter is not enough
+        let history = Mapping::new("H", "#e0f0d0", "").label("History");
+        assert_eq!(closest_map_id(&history, &["Hall", "Music_Rooms"]), None);
+        let drama = Mapping::new("D", "#e0f0d0", "").label("Drama");
+        assert_eq!(closest_map_id(&drama, &["Dining_Hall"]), None);
+    }
+
+    #[test]
+    fn is_hex_color_accepts_short_and_long_forms() {
+        assert!(is_hex_color("#fcdcd8"));
+        assert!(is_hex_color("#FFF"));
+        assert!(!is_hex_color("fcdcd8"));
+        assert!(!is_hex_color("#fcdc"));
+        assert!(!is_hex_color("#ggg"));
+        assert!(!is_hex_color("red"));
+    }
+
+    #[test]
+    fn run_wizard_reads_answers_and_keeps_suggestions() {
+        let lesson = |room: &str, subject: &str| Lesson {
+            room: room.into(),
+            subject: subject.into(),
+            ..Default::default()
+        };
+        let weeks = [Week {
+            lessons: vec![
+                lesson("M12", "Music"),
+                lesson("MA3", "Maths"),
+                lesson("SC1", "Science"),
+            ],
+            ..Default::default()
+        }];
+        let suggested = starter_mappings(&weeks);
+        let prefixes: Vec<&str> = suggested.iter().map(|m| m.prefix.as_str()).collect();
+        assert_eq!(prefixes, ["M", "MA", "SC"]);
+        let science = suggested[2].clone();
+
+        let names = ["Music_Rooms", "Maths_Rooms"];
+        // Leave out M; rename MA, retry a bad colour and pick element 2; keep
+        // SC's suggestions but give an element not in the map
+        let mut answers = "-\nMathematics\nred\n#fcdcd8\n2\n\n\nLab\n".as_bytes();
+        let chosen = run_wizard(&mut answers, &weeks, suggested, &names).unwrap();
+
+        assert_eq!(chosen.len(), 2);
+        assert_eq!(chosen[0].prefix, "MA");
+        assert_eq!(chosen[0].label.as_deref(), Some("Mathematics"));
+        assert_eq!(chosen[0].bg_color, "#fcdcd8");
+        assert_eq!(chosen[0].fg_color, "#231f20");
+        assert_eq!(chosen[0].map_id, "Maths_Rooms");
+        assert_eq!(chosen[1].label, science.label);
+        assert_eq!(chosen[1].bg_color, science.bg_color);
+        assert_eq!(chosen[1].fg_color, science.fg_color);
+        assert_eq!(chosen[1].map_id, "Lab");
+    }
+
+    #[test]
+    fn wildcard_match_handles_stars_and_question_marks() {
+        assert!(wildcard_match("*.pdf", "Alex Testington.pdf"));
+        assert!(wildcard_match("*.pdf", ".pdf"));
+        assert!(wildcard_match("year?-*.pdf", "year9-form.pdf"));
+        assert!(wildcard_match("*a*b*", "xxaxxbxx"));
+        assert!(wildcard_match("report", "report"));
+        assert!(!wildcard_match("*.pdf", "notes.txt"));
+        assert!(!wildcard_match("year?.pdf", "year10.pdf"));
+        assert!(!wildcard_match("*a*b", "xxbxxa"));
+        assert!(!wildcard_match("*.PDF", "timetable.pdf"));
+    }
+
+    #[test]
+    fn expand_inputs_reads_directories_and_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.pdf", "a.PDF", "notes.txt"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        fs::create_dir(dir.path().join("sub.pdf")).unwrap();
+
+        let files = |paths: Vec<PathBuf>| -> Vec<String> {
+            expand_inputs(&paths)
+                .unwrap()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(files(vec![dir.path().to_path_buf()]), ["a.PDF", "b.pdf"]);
+        assert_eq!(files(vec![dir.path().join("*.txt")]), ["notes.txt"]);
+        assert_eq!(
+            files(vec![dir.path().join("b.pdf"), dir.path().join("?.*")]),
+            ["b.pdf", "a.PDF", "b.pdf"]
+        );
+        // Plain paths are passed on for the parser to report
+        assert_eq!(files(vec![PathBuf::from("missing.pdf")]), ["missing.pdf"]);
+        assert!(expand_inputs(&[dir.path().join("*.doc")]).is_err());
+    }
+
+    #[test]
+    fn student_dir_never_reuses_a_directory() {
+        let weeks = |name: Option<&str>| {
+            vec![Week {
+                student_name: name.map(String::from),
+                ..Default::default()
+            }]
+        };
+        let mut used = HashSet::new();
+        let mut dir = |name: Option<&str>, input: &str| {
+            student_dir(&weeks(name), Path::new(input), &mut used)
+        };
+
+        assert_eq!(
+            dir(Some("Alex Testington"), "a/alex.pdf"),
+            "Alex Testington"
+        );
+        assert_eq!(
+            dir(Some("Alex Testington"), "b/alex2.pdf"),
+            "Alex Testington alex2"
+        );
+        assert_eq!(
+            dir(Some("Alex Testington"), "c/alex2.pdf"),
+            "Alex Testington alex2 2"
+        );
+        assert_eq!(dir(None, "a/export.pdf"), "export");
+        assert_eq!(dir(None, "b/export.pdf"), "export 2");
+        assert_eq!(dir(None, "c/export.pdf"), "export 3");
+        assert_eq!(dir(Some("O'Neil/Sam"), "sam.pdf"), "O_Neil_Sam");
+    }
+}
@@ -24,3 +24,64 @@ fn cli_runs_without_map_flag_and_prints_processing() {
             || !stderr.is_empty()
     );
 }
+
+#[test]
+#[allow(deprecated)]
+fn cli_rejects_bad_week_selections() {
+    for week in ["0", "3-1", "two", "1,,2"] {
+        let mut cmd = Command::cargo_bin("timetable_cli").expect("binary exists");
+        cmd.args(["--input", "in.pdf", "--config", "config.toml", "--output"])
+            .arg("target/test_cli_out")
+            .args(["--week", week]);
+
+        let result = cmd.output().expect("run command");
+        let stderr = String::from_utf8_lossy(&result.stderr);
+
+        assert_eq!(result.status.code(), Some(2), "--week {}", week);
+        assert!(
+            stderr.contains("invalid value"),
+            "--week {}: {}",
+            week,
+            stderr
+        );
+        assert!(!String::from_utf8_lossy(&result.stdout).contains("Processing timetable"));
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn cli_rejects_map_pages_outside_pdf() {
+    let mut cmd = Command::cargo_bin("timetable_cli").expect("binary exists");
+    cmd.args(["--input", "in.pdf", "--config", "config.toml", "--output"])
+        .arg("target/test_cli_out")
+        .args(["--map-placement", "page", "--format", "svg"]);
+
+    let result = cmd.output().expect("run command");
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert!(!result.status.success());
+    assert!(stderr.contains("--map-placement page adds map pages to PDF output only"));
+    assert!(!String::from_utf8_lossy(&result.stdout).contains("Processing timetable"));
+}
+
+#[test]
+#[allow(deprecated)]
+fn cli_rejects_combined_pdfs_in_other_formats() {
+    for flag in ["--combine", "--duplex"] {
+        let mut cmd = Command::cargo_bin("timetable_cli").expect("binary exists");
+        cmd.args(["--input", "in.pdf", "--config", "config.toml", "--output"])
+            .arg("target/test_cli_out")
+            .args([flag, "--format", "png"]);
+
+        let result = cmd.output().expect("run command");
+        let stderr = String::from_utf8_lossy(&result.stderr);
+
+        assert!(!result.status.success(), "{}", flag);
+        assert!(
+            stderr.contains(&format!("{} writes PDF output only", flag)),
+            "{}: {}",
+            flag,
+            stderr
+        );
+    }
+}
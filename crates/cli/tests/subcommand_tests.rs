@@ -0,0 +1,33 @@
+use assert_cmd::Command;
+use std::path::PathBuf;
+
+/// A file in the repository root, such as the sample timetable.
+fn repo_file(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .join(path)
+}
+
+#[test]
+#[allow(deprecated)]
+fn check_reports_problems_and_fails() {
+    let mut cmd = Command::cargo_bin("timetable_cli").expect("binary exists");
+    cmd.arg("check")
+        .arg("--input")
+        .arg(repo_file("input/Sample_Student_Timetable.pdf"))
+        .arg("--config")
+        .arg(repo_file("config.toml"))
+        .arg("--map")
+        .arg(repo_file("resources/Sample_SchoolMap.svg"))
+        .env("RUST_BACKTRACE", "0");
+
+    let result = cmd.output().expect("run command");
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+
+    assert_eq!(result.status.code(), Some(1));
+    assert!(stdout.contains("Unread room: 5 lessons have no room in the PDF"));
+    assert!(stdout.contains("Missing map_id: \"IT_Rooms\" (for IT) matches nothing in the map"));
+    assert!(!stdout.contains("No problems found"));
+    assert!(stderr.contains("4 problems found"));
+}
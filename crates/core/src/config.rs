@@ -3,9 +3,13 @@
 //! This module handles loading TOML configuration files, managing room-to-department
 //! mappings, and applying lesson overrides.
 
-use serde::Deserialize;
+use crate::processor::{HighlightStyle, MapArea, MapDocument, MapHighlight, MapLabels, MapRoute};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Errors that can occur during configuration operations.
@@ -17,6 +21,12 @@ pub enum ConfigError {
     /// TOML parsing error
     #[error("TOML parsing error: {0}")]
     Toml(#[from] toml::de::Error),
+    /// TOML serialization error
+    #[error("TOML serialization error: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    /// A value that parses but cannot be used, e.g. a malformed date format
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
 }
 
 #[cfg(test)]
@@ -57,6 +67,214 @@ mod tests {
         let cfg: Config = toml::from_str(toml).unwrap();
         let m = cfg.get_style_for_room("EN4").unwrap();
         assert_eq!(m.fg_color, "#231f20");
+        assert_eq!(Mapping::default().fg_color, m.fg_color);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_date_format() {
+        let mut cfg = Config::default();
+        assert!(cfg.validate().is_ok());
+
+        cfg.templates.date_format = "%d %Q".to_string();
+        let err = cfg.validate().unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid(_)));
+        assert!(err.to_string().contains("%d %Q"));
+    }
+
+    #[test]
+    fn test_validate_rejects_unusable_stroke_widths() {
+        let toml = r###"
+            [[mappings]]
+            prefix = "MA"
+            bg_color = "#fcdcd8"
+            map_id = "Maths_Rooms"
+            stroke_width = nan
+        "###;
+        let mut cfg: Config = toml::from_str(toml).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Invalid(_))));
+
+        cfg.mappings[0].stroke_width = Some(-1.0);
+        assert!(cfg.validate().is_err());
+        cfg.mappings[0].stroke_width = Some(0.0);
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_periods_and_long_breaks() {
+        let mut cfg = Config {
+            breaks: Some(vec![BreakRow::new("L6", "Break")]),
+            ..Config::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown period \"L6\""), "{}", err);
+
+        cfg.breaks = Some(vec![BreakRow::new("l2", "Break").duration(4_000_000_000)]);
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("4000000000 minutes"), "{}", err);
+        cfg.breaks = Some(vec![BreakRow::new("L2", "Break").duration(0)]);
+        assert!(cfg.validate().is_err());
+        cfg.breaks = Some(vec![
+            BreakRow::new("L2", "Break").duration(MAX_BREAK_MINUTES)
+        ]);
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_reminders_for_unknown_days() {
+        let mut cfg = Config {
+            reminders: vec![Reminder::new("Kit").day("Tues")],
+            ..Config::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown day \"Tues\""), "{}", err);
+
+        cfg.reminders = vec![Reminder::new("Kit").day("tue")];
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_reminders_for_unknown_periods() {
+        let mut cfg = Config {
+            reminders: vec![Reminder::new("Kit").period("P3")],
+            ..Config::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("unknown period \"P3\""), "{}", err);
+
+        cfg.reminders = vec![Reminder::new("Kit").day("Friday").period("l3")];
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_shapeless_map_areas() {
+        let toml = r#"
+            mappings = []
+
+            [[map_areas]]
+            id = "Maths_Rooms"
+
+            [[maps]]
+            path = "annexe.png"
+
+            [[maps.areas]]
+            id = "Hall"
+            points = [[200, 0], [300, 0], [250, 80]]
+        "#;
+        let mut cfg: Config = toml::from_str(toml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("map area \"Maths_Rooms\""), "{}", err);
+
+        cfg.map_areas[0] = MapArea::rect("Maths_Rooms", 10.0, 20.0, 0.0, 50.0);
+        assert!(cfg.validate().is_err());
+        cfg.map_areas[0] = MapArea::rect("Maths_Rooms", 10.0, 20.0, 100.0, 50.0);
+        assert!(cfg.validate().is_ok());
+        cfg.maps[0].areas[0].points.pop();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("map area \"Hall\""), "{}", err);
+    }
+
+    #[test]
+    fn test_map_highlights_prefer_rooms_and_list_each_fill_once() {
+        use crate::parser::{Lesson, Week};
+        use crate::processor::{MapDocument, MapLabels};
+
+        let cfg = Config::builder()
+            .mapping(Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths"))
+            .mapping(Mapping::new("DR", "#fad7e6", "Hall"))
+            .mapping(Mapping::new("PE", "#d0e8d0", "Hall"))
+            .build();
+        let lesson = |room: &str, day_index, period_index| Lesson {
+            room: room.into(),
+            day_index,
+            period_index,
+            ..Default::default()
+        };
+        let week = Week {
+            lessons: vec![
+                lesson("MA3", 0, 1),
+                lesson("MA3", 2, 3),
+                lesson("MA6", 1, 1),
+                lesson("DR1", 0, 2),
+                lesson("PE2", 1, 2),
+                lesson("XX1", 1, 3),
+            ],
+            ..Default::default()
+        };
+        let map = MapDocument::parse(
+            r#"<svg><g id="Maths_Rooms"><rect id="MA3"/><rect/></g><g id="Hall"><rect/></g></svg>"#,
+        )
+        .unwrap();
+
+        let ids = |highlights: &[MapHighlight]| -> Vec<(String, String)> {
+            highlights
+                .iter()
+                .map(|h| (h.id.clone(), h.color.clone()))
+                .collect()
+        };
+        let by_department = cfg.map_highlights([&week], None, None);
+        assert_eq!(
+            ids(&by_department),
+            [
+                ("Maths_Rooms".to_string(), "#fcdcd8".to_string()),
+                ("Hall".to_string(), "#fad7e6".to_string()),
+                ("Hall".to_string(), "#d0e8d0".to_string()),
+            ]
+        );
+        assert_eq!(by_department[1].name.as_deref(), Some("DR"));
+
+        let by_room = cfg.map_highlights([&week], Some(&map), Some(MapLabels::Periods));
+        assert_eq!(by_room[0].id, "MA3");
+        assert_eq!(by_room[0].label.as_deref(), Some("MA3\nMon L1, Wed L3"));
+        assert_eq!(by_room[1].id, "Maths_Rooms");
+        assert_eq!(by_room[1].label, None);
+        assert_eq!(
+            cfg.highlight_id(&week.lessons[2], Some(&map)),
+            Some("Maths_Rooms")
+        );
+        assert_eq!(cfg.highlight_id(&week.lessons[5], Some(&map)), None);
+
+        let codes = cfg.map_highlights([&week], Some(&map), Some(MapLabels::Codes));
+        assert_eq!(codes[0].label.as_deref(), Some("MA3"));
+    }
+
+    #[test]
+    fn test_day_route_places_rooms_and_merges_stops() {
+        use crate::parser::{Lesson, Week};
+        use crate::processor::MapDocument;
+
+        let cfg = Config::builder()
+            .mapping(Mapping::new("MA", "#fcdcd8", "Maths_Rooms"))
+            .mapping(Mapping::new("SC", "#fad7e6", "Science_Rooms"))
+            .room_position("SC8", 90.0, 10.0)
+            .build();
+        let lesson = |room: &str, day_index, period_index| Lesson {
+            room: room.into(),
+            day_index,
+            period_index,
+            ..Default::default()
+        };
+        let week = Week {
+            lessons: vec![
+                lesson("SC8", 0, 3),
+                lesson("MA3", 0, 1),
+                lesson("MA6", 0, 2),
+                lesson("XX1", 0, 4),
+                lesson("SC8", 1, 1),
+            ],
+            ..Default::default()
+        };
+        let map = MapDocument::parse(
+            r#"<svg><rect id="Maths_Rooms" x="0" y="0" width="20" height="10"/></svg>"#,
+        )
+        .unwrap();
+
+        let route = cfg.day_route(&week, 0, &map).unwrap();
+        assert_eq!(
+            route,
+            MapRoute::new(vec![(10.0, 5.0), (90.0, 10.0)])
+                .key(vec!["L1, L2 → MA3/MA6".to_string(), "L3 → SC8".to_string()])
+        );
+        assert!(cfg.day_route(&week, 4, &map).is_none());
     }
 
     #[test]
@@ -70,6 +288,7 @@ mod tests {
             class_code: "MA3".into(),
             day_index: 3,    // Thursday
             period_index: 1, // L1
+            note: None,
         }];
 
         let mut weeks = vec![Week {
@@ -77,6 +296,7 @@ mod tests {
             week_name: "Week 1".into(),
             student_name: None,
             form: None,
+            ..Default::default()
         }];
 
         let toml = r###"
@@ -87,6 +307,7 @@ mod tests {
             period = "L1"
             room = "SC6"
             teacher = "Mr Test B"
+            note = "Mock exam"
         "###;
 
         let cfg: Config = toml::from_str(toml).unwrap();
@@ -95,6 +316,473 @@ mod tests {
         let lesson = &weeks[0].lessons[0];
         assert_eq!(lesson.room, "SC6");
         assert_eq!(lesson.teacher, "Mr Test B");
+        assert_eq!(lesson.note.as_deref(), Some("Mock exam"));
+    }
+
+    #[test]
+    fn test_apply_overrides_quietly_reports_unmatched_rules() {
+        use crate::parser::{Lesson, Week};
+
+        let mut weeks = vec![Week {
+            lessons: vec![Lesson {
+                room: "Unknown".into(),
+                day_index: 0,
+                period_index: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let toml = r###"
+            mappings = []
+            [[overrides]]
+            week = 1
+            day = "Mon"
+            period = "L2"
+            room = "MA5"
+            [[overrides]]
+            week = 1
+            day = "Tue"
+            period = "L2"
+            room = "SC1"
+            [[overrides]]
+            week = 3
+            day = "Mon"
+            period = "L2"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let problems = cfg.apply_overrides_quietly(&mut weeks);
+
+        assert_eq!(weeks[0].lessons[0].room, "MA5");
+        assert_eq!(
+            problems,
+            [
+                "No lesson found for Week 1, Tue, L2",
+                "Override week 3 is out of range"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_aliases_normalises_subjects() {
+        use crate::parser::{Lesson, Week};
+
+        let lesson = |subject: &str| Lesson {
+            subject: subject.into(),
+            room: "MA3".into(),
+            teacher: "Mr A".into(),
+            class_code: "MA3".into(),
+            day_index: 0,
+            period_index: 1,
+            note: None,
+        };
+
+        let mut weeks = vec![Week {
+            lessons: vec![lesson("Ma"), lesson(" maths "), lesson("French")],
+            week_name: "Week 1".into(),
+            student_name: None,
+            form: None,
+            ..Default::default()
+        }];
+
+        let toml = r###"
+            mappings = []
+            [subject_aliases]
+            "Ma" = "Mathematics"
+            "Maths" = "Mathematics"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        cfg.apply_aliases(&mut weeks);
+
+        let subjects: Vec<&str> = weeks[0]
+            .lessons
+            .iter()
+            .map(|l| l.subject.as_str())
+            .collect();
+        assert_eq!(subjects, ["Mathematics", "Mathematics", "French"]);
+    }
+
+    #[test]
+    fn raster_map_areas_load_from_toml() {
+        let toml = r#"
+            mappings = []
+
+            [[map_areas]]
+            id = "Maths_Rooms"
+            rect = [10, 20, 100, 50]
+
+            [[maps]]
+            path = "annexe.png"
+
+            [[maps.areas]]
+            id = "Hall"
+            points = [[200, 0], [300, 0], [250, 80]]
+        "#;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(
+            cfg.map_areas,
+            [MapArea::rect("Maths_Rooms", 10.0, 20.0, 100.0, 50.0)]
+        );
+        assert_eq!(
+            cfg.maps[0].areas,
+            [MapArea::polygon(
+                "Hall",
+                vec![[200.0, 0.0], [300.0, 0.0], [250.0, 80.0]]
+            )]
+        );
+    }
+
+    #[test]
+    fn site_maps_route_rooms_by_longest_prefix() {
+        let toml = r#"
+            mappings = []
+
+            [[maps]]
+            path = "main.svg"
+
+            [[maps]]
+            path = "north.svg"
+            rooms = ["N"]
+
+            [[maps]]
+            path = "annexe.svg"
+            rooms = ["NA", "T"]
+
+            [room_aliases]
+            "OLD1" = "T1"
+        "#;
+
+        let mut cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.site_map_for_room("N12"), Some(1));
+        assert_eq!(cfg.site_map_for_room("NA2"), Some(2));
+        assert_eq!(cfg.site_map_for_room("OLD1"), Some(2));
+        assert_eq!(cfg.site_map_for_room("MA3"), Some(0));
+
+        cfg.maps.remove(0);
+        assert_eq!(cfg.site_map_for_room("MA3"), None);
+        assert!(cfg.maps[0].areas.is_empty());
+        cfg.resolve_paths(Path::new("/school"));
+        assert_eq!(cfg.maps[0].path, Path::new("/school/north.svg"));
+    }
+
+    #[test]
+    fn test_room_aliases_resolve_before_mapping_lookup() {
+        use crate::parser::{Lesson, Week};
+
+        let toml = r###"
+            [[mappings]]
+            prefix = "DT"
+            bg_color = "#b9a9bb"
+            map_id = "DT_Rooms"
+
+            [room_aliases]
+            "T14" = "DT4"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.get_style_for_room("T14").unwrap().map_id, "DT_Rooms");
+
+        let mut weeks = vec![Week {
+            lessons: vec![Lesson {
+                subject: "Design Technology".into(),
+                room: "T14".into(),
+                teacher: "Mr A".into(),
+                class_code: "T14".into(),
+                day_index: 2,
+                period_index: 3,
+                note: None,
+            }],
+            week_name: "Week 1".into(),
+            student_name: None,
+            form: None,
+            ..Default::default()
+        }];
+        cfg.apply_aliases(&mut weeks);
+
+        let lesson = &weeks[0].lessons[0];
+        assert_eq!(lesson.room, "DT4");
+        assert_eq!(lesson.class_code, "DT4");
+    }
+
+    #[test]
+    fn test_reminders_match_all_conditions() {
+        use crate::parser::Lesson;
+
+        let toml = r###"
+            [[mappings]]
+            prefix = "FT"
+            bg_color = "#b9a9bb"
+            map_id = "Arts_Rooms"
+            label = "Food Tech"
+
+            [[reminders]]
+            department = "food tech"
+            day = "Tue"
+            badge = "Ingredients"
+            color = "#e8c570"
+
+            [[reminders]]
+            subject = "Music"
+            badge = "Instrument"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let lesson = |day_index: usize| Lesson {
+            subject: "Food Technology".into(),
+            room: "FT1".into(),
+            teacher: "Ms Test A".into(),
+            class_code: String::new(),
+            day_index,
+            period_index: 4,
+            note: None,
+        };
+
+        let tuesday = cfg.reminders_for(&lesson(1));
+        assert_eq!(tuesday.len(), 1);
+        assert_eq!(tuesday[0].badge, "Ingredients");
+        assert_eq!(tuesday[0].color, "#e8c570");
+        assert!(cfg.reminders_for(&lesson(3)).is_empty());
+    }
+
+    #[test]
+    fn test_theme_defaults_fill_unspecified_fields() {
+        let toml = r###"
+            mappings = []
+            [theme]
+            font_family = "Comic Neue, sans-serif"
+            stroke_width = 2.5
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.theme.font_family, "Comic Neue, sans-serif");
+        assert_eq!(cfg.theme.stroke_width, 2.5);
+        assert_eq!(cfg.theme.stroke_color, "#231f20");
+        assert_eq!(cfg.theme.cell_padding, 3);
+    }
+
+    #[test]
+    fn test_strings_fall_back_to_english() {
+        let toml = r###"
+            mappings = []
+            [strings]
+            days = ["Dydd Llun", "Dydd Mawrth"]
+            break_label = "Egwyl"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.strings.day(1), "Dydd Mawrth");
+        assert_eq!(cfg.strings.day(4), "Friday");
+        assert_eq!(cfg.strings.break_label, "Egwyl");
+        assert_eq!(cfg.strings.month(3), "March");
+    }
+
+    #[test]
+    fn test_strings_locale_preset_with_overrides() {
+        let toml = r###"
+            mappings = []
+            [strings]
+            locale = "cy"
+            periods = ["Cof", "1", "2", "3", "4", "5"]
+            lunch_label = "Cinio"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.strings.day(0), "Dydd Llun");
+        assert_eq!(cfg.strings.month(1), "Ionawr");
+        assert_eq!(cfg.strings.period(0), "Cof");
+        assert_eq!(cfg.strings.lunch_label, "Cinio");
+        assert_eq!(cfg.strings.break_label, "Egwyl (11:00 - 11:30)");
+
+        let err = toml::from_str::<Config>("mappings = []\n[strings]\nlocale = \"xx\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown locale 'xx'"));
+    }
+
+    #[test]
+    fn test_break_rows_default_to_localised_break_and_lunch() {
+        let toml = r###"
+            mappings = []
+            [strings]
+            locale = "cy"
+        "###;
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let rows = cfg.break_rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            (rows[0].0, rows[0].1.label.as_str()),
+            (2, "Egwyl (11:00 - 11:30)")
+        );
+        assert_eq!(rows[1].0, 4);
+
+        let toml = r###"
+            mappings = []
+            [[breaks]]
+            after = "L4"
+            label = "Lunch"
+            duration = 45
+
+            [[breaks]]
+            after = "l1"
+            label = "Break"
+        "###;
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let rows = cfg.break_rows();
+        assert_eq!((rows[0].0, rows[0].1.label.as_str()), (1, "Break"));
+        assert_eq!(rows[1].1.duration, Some(45));
+
+        let cfg: Config = toml::from_str("mappings = []\nbreaks = []").unwrap();
+        assert!(cfg.break_rows().is_empty());
+    }
+
+    #[test]
+    fn test_apply_calendar_uses_next_unfinished_week() {
+        use crate::parser::Week;
+
+        let toml = r###"
+            mappings = []
+
+            [[calendar]]
+            starts = "2025-01-06"
+            week = "Week 1"
+
+            [[calendar]]
+            starts = "2025-01-13"
+            week = "Week 2"
+
+            [[calendar]]
+            starts = "2025-01-20"
+            week = "Week 1"
+        "###;
+
+        let cfg: Config = toml::from_str(toml).unwrap();
+        let mut weeks = vec![
+            Week {
+                week_name: "Week 1".into(),
+                ..Default::default()
+            },
+            Week {
+                week_name: "week 2".into(),
+                ..Default::default()
+            },
+        ];
+        let date = |d| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+
+        // Friday 10th is the last day of the first Week 1
+        cfg.apply_calendar(&mut weeks, date(11));
+
+        assert_eq!(weeks[0].starts_on, Some(date(20)));
+        assert_eq!(weeks[1].starts_on, Some(date(13)));
+        assert_eq!(cfg.strings.week_range(date(13)), "13–17 January");
+        assert_eq!(
+            cfg.strings
+                .week_range(NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()),
+            "31 March – 4 April"
+        );
+    }
+
+    #[test]
+    fn test_builder_config_round_trips_through_toml() {
+        let cfg = Config::builder()
+            .mapping(
+                Mapping::new("MA", "#fcdcd8", "Maths_Rooms")
+                    .fg_color("#f0503f")
+                    .label("Maths")
+                    .stroke("#231f20")
+                    .stroke_width(2.5),
+            )
+            .override_rule(Override::new(1, "Monday", "L2").room("SC3"))
+            .room_alias("T14", "DT4")
+            .reminder(Reminder::new("Kit").subject("PE"))
+            .logo(Logo::new("crest.png"))
+            .build();
+
+        let toml = cfg.to_toml_string().unwrap();
+        let loaded: Config = toml::from_str(&toml).unwrap();
+
+        assert_eq!(loaded.mappings[0].fg_color, "#f0503f");
+        assert_eq!(loaded.mappings[0].label.as_deref(), Some("Maths"));
+        assert_eq!(loaded.mappings[0].stroke.as_deref(), Some("#231f20"));
+        assert_eq!(loaded.mappings[0].stroke_width, Some(2.5));
+        assert_eq!(loaded.overrides[0].room.as_deref(), Some("SC3"));
+        assert_eq!(loaded.resolve_room("T14"), "DT4");
+        assert_eq!(loaded.reminders[0].subject.as_deref(), Some("PE"));
+        assert_eq!(loaded.logo.unwrap().size, 48);
+        assert_eq!(loaded.theme.stroke_color, cfg.theme.stroke_color);
+    }
+
+    #[test]
+    fn test_starter_config_groups_rooms_by_prefix() {
+        use crate::parser::{Lesson, Week};
+
+        let lesson = |subject: &str, room: &str, day_index| Lesson {
+            subject: subject.into(),
+            room: room.into(),
+            day_index,
+            ..Default::default()
+        };
+        let weeks = [Week {
+            lessons: vec![
+                lesson("Physics", "SC2", 0),
+                lesson("Chemistry", "SC7", 1),
+                lesson("Chemistry", "SC2", 2),
+                lesson("Mathematics", "MA3", 3),
+                lesson("PE", "Unknown", 4),
+            ],
+            ..Default::default()
+        }];
+
+        let toml = starter_config(&weeks);
+        let cfg: Config = toml::from_str(&toml).unwrap();
+
+        let prefixes: Vec<&str> = cfg.mappings.iter().map(|m| m.prefix.as_str()).collect();
+        assert_eq!(prefixes, ["MA", "SC"]);
+        assert_eq!(cfg.mappings[1].label.as_deref(), Some("Chemistry"));
+        assert_eq!(cfg.mappings[1].map_id, "Chemistry_Rooms");
+        assert_ne!(cfg.mappings[0].bg_color, cfg.mappings[1].bg_color);
+        assert!(toml.contains("# Rooms (lessons): SC2 (2), SC7 (1)"));
+        assert!(toml.contains("# day = \"Friday\"\n# period = \"PD\""));
+        assert!(cfg.overrides.is_empty());
+    }
+
+    #[test]
+    fn test_icon_for_lesson_prefers_subject_over_department() {
+        use crate::parser::Lesson;
+
+        let toml = r###"
+            icon_set = "icons.svg"
+
+            [[mappings]]
+            prefix = "HU"
+            bg_color = "#faeed2"
+            map_id = "Humanities_Rooms"
+            icon = "globe"
+
+            [subject_icons]
+            "history" = "scroll"
+        "###;
+
+        let mut cfg: Config = toml::from_str(toml).unwrap();
+        let lesson = |subject: &str| Lesson {
+            subject: subject.into(),
+            room: "HU4".into(),
+            teacher: "Ms Test A".into(),
+            class_code: String::new(),
+            day_index: 0,
+            period_index: 2,
+            note: None,
+        };
+
+        assert_eq!(cfg.icon_for_lesson(&lesson("History")), Some("scroll"));
+        assert_eq!(cfg.icon_for_lesson(&lesson("Geography")), Some("globe"));
+
+        cfg.resolve_paths(Path::new("/etc/timetable"));
+        assert_eq!(
+            cfg.icon_set.as_deref(),
+            Some(Path::new("/etc/timetable/icons.svg"))
+        );
     }
 }
 
@@ -102,20 +790,71 @@ mod tests {
 ///
 /// Loaded from a TOML file containing room-to-department mappings and
 /// optional per-lesson overrides.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Room-to-department mapping rules
     pub mappings: Vec<Mapping>,
     /// Per-week/day/period lesson overrides
     #[serde(default)]
     pub overrides: Vec<Override>,
+    /// Subject name aliases (parsed name -> canonical name), matched case-insensitively
+    #[serde(default)]
+    pub subject_aliases: BTreeMap<String, String>,
+    /// Room code aliases (legacy code -> current code), matched case-insensitively
+    #[serde(default)]
+    pub room_aliases: BTreeMap<String, String>,
+    /// Map coordinates of rooms (room code -> [x, y]) for routes, overriding
+    /// the centres of map elements
+    #[serde(default)]
+    pub room_positions: BTreeMap<String, [f64; 2]>,
+    /// Icon identifiers per subject (subject name -> icon id), matched case-insensitively
+    #[serde(default)]
+    pub subject_icons: BTreeMap<String, String>,
+    /// SVG file of `<symbol>` elements providing user-defined icons (relative to the config file)
+    pub icon_set: Option<PathBuf>,
+    /// Reminder rules producing badges on matching lessons (e.g., "Kit" for PE)
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    /// Break and lunch rows between periods; unset gives a break after L2 and
+    /// lunch after L4, an empty list gives none
+    pub breaks: Option<Vec<BreakRow>>,
+    /// Renderer styling (fonts, sizes, colors, paddings)
+    #[serde(default)]
+    pub theme: Theme,
+    /// Header, title and footer text templates
+    #[serde(default)]
+    pub templates: Templates,
+    /// School logo drawn in the sheet header (optional)
+    pub logo: Option<Logo>,
+    /// Faint image or pattern filling the page behind the timetable (optional)
+    pub background: Option<Background>,
+    /// Font files embedded in the SVG so it renders the same everywhere
+    #[serde(default)]
+    pub fonts: Vec<FontFace>,
+    /// Rendered text for localised sheets
+    #[serde(default)]
+    pub strings: Strings,
+    /// How teacher names appear on the sheet, for documents that leave the school
+    #[serde(default)]
+    pub teacher_names: TeacherNames,
+    /// Term calendar giving the dates of each teaching week
+    #[serde(default)]
+    pub calendar: Vec<TermWeek>,
+    /// Separate maps for the sites of a multi-site school, each showing the
+    /// rooms with its prefixes; `--map` replaces them with a single map
+    #[serde(default)]
+    pub maps: Vec<SiteMap>,
+    /// Areas marked on a PNG or JPEG `--map`, which has no elements of its own
+    /// to highlight
+    #[serde(default)]
+    pub map_areas: Vec<MapArea>,
 }
 
 /// Maps a room code prefix to visual styling and map element.
 ///
 /// Used to color-code timetable cells and highlight map regions
 /// based on the room where a lesson takes place.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mapping {
     /// Room code prefix to match (e.g., "MA" matches MA1, MA2, MA3, etc.)
     pub prefix: String,
@@ -129,32 +868,1639 @@ pub struct Mapping {
     pub map_id: String,
     /// Human-readable department label (e.g., "Maths", "Science")
     pub label: Option<String>,
+    /// Icon identifier drawn beside subjects taught in this department (e.g., "flask")
+    pub icon: Option<String>,
+    /// How the department's rooms are marked on the map
+    #[serde(default)]
+    pub highlight: HighlightStyle,
+    /// Colour of a border drawn around the department's rooms on the map
+    pub stroke: Option<String>,
+    /// Width of that border in map units
+    pub stroke_width: Option<f64>,
+}
+
+impl Default for Mapping {
+    /// An empty mapping with the same foreground color as one read from a
+    /// config file that leaves `fg_color` out.
+    fn default() -> Self {
+        Self::new("", "", "")
+    }
+}
+
+impl Mapping {
+    /// Create a mapping for rooms starting with `prefix`.
+    ///
+    /// The foreground color defaults to "#231f20"; use the chained setters
+    /// for the optional fields.
+    pub fn new(
+        prefix: impl Into<String>,
+        bg_color: impl Into<String>,
+        map_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            prefix: prefix.into(),
+            bg_color: bg_color.into(),
+            fg_color: default_fg_color(),
+            map_id: map_id.into(),
+            label: None,
+            icon: None,
+            highlight: HighlightStyle::default(),
+            stroke: None,
+            stroke_width: None,
+        }
+    }
+
+    /// Set the foreground/text color.
+    pub fn fg_color(mut self, fg_color: impl Into<String>) -> Self {
+        self.fg_color = fg_color.into();
+        self
+    }
+
+    /// Set the department label.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the department icon.
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    /// Set how the department's rooms are marked on the map.
+    pub fn highlight(mut self, highlight: HighlightStyle) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    /// Set the colour of the border around the department's rooms on the map.
+    pub fn stroke(mut self, stroke: impl Into<String>) -> Self {
+        self.stroke = Some(stroke.into());
+        self
+    }
+
+    /// Set the width of the border around the department's rooms on the map.
+    pub fn stroke_width(mut self, stroke_width: f64) -> Self {
+        self.stroke_width = Some(stroke_width);
+        self
+    }
+}
+
+/// Override for a specific lesson in the timetable.
+///
+/// Allows correcting parsing errors or making manual adjustments
+/// to specific lessons by week, day, and period.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Override {
+    /// Week number (1-based, e.g., 1 = Week 1, 2 = Week 2)
+    pub week: usize,
+    /// Day name ("Monday", "Tuesday", etc. or abbreviated "Mon", "Tue")
+    pub day: String,
+    /// Period identifier ("PD", "L1", "L2", "L3", "L4", "L5")
+    pub period: String,
+    /// Override subject name (optional)
+    pub subject: Option<String>,
+    /// Override room code (optional)
+    pub room: Option<String>,
+    /// Override teacher name (optional)
+    pub teacher: Option<String>,
+    /// Override class code (optional)
+    pub class_code: Option<String>,
+    /// Note printed in the lesson's cell (optional)
+    pub note: Option<String>,
+}
+
+impl Override {
+    /// Create an override targeting a week (1-based), day and period.
+    ///
+    /// No fields are changed until one of the chained setters is used.
+    pub fn new(week: usize, day: impl Into<String>, period: impl Into<String>) -> Self {
+        Self {
+            week,
+            day: day.into(),
+            period: period.into(),
+            subject: None,
+            room: None,
+            teacher: None,
+            class_code: None,
+            note: None,
+        }
+    }
+
+    /// Replace the lesson's subject.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Replace the lesson's room code.
+    pub fn room(mut self, room: impl Into<String>) -> Self {
+        self.room = Some(room.into());
+        self
+    }
+
+    /// Replace the lesson's teacher.
+    pub fn teacher(mut self, teacher: impl Into<String>) -> Self {
+        self.teacher = Some(teacher.into());
+        self
+    }
+
+    /// Replace the lesson's class code.
+    pub fn class_code(mut self, class_code: impl Into<String>) -> Self {
+        self.class_code = Some(class_code.into());
+        self
+    }
+
+    /// Add a note to the lesson's cell.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// Builder for [`Config`], created with [`Config::builder`].
+#[derive(Debug, Default, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Add a room-to-department mapping.
+    pub fn mapping(mut self, mapping: Mapping) -> Self {
+        self.config.mappings.push(mapping);
+        self
+    }
+
+    /// Add a lesson override.
+    pub fn override_rule(mut self, override_rule: Override) -> Self {
+        self.config.overrides.push(override_rule);
+        self
+    }
+
+    /// Add a subject alias.
+    pub fn subject_alias(mut self, alias: impl Into<String>, canonical: impl Into<String>) -> Self {
+        self.config
+            .subject_aliases
+            .insert(alias.into(), canonical.into());
+        self
+    }
+
+    /// Add a room alias.
+    pub fn room_alias(mut self, alias: impl Into<String>, current: impl Into<String>) -> Self {
+        self.config
+            .room_aliases
+            .insert(alias.into(), current.into());
+        self
+    }
+
+    /// Add a site map showing the rooms with the given prefixes.
+    pub fn site_map(mut self, path: impl Into<PathBuf>, rooms: &[&str]) -> Self {
+        self.config.maps.push(SiteMap {
+            path: path.into(),
+            rooms: rooms.iter().map(|room| room.to_string()).collect(),
+            areas: Vec::new(),
+        });
+        self
+    }
+
+    /// Mark an area on a PNG or JPEG `--map`.
+    pub fn map_area(mut self, area: MapArea) -> Self {
+        self.config.map_areas.push(area);
+        self
+    }
+
+    /// Place a room on the map at `(x, y)` for routes.
+    pub fn room_position(mut self, room: impl Into<String>, x: f64, y: f64) -> Self {
+        self.config.room_positions.insert(room.into(), [x, y]);
+        self
+    }
+
+    /// Assign an icon to a subject.
+    pub fn subject_icon(mut self, subject: impl Into<String>, icon: impl Into<String>) -> Self {
+        self.config
+            .subject_icons
+            .insert(subject.into(), icon.into());
+        self
+    }
+
+    /// Set the user-provided icon symbol file.
+    pub fn icon_set(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.icon_set = Some(path.into());
+        self
+    }
+
+    /// Add a reminder rule.
+    pub fn reminder(mut self, reminder: Reminder) -> Self {
+        self.config.reminders.push(reminder);
+        self
+    }
+
+    /// Add a break or lunch row, replacing the default break and lunch.
+    pub fn break_row(mut self, row: BreakRow) -> Self {
+        self.config.breaks.get_or_insert_with(Vec::new).push(row);
+        self
+    }
+
+    /// Set the renderer theme.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.config.theme = theme;
+        self
+    }
+
+    /// Set the header/footer templates.
+    pub fn templates(mut self, templates: Templates) -> Self {
+        self.config.templates = templates;
+        self
+    }
+
+    /// Set the school logo.
+    pub fn logo(mut self, logo: Logo) -> Self {
+        self.config.logo = Some(logo);
+        self
+    }
+
+    /// Set the page background image.
+    pub fn background(mut self, background: Background) -> Self {
+        self.config.background = Some(background);
+        self
+    }
+
+    /// Embed a font file in the rendered SVG.
+    pub fn font_face(mut self, font: FontFace) -> Self {
+        self.config.fonts.push(font);
+        self
+    }
+
+    /// Set the localised strings.
+    pub fn strings(mut self, strings: Strings) -> Self {
+        self.config.strings = strings;
+        self
+    }
+
+    /// Set how teacher names appear on the sheet.
+    pub fn teacher_names(mut self, teacher_names: TeacherNames) -> Self {
+        self.config.teacher_names = teacher_names;
+        self
+    }
+
+    /// Add a week to the term calendar.
+    pub fn term_week(mut self, term_week: TermWeek) -> Self {
+        self.config.calendar.push(term_week);
+        self
+    }
+
+    /// Finish building the configuration.
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+/// Visual styling applied by the renderer.
+///
+/// Every field is optional in the `[theme]` table; omitted fields keep the
+/// default look (Bahnschrift fonts, dark grey text and strokes on white).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Theme {
+    /// Font stack for regular text (subjects, headers)
+    pub font_family: String,
+    /// Font stack for light text (rooms, teachers, break rows)
+    pub light_font_family: String,
+    /// Font stack for emphasised text (week title, period labels, class codes)
+    pub bold_font_family: String,
+    /// Font size for the student name and day headers (px)
+    pub header_font_size: f64,
+    /// Font size for the week title (px)
+    pub week_font_size: f64,
+    /// Font size for period labels (px)
+    pub period_font_size: f64,
+    /// Font size for subject names (px)
+    pub subject_font_size: f64,
+    /// Font size for rooms, break rows and footer (px)
+    pub detail_font_size: f64,
+    /// Font size for teacher names (px)
+    pub teacher_font_size: f64,
+    /// Font size for the vertical class code label (px)
+    pub class_code_font_size: f64,
+    /// Width of the coloured class code strip (its height when at the top) (px)
+    pub label_width: i32,
+    /// Side of the cell the class code strip is drawn on
+    pub label_position: LabelPosition,
+    /// Reading direction of the class code in a side strip
+    pub label_rotation: LabelRotation,
+    /// Stack the room in small text beneath the class code in the strip,
+    /// for lessons that have both
+    pub label_room: bool,
+    /// Text color (hex code)
+    pub text_color: String,
+    /// Page background color (hex code)
+    pub page_color: String,
+    /// Lesson cell background color (hex code)
+    pub cell_color: String,
+    /// Strength of the department colour tint over the whole lesson cell,
+    /// from 0 (plain `cell_color`) to 1 (full department colour)
+    pub cell_tint: f64,
+    /// Break and lunch row background color (hex code)
+    pub break_color: String,
+    /// Label strip background for lessons with an unknown room (hex code)
+    pub unknown_bg_color: String,
+    /// Label text color for lessons with an unknown room (hex code)
+    pub unknown_fg_color: String,
+    /// Border color for cells and rows (hex code)
+    pub stroke_color: String,
+    /// Tint over the highlighted day column (hex code)
+    pub highlight_color: String,
+    /// Alternate rows or columns to shade, to help the eye track across the grid
+    pub zebra: Zebra,
+    /// Background of the shaded rows or columns (hex code)
+    pub zebra_color: String,
+    /// Fill class code strips with black hatch patterns instead of department
+    /// colours, for e-ink displays and mono printers
+    pub patterns: bool,
+    /// Border width for cells and rows (px)
+    pub stroke_width: f64,
+    /// Corner radius of cells and rows, for rounded cards (px)
+    pub corner_radius: f64,
+    /// Gap between neighbouring cells (px)
+    pub cell_padding: i32,
+    /// Inset of text from the left edge of a cell (px)
+    pub text_padding: i32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            font_family: "Bahnschrift, Arial, sans-serif".to_string(),
+            light_font_family: "'Bahnschrift Light', Bahnschrift, Arial, sans-serif".to_string(),
+            bold_font_family: "'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif".to_string(),
+            header_font_size: 14.0,
+            week_font_size: 16.0,
+            period_font_size: 12.0,
+            subject_font_size: 11.0,
+            detail_font_size: 11.0,
+            teacher_font_size: 9.0,
+            class_code_font_size: 20.0,
+            label_width: 30,
+            label_position: LabelPosition::Right,
+            label_rotation: LabelRotation::Clockwise,
+            label_room: false,
+            text_color: "#231f20".to_string(),
+            page_color: "#ffffff".to_string(),
+            cell_color: "#ffffff".to_string(),
+            cell_tint: 0.0,
+            break_color: "#eeeeee".to_string(),
+            unknown_bg_color: "#e0e0e0".to_string(),
+            unknown_fg_color: "#4a4a4a".to_string(),
+            stroke_color: "#231f20".to_string(),
+            highlight_color: "#ffd84d".to_string(),
+            zebra: Zebra::None,
+            zebra_color: "#f2f2f2".to_string(),
+            patterns: false,
+            stroke_width: 1.0,
+            corner_radius: 0.0,
+            cell_padding: 3,
+            text_padding: 5,
+        }
+    }
+}
+
+impl Theme {
+    /// Return a copy of the theme with every font size multiplied by `factor`.
+    ///
+    /// Used by compact layouts whose pages are printed much smaller than A4.
+    pub fn with_font_scale(&self, factor: f64) -> Theme {
+        Theme {
+            header_font_size: self.header_font_size * factor,
+            week_font_size: self.week_font_size * factor,
+            period_font_size: self.period_font_size * factor,
+            subject_font_size: self.subject_font_size * factor,
+            detail_font_size: self.detail_font_size * factor,
+            teacher_font_size: self.teacher_font_size * factor,
+            class_code_font_size: self.class_code_font_size * factor,
+            ..self.clone()
+        }
+    }
+
+    /// Return a copy of the theme with a dark background and light text.
+    ///
+    /// Fonts and sizes are kept; department colours come from the mappings and
+    /// are unaffected. Intended for phone lock screens.
+    pub fn with_dark_colors(&self) -> Theme {
+        Theme {
+            text_color: "#f5f5f5".to_string(),
+            page_color: "#121212".to_string(),
+            cell_color: "#1e1e1e".to_string(),
+            break_color: "#2a2a2a".to_string(),
+            unknown_bg_color: "#333333".to_string(),
+            unknown_fg_color: "#cccccc".to_string(),
+            stroke_color: "#3a3a3a".to_string(),
+            highlight_color: "#8a7a3a".to_string(),
+            zebra_color: "#262626".to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Return a copy of the theme in black and white, with departments told
+    /// apart by hatch patterns instead of colours.
+    ///
+    /// Pastel department colours all print as the same grey on mono laser
+    /// printers and e-ink displays; patterns stay distinct on both.
+    pub fn with_mono_colors(&self) -> Theme {
+        Theme {
+            text_color: "#000000".to_string(),
+            page_color: "#ffffff".to_string(),
+            cell_color: "#ffffff".to_string(),
+            cell_tint: 0.0,
+            break_color: "#ffffff".to_string(),
+            unknown_bg_color: "#000000".to_string(),
+            unknown_fg_color: "#ffffff".to_string(),
+            stroke_color: "#000000".to_string(),
+            highlight_color: "#b3b3b3".to_string(),
+            zebra_color: "#e6e6e6".to_string(),
+            patterns: true,
+            ..self.clone()
+        }
+    }
+}
+
+/// Side of a lesson cell carrying the coloured class code strip.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelPosition {
+    /// Left edge, for left-handed layouts
+    Left,
+    /// Right edge
+    #[default]
+    Right,
+    /// Across the top, with horizontal text, for narrow columns
+    Top,
+}
+
+/// Reading direction of the class code in a left or right strip.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelRotation {
+    /// Reads top to bottom
+    #[default]
+    Clockwise,
+    /// Reads bottom to top
+    Counterclockwise,
+}
+
+/// Alternate shading of the timetable grid.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Zebra {
+    /// No shading
+    #[default]
+    None,
+    /// Shade every other period row
+    Rows,
+    /// Shade every other day column
+    Columns,
+}
+
+impl Zebra {
+    /// Whether the slot at `day_index` and `period_index` falls in a shaded row or column.
+    pub fn shades(self, day_index: usize, period_index: usize) -> bool {
+        match self {
+            Zebra::None => false,
+            Zebra::Rows => period_index % 2 == 1,
+            Zebra::Columns => day_index % 2 == 1,
+        }
+    }
+}
+
+/// Text templates for the sheet header and footer.
+///
+/// Templates may contain `{placeholder}` variables which the renderer replaces:
+/// `{student}` (name and form, or a generic title), `{name}`, `{form}`,
+/// `{year}` (year group), `{house}`, `{details}` (year group and house,
+/// whichever are known, separated by " · "), `{department}` (the department
+/// with the most lessons), `{week}` (week number), `{week_name}`, `{dates}` (the week's date range
+/// from the term calendar), `{date}`, `{time}`, `{source}`
+/// (the PDF file name) and `{updated}` (the localised "Updated" word). An
+/// empty template omits that text entirely.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Templates {
+    /// Header text drawn at the top left (defaults to "{student}")
+    pub header: String,
+    /// Smaller line drawn under the header (defaults to "{details}")
+    pub subheader: String,
+    /// Title drawn at the top centre (defaults to "{week_name}")
+    pub title: String,
+    /// Footer text drawn at the bottom right (defaults to "{updated}: {date}")
+    pub footer: String,
+    /// `strftime`-style format used for `{date}` (defaults to "%d %B %Y")
+    pub date_format: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            header: "{student}".to_string(),
+            subheader: "{details}".to_string(),
+            title: "{week_name}".to_string(),
+            footer: "{updated}: {date}".to_string(),
+            date_format: "%d %B %Y".to_string(),
+        }
+    }
+}
+
+/// Words and labels drawn on the sheet, for schools that teach in other languages.
+///
+/// `locale` picks a built-in translation (`en`, `cy`, `fr`, `de` or `es`) and
+/// every other field in the `[strings]` table overrides a single word of it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "StringsTable")]
+pub struct Strings {
+    /// Built-in translation the other fields start from
+    pub locale: String,
+    /// Header shown when no student name is known
+    pub student_timetable: String,
+    /// Day column headers, Monday to Friday
+    pub days: Vec<String>,
+    /// Period row labels, PD to L5
+    pub periods: Vec<String>,
+    /// Month names, January to December, used for `%B` in dates
+    pub months: Vec<String>,
+    /// Short month names, January to December, used for `%b` in dates
+    pub short_months: Vec<String>,
+    /// Weekday names, Monday to Sunday, used for `%A` in dates
+    pub weekdays: Vec<String>,
+    /// Short weekday names, Monday to Sunday, used for `%a` in dates
+    pub short_weekdays: Vec<String>,
+    /// Text of the break row
+    pub break_label: String,
+    /// Text of the lunch row
+    pub lunch_label: String,
+    /// Word used for `{updated}` in the footer template
+    pub updated: String,
+    /// Label of empty slots when free periods are drawn
+    pub free_period: String,
+    /// Added to a lesson's spoken description when it has a room, e.g. " in {room}"
+    pub lesson_room: String,
+    /// Added to a lesson's spoken description when it has a teacher, e.g. " with {teacher}"
+    pub lesson_teacher: String,
+    /// Added to a lesson's spoken description when it has a note, e.g. ". {note}"
+    pub lesson_note: String,
+    /// Added to a lesson's spoken description when reminders apply, e.g. " ({reminders})"
+    pub lesson_reminders: String,
+    /// Accessible name of the school map
+    pub school_map: String,
+    /// Spoken description of a grid of `{count}` lessons
+    pub sheet_description: String,
+    /// Spoken description of a grid holding a single lesson
+    pub sheet_description_one: String,
+    /// HTML tooltip line naming a lesson's teacher, e.g. "Teacher: {teacher}"
+    pub tooltip_teacher: String,
+    /// HTML tooltip line naming a lesson's class, e.g. "Class: {class}"
+    pub tooltip_class: String,
+}
+
+/// The `[strings]` table as written, before it is layered over its locale.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StringsTable {
+    locale: Option<String>,
+    student_timetable: Option<String>,
+    days: Option<Vec<String>>,
+    periods: Option<Vec<String>>,
+    months: Option<Vec<String>>,
+    short_months: Option<Vec<String>>,
+    weekdays: Option<Vec<String>>,
+    short_weekdays: Option<Vec<String>>,
+    break_label: Option<String>,
+    lunch_label: Option<String>,
+    updated: Option<String>,
+    free_period: Option<String>,
+    lesson_room: Option<String>,
+    lesson_teacher: Option<String>,
+    lesson_note: Option<String>,
+    lesson_reminders: Option<String>,
+    school_map: Option<String>,
+    sheet_description: Option<String>,
+    sheet_description_one: Option<String>,
+    tooltip_teacher: Option<String>,
+    tooltip_class: Option<String>,
+}
+
+impl TryFrom<StringsTable> for Strings {
+    type Error = String;
+
+    fn try_from(table: StringsTable) -> Result<Self, Self::Error> {
+        let locale = table.locale.as_deref().unwrap_or("en");
+        let base = Strings::for_locale(locale).ok_or_else(|| {
+            format!(
+                "unknown locale '{}', expected one of: {}",
+                locale,
+                LOCALES.join(", ")
+            )
+        })?;
+        Ok(Self {
+            locale: base.locale,
+            student_timetable: table.student_timetable.unwrap_or(base.student_timetable),
+            days: table.days.unwrap_or(base.days),
+            periods: table.periods.unwrap_or(base.periods),
+            months: table.months.unwrap_or(base.months),
+            short_months: table.short_months.unwrap_or(base.short_months),
+            weekdays: table.weekdays.unwrap_or(base.weekdays),
+            short_weekdays: table.short_weekdays.unwrap_or(base.short_weekdays),
+            break_label: table.break_label.unwrap_or(base.break_label),
+            lunch_label: table.lunch_label.unwrap_or(base.lunch_label),
+            updated: table.updated.unwrap_or(base.updated),
+            free_period: table.free_period.unwrap_or(base.free_period),
+            lesson_room: table.lesson_room.unwrap_or(base.lesson_room),
+            lesson_teacher: table.lesson_teacher.unwrap_or(base.lesson_teacher),
+            lesson_note: table.lesson_note.unwrap_or(base.lesson_note),
+            lesson_reminders: table.lesson_reminders.unwrap_or(base.lesson_reminders),
+            school_map: table.school_map.unwrap_or(base.school_map),
+            sheet_description: table.sheet_description.unwrap_or(base.sheet_description),
+            sheet_description_one: table
+                .sheet_description_one
+                .unwrap_or(base.sheet_description_one),
+            tooltip_teacher: table.tooltip_teacher.unwrap_or(base.tooltip_teacher),
+            tooltip_class: table.tooltip_class.unwrap_or(base.tooltip_class),
+        })
+    }
+}
+
+/// Locales with a built-in translation.
+pub const LOCALES: [&str; 5] = ["en", "cy", "fr", "de", "es"];
+
+impl Default for Strings {
+    fn default() -> Self {
+        Self::for_locale("en").expect("English strings are built in")
+    }
+}
+
+impl Strings {
+    /// Built-in translation for a locale code such as `"cy"`, or `None` if
+    /// the locale is not one of [`LOCALES`].
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        let (student_timetable, days, months, break_label, lunch_label, updated, free_period) =
+            match locale.to_ascii_lowercase().as_str() {
+                "en" => (
+                    "Student Timetable",
+                    ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"],
+                    [
+                        "January",
+                        "February",
+                        "March",
+                        "April",
+                        "May",
+                        "June",
+                        "July",
+                        "August",
+                        "September",
+                        "October",
+                        "November",
+                        "December",
+                    ],
+                    "Break (11:00 - 11:30)",
+                    "Lunch (13:30 - 14:10)",
+                    "Updated",
+                    "Free / Study",
+                ),
+                "cy" => (
+                    "Amserlen Disgybl",
+                    [
+                        "Dydd Llun",
+                        "Dydd Mawrth",
+                        "Dydd Mercher",
+                        "Dydd Iau",
+                        "Dydd Gwener",
+                    ],
+                    [
+                        "Ionawr",
+                        "Chwefror",
+                        "Mawrth",
+                        "Ebrill",
+                        "Mai",
+                        "Mehefin",
+                        "Gorffennaf",
+                        "Awst",
+                        "Medi",
+                        "Hydref",
+                        "Tachwedd",
+                        "Rhagfyr",
+                    ],
+                    "Egwyl (11:00 - 11:30)",
+                    "Cinio (13:30 - 14:10)",
+                    "Diweddarwyd",
+                    "Rhydd / Astudio",
+                ),
+                "fr" => (
+                    "Emploi du temps",
+                    ["Lundi", "Mardi", "Mercredi", "Jeudi", "Vendredi"],
+                    [
+                        "janvier",
+                        "février",
+                        "mars",
+                        "avril",
+                        "mai",
+                        "juin",
+                        "juillet",
+                        "août",
+                        "septembre",
+                        "octobre",
+                        "novembre",
+                        "décembre",
+                    ],
+                    "Récréation (11:00 - 11:30)",
+                    "Déjeuner (13:30 - 14:10)",
+                    "Mis à jour",
+                    "Libre / Étude",
+                ),
+                "de" => (
+                    "Stundenplan",
+                    ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag"],
+                    [
+                        "Januar",
+                        "Februar",
+                        "März",
+                        "April",
+                        "Mai",
+                        "Juni",
+                        "Juli",
+                        "August",
+                        "September",
+                        "Oktober",
+                        "November",
+                        "Dezember",
+                    ],
+                    "Pause (11:00 - 11:30)",
+                    "Mittagspause (13:30 - 14:10)",
+                    "Aktualisiert",
+                    "Frei / Lernzeit",
+                ),
+                "es" => (
+                    "Horario del alumno",
+                    ["Lunes", "Martes", "Miércoles", "Jueves", "Viernes"],
+                    [
+                        "enero",
+                        "febrero",
+                        "marzo",
+                        "abril",
+                        "mayo",
+                        "junio",
+                        "julio",
+                        "agosto",
+                        "septiembre",
+                        "octubre",
+                        "noviembre",
+                        "diciembre",
+                    ],
+                    "Recreo (11:00 - 11:30)",
+                    "Almuerzo (13:30 - 14:10)",
+                    "Actualizado",
+                    "Libre / Estudio",
+                ),
+                _ => return None,
+            };
+        let (
+            lesson_room,
+            lesson_teacher,
+            lesson_note,
+            lesson_reminders,
+            school_map,
+            sheet_description,
+            sheet_description_one,
+            tooltip_teacher,
+            tooltip_class,
+        ) = match locale.to_ascii_lowercase().as_str() {
+            "cy" => (
+                " yn {room}",
+                " gyda {teacher}",
+                ". {note}",
+                " ({reminders})",
+                "Map yr ysgol",
+                "{count} gwers, pob un yn cael ei darllen fel diwrnod, cyfnod, pwnc, ystafell ac athro",
+                "1 wers, yn cael ei darllen fel diwrnod, cyfnod, pwnc, ystafell ac athro",
+                "Athro: {teacher}",
+                "Dosbarth: {class}",
+            ),
+            "fr" => (
+                " en salle {room}",
+                " avec {teacher}",
+                ". {note}",
+                " ({reminders})",
+                "Plan de l'établissement",
+                "{count} cours, chacun lu comme jour, période, matière, salle et professeur",
+                "1 cours, lu comme jour, période, matière, salle et professeur",
+                "Professeur : {teacher}",
+                "Classe : {class}",
+            ),
+            "de" => (
+                " in Raum {room}",
+                " bei {teacher}",
+                ". {note}",
+                " ({reminders})",
+                "Schulplan",
+                "{count} Stunden, jeweils vorgelesen als Tag, Stunde, Fach, Raum und Lehrkraft",
+                "1 Stunde, vorgelesen als Tag, Stunde, Fach, Raum und Lehrkraft",
+                "Lehrkraft: {teacher}",
+                "Klasse: {class}",
+            ),
+            "es" => (
+                " en el aula {room}",
+                " con {teacher}",
+                ". {note}",
+                " ({reminders})",
+                "Plano del colegio",
+                "{count} clases, cada una leída como día, periodo, asignatura, aula y profesor",
+                "1 clase, leída como día, periodo, asignatura, aula y profesor",
+                "Profesor: {teacher}",
+                "Clase: {class}",
+            ),
+            _ => (
+                " in {room}",
+                " with {teacher}",
+                ". {note}",
+                " ({reminders})",
+                "School map",
+                "{count} lessons, each read out as day, period, subject, room and teacher",
+                "1 lesson, read out as day, period, subject, room and teacher",
+                "Teacher: {teacher}",
+                "Class: {class}",
+            ),
+        };
+        let (short_months, weekdays, short_weekdays) = match locale.to_ascii_lowercase().as_str() {
+            "cy" => (
+                [
+                    "Ion", "Chwef", "Maw", "Ebr", "Mai", "Meh", "Gorff", "Awst", "Medi", "Hyd",
+                    "Tach", "Rhag",
+                ],
+                [
+                    "Dydd Llun",
+                    "Dydd Mawrth",
+                    "Dydd Mercher",
+                    "Dydd Iau",
+                    "Dydd Gwener",
+                    "Dydd Sadwrn",
+                    "Dydd Sul",
+                ],
+                ["Llun", "Maw", "Mer", "Iau", "Gwe", "Sad", "Sul"],
+            ),
+            "fr" => (
+                [
+                    "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.",
+                    "oct.", "nov.", "déc.",
+                ],
+                [
+                    "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+                ],
+                ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+            ),
+            "de" => (
+                [
+                    "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov",
+                    "Dez",
+                ],
+                [
+                    "Montag",
+                    "Dienstag",
+                    "Mittwoch",
+                    "Donnerstag",
+                    "Freitag",
+                    "Samstag",
+                    "Sonntag",
+                ],
+                ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+            ),
+            "es" => (
+                [
+                    "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov",
+                    "dic",
+                ],
+                [
+                    "lunes",
+                    "martes",
+                    "miércoles",
+                    "jueves",
+                    "viernes",
+                    "sábado",
+                    "domingo",
+                ],
+                ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+            ),
+            _ => (
+                [
+                    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov",
+                    "Dec",
+                ],
+                [
+                    "Monday",
+                    "Tuesday",
+                    "Wednesday",
+                    "Thursday",
+                    "Friday",
+                    "Saturday",
+                    "Sunday",
+                ],
+                ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            ),
+        };
+
+        Some(Self {
+            locale: locale.to_ascii_lowercase(),
+            student_timetable: student_timetable.to_string(),
+            days: days.map(String::from).to_vec(),
+            periods: ["PD", "L1", "L2", "L3", "L4", "L5"]
+                .map(String::from)
+                .to_vec(),
+            months: months.map(String::from).to_vec(),
+            short_months: short_months.map(String::from).to_vec(),
+            weekdays: weekdays.map(String::from).to_vec(),
+            short_weekdays: short_weekdays.map(String::from).to_vec(),
+            break_label: break_label.to_string(),
+            lunch_label: lunch_label.to_string(),
+            updated: updated.to_string(),
+            free_period: free_period.to_string(),
+            lesson_room: lesson_room.to_string(),
+            lesson_teacher: lesson_teacher.to_string(),
+            lesson_note: lesson_note.to_string(),
+            lesson_reminders: lesson_reminders.to_string(),
+            school_map: school_map.to_string(),
+            sheet_description: sheet_description.to_string(),
+            sheet_description_one: sheet_description_one.to_string(),
+            tooltip_teacher: tooltip_teacher.to_string(),
+            tooltip_class: tooltip_class.to_string(),
+        })
+    }
+
+    /// Label of the period at `index` (0 = PD), falling back to the Bromcom
+    /// identifier when the configured list is too short.
+    pub fn period(&self, index: usize) -> String {
+        self.periods
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().periods[index % 6].clone())
+    }
+
+    /// Name of the day at `index` (0 = Monday), falling back to English
+    /// when the configured list is too short.
+    pub fn day(&self, index: usize) -> String {
+        self.days
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().days[index % 5].clone())
+    }
+
+    /// Name of the month numbered `month` (1 = January), falling back to
+    /// English when the configured list is too short.
+    pub fn month(&self, month: u32) -> String {
+        let index = (month.clamp(1, 12) - 1) as usize;
+        self.months
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().months[index].clone())
+    }
+
+    /// Short name of the month numbered `month` (1 = January), falling back
+    /// to English when the configured list is too short.
+    pub fn short_month(&self, month: u32) -> String {
+        let index = (month.clamp(1, 12) - 1) as usize;
+        self.short_months
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().short_months[index].clone())
+    }
+
+    /// Name of `weekday`, falling back to English when the configured list
+    /// is too short.
+    pub fn weekday(&self, weekday: Weekday) -> String {
+        let index = weekday.num_days_from_monday() as usize;
+        self.weekdays
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().weekdays[index].clone())
+    }
+
+    /// Short name of `weekday`, falling back to English when the configured
+    /// list is too short.
+    pub fn short_weekday(&self, weekday: Weekday) -> String {
+        let index = weekday.num_days_from_monday() as usize;
+        self.short_weekdays
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| Strings::default().short_weekdays[index].clone())
+    }
+
+    /// Monday-to-Friday range of the week starting `starts`, e.g. "13–17 January"
+    /// or "27 January – 1 February" when the week spans two months.
+    pub fn week_range(&self, starts: NaiveDate) -> String {
+        let ends = starts + chrono::Days::new(4);
+        if starts.month() == ends.month() {
+            format!(
+                "{}–{} {}",
+                starts.day(),
+                ends.day(),
+                self.month(ends.month())
+            )
+        } else {
+            format!(
+                "{} {} – {} {}",
+                starts.day(),
+                self.month(starts.month()),
+                ends.day(),
+                self.month(ends.month())
+            )
+        }
+    }
+}
+
+/// How teacher names appear on rendered sheets.
+///
+/// Some schools don't allow staff names on documents that leave the building.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TeacherNames {
+    /// Names as they appear in the timetable (default)
+    #[default]
+    Full,
+    /// Title followed by initials, e.g. "Mr J S" for "Mr John Smith"
+    Initials,
+    /// No teacher names at all
+    Hidden,
+}
+
+impl TeacherNames {
+    /// Form of `name` to draw, or `None` when names are hidden.
+    pub fn apply(self, name: &str) -> Option<String> {
+        match self {
+            TeacherNames::Full => Some(name.to_string()),
+            TeacherNames::Initials => {
+                let initials = name
+                    .split_whitespace()
+                    .enumerate()
+                    .map(|(i, word)| {
+                        let bare = word.trim_end_matches('.').to_ascii_lowercase();
+                        if i == 0 && crate::parser::TEACHER_TITLES.contains(&bare.as_str()) {
+                            word.to_string()
+                        } else {
+                            word.chars().take(1).collect()
+                        }
+                    })
+                    .collect::<Vec<String>>();
+                Some(initials.join(" "))
+            }
+            TeacherNames::Hidden => None,
+        }
+    }
+}
+
+/// The map of one site of a multi-site school, such as a separate building.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SiteMap {
+    /// Map SVG, PNG or JPEG file (relative to the config file)
+    pub path: PathBuf,
+    /// Prefixes of the room codes on this map (e.g. "N" for North building
+    /// rooms); a map without any takes the rooms no other map claims
+    #[serde(default)]
+    pub rooms: Vec<String>,
+    /// Areas marked on a PNG or JPEG map
+    #[serde(default)]
+    pub areas: Vec<MapArea>,
+}
+
+/// School logo embedded in the sheet header.
+///
+/// SVG, PNG, JPEG and GIF images are embedded as data URIs so the output
+/// remains a single self-contained file.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Logo {
+    /// Image file (relative to the config file)
+    pub path: PathBuf,
+    /// Header corner the logo is drawn in (defaults to the right)
+    #[serde(default)]
+    pub position: LogoPosition,
+    /// Height of the logo box in px; the image keeps its aspect ratio (defaults to 48)
+    #[serde(default = "default_logo_size")]
+    pub size: i32,
+}
+
+/// Header corner used for the school logo.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogoPosition {
+    /// Top-left corner, beside the student name
+    Left,
+    /// Top-right corner
+    #[default]
+    Right,
+}
+
+impl Logo {
+    /// Create a logo drawn at the default position and size.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            position: LogoPosition::default(),
+            size: default_logo_size(),
+        }
+    }
+}
+
+fn default_logo_size() -> i32 {
+    48
+}
+
+/// Image drawn faintly across the whole page, behind the white cells.
+///
+/// Embedded as a data URI like the [`Logo`], either stretched to cover the
+/// page or repeated as a pattern of `tile` px squares.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Background {
+    /// Image file (relative to the config file)
+    pub path: PathBuf,
+    /// Opacity of the image, from 0 to 1 (defaults to 0.08)
+    #[serde(default = "default_background_opacity")]
+    pub opacity: f64,
+    /// Repeat the image in squares of this size (px) instead of covering the page
+    pub tile: Option<i32>,
+}
+
+impl Background {
+    /// Create a background covering the page at the default opacity.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            opacity: default_background_opacity(),
+            tile: None,
+        }
+    }
+}
+
+fn default_background_opacity() -> f64 {
+    0.08
+}
+
+/// Font file embedded in the SVG as an `@font-face` rule.
+///
+/// TrueType, OpenType, WOFF and WOFF2 files are embedded as data URIs, so the
+/// sheet keeps its typeface on machines without the font installed. Name the
+/// same `family` in the theme's font stacks to use it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FontFace {
+    /// Family name the font is registered under
+    pub family: String,
+    /// Font file (relative to the config file)
+    pub path: PathBuf,
+    /// CSS weight the file provides, e.g. 300 for light or 700 for bold (defaults to 400)
+    #[serde(default = "default_font_weight")]
+    pub weight: u16,
+}
+
+impl FontFace {
+    /// Create a regular-weight font face for `family`.
+    pub fn new(family: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            family: family.into(),
+            path: path.into(),
+            weight: default_font_weight(),
+        }
+    }
+
+    /// Set the CSS weight the file provides.
+    pub fn weight(mut self, weight: u16) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+fn default_font_weight() -> u16 {
+    400
+}
+
+/// One teaching week in the term calendar.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TermWeek {
+    /// Monday of the week (e.g., "2025-01-13")
+    pub starts: NaiveDate,
+    /// Timetable week taught that week (e.g., "Week 2"), matched case-insensitively
+    pub week: String,
+}
+
+impl TermWeek {
+    /// Create a calendar entry for `week` starting on `starts`.
+    pub fn new(starts: NaiveDate, week: &str) -> Self {
+        Self {
+            starts,
+            week: week.to_string(),
+        }
+    }
+}
+
+/// A reminder rule attaching a badge to matching lessons.
+///
+/// Every condition that is set must match for the rule to apply; a rule
+/// without conditions applies to every lesson.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reminder {
+    /// Badge text shown on matching lessons (e.g., "Kit", "Ingredients")
+    pub badge: String,
+    /// Badge background color (hex code, defaults to "#231f20")
+    #[serde(default = "default_fg_color")]
+    pub color: String,
+    /// Subject name to match, case-insensitive (optional)
+    pub subject: Option<String>,
+    /// Department label (from [`Mapping::label`]) to match, case-insensitive (optional)
+    pub department: Option<String>,
+    /// Room code prefix to match (optional)
+    pub room: Option<String>,
+    /// Day name to match ("Monday" or "Mon", optional)
+    pub day: Option<String>,
+    /// Period identifier to match ("PD", "L1".."L5", optional)
+    pub period: Option<String>,
+}
+
+impl Reminder {
+    /// Create a reminder showing `badge` on every lesson.
+    ///
+    /// Narrow it down with the chained condition setters.
+    pub fn new(badge: impl Into<String>) -> Self {
+        Self {
+            badge: badge.into(),
+            color: default_fg_color(),
+            subject: None,
+            department: None,
+            room: None,
+            day: None,
+            period: None,
+        }
+    }
+
+    /// Set the badge color.
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Only match lessons with this subject.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Only match lessons in this department.
+    pub fn department(mut self, department: impl Into<String>) -> Self {
+        self.department = Some(department.into());
+        self
+    }
+
+    /// Only match lessons in rooms with this prefix.
+    pub fn room(mut self, room: impl Into<String>) -> Self {
+        self.room = Some(room.into());
+        self
+    }
+
+    /// Only match lessons on this day.
+    pub fn day(mut self, day: impl Into<String>) -> Self {
+        self.day = Some(day.into());
+        self
+    }
+
+    /// Only match lessons in this period.
+    pub fn period(mut self, period: impl Into<String>) -> Self {
+        self.period = Some(period.into());
+        self
+    }
+}
+
+/// Longest break `duration` accepted, in minutes.
+pub const MAX_BREAK_MINUTES: u32 = 120;
+
+/// A break or lunch row drawn across the grid between two periods.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BreakRow {
+    /// Period the row follows ("PD", "L1".."L5")
+    pub after: String,
+    /// Row text (e.g., "Lunch 1 (12:20 - 12:50)")
+    pub label: String,
+    /// Length of the break in minutes (1 to [`MAX_BREAK_MINUTES`]), which sets the row height (optional)
+    pub duration: Option<u32>,
+}
+
+impl BreakRow {
+    /// Create a row labelled `label` after the period `after`, at the standard height.
+    pub fn new(after: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            after: after.into(),
+            label: label.into(),
+            duration: None,
+        }
+    }
+
+    /// Size the row for a break of `minutes`.
+    pub fn duration(mut self, minutes: u32) -> Self {
+        self.duration = Some(minutes);
+        self
+    }
+}
+
+fn default_fg_color() -> String {
+    "#231f20".to_string()
+}
+
+/// Convert a day name ("Monday", "mon", ...) to a 0-based weekday index.
+pub(crate) fn day_index_from_name(day: &str) -> Option<usize> {
+    match day.trim().to_lowercase().as_str() {
+        "monday" | "mon" => Some(0),
+        "tuesday" | "tue" => Some(1),
+        "wednesday" | "wed" => Some(2),
+        "thursday" | "thu" => Some(3),
+        "friday" | "fri" => Some(4),
+        _ => None,
+    }
+}
+
+/// Convert a period identifier ("PD", "L1".."L5") to a period index.
+pub(crate) fn period_index_from_name(period: &str) -> Option<usize> {
+    match period.trim().to_uppercase().as_str() {
+        "PD" => Some(0),
+        "L1" => Some(1),
+        "L2" => Some(2),
+        "L3" => Some(3),
+        "L4" => Some(4),
+        "L5" => Some(5),
+        _ => None,
+    }
+}
+
+/// Hue step between successive departments in a starter config, in degrees;
+/// the golden angle keeps neighbouring hues far apart however many there are.
+const STARTER_HUE_STEP: f64 = 137.508;
+
+/// A commented starter configuration for the timetable `weeks`, as written by
+/// `timetable_cli init`: [`starter_mappings`] written out by
+/// [`starter_config_with`].
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::config::{starter_config, Config};
+/// use timetable_core::parser::{Lesson, Week};
+///
+/// let lesson = Lesson {
+///     subject: "Mathematics".into(),
+///     room: "MA3".into(),
+///     ..Default::default()
+/// };
+/// let weeks = [Week { lessons: vec![lesson], ..Default::default() }];
+///
+/// let config: Config = toml::from_str(&starter_config(&weeks)).unwrap();
+/// assert_eq!(config.mappings[0].prefix, "MA");
+/// assert_eq!(config.mappings[0].map_id, "Mathematics_Rooms");
+/// ```
+pub fn starter_config(weeks: &[crate::parser::Week]) -> String {
+    starter_config_with(weeks, &starter_mappings(weeks))
+}
+
+/// The letters a room code starts with (`MA` for `MA3`), or the whole code if
+/// it starts with none; nothing for a lesson without a room. Rooms are grouped
+/// by this when suggesting mappings.
+///
+/// ```
+/// use timetable_core::config::room_prefix;
+///
+/// assert_eq!(room_prefix("MA3"), Some("MA"));
+/// assert_eq!(room_prefix("M12"), Some("M"));
+/// assert_eq!(room_prefix("Unknown"), None);
+/// ```
+pub fn room_prefix(room: &str) -> Option<&str> {
+    let room = room.trim();
+    if room.is_empty() || room == crate::parser::UNKNOWN {
+        return None;
+    }
+    let letters = room
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(room.len());
+    Some(if letters == 0 { room } else { &room[..letters] })
+}
+
+/// Suggested mappings for the rooms in `weeks`.
+///
+/// Rooms are grouped by [`room_prefix`], the letters their codes start with
+/// (`MA3` and `MA6` under `MA`), and each group becomes a mapping labelled with the subject most
+/// often taught there, a `map_id` guessed from that label, and a pastel colour
+/// from an evenly spread palette.
+pub fn starter_mappings(weeks: &[crate::parser::Week]) -> Vec<Mapping> {
+    // Lessons per subject taught in the rooms under each prefix
+    let mut groups: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for lesson in weeks.iter().flat_map(|week| &week.lessons) {
+        let Some(prefix) = room_prefix(&lesson.room) else {
+            continue;
+        };
+        *groups
+            .entry(prefix)
+            .or_default()
+            .entry(lesson.subject.as_str())
+            .or_default() += 1;
+    }
+
+    groups
+        .iter()
+        .enumerate()
+        .map(|(index, (prefix, subjects))| {
+            let label = subjects
+                .iter()
+                .max_by_key(|(subject, count)| (**count, std::cmp::Reverse(**subject)))
+                .map(|(subject, _)| *subject)
+                .filter(|subject| !subject.is_empty())
+                .unwrap_or(prefix);
+            let map_id: String = label
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            let hue = index as f64 * STARTER_HUE_STEP % 360.0;
+            Mapping::new(
+                *prefix,
+                hsl_color(hue, 0.7, 0.9),
+                format!("{}_Rooms", map_id),
+            )
+            .fg_color(hsl_color(hue, 0.6, 0.35))
+            .label(label)
+        })
+        .collect()
 }
 
-/// Override for a specific lesson in the timetable.
-///
-/// Allows correcting parsing errors or making manual adjustments
-/// to specific lessons by week, day, and period.
-#[derive(Debug, Deserialize, Clone)]
-pub struct Override {
-    /// Week number (1-based, e.g., 1 = Week 1, 2 = Week 2)
-    pub week: usize,
-    /// Day name ("Monday", "Tuesday", etc. or abbreviated "Mon", "Tue")
-    pub day: String,
-    /// Period identifier ("PD", "L1", "L2", "L3", "L4", "L5")
-    pub period: String,
-    /// Override subject name (optional)
-    pub subject: Option<String>,
-    /// Override room code (optional)
-    pub room: Option<String>,
-    /// Override teacher name (optional)
-    pub teacher: Option<String>,
-    /// Override class code (optional)
-    pub class_code: Option<String>,
+/// A commented configuration holding `mappings`, each listed with the rooms in
+/// `weeks` it covers. Lessons in `weeks` without a room get commented-out
+/// overrides to fill in.
+pub fn starter_config_with(weeks: &[crate::parser::Week], mappings: &[Mapping]) -> String {
+    use std::fmt::Write as _;
+
+    let config = Config {
+        mappings: mappings.to_vec(),
+        ..Config::default()
+    };
+    // Lessons per room under each mapping, and lessons without a room
+    let mut rooms: Vec<BTreeMap<&str, usize>> = vec![BTreeMap::new(); mappings.len()];
+    let mut roomless = Vec::new();
+    for (week, lesson) in weeks
+        .iter()
+        .enumerate()
+        .flat_map(|(week, w)| w.lessons.iter().map(move |lesson| (week, lesson)))
+    {
+        let room = lesson.room.trim();
+        if room.is_empty() || room == crate::parser::UNKNOWN {
+            roomless.push((week, lesson));
+        } else if let Some(index) = config.get_style_for_room(room).and_then(|mapping| {
+            config
+                .mappings
+                .iter()
+                .position(|m| std::ptr::eq(m, mapping))
+        }) {
+            *rooms[index].entry(room).or_default() += 1;
+        }
+    }
+
+    let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+    let mut toml = String::new();
+    let source = weeks.iter().find_map(|week| week.source.as_deref());
+    let _ = writeln!(
+        toml,
+        "# Starter configuration generated from {}.",
+        source.unwrap_or("a timetable")
+    );
+    toml.push_str(concat!(
+        "# Check each department's label and map_id against your map (list the\n",
+        "# names it offers with `timetable_cli map ids <map.svg>`), merge prefixes\n",
+        "# that belong to one department, and change the colours to taste.\n",
+    ));
+    for (mapping, rooms) in mappings.iter().zip(&rooms) {
+        let rooms: Vec<String> = rooms
+            .iter()
+            .map(|(room, count)| format!("{} ({})", room, count))
+            .collect();
+        let _ = write!(
+            toml,
+            concat!(
+                "\n# Rooms (lessons): {}\n",
+                "[[mappings]]\n",
+                "prefix = {}\n",
+                "bg_color = {}\n",
+                "fg_color = {}\n",
+                "map_id = {}\n",
+            ),
+            if rooms.is_empty() {
+                "none".to_string()
+            } else {
+                rooms.join(", ")
+            },
+            quote(&mapping.prefix),
+            quote(&mapping.bg_color),
+            quote(&mapping.fg_color),
+            quote(&mapping.map_id)
+        );
+        if let Some(label) = &mapping.label {
+            let _ = writeln!(toml, "label = {}", quote(label));
+        }
+    }
+
+    if !roomless.is_empty() {
+        let days = Strings::default().days;
+        let _ = write!(
+            toml,
+            "\n# {} lessons have no room in the PDF; uncomment and fill in their rooms:\n",
+            roomless.len()
+        );
+        for (week, lesson) in roomless {
+            let day = days.get(lesson.day_index).map_or("?", String::as_str);
+            let period = crate::layout::PERIOD_LABELS
+                .get(lesson.period_index)
+                .unwrap_or(&"?");
+            let _ = write!(
+                toml,
+                concat!(
+                    "\n# {}\n",
+                    "# [[overrides]]\n",
+                    "# week = {}\n",
+                    "# day = \"{}\"\n",
+                    "# period = \"{}\"\n",
+                    "# room = \"\"\n",
+                ),
+                lesson.subject,
+                week + 1,
+                day,
+                period
+            );
+        }
+    }
+    toml
 }
 
-fn default_fg_color() -> String {
-    "#231f20".to_string()
+/// A `#rrggbb` colour from a hue in degrees and saturation and lightness in `0..=1`.
+fn hsl_color(hue: f64, saturation: f64, lightness: f64) -> String {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+/// Apply one override to the lesson it targets, or say why there is none.
+fn apply_override(
+    override_rule: &Override,
+    weeks: &mut [crate::parser::Week],
+) -> Result<(), String> {
+    // Find the target week (1-based index)
+    if override_rule.week == 0 || override_rule.week > weeks.len() {
+        return Err(format!(
+            "Override week {} is out of range",
+            override_rule.week
+        ));
+    }
+
+    let week = &mut weeks[override_rule.week - 1];
+
+    // Parse day to index
+    let day_index = day_index_from_name(&override_rule.day)
+        .ok_or_else(|| format!("Unknown day '{}'", override_rule.day))?;
+
+    // Parse period to index
+    let period_index = period_index_from_name(&override_rule.period)
+        .ok_or_else(|| format!("Unknown period '{}'", override_rule.period))?;
+
+    // Find and update the lesson
+    let lesson = week
+        .lessons
+        .iter_mut()
+        .find(|l| l.day_index == day_index && l.period_index == period_index)
+        .ok_or_else(|| {
+            format!(
+                "No lesson found for Week {}, {}, {}",
+                override_rule.week, override_rule.day, override_rule.period
+            )
+        })?;
+    if let Some(subject) = &override_rule.subject {
+        lesson.subject = subject.clone();
+    }
+    if let Some(room) = &override_rule.room {
+        lesson.room = room.clone();
+    }
+    if let Some(teacher) = &override_rule.teacher {
+        lesson.teacher = teacher.clone();
+    }
+    if let Some(class_code) = &override_rule.class_code {
+        lesson.class_code = class_code.clone();
+    }
+    if let Some(note) = &override_rule.note {
+        lesson.note = Some(note.clone());
+    }
+    Ok(())
 }
 
 impl Config {
@@ -174,6 +2520,7 @@ impl Config {
     /// - The file cannot be read
     /// - The TOML syntax is invalid
     /// - Required fields are missing
+    /// - A value is unusable (see [`Config::validate`])
     ///
     /// # Example
     ///
@@ -189,15 +2536,182 @@ impl Config {
     /// ```
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
         let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        let mut config: Config = toml::from_str(&content)?;
+        if let Some(base_dir) = path.parent() {
+            config.resolve_paths(base_dir);
+        }
+        config.validate()?;
         Ok(config)
     }
 
+    /// Check values that parse but would fail when rendering, such as a
+    /// `date_format` chrono cannot format or a negative `stroke_width`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::Invalid`] naming the first unusable value.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let date_format = &self.templates.date_format;
+        if StrftimeItems::new(date_format).any(|item| matches!(item, Item::Error)) {
+            return Err(ConfigError::Invalid(format!(
+                "templates.date_format \"{}\" is not a valid strftime format",
+                date_format
+            )));
+        }
+        for mapping in &self.mappings {
+            if let Some(width) = mapping.stroke_width {
+                if !width.is_finite() || width < 0.0 {
+                    return Err(ConfigError::Invalid(format!(
+                        "stroke_width {} of mapping \"{}\" must be a number of at least 0",
+                        width, mapping.prefix
+                    )));
+                }
+            }
+        }
+        for row in self.breaks.iter().flatten() {
+            if period_index_from_name(&row.after).is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "break \"{}\" follows unknown period \"{}\", expected PD or L1 to L5",
+                    row.label, row.after
+                )));
+            }
+            if let Some(minutes) = row.duration {
+                if !(1..=MAX_BREAK_MINUTES).contains(&minutes) {
+                    return Err(ConfigError::Invalid(format!(
+                        "break \"{}\" lasts {} minutes, expected 1 to {}",
+                        row.label, minutes, MAX_BREAK_MINUTES
+                    )));
+                }
+            }
+        }
+        for rule in &self.reminders {
+            if let Some(day) = rule.day.as_deref() {
+                if day_index_from_name(day).is_none() {
+                    return Err(ConfigError::Invalid(format!(
+                        "reminder \"{}\" is for unknown day \"{}\", expected Monday to Friday",
+                        rule.badge, day
+                    )));
+                }
+            }
+            if let Some(period) = rule.period.as_deref() {
+                if period_index_from_name(period).is_none() {
+                    return Err(ConfigError::Invalid(format!(
+                        "reminder \"{}\" is for unknown period \"{}\", expected PD or L1 to L5",
+                        rule.badge, period
+                    )));
+                }
+            }
+        }
+        let areas = self
+            .map_areas
+            .iter()
+            .chain(self.maps.iter().flat_map(|map| &map.areas));
+        for area in areas {
+            let usable = match area.rect {
+                Some(rect) => rect.iter().all(|n| n.is_finite()) && rect[2] > 0.0 && rect[3] > 0.0,
+                None => {
+                    area.points.len() >= 3 && area.points.iter().flatten().all(|n| n.is_finite())
+                }
+            };
+            if !usable {
+                return Err(ConfigError::Invalid(format!(
+                    "map area \"{}\" needs a rect with a positive width and height, or at least 3 points",
+                    area.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Start building a configuration programmatically.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::config::{Config, Mapping};
+    ///
+    /// let config = Config::builder()
+    ///     .mapping(Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths"))
+    ///     .subject_alias("Ma", "Mathematics")
+    ///     .build();
+    ///
+    /// assert_eq!(config.get_style_for_room("MA3").unwrap().map_id, "Maths_Rooms");
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Serialize the configuration to a TOML string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::TomlSer`] if the configuration cannot be represented as TOML.
+    pub fn to_toml_string(&self) -> Result<String, ConfigError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Save the configuration as a TOML file.
+    ///
+    /// The written file can be loaded again with [`Config::load`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError`] if the configuration cannot be serialized or
+    /// the file cannot be written.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use timetable_core::config::{Config, Mapping};
+    /// use std::path::Path;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// Config::builder()
+    ///     .mapping(Mapping::new("SC", "#fad7e6", "Science_Rooms"))
+    ///     .build()
+    ///     .save(Path::new("config.toml"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        fs::write(path, self.to_toml_string()?)?;
+        Ok(())
+    }
+
+    /// Make file references in the config relative to the config file's directory.
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        if let Some(icon_set) = &mut self.icon_set {
+            if icon_set.is_relative() {
+                *icon_set = base_dir.join(&*icon_set);
+            }
+        }
+        if let Some(logo) = &mut self.logo {
+            if logo.path.is_relative() {
+                logo.path = base_dir.join(&logo.path);
+            }
+        }
+        if let Some(background) = &mut self.background {
+            if background.path.is_relative() {
+                background.path = base_dir.join(&background.path);
+            }
+        }
+        for font in &mut self.fonts {
+            if font.path.is_relative() {
+                font.path = base_dir.join(&font.path);
+            }
+        }
+        for map in &mut self.maps {
+            if map.path.is_relative() {
+                map.path = base_dir.join(&map.path);
+            }
+        }
+    }
+
     /// Find the mapping for a given room code.
     ///
     /// Returns the mapping with the longest matching prefix. If both 'MA' and 'MA1'
     /// are configured, room 'MA10' matches 'MA1' (3 chars) over 'MA' (2 chars),
-    /// regardless of configuration order.
+    /// regardless of configuration order. Room aliases are resolved before matching.
     ///
     /// # Arguments
     ///
@@ -221,6 +2735,9 @@ impl Config {
     /// # }
     /// ```
     pub fn get_style_for_room(&self, room_code: &str) -> Option<&Mapping> {
+        // Legacy room codes are looked up under their current code
+        let room_code = self.resolve_room(room_code);
+
         // Find the longest matching prefix
         self.mappings
             .iter()
@@ -228,6 +2745,238 @@ impl Config {
             .max_by_key(|m| m.prefix.len())
     }
 
+    /// Find the site map showing a room, as an index into [`Config::maps`].
+    ///
+    /// The map with the longest matching room prefix wins, as with
+    /// [`Config::get_style_for_room`]; rooms no map claims go to the first map
+    /// without prefixes. Room aliases are resolved before matching.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::config::Config;
+    ///
+    /// let config = Config::builder()
+    ///     .site_map("north.svg", &["N"])
+    ///     .site_map("main.svg", &[])
+    ///     .build();
+    /// assert_eq!(config.site_map_for_room("N12"), Some(0));
+    /// assert_eq!(config.site_map_for_room("MA3"), Some(1));
+    /// ```
+    pub fn site_map_for_room(&self, room_code: &str) -> Option<usize> {
+        let room_code = self.resolve_room(room_code);
+        self.maps
+            .iter()
+            .enumerate()
+            .flat_map(|(index, map)| {
+                map.rooms
+                    .iter()
+                    .filter(|prefix| room_code.starts_with(prefix.as_str()))
+                    .map(move |prefix| (index, prefix.len()))
+            })
+            .max_by_key(|&(index, len)| (len, std::cmp::Reverse(index)))
+            .map(|(index, _)| index)
+            .or_else(|| self.maps.iter().position(|map| map.rooms.is_empty()))
+    }
+
+    /// Every mapping's `map_id`, sorted, each listed once.
+    pub fn map_ids(&self) -> Vec<&str> {
+        let mut map_ids: Vec<&str> = self.mappings.iter().map(|m| m.map_id.as_str()).collect();
+        map_ids.sort_unstable();
+        map_ids.dedup();
+        map_ids
+    }
+
+    /// Each `map_id` that names no element on any of `maps`, sorted, with the
+    /// prefixes of the mappings using it; nothing when there are no maps.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::config::{Config, Mapping};
+    /// use timetable_core::processor::MapDocument;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = Config::builder()
+    ///     .mapping(Mapping::new("MA", "#fcdcd8", "Maths_Rooms"))
+    ///     .mapping(Mapping::new("SC", "#fad7e6", "Science_Rooms"))
+    ///     .build();
+    /// let map = MapDocument::parse(r#"<svg><g id="Maths_Rooms"><rect/></g></svg>"#)?;
+    /// assert_eq!(
+    ///     config.unmatched_map_ids(&[map]),
+    ///     [("Science_Rooms", vec!["SC"])]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unmatched_map_ids(&self, maps: &[MapDocument]) -> Vec<(&str, Vec<&str>)> {
+        if maps.is_empty() {
+            return Vec::new();
+        }
+        self.map_ids()
+            .into_iter()
+            .filter(|id| !maps.iter().any(|map| map.contains(id)))
+            .map(|id| {
+                let prefixes = self
+                    .mappings
+                    .iter()
+                    .filter(|m| m.map_id == id)
+                    .map(|m| m.prefix.as_str())
+                    .collect();
+                (id, prefixes)
+            })
+            .collect()
+    }
+
+    /// Map highlights for every department with a lesson in `weeks`, each
+    /// listed once per element and fill, and named for map legends. Departments
+    /// sharing a `map_id` in different colours each get a highlight, so the map
+    /// can split the area between them.
+    ///
+    /// With `rooms`, a lesson whose room has its own element in that map
+    /// highlights just the room, in its department's colours, labelled as
+    /// `labels` asks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::config::{Config, Mapping};
+    /// use timetable_core::parser::{Lesson, Week};
+    ///
+    /// let config = Config::builder()
+    ///     .mapping(Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths"))
+    ///     .build();
+    /// let lesson = Lesson { room: "MA3".into(), ..Default::default() };
+    /// let week = Week { lessons: vec![lesson.clone(), lesson], ..Default::default() };
+    ///
+    /// let highlights = config.map_highlights([&week], None, None);
+    /// assert_eq!(highlights.len(), 1);
+    /// assert_eq!(highlights[0].id, "Maths_Rooms");
+    /// assert_eq!(highlights[0].name.as_deref(), Some("Maths"));
+    /// ```
+    pub fn map_highlights<'a>(
+        &self,
+        weeks: impl IntoIterator<Item = &'a crate::parser::Week>,
+        rooms: Option<&MapDocument>,
+        labels: Option<MapLabels>,
+    ) -> Vec<MapHighlight> {
+        let lessons: Vec<&crate::parser::Lesson> =
+            weeks.into_iter().flat_map(|week| &week.lessons).collect();
+        let mut highlights = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for lesson in &lessons {
+            let (Some(mapping), Some(id)) = (
+                self.get_style_for_room(&lesson.room),
+                self.highlight_id(lesson, rooms),
+            ) else {
+                continue;
+            };
+            let own_room = id == lesson.room;
+            let fill = crate::renderer::department_fill(self, mapping);
+            if !seen.insert((id, fill.clone())) {
+                continue;
+            }
+            let mut highlight = MapHighlight::new(id, fill)
+                .style(mapping.highlight)
+                .name(mapping.label.as_deref().unwrap_or(&mapping.prefix));
+            highlight.stroke = mapping.stroke.clone();
+            highlight.stroke_width = mapping.stroke_width;
+            match labels.filter(|_| own_room) {
+                Some(MapLabels::Codes) => highlight = highlight.label(&lesson.room),
+                Some(MapLabels::Periods) => {
+                    let mut slots: Vec<(usize, usize)> = lessons
+                        .iter()
+                        .filter(|l| l.room == lesson.room)
+                        .map(|l| (l.day_index, l.period_index))
+                        .collect();
+                    slots.sort_unstable();
+                    slots.dedup();
+                    let periods: Vec<String> = slots
+                        .into_iter()
+                        .map(|(day, period)| {
+                            let day: String = self.strings.day(day).chars().take(3).collect();
+                            format!("{} {}", day, self.strings.period(period))
+                        })
+                        .collect();
+                    highlight = highlight.label(format!("{}\n{}", lesson.room, periods.join(", ")));
+                }
+                None => {}
+            }
+            highlights.push(highlight);
+        }
+        highlights
+    }
+
+    /// The map element highlighted for `lesson`: its room's own element in
+    /// `rooms`, or else its department's `map_id`; `None` for rooms without a
+    /// mapping.
+    pub fn highlight_id<'a>(
+        &'a self,
+        lesson: &'a crate::parser::Lesson,
+        rooms: Option<&MapDocument>,
+    ) -> Option<&'a str> {
+        let mapping = self.get_style_for_room(&lesson.room)?;
+        if rooms.is_some_and(|map| map.contains(&lesson.room)) {
+            Some(&lesson.room)
+        } else {
+            Some(&mapping.map_id)
+        }
+    }
+
+    /// The route between the rooms of the lessons on `day_index` (0 = Monday)
+    /// of `week` in period order, placing each room from `room_positions`, its
+    /// own element in `map` or its department's. Rooms that cannot be placed
+    /// are skipped, and consecutive lessons in one place share a stop. The key
+    /// lists the periods spent at each stop, e.g. "L1, L2 → MA3".
+    pub fn day_route(
+        &self,
+        week: &crate::parser::Week,
+        day_index: usize,
+        map: &MapDocument,
+    ) -> Option<MapRoute> {
+        let mut lessons: Vec<&crate::parser::Lesson> = week
+            .lessons
+            .iter()
+            .filter(|lesson| lesson.day_index == day_index)
+            .collect();
+        lessons.sort_by_key(|lesson| lesson.period_index);
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        // Periods and rooms at each stop; rooms sharing a department's centre share a stop
+        let mut stops: Vec<(Vec<String>, Vec<&str>)> = Vec::new();
+        for lesson in lessons {
+            let point = self
+                .room_positions
+                .get(&lesson.room)
+                .map(|&[x, y]| (x, y))
+                .or_else(|| map.centre(&lesson.room))
+                .or_else(|| {
+                    self.get_style_for_room(&lesson.room)
+                        .and_then(|mapping| map.centre(&mapping.map_id))
+                });
+            let Some(point) = point else {
+                continue;
+            };
+            let period = self.strings.period(lesson.period_index);
+            match stops.last_mut() {
+                Some((periods, rooms)) if points.last() == Some(&point) => {
+                    periods.push(period);
+                    if !rooms.contains(&lesson.room.as_str()) {
+                        rooms.push(&lesson.room);
+                    }
+                }
+                _ => {
+                    points.push(point);
+                    stops.push((vec![period], vec![&lesson.room]));
+                }
+            }
+        }
+        let key = stops
+            .into_iter()
+            .map(|(periods, rooms)| format!("{} → {}", periods.join(", "), rooms.join("/")))
+            .collect();
+        (!points.is_empty()).then(|| MapRoute::new(points).key(key))
+    }
+
     /// Apply configured overrides to parsed weeks.
     ///
     /// Modifies lessons in-place based on override rules. Each override
@@ -260,71 +3009,267 @@ impl Config {
     /// ```
     pub fn apply_overrides(&self, weeks: &mut [crate::parser::Week]) {
         for override_rule in &self.overrides {
-            // Find the target week (1-based index)
-            if override_rule.week == 0 || override_rule.week > weeks.len() {
-                eprintln!(
-                    "Warning: Override week {} is out of range",
-                    override_rule.week
-                );
-                continue;
+            match apply_override(override_rule, weeks) {
+                Ok(()) => println!(
+                    "Applied override: Week {}, {}, {}",
+                    override_rule.week, override_rule.day, override_rule.period
+                ),
+                Err(problem) => eprintln!("Warning: {}", problem),
             }
+        }
+    }
 
-            let week = &mut weeks[override_rule.week - 1];
+    /// Apply configured overrides like [`Config::apply_overrides`], without
+    /// printing anything.
+    ///
+    /// Returns why each override that changed nothing did not apply (e.g.
+    /// "No lesson found for Week 1, Monday, L2"), in config order.
+    pub fn apply_overrides_quietly(&self, weeks: &mut [crate::parser::Week]) -> Vec<String> {
+        self.overrides
+            .iter()
+            .filter_map(|override_rule| apply_override(override_rule, weeks).err())
+            .collect()
+    }
 
-            // Parse day to index
-            let day_index = match override_rule.day.to_lowercase().as_str() {
-                "monday" | "mon" => 0,
-                "tuesday" | "tue" => 1,
-                "wednesday" | "wed" => 2,
-                "thursday" | "thu" => 3,
-                "friday" | "fri" => 4,
-                _ => {
-                    eprintln!("Warning: Unknown day '{}'", override_rule.day);
-                    continue;
-                }
-            };
+    /// Resolve a subject name through the `[subject_aliases]` table.
+    ///
+    /// Matching is case-insensitive and ignores surrounding whitespace. Subjects
+    /// without an alias are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use timetable_core::config::Config;
+    /// let config: Config = toml::from_str(r#"
+    ///     mappings = []
+    ///     [subject_aliases]
+    ///     "Ma" = "Mathematics"
+    /// "#).unwrap();
+    ///
+    /// assert_eq!(config.resolve_subject("ma"), "Mathematics");
+    /// assert_eq!(config.resolve_subject("French"), "French");
+    /// ```
+    pub fn resolve_subject<'a>(&'a self, subject: &'a str) -> &'a str {
+        let trimmed = subject.trim();
+        self.subject_aliases
+            .iter()
+            .find(|(alias, _)| alias.trim().eq_ignore_ascii_case(trimmed))
+            .map(|(_, canonical)| canonical.as_str())
+            .unwrap_or(subject)
+    }
 
-            // Parse period to index
-            let period_index = match override_rule.period.to_uppercase().as_str() {
-                "PD" => 0,
-                "L1" => 1,
-                "L2" => 2,
-                "L3" => 3,
-                "L4" => 4,
-                "L5" => 5,
-                _ => {
-                    eprintln!("Warning: Unknown period '{}'", override_rule.period);
-                    continue;
-                }
-            };
+    /// Resolve a room code through the `[room_aliases]` table.
+    ///
+    /// Matching is case-insensitive. Rooms without an alias are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use timetable_core::config::Config;
+    /// let config: Config = toml::from_str(r#"
+    ///     mappings = []
+    ///     [room_aliases]
+    ///     "T14" = "DT4"
+    /// "#).unwrap();
+    ///
+    /// assert_eq!(config.resolve_room("T14"), "DT4");
+    /// assert_eq!(config.resolve_room("MA3"), "MA3");
+    /// ```
+    pub fn resolve_room<'a>(&'a self, room_code: &'a str) -> &'a str {
+        let trimmed = room_code.trim();
+        self.room_aliases
+            .iter()
+            .find(|(alias, _)| alias.trim().eq_ignore_ascii_case(trimmed))
+            .map(|(_, current)| current.as_str())
+            .unwrap_or(room_code)
+    }
 
-            // Find and update the lesson
-            if let Some(lesson) = week
-                .lessons
-                .iter_mut()
-                .find(|l| l.day_index == day_index && l.period_index == period_index)
-            {
-                if let Some(subject) = &override_rule.subject {
-                    lesson.subject = subject.clone();
-                }
-                if let Some(room) = &override_rule.room {
-                    lesson.room = room.clone();
-                }
-                if let Some(teacher) = &override_rule.teacher {
-                    lesson.teacher = teacher.clone();
+    /// Find the icon identifier for a lesson.
+    ///
+    /// A `[subject_icons]` entry for the lesson's subject takes precedence; otherwise
+    /// the `icon` of the department mapping for the lesson's room is used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use timetable_core::config::Config;
+    /// # use timetable_core::parser::Lesson;
+    /// let config: Config = toml::from_str(r##"
+    ///     [[mappings]]
+    ///     prefix = "SC"
+    ///     bg_color = "#fad7e6"
+    ///     map_id = "Science_Rooms"
+    ///     icon = "flask"
+    ///
+    ///     [subject_icons]
+    ///     "PE" = "football"
+    /// "##).unwrap();
+    ///
+    /// let lesson = Lesson {
+    ///     subject: "Chemistry".into(),
+    ///     room: "SC2".into(),
+    ///     teacher: "Mr Test B".into(),
+    ///     class_code: String::new(),
+    ///     day_index: 0,
+    ///     period_index: 1,
+    ///     note: None,
+    /// };
+    /// assert_eq!(config.icon_for_lesson(&lesson), Some("flask"));
+    /// ```
+    pub fn icon_for_lesson(&self, lesson: &crate::parser::Lesson) -> Option<&str> {
+        let subject = lesson.subject.trim();
+        self.subject_icons
+            .iter()
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(subject))
+            .map(|(_, icon)| icon.as_str())
+            .or_else(|| {
+                self.get_style_for_room(&lesson.room)
+                    .and_then(|m| m.icon.as_deref())
+            })
+    }
+
+    /// Evaluate the reminder rules for a lesson.
+    ///
+    /// Returns every [`Reminder`] whose conditions all match the lesson, in
+    /// configuration order. Rules with an unrecognised day or period never match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use timetable_core::config::Config;
+    /// # use timetable_core::parser::Lesson;
+    /// let config: Config = toml::from_str(r#"
+    ///     mappings = []
+    ///
+    ///     [[reminders]]
+    ///     subject = "PE"
+    ///     badge = "Kit"
+    ///
+    ///     [[reminders]]
+    ///     subject = "Food Technology"
+    ///     day = "Tuesday"
+    ///     badge = "Ingredients"
+    /// "#).unwrap();
+    ///
+    /// let pe = Lesson {
+    ///     subject: "PE".into(),
+    ///     room: "SH1".into(),
+    ///     teacher: "Mr Test B".into(),
+    ///     class_code: String::new(),
+    ///     day_index: 0,
+    ///     period_index: 3,
+    ///     note: None,
+    /// };
+    /// let badges: Vec<&str> = config.reminders_for(&pe).iter().map(|r| r.badge.as_str()).collect();
+    /// assert_eq!(badges, ["Kit"]);
+    /// ```
+    pub fn reminders_for(&self, lesson: &crate::parser::Lesson) -> Vec<&Reminder> {
+        self.reminders
+            .iter()
+            .filter(|rule| self.reminder_matches(rule, lesson))
+            .collect()
+    }
+
+    fn reminder_matches(&self, rule: &Reminder, lesson: &crate::parser::Lesson) -> bool {
+        if let Some(subject) = &rule.subject {
+            if !subject.trim().eq_ignore_ascii_case(lesson.subject.trim()) {
+                return false;
+            }
+        }
+        if let Some(department) = &rule.department {
+            let label = self
+                .get_style_for_room(&lesson.room)
+                .and_then(|m| m.label.as_deref());
+            if !label.is_some_and(|l| l.eq_ignore_ascii_case(department.trim())) {
+                return false;
+            }
+        }
+        if let Some(room) = &rule.room {
+            if !lesson.room.starts_with(room.as_str()) {
+                return false;
+            }
+        }
+        if let Some(day) = &rule.day {
+            if day_index_from_name(day) != Some(lesson.day_index) {
+                return false;
+            }
+        }
+        if let Some(period) = &rule.period {
+            if period_index_from_name(period) != Some(lesson.period_index) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Break and lunch rows in grid order, with the index of the period each follows.
+    ///
+    /// Without a `breaks` list, a break follows L2 and lunch follows L4, labelled
+    /// from [`Strings`]. Rows naming an unknown period are left out.
+    pub fn break_rows(&self) -> Vec<(usize, BreakRow)> {
+        let rows = self.breaks.clone().unwrap_or_else(|| {
+            vec![
+                BreakRow::new("L2", self.strings.break_label.as_str()),
+                BreakRow::new("L4", self.strings.lunch_label.as_str()),
+            ]
+        });
+        let mut rows: Vec<_> = rows
+            .into_iter()
+            .filter_map(|row| Some((period_index_from_name(&row.after)?, row)))
+            .collect();
+        rows.sort_by_key(|(period_index, _)| *period_index);
+        rows
+    }
+
+    /// Normalise parsed lessons using the configured alias tables.
+    ///
+    /// Replaces every lesson subject that has an entry in `[subject_aliases]`
+    /// with its canonical name, so the same subject is spelled consistently
+    /// across weeks. Room codes listed in `[room_aliases]` are replaced with
+    /// their current code; a class code that simply repeats the legacy room code
+    /// is updated as well so the cell label matches the map highlight.
+    ///
+    /// Call this after [`apply_overrides`](Self::apply_overrides) so overridden
+    /// values are normalised too.
+    pub fn apply_aliases(&self, weeks: &mut [crate::parser::Week]) {
+        if self.subject_aliases.is_empty() && self.room_aliases.is_empty() {
+            return;
+        }
+
+        for week in weeks.iter_mut() {
+            for lesson in week.lessons.iter_mut() {
+                let canonical = self.resolve_subject(&lesson.subject);
+                if canonical != lesson.subject {
+                    lesson.subject = canonical.to_string();
                 }
-                if let Some(class_code) = &override_rule.class_code {
-                    lesson.class_code = class_code.clone();
+
+                let current = self.resolve_room(&lesson.room);
+                if current != lesson.room {
+                    if lesson.class_code == lesson.room {
+                        lesson.class_code = current.to_string();
+                    }
+                    lesson.room = current.to_string();
                 }
-                println!(
-                    "Applied override: Week {}, {}, {}",
-                    override_rule.week, override_rule.day, override_rule.period
-                );
-            } else {
-                eprintln!(
-                    "Warning: No lesson found for Week {}, {}, {}",
-                    override_rule.week, override_rule.day, override_rule.period
-                );
+            }
+        }
+    }
+
+    /// Set each week's start date from the term calendar.
+    ///
+    /// A timetable week usually recurs through the term, so the first calendar
+    /// entry for that week which has not finished by `today` is used. Weeks
+    /// with no such entry keep their existing date.
+    pub fn apply_calendar(&self, weeks: &mut [crate::parser::Week], today: NaiveDate) {
+        for week in weeks.iter_mut() {
+            let next = self
+                .calendar
+                .iter()
+                .filter(|t| t.week.eq_ignore_ascii_case(&week.week_name))
+                .filter(|t| t.starts + chrono::Days::new(4) >= today)
+                .map(|t| t.starts)
+                .min();
+            if next.is_some() {
+                week.starts_on = next;
             }
         }
     }
@@ -0,0 +1,475 @@
+//! Interactive HTML timetable rendering.
+//!
+//! This module produces a self-contained, responsive HTML page with the same
+//! colour coding as the SVG sheet. Hovering (or tapping) a lesson shows the
+//! teacher and class code, and the highlighted map is embedded inline, making
+//! the page suitable for sharing on phones rather than printing.
+
+use crate::config::{Config, LabelPosition, LabelRotation, Strings, Theme};
+use crate::icons::IconSet;
+use crate::layout::{LessonCell, SheetLayout};
+use crate::parser::Week;
+use crate::renderer::{hatch_patterns, RenderError, RenderOptions, HATCHES};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Render a timetable week to an HTML file.
+///
+/// # Arguments
+///
+/// * `week` - The parsed week containing lessons
+/// * `config` - Configuration with room mappings, theme, and strings
+/// * `map_content` - Processed SVG map content, or an empty string for no map
+/// * `options` - Rendering options; only those that change the text, such as
+///   [`RenderOptions::generated_at`], apply, as the browser lays out the page
+/// * `output_path` - Path where the HTML file will be written
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if the file cannot be written.
+pub fn render_timetable_html(
+    week: &Week,
+    config: &Config,
+    map_content: &str,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let html = render_timetable_html_to_string(week, config, map_content, options);
+    fs::write(output_path, html)?;
+
+    Ok(())
+}
+
+/// Render a timetable week to an HTML document string.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, html::render_timetable_html_to_string, parser::Week};
+/// use timetable_core::renderer::RenderOptions;
+///
+/// let week = Week {
+///     week_name: "Week 1".into(),
+///     ..Default::default()
+/// };
+/// let html = render_timetable_html_to_string(&week, &Config::default(), "", &RenderOptions::default());
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// ```
+pub fn render_timetable_html_to_string(
+    week: &Week,
+    config: &Config,
+    map_content: &str,
+    options: &RenderOptions,
+) -> String {
+    // Colours and texts come from the same layout model as the SVG sheet;
+    // positions are left to the browser, and no subject icons are drawn
+    let layout = SheetLayout::compute(
+        week,
+        config,
+        &IconSet::default(),
+        options,
+        options.width_px(),
+        options.timetable_height,
+    );
+    let header = &layout.header.text;
+    let title = &layout.title.text;
+    let footer = &layout.footer.text;
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n",
+        escape_html(&config.strings.locale)
+    );
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    let _ = writeln!(html, "<title>{}</title>", escape_html(title));
+    let _ = writeln!(html, "<style>{}</style>", build_page_styles(&config.theme));
+    html.push_str("</head>\n<body>\n<header>\n");
+    let subheader = &layout.subheader.text;
+    if !subheader.is_empty() {
+        let _ = writeln!(
+            html,
+            "<div class=\"student\"><h1>{}</h1><p class=\"student-details\">{}</p></div>",
+            escape_html(header),
+            escape_html(subheader)
+        );
+    } else if !header.is_empty() {
+        let _ = writeln!(html, "<h1>{}</h1>", escape_html(header));
+    }
+    if !title.is_empty() {
+        let _ = writeln!(html, "<h2>{}</h2>", escape_html(title));
+    }
+    if !layout.dates.text.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"dates\">{}</p>",
+            escape_html(&layout.dates.text)
+        );
+    }
+    html.push_str("</header>\n<main>\n<div class=\"grid\">\n<table>\n<thead>\n<tr><th></th>");
+    for day in 0..5 {
+        let _ = write!(html, "<th>{}</th>", escape_html(&config.strings.day(day)));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+
+    for (period_idx, label) in layout.periods.iter().enumerate() {
+        let _ = write!(
+            html,
+            "<tr><th class=\"period\">{}</th>",
+            escape_html(&label.text)
+        );
+        for day_idx in 0..5 {
+            let cell = layout
+                .cells
+                .iter()
+                .find(|c| c.day_index == day_idx && c.period_index == period_idx);
+            match cell {
+                Some(cell) => html.push_str(&lesson_cell(cell, &config.strings)),
+                None if config.theme.zebra.shades(day_idx, period_idx) => {
+                    html.push_str("<td class=\"empty stripe\"></td>")
+                }
+                None => html.push_str("<td class=\"empty\"></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+
+        // Break and lunch rows follow their periods, matching the SVG sheet
+        for gap in layout.gaps.iter().filter(|g| g.after_period == period_idx) {
+            let _ = writeln!(
+                html,
+                "<tr class=\"break\"><th></th><td colspan=\"5\">{}</td></tr>",
+                escape_html(&gap.label)
+            );
+        }
+    }
+    html.push_str("</tbody>\n</table>\n</div>\n");
+
+    if !map_content.trim().is_empty() {
+        // Drop any XML prolog or doctype so the SVG can be inlined
+        let svg = map_content
+            .find("<svg")
+            .map(|i| &map_content[i..])
+            .unwrap_or(map_content);
+        // Map highlights from `department_fill` refer to these patterns
+        if config.theme.patterns {
+            html.push_str("<svg width=\"0\" height=\"0\" aria-hidden=\"true\"><defs>");
+            for hatch in hatch_patterns() {
+                html.push_str(&hatch.to_string());
+            }
+            html.push_str("</defs></svg>\n");
+        }
+        let _ = writeln!(
+            html,
+            "<div class=\"map\" role=\"img\" aria-label=\"{}\">\n{}\n</div>",
+            escape_html(&config.strings.school_map),
+            svg
+        );
+    }
+
+    html.push_str("</main>\n");
+    if !footer.is_empty() {
+        let _ = writeln!(html, "<footer>{}</footer>", escape_html(footer));
+    }
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+/// Build the table cell for a lesson, with a hover/tap tooltip.
+fn lesson_cell(cell: &LessonCell, strings: &Strings) -> String {
+    let mut tooltip = Vec::new();
+    if let Some(teacher) = cell.teacher.as_ref().filter(|t| !t.is_empty()) {
+        tooltip.push(strings.tooltip_teacher.replace("{teacher}", teacher));
+    }
+    if let Some(class_code) = &cell.class_code {
+        tooltip.push(strings.tooltip_class.replace("{class}", class_code));
+    }
+
+    let mut html = format!(
+        "<td class=\"lesson\" tabindex=\"0\" aria-label=\"{}\" style=\"--bg: {}; --fg: {}; --cell: {}\" data-tooltip=\"{}\">",
+        escape_html(&cell.description),
+        escape_html(&cell.bg_color),
+        escape_html(&cell.fg_color),
+        escape_html(&cell.fill_color),
+        escape_html(&tooltip.join(" · "))
+    );
+    html.push_str("<div class=\"details\">");
+    let _ = write!(
+        html,
+        "<span class=\"subject\">{}</span>",
+        escape_html(&cell.subject)
+    );
+    if let Some(room) = &cell.room {
+        let _ = write!(html, "<span class=\"detail\">{}</span>", escape_html(room));
+    }
+    if let Some(teacher) = &cell.teacher {
+        let _ = write!(
+            html,
+            "<span class=\"teacher\">{}</span>",
+            escape_html(teacher)
+        );
+    }
+    html.push_str("</div>");
+    match cell.pattern {
+        Some(i) => {
+            let _ = write!(
+                html,
+                "<div class=\"code\" style=\"background: {}, var(--bg)\">{}</div></td>",
+                HATCHES[i].3,
+                escape_html(&cell.label)
+            );
+        }
+        None => {
+            let _ = write!(
+                html,
+                "<div class=\"code\">{}</div></td>",
+                escape_html(&cell.label)
+            );
+        }
+    }
+
+    html
+}
+
+/// Build the page stylesheet from the theme.
+fn build_page_styles(theme: &Theme) -> String {
+    let strip = theme.label_width;
+    let border = format!("{}px solid {}", theme.stroke_width, theme.stroke_color);
+    let writing_mode = match theme.label_rotation {
+        LabelRotation::Clockwise => "writing-mode: vertical-rl;",
+        LabelRotation::Counterclockwise => "writing-mode: vertical-rl; transform: rotate(180deg);",
+    };
+    let radius = theme.corner_radius;
+    let (details_offset, code_position) = match theme.label_position {
+        LabelPosition::Right => (
+            format!("margin-right: {}px;", strip),
+            format!(
+                "top: 0; right: 0; bottom: 0; width: {}px; border-left: {}; border-radius: 0 {r}px {r}px 0; {}",
+                strip, border, writing_mode, r = radius
+            ),
+        ),
+        LabelPosition::Left => (
+            format!("margin-left: {}px;", strip),
+            format!(
+                "top: 0; left: 0; bottom: 0; width: {}px; border-right: {}; border-radius: {r}px 0 0 {r}px; {}",
+                strip, border, writing_mode, r = radius
+            ),
+        ),
+        LabelPosition::Top => (
+            format!("margin-top: {}px;", strip),
+            format!(
+                "top: 0; left: 0; right: 0; height: {}px; border-bottom: {}; border-radius: {r}px {r}px 0 0;",
+                strip, border, r = radius
+            ),
+        ),
+    };
+
+    format!(
+        r#"
+        body {{
+            margin: 0 auto;
+            max-width: 1100px;
+            padding: 12px;
+            background: {page};
+            color: {text};
+            font-family: {light_font};
+            font-size: {detail_size}px;
+        }}
+        header {{ display: flex; flex-wrap: wrap; justify-content: space-between; align-items: baseline; }}
+        h1 {{ font-family: {bold_font}; font-size: {header_size}px; margin: 0 0 8px; }}
+        h2 {{ font-family: {bold_font}; font-size: {week_size}px; margin: 0 0 8px; }}
+        .dates, .student-details {{ margin: 0 0 8px; }}
+        .grid {{ overflow-x: auto; }}
+        table {{ width: 100%; min-width: 560px; border-collapse: separate; border-spacing: {padding}px; table-layout: fixed; }}
+        th {{ font-family: {bold_font}; font-size: {period_size}px; }}
+        th.period {{ width: 32px; }}
+        td.lesson {{
+            position: relative;
+            padding: 0;
+            background: var(--cell, {cell});
+            border: {stroke_width}px solid {stroke};
+            border-radius: {radius}px;
+            vertical-align: top;
+        }}
+        td.lesson .details {{ display: flex; flex-direction: column; gap: 2px; padding: {text_padding}px; {details_offset} }}
+        td.lesson .subject {{ font-family: {font}; font-size: {subject_size}px; font-weight: bold; }}
+        td.lesson .teacher {{ font-size: {teacher_size}px; }}
+        td.lesson .code {{
+            position: absolute;
+            {code_position}
+            display: flex;
+            align-items: center;
+            justify-content: center;
+            background: var(--bg);
+            color: var(--fg);
+            font-family: {bold_font};
+            font-size: {code_size}px;
+            font-weight: 600;
+            overflow: hidden;
+        }}
+        td.lesson[data-tooltip]:not([data-tooltip=""]):hover::after,
+        td.lesson[data-tooltip]:not([data-tooltip=""]):focus::after {{
+            content: attr(data-tooltip);
+            position: absolute;
+            left: 0;
+            bottom: 100%;
+            z-index: 1;
+            padding: 4px 8px;
+            white-space: nowrap;
+            background: {text};
+            color: {page};
+            border-radius: 4px;
+        }}
+        td.stripe {{ background: {zebra}; }}
+        tr.break td {{
+            background: {break_color};
+            border: {stroke_width}px solid {stroke};
+            border-radius: {radius}px;
+            text-align: center;
+        }}
+        .map svg {{ width: 100%; height: auto; margin-top: 16px; }}
+        footer {{ text-align: right; margin-top: 8px; }}
+        @media (max-width: 600px) {{
+            body {{ padding: 4px; }}
+            td.lesson .details {{ padding: 2px; }}
+        }}
+    "#,
+        page = theme.page_color,
+        text = theme.text_color,
+        cell = theme.cell_color,
+        break_color = theme.break_color,
+        zebra = theme.zebra_color,
+        stroke = theme.stroke_color,
+        stroke_width = theme.stroke_width,
+        radius = radius,
+        padding = theme.cell_padding,
+        text_padding = theme.text_padding,
+        font = theme.font_family,
+        light_font = theme.light_font_family,
+        bold_font = theme.bold_font_family,
+        header_size = theme.header_font_size,
+        week_size = theme.week_font_size,
+        period_size = theme.period_font_size,
+        subject_size = theme.subject_font_size,
+        detail_size = theme.detail_font_size,
+        teacher_size = theme.teacher_font_size,
+        code_size = theme.class_code_font_size,
+        details_offset = details_offset,
+        code_position = code_position,
+    )
+}
+
+/// Escape text for use in HTML content and double-quoted attributes.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Mapping, Strings};
+    use crate::parser::Lesson;
+
+    fn sample_week() -> Week {
+        Week {
+            week_name: "Week 1".into(),
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Mr Smith".into(),
+                class_code: "10A/Ma1".into(),
+                day_index: 0,
+                period_index: 1,
+                note: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn html_cells_use_mapping_colours_and_tooltips() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").fg_color("#f0503f")],
+            ..Default::default()
+        };
+
+        let html =
+            render_timetable_html_to_string(&sample_week(), &config, "", &RenderOptions::default());
+
+        assert!(html.contains("--bg: #fcdcd8; --fg: #f0503f"));
+        assert!(html.contains("data-tooltip=\"Teacher: Mr Smith · Class: 10A/Ma1\""));
+        assert!(html.contains("<span class=\"subject\">Maths</span>"));
+        assert!(html.contains("aria-label=\"Monday, L1, Maths in MA3 with Mr Smith\""));
+        assert!(!html.contains("class=\"map\""));
+    }
+
+    #[test]
+    fn html_inlines_map_without_xml_prolog() {
+        let map =
+            r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"><rect id="r"/></svg>"#;
+
+        let html = render_timetable_html_to_string(
+            &sample_week(),
+            &Config::default(),
+            map,
+            &RenderOptions::default(),
+        );
+
+        assert!(
+            html.contains("<div class=\"map\" role=\"img\" aria-label=\"School map\">\n<svg xmlns")
+        );
+        assert!(!html.contains("<?xml"));
+    }
+
+    #[test]
+    fn html_uses_locale_for_language_and_labels() {
+        let config = Config {
+            strings: Strings::for_locale("fr").unwrap(),
+            ..Default::default()
+        };
+
+        let html =
+            render_timetable_html_to_string(&sample_week(), &config, "", &RenderOptions::default());
+
+        assert!(html.contains("<html lang=\"fr\">"));
+        assert!(html.contains("<th>Mercredi</th>"));
+        assert!(html.contains("Récréation (11:00 - 11:30)"));
+        assert!(html.contains("data-tooltip=\"Professeur : Mr Smith · Classe : 10A/Ma1\""));
+    }
+
+    #[test]
+    fn html_footer_uses_generated_at() {
+        let time = chrono::NaiveDate::from_ymd_opt(2026, 1, 5)
+            .and_then(|date| date.and_hms_opt(9, 30, 0))
+            .and_then(|time| time.and_local_timezone(chrono::Local).single())
+            .expect("valid time");
+        let options = RenderOptions::builder().generated_at(time).build();
+
+        let render =
+            || render_timetable_html_to_string(&sample_week(), &Config::default(), "", &options);
+        let html = render();
+
+        assert!(html.contains("Updated: 05 January 2026"));
+        assert_eq!(html, render());
+    }
+
+    #[test]
+    fn escape_html_escapes_markup() {
+        assert_eq!(
+            escape_html(r#"<b>"R&D"</b>"#),
+            "&lt;b&gt;&quot;R&amp;D&quot;&lt;/b&gt;"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Subject icons drawn beside subject names.
+//!
+//! Icons are SVG `<symbol>` elements looked up by their `id`. They come from
+//! the config's `icon_set` file and, with the `icons` feature, from a bundled
+//! set of `books`, `flask`, `football` and `paintbrush`. Symbols in the
+//! `icon_set` file replace bundled ones with the same id.
+
+use crate::config::Config;
+use crate::processor::{copy_element, CopiedElement};
+use roxmltree::{Document, ParsingOptions};
+use std::fs;
+use svg::Node;
+use thiserror::Error;
+
+/// Bundled symbols, drawn in the text colour on a 24 × 24 grid.
+#[cfg(feature = "icons")]
+const BUNDLED_ICONS: &str = include_str!("../assets/icons.svg");
+
+/// Errors that can occur while loading icons.
+#[derive(Error, Debug)]
+pub enum IconError {
+    /// The `icon_set` file could not be read
+    #[error("IO error reading icon set: {0}")]
+    Io(#[from] std::io::Error),
+    /// The `icon_set` file is not valid XML
+    #[error("XML parsing error in icon set: {0}")]
+    Xml(#[from] roxmltree::Error),
+}
+
+/// The icons available for drawing, by id.
+#[derive(Debug, Clone, Default)]
+pub struct IconSet {
+    symbols: Vec<(String, CopiedElement)>,
+}
+
+impl IconSet {
+    /// Load the bundled icons (with the `icons` feature) and those in `config.icon_set`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IconError`] if the `icon_set` file cannot be read or parsed.
+    pub fn load(config: &Config) -> Result<Self, IconError> {
+        let mut set = IconSet::default();
+        #[cfg(feature = "icons")]
+        set.add_symbols(BUNDLED_ICONS)?;
+        if let Some(path) = &config.icon_set {
+            set.add_symbols(&fs::read_to_string(path)?)?;
+        }
+        Ok(set)
+    }
+
+    /// Whether an icon with this id is available.
+    pub fn contains(&self, id: &str) -> bool {
+        self.symbols.iter().any(|(symbol, _)| symbol == id)
+    }
+
+    /// The `<symbol>` for `id`, with its id changed to [`icon_ref`]'s so it
+    /// cannot clash with ids in the map.
+    pub fn symbol(&self, id: &str) -> Option<CopiedElement> {
+        let (_, symbol) = self.symbols.iter().find(|(symbol, _)| symbol == id)?;
+        let mut symbol = symbol.clone();
+        symbol.assign("id", icon_ref(id));
+        Some(symbol)
+    }
+
+    /// Add every `<symbol>` with an id in `svg`, replacing any with the same id.
+    fn add_symbols(&mut self, svg: &str) -> Result<(), IconError> {
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        };
+        let doc = Document::parse_with_options(svg, options)?;
+        for node in doc.descendants().filter(|n| n.has_tag_name("symbol")) {
+            let Some(id) = node.attribute("id") else {
+                continue;
+            };
+            self.symbols.retain(|(symbol, _)| symbol != id);
+            self.symbols.push((id.to_string(), copy_element(node, "")));
+        }
+        Ok(())
+    }
+}
+
+/// Id an icon's symbol is given in rendered documents, e.g. "icon-flask".
+pub fn icon_ref(id: &str) -> String {
+    format!("icon-{}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn icon_set_file_adds_and_replaces_symbols() {
+        let path = env::temp_dir().join("timetable_icons_test.svg");
+        fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <symbol id="flask" viewBox="0 0 10 10"><circle r="5"/></symbol>
+                <symbol id="globe" viewBox="0 0 10 10"><rect width="10" height="10"/></symbol>
+                <symbol viewBox="0 0 10 10"/>
+            </svg>"#,
+        )
+        .expect("write icon set");
+        let config = Config::builder().icon_set(&path).build();
+
+        let icons = IconSet::load(&config).expect("icons load");
+
+        assert!(icons.contains("globe"));
+        let flask = icons.symbol("flask").expect("flask").to_string();
+        assert!(flask.contains("id=\"icon-flask\""));
+        assert!(flask.contains("<circle r=\"5\"/>"));
+        assert!(!icons.contains(""));
+        #[cfg(feature = "icons")]
+        assert!(icons.contains("paintbrush"));
+
+        fs::remove_file(&path).ok();
+    }
+}
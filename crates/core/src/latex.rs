@@ -0,0 +1,255 @@
+//! LaTeX export.
+//!
+//! This module emits a `tabularx` fragment of the week for student planners
+//! that are already typeset in LaTeX. The fragment is meant to be `\input`
+//! into an existing document, which must load the `tabularx` package and
+//! `xcolor` with the `table` option. Each lesson cell is tinted with its
+//! department colour. The map is not included, as LaTeX cannot embed SVG
+//! without external tools.
+
+use crate::config::Config;
+use crate::icons::IconSet;
+use crate::layout::{LessonCell, SheetLayout};
+use crate::parser::Week;
+use crate::renderer::{RenderError, RenderOptions};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Render a timetable week to a LaTeX fragment file.
+///
+/// # Arguments
+///
+/// * `week` - The parsed week containing lessons
+/// * `config` - Configuration with room mappings, theme, and strings
+/// * `options` - Rendering options; only those that change the text, such as
+///   [`RenderOptions::generated_at`], apply, as LaTeX lays out the table
+/// * `output_path` - Path where the `.tex` file will be written
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if the file cannot be written.
+pub fn render_timetable_latex(
+    week: &Week,
+    config: &Config,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let latex = render_timetable_latex_to_string(week, config, options);
+    fs::write(output_path, latex)?;
+
+    Ok(())
+}
+
+/// Render a timetable week to a LaTeX `tabularx` fragment.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, latex::render_timetable_latex_to_string, parser::Week};
+/// use timetable_core::renderer::RenderOptions;
+///
+/// let week = Week { week_name: "Week 1".into(), ..Default::default() };
+/// let tex = render_timetable_latex_to_string(&week, &Config::default(), &RenderOptions::default());
+/// assert!(tex.contains("\\begin{tabularx}{\\linewidth}"));
+/// ```
+pub fn render_timetable_latex_to_string(
+    week: &Week,
+    config: &Config,
+    options: &RenderOptions,
+) -> String {
+    // Colours and texts come from the same layout model as the SVG sheet;
+    // LaTeX cells draw no subject icons, so none are loaded
+    let layout = SheetLayout::compute(
+        week,
+        config,
+        &IconSet::default(),
+        options,
+        options.width_px(),
+        options.timetable_height,
+    );
+
+    let mut tex = String::new();
+    tex.push_str("% Timetable generated by bromcom-timetable-formatter\n");
+    tex.push_str("% Requires \\usepackage{tabularx} and \\usepackage[table]{xcolor}\n");
+
+    let header = &layout.header.text;
+    let title = &layout.title.text;
+    if !header.is_empty() || !title.is_empty() {
+        let _ = writeln!(
+            tex,
+            "\\noindent{{\\large\\textbf{{{}}}}}\\hfill{{\\large\\textbf{{{}}}}}\\par\\medskip",
+            escape_latex(header),
+            escape_latex(title)
+        );
+    }
+    if !layout.subheader.text.is_empty() {
+        let _ = writeln!(
+            tex,
+            "\\noindent{{\\small {}}}\\par\\medskip",
+            escape_latex(&layout.subheader.text)
+        );
+    }
+
+    tex.push_str("\\noindent\n\\begin{tabularx}{\\linewidth}{|l|X|X|X|X|X|}\n\\hline\n");
+    for day in &layout.days {
+        let _ = write!(tex, " & \\textbf{{{}}}", escape_latex(&day.text));
+    }
+    tex.push_str(" \\\\\n\\hline\n");
+
+    for (period_idx, period) in layout.periods.iter().enumerate() {
+        let _ = write!(tex, "\\textbf{{{}}}", escape_latex(&period.text));
+        for day_idx in 0..layout.days.len() {
+            tex.push_str(" & ");
+            let cell = layout
+                .cells
+                .iter()
+                .find(|c| c.day_index == day_idx && c.period_index == period_idx);
+            if let Some(cell) = cell {
+                tex.push_str(&lesson_cell(cell));
+            }
+        }
+        tex.push_str(" \\\\\n\\hline\n");
+
+        for gap in layout.gaps.iter().filter(|g| g.after_period == period_idx) {
+            let fill = latex_color(&config.theme.break_color)
+                .map(|c| format!("\\cellcolor[HTML]{{{}}}", c))
+                .unwrap_or_default();
+            let _ = writeln!(
+                tex,
+                " & \\multicolumn{{5}}{{c|}}{{{}{}}} \\\\\n\\hline",
+                fill,
+                escape_latex(&gap.label)
+            );
+        }
+    }
+    tex.push_str("\\end{tabularx}\n");
+
+    if !layout.footer.text.is_empty() {
+        let _ = writeln!(
+            tex,
+            "\\par\\hfill{{\\footnotesize {}}}",
+            escape_latex(&layout.footer.text)
+        );
+    }
+
+    tex
+}
+
+/// Build the table cell for a lesson, tinted with the department colour.
+fn lesson_cell(cell: &LessonCell) -> String {
+    let mut tex = String::new();
+    if let Some(bg) = latex_color(&cell.bg_color) {
+        let _ = write!(tex, "\\cellcolor[HTML]{{{}}}", bg);
+    }
+    let _ = write!(tex, "\\textbf{{{}}}", escape_latex(&cell.subject));
+    if let Some(room) = &cell.room {
+        let _ = write!(tex, "\\newline {}", escape_latex(room));
+    }
+    if let Some(teacher) = &cell.teacher {
+        let _ = write!(
+            tex,
+            "\\newline {{\\footnotesize {}}}",
+            escape_latex(teacher)
+        );
+    }
+    if let Some(class_code) = &cell.class_code {
+        let code = escape_latex(class_code);
+        match latex_color(&cell.fg_color) {
+            Some(fg) => {
+                let _ = write!(
+                    tex,
+                    "\\newline {{\\footnotesize\\textcolor[HTML]{{{}}}{{\\textbf{{{}}}}}}}",
+                    fg, code
+                );
+            }
+            None => {
+                let _ = write!(tex, "\\newline {{\\footnotesize\\textbf{{{}}}}}", code);
+            }
+        }
+    }
+    tex
+}
+
+/// Convert a `#rrggbb` or `#rgb` colour into the `HTML` model used by xcolor.
+fn latex_color(color: &str) -> Option<String> {
+    let hex = color.trim().strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        6 => Some(hex.to_ascii_uppercase()),
+        3 => Some(
+            hex.chars()
+                .flat_map(|c| [c, c])
+                .collect::<String>()
+                .to_ascii_uppercase(),
+        ),
+        _ => None,
+    }
+}
+
+/// Escape text for use in LaTeX paragraph content.
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Mapping;
+    use crate::parser::Lesson;
+
+    #[test]
+    fn latex_cells_use_department_colours() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").fg_color("#f0503f")],
+            ..Default::default()
+        };
+        let week = Week {
+            week_name: "Week 1".into(),
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Mr Smith".into(),
+                class_code: "10A/Ma_1".into(),
+                day_index: 0,
+                period_index: 1,
+                note: None,
+            }],
+            ..Default::default()
+        };
+
+        let tex = render_timetable_latex_to_string(&week, &config, &RenderOptions::default());
+
+        assert!(tex.contains(
+            r"\cellcolor[HTML]{FCDCD8}\textbf{Maths}\newline MA3\newline {\footnotesize Mr Smith}"
+        ));
+        assert!(tex.contains(r"\textcolor[HTML]{F0503F}{\textbf{10A/Ma\_1}}"));
+        assert!(tex.contains(r"\multicolumn{5}{c|}{\cellcolor[HTML]{EEEEEE}Break"));
+    }
+
+    #[test]
+    fn latex_color_expands_short_hex() {
+        assert_eq!(latex_color("#abc"), Some("AABBCC".to_string()));
+        assert_eq!(latex_color("white"), None);
+    }
+
+    #[test]
+    fn escape_latex_escapes_specials() {
+        assert_eq!(escape_latex("R&D 50% #1"), r"R\&D 50\% \#1");
+    }
+}
@@ -0,0 +1,1270 @@
+//! Computed sheet layout.
+//!
+//! This module turns a [`Week`] into a [`SheetLayout`]: the positions, colours
+//! and texts of every element on the timetable grid. The SVG renderer draws
+//! from this model, and because it is serialisable it can also be handed to
+//! user-supplied templates (see
+//! [`render_timetable_with_template`](crate::renderer::render_timetable_with_template))
+//! or exported as JSON for other tools.
+
+use crate::config::{BreakRow, Config, LabelPosition, Theme, Zebra, MAX_BREAK_MINUTES};
+use crate::icons::IconSet;
+use crate::metrics::TextMetrics;
+use crate::parser::{Lesson, Week, UNKNOWN};
+use crate::renderer::{fill_template, hatch_index, template_vars, RenderOptions};
+use serde::Serialize;
+
+/// Period labels down the left of the grid.
+pub const PERIOD_LABELS: [&str; 6] = ["PD", "L1", "L2", "L3", "L4", "L5"];
+
+/// Height of the break and lunch rows (px).
+pub(crate) const GAP_HEIGHT: i32 = 24;
+
+/// Break length drawn at the standard [`GAP_HEIGHT`] (minutes).
+const GAP_MINUTES: i32 = 20;
+
+/// Smallest subject size, as a fraction of the theme size, when shrinking to fit.
+const MIN_SUBJECT_SCALE: f64 = 0.75;
+
+/// Vertical space kept below the subject for the room and teacher lines (px).
+const DETAIL_LINES_HEIGHT: i32 = 36;
+
+/// Extra space kept below the subject for a lesson note (px).
+const NOTE_LINE_HEIGHT: i32 = 12;
+
+/// Space between a subject icon and the subject (px).
+const ICON_GAP: i32 = 3;
+
+/// Class code size, as a fraction of its usual size, when the room is stacked beneath it.
+const STACKED_LABEL_SCALE: f64 = 0.7;
+
+/// Height of a reminder badge (px).
+const BADGE_HEIGHT: i32 = 13;
+
+/// Font size of reminder badge text (px).
+pub(crate) const BADGE_FONT_SIZE: f64 = 8.0;
+
+/// Space around and between reminder badges (px).
+const BADGE_GAP: i32 = 3;
+
+/// An axis-aligned rectangle in sheet coordinates (px).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Rect {
+    /// Left edge
+    pub x: i32,
+    /// Top edge
+    pub y: i32,
+    /// Width
+    pub width: i32,
+    /// Height
+    pub height: i32,
+}
+
+impl Rect {
+    /// Whether `other` lies entirely inside this rectangle.
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+}
+
+/// A reminder badge drawn as a pill in the top right corner of a lesson cell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Badge {
+    /// Badge text, e.g. "Kit"
+    pub text: String,
+    /// Pill colour
+    pub color: String,
+    /// Text colour chosen to stand out against the pill
+    pub text_color: String,
+    /// Pill outline
+    pub rect: Rect,
+}
+
+/// A piece of text anchored at a point.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextLabel {
+    /// Text content
+    pub text: String,
+    /// Anchor x position
+    pub x: i32,
+    /// Baseline (or middle, for centred labels) y position
+    pub y: i32,
+}
+
+/// A full-width break or lunch row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GapRow {
+    /// Index of the period the row follows
+    pub after_period: usize,
+    /// Localised row label (e.g., "Break (11:00 - 11:30)")
+    pub label: String,
+    /// Row rectangle
+    pub rect: Rect,
+}
+
+/// A positioned lesson cell with resolved colours and texts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LessonCell {
+    /// Day of week (0-4 for Monday-Friday)
+    pub day_index: usize,
+    /// Period index (0 = PD, 1 = L1, ...)
+    pub period_index: usize,
+    /// Main (white) cell area
+    pub rect: Rect,
+    /// Coloured strip on the right of the cell
+    pub label_rect: Rect,
+    /// Department background colour for the strip
+    pub bg_color: String,
+    /// Department foreground colour for the strip text
+    pub fg_color: String,
+    /// Hatch pattern filling the strip when `theme.patterns` is set
+    pub pattern: Option<usize>,
+    /// Background of the main cell area, tinted when `theme.cell_tint` is set
+    pub fill_color: String,
+    /// Subject name
+    pub subject: String,
+    /// Subject wrapped onto one or more lines to fit the cell width
+    pub subject_lines: Vec<String>,
+    /// Subject font size, below `theme.subject_font_size` when shrunk to fit (px)
+    pub subject_font_size: f64,
+    /// Whether the subject lines were cut short with an ellipsis
+    pub subject_truncated: bool,
+    /// Room code, unless unknown
+    pub room: Option<String>,
+    /// Teacher name, unless unknown
+    pub teacher: Option<String>,
+    /// Bottom line of the cell: the teacher name shortened with an ellipsis if
+    /// wider than the cell, or the class code on teacher sheets
+    pub teacher_line: Option<String>,
+    /// Class code, if the timetable gave one
+    pub class_code: Option<String>,
+    /// Note from an override, if any
+    pub note: Option<String>,
+    /// Note shortened with an ellipsis if wider than the cell
+    pub note_line: Option<String>,
+    /// Text drawn vertically in the strip (class code, room, or subject)
+    pub label: String,
+    /// Room stacked in small text beneath the class code when `theme.label_room` is set
+    pub label_room: Option<String>,
+    /// Whether the strip text is longer than the strip
+    pub label_overflows: bool,
+    /// Whether the lesson has no known room
+    pub unknown_room: bool,
+    /// X position where cell texts start
+    pub text_x: i32,
+    /// Icon drawn at `text_x` before the subject, if one is configured and available
+    pub icon: Option<String>,
+    /// X position where the subject starts, after any icon
+    pub subject_x: i32,
+    /// Badges from the reminder rules matching the lesson
+    pub badges: Vec<Badge>,
+    /// Spoken summary for screen readers, e.g. "Tuesday, L3, Science in SC8 with Mr Test B"
+    pub description: String,
+}
+
+/// Text in a lesson cell that did not fit inside the cell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CellOverflow {
+    /// Day of week (0-4 for Monday-Friday)
+    pub day_index: usize,
+    /// Period index (0 = PD, 1 = L1, ...)
+    pub period_index: usize,
+    /// Which text overflowed
+    pub kind: OverflowKind,
+    /// The full text
+    pub text: String,
+}
+
+/// The text of a lesson cell that overflowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum OverflowKind {
+    /// Subject, shortened with an ellipsis
+    Subject,
+    /// Teacher name, shortened with an ellipsis
+    Teacher,
+    /// Lesson note, shortened with an ellipsis
+    Note,
+    /// Class code (or room) running past the end of the strip
+    Label,
+}
+
+impl std::fmt::Display for OverflowKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OverflowKind::Subject => "subject",
+            OverflowKind::Teacher => "teacher",
+            OverflowKind::Note => "note",
+            OverflowKind::Label => "class code",
+        })
+    }
+}
+
+/// The computed layout of one week's timetable area.
+///
+/// Coordinates are in the layout units of the rendered SVG (see
+/// [`RenderOptions`]), relative to the top left of the timetable area.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SheetLayout {
+    /// Width of the timetable area
+    pub width: i32,
+    /// Height of the timetable area
+    pub height: i32,
+    /// Grid rectangle (excluding period labels and header)
+    pub grid: Rect,
+    /// Height of a lesson row
+    pub row_height: i32,
+    /// Width of a day column
+    pub col_width: i32,
+    /// Header text (top left), empty when omitted
+    pub header: TextLabel,
+    /// Smaller line beneath the header, empty when omitted or unknown
+    pub subheader: TextLabel,
+    /// Title text (top centre), empty when omitted
+    pub title: TextLabel,
+    /// Week date range beneath the title, empty when the dates are unknown
+    pub dates: TextLabel,
+    /// Footer text (bottom right), empty when omitted
+    pub footer: TextLabel,
+    /// Day headers above each column
+    pub days: Vec<TextLabel>,
+    /// Period labels beside each row
+    pub periods: Vec<TextLabel>,
+    /// Break and lunch rows
+    pub gaps: Vec<GapRow>,
+    /// Lesson cells
+    pub cells: Vec<LessonCell>,
+    /// Empty slots drawn as free periods (only with `show_free_periods`)
+    pub free_periods: Vec<Rect>,
+    /// Alternate rows or columns shaded with `theme.zebra_color`
+    pub stripes: Vec<Rect>,
+    /// Shaded day column, from the day header to the bottom of the grid
+    pub highlight: Option<Rect>,
+}
+
+impl SheetLayout {
+    /// Compute the layout of `week` inside a timetable area of `width` × `height`,
+    /// with subject icons from `icons` (see [`IconSet::load`]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::{config::Config, icons::IconSet, layout::SheetLayout, parser::Week};
+    /// use timetable_core::renderer::RenderOptions;
+    ///
+    /// let layout = SheetLayout::compute(
+    ///     &Week::default(),
+    ///     &Config::default(),
+    ///     &IconSet::default(),
+    ///     &RenderOptions::default(),
+    ///     794,
+    ///     650,
+    /// );
+    /// assert_eq!(layout.days.len(), 5);
+    /// assert_eq!(layout.gaps.len(), 2);
+    /// ```
+    pub fn compute(
+        week: &Week,
+        config: &Config,
+        icons: &IconSet,
+        options: &RenderOptions,
+        width: i32,
+        height: i32,
+    ) -> SheetLayout {
+        let theme = &config.theme;
+
+        // Grid dimensions
+        let cols = 5; // Mon-Fri
+        let periods = PERIOD_LABELS.len() as i32;
+
+        let left_margin = options.margins.left; // Space for period labels
+        let top_margin = options.margins.top; // Space for student name and week
+        let right_margin = options.margins.right;
+        let bottom_margin = options.margins.bottom; // Space for update date
+
+        let grid_width = width - left_margin - right_margin;
+        let grid_height = height - top_margin - bottom_margin;
+
+        let breaks: Vec<_> = config
+            .break_rows()
+            .into_iter()
+            .map(|(period_idx, row)| (period_idx, gap_height(&row), row))
+            .collect();
+        let gaps_height: i32 = breaks.iter().map(|(_, height, _)| height).sum();
+        let row_height = (grid_height - gaps_height) / periods;
+        let col_width = grid_width / cols;
+        let cell_padding = theme.cell_padding;
+
+        // Header and footer text come from the configured templates, except that
+        // teacher sheets name the staff member and their department
+        let vars = template_vars(week, config, options.generation_time());
+        let (header_template, subheader_template) = if options.teacher_sheet {
+            ("{name}", "{department}")
+        } else {
+            (
+                config.templates.header.as_str(),
+                config.templates.subheader.as_str(),
+            )
+        };
+        let header = TextLabel {
+            text: fill_template(header_template, &vars),
+            x: left_margin,
+            y: 30,
+        };
+        let subheader = TextLabel {
+            text: fill_template(subheader_template, &vars),
+            x: left_margin,
+            y: 50,
+        };
+        let title = TextLabel {
+            text: fill_template(&config.templates.title, &vars),
+            x: width / 2,
+            y: 30,
+        };
+        let dates = TextLabel {
+            text: week
+                .starts_on
+                .map(|starts| config.strings.week_range(starts))
+                .unwrap_or_default(),
+            x: width / 2,
+            y: 50,
+        };
+        let footer = TextLabel {
+            text: fill_template(&config.templates.footer, &vars),
+            x: width - right_margin,
+            y: height - 10,
+        };
+
+        let days = (0..cols)
+            .map(|i| TextLabel {
+                text: config.strings.day(i as usize),
+                x: left_margin + (i * col_width) + (col_width / 2),
+                y: top_margin - 15,
+            })
+            .collect();
+
+        // Each period sits below the break and lunch rows that come before it
+        let row_top = |period_idx: usize| {
+            let gaps_above: i32 = breaks
+                .iter()
+                .filter(|(after, _, _)| *after < period_idx)
+                .map(|(_, height, _)| height)
+                .sum();
+            top_margin + (period_idx as i32 * row_height) + gaps_above
+        };
+
+        let periods = (0..PERIOD_LABELS.len())
+            .map(|period_idx| TextLabel {
+                text: config.strings.period(period_idx),
+                x: left_margin / 2,
+                y: row_top(period_idx) + (row_height / 2),
+            })
+            .collect();
+
+        let total_content_width = col_width * cols;
+        // Several rows after the same period stack in the order they are listed
+        let gaps = breaks
+            .iter()
+            .enumerate()
+            .map(|(i, (period_idx, height, row))| {
+                let stacked_above: i32 = breaks[..i]
+                    .iter()
+                    .filter(|(after, _, _)| after == period_idx)
+                    .map(|(_, height, _)| height)
+                    .sum();
+                GapRow {
+                    after_period: *period_idx,
+                    label: row.label.clone(),
+                    rect: Rect {
+                        x: left_margin + cell_padding,
+                        y: row_top(*period_idx) + row_height + stacked_above + cell_padding,
+                        width: total_content_width - (cell_padding * 2),
+                        height: height - (cell_padding * 2),
+                    },
+                }
+            })
+            .collect();
+
+        let fonts = CellFonts::new(config);
+        let cells = week
+            .lessons
+            .iter()
+            .map(|lesson| {
+                let x = left_margin + (lesson.day_index as i32 * col_width);
+                let y = row_top(lesson.period_index);
+                let mut cell =
+                    lesson_cell(lesson, config, &fonts, icons, x, y, col_width, row_height);
+                if theme.zebra.shades(lesson.day_index, lesson.period_index) {
+                    cell.fill_color =
+                        mix_colors(&theme.zebra_color, &cell.bg_color, theme.cell_tint)
+                            .unwrap_or_else(|| theme.zebra_color.clone());
+                }
+                // Teacher sheets show the class instead of the teacher, who is always the same
+                if options.teacher_sheet {
+                    cell.teacher = None;
+                    cell.teacher_line = cell.class_code.as_ref().map(|class| {
+                        let width = (cell.rect.width - theme.text_padding * 2) as f64;
+                        fonts.detail.truncate(class, theme.detail_font_size, width)
+                    });
+                    cell.description = describe_lesson(
+                        lesson,
+                        config,
+                        cell.room.as_deref(),
+                        cell.class_code.as_deref(),
+                    );
+                }
+                // Compact cells leave the teacher off the sheet entirely
+                if options.compact && !options.teacher_sheet {
+                    cell.description = describe_lesson(lesson, config, cell.room.as_deref(), None);
+                }
+                cell
+            })
+            .collect();
+
+        let stripes = match theme.zebra {
+            Zebra::None => Vec::new(),
+            Zebra::Rows => (0..PERIOD_LABELS.len())
+                .filter(|&period_idx| theme.zebra.shades(0, period_idx))
+                .map(|period_idx| Rect {
+                    x: left_margin,
+                    y: row_top(period_idx),
+                    width: total_content_width,
+                    height: row_height,
+                })
+                .collect(),
+            Zebra::Columns => (0..cols as usize)
+                .filter(|&day_idx| theme.zebra.shades(day_idx, 0))
+                .map(|day_idx| Rect {
+                    x: left_margin + (day_idx as i32 * col_width),
+                    y: top_margin,
+                    width: col_width,
+                    height: grid_height,
+                })
+                .collect(),
+        };
+
+        let mut free_periods = Vec::new();
+        if options.show_free_periods {
+            for period_idx in 0..PERIOD_LABELS.len() {
+                for day_idx in 0..cols as usize {
+                    let taken = week
+                        .lessons
+                        .iter()
+                        .any(|l| l.day_index == day_idx && l.period_index == period_idx);
+                    if !taken {
+                        free_periods.push(Rect {
+                            x: left_margin + (day_idx as i32 * col_width) + cell_padding,
+                            y: row_top(period_idx) + cell_padding,
+                            width: col_width - (cell_padding * 2),
+                            height: row_height - (cell_padding * 2),
+                        });
+                    }
+                }
+            }
+        }
+
+        let highlight = options
+            .highlight_day
+            .map(|day| day.num_days_from_monday() as i32)
+            .filter(|&day| day < cols)
+            .map(|day| Rect {
+                x: left_margin + day * col_width,
+                y: top_margin - 32,
+                width: col_width,
+                height: grid_height + 32,
+            });
+
+        SheetLayout {
+            width,
+            height,
+            grid: Rect {
+                x: left_margin,
+                y: top_margin,
+                width: grid_width,
+                height: grid_height,
+            },
+            row_height,
+            col_width,
+            header,
+            subheader,
+            title,
+            dates,
+            footer,
+            days,
+            periods,
+            gaps,
+            cells,
+            free_periods,
+            stripes,
+            highlight,
+        }
+    }
+
+    /// List the cells whose text did not fit, in the order the cells were laid out.
+    pub fn overflows(&self) -> Vec<CellOverflow> {
+        let mut overflows = Vec::new();
+        for cell in &self.cells {
+            let mut push = |kind, text: &str| {
+                overflows.push(CellOverflow {
+                    day_index: cell.day_index,
+                    period_index: cell.period_index,
+                    kind,
+                    text: text.to_string(),
+                })
+            };
+            if cell.subject_truncated {
+                push(OverflowKind::Subject, &cell.subject);
+            }
+            if let (Some(teacher), Some(line)) = (&cell.teacher, &cell.teacher_line) {
+                if teacher != line {
+                    push(OverflowKind::Teacher, teacher);
+                }
+            }
+            if let (Some(note), Some(line)) = (&cell.note, &cell.note_line) {
+                if note != line {
+                    push(OverflowKind::Note, note);
+                }
+            }
+            if cell.label_overflows {
+                push(OverflowKind::Label, &cell.label);
+            }
+        }
+        overflows
+    }
+}
+
+/// Text measurement for the fonts used inside lesson cells.
+pub(crate) struct CellFonts {
+    /// Subjects, in the regular weight of the theme font (see `.subject` in the stylesheet)
+    subject: TextMetrics,
+    /// Rooms and teachers, in the light font
+    detail: TextMetrics,
+    /// Class codes in the strip, in the semi-bold font
+    label: TextMetrics,
+}
+
+impl CellFonts {
+    /// Load metrics for the theme's fonts, using any embedded font files.
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            subject: TextMetrics::for_font_stack(config, &config.theme.font_family, 400),
+            detail: TextMetrics::for_font_stack(config, &config.theme.light_font_family, 300),
+            label: TextMetrics::for_font_stack(config, &config.theme.bold_font_family, 600),
+        }
+    }
+
+    #[cfg(test)]
+    fn heuristic() -> Self {
+        Self {
+            subject: TextMetrics::heuristic(),
+            detail: TextMetrics::heuristic(),
+            label: TextMetrics::heuristic(),
+        }
+    }
+}
+
+/// Spoken summary of a lesson, e.g. "Tuesday, L3, Science in SC8 with Mr Test B".
+fn describe_lesson(
+    lesson: &Lesson,
+    config: &Config,
+    room: Option<&str>,
+    teacher: Option<&str>,
+) -> String {
+    let mut description = format!(
+        "{}, {}, {}",
+        config.strings.day(lesson.day_index),
+        config.strings.period(lesson.period_index),
+        lesson.subject
+    );
+    let strings = &config.strings;
+    if let Some(room) = room {
+        description.push_str(&strings.lesson_room.replace("{room}", room));
+    }
+    if let Some(teacher) = teacher {
+        description.push_str(&strings.lesson_teacher.replace("{teacher}", teacher));
+    }
+    if let Some(note) = lesson.note.as_deref().filter(|n| !n.trim().is_empty()) {
+        description.push_str(&strings.lesson_note.replace("{note}", note));
+    }
+    let reminders: Vec<&str> = config
+        .reminders_for(lesson)
+        .iter()
+        .map(|rule| rule.badge.as_str())
+        .collect();
+    if !reminders.is_empty() {
+        description.push_str(
+            &strings
+                .lesson_reminders
+                .replace("{reminders}", &reminders.join(", ")),
+        );
+    }
+    description
+}
+
+/// Lay out reminder badges from the top right corner of `area`, right to left,
+/// starting a new row below when a row is full.
+fn layout_badges(lesson: &Lesson, config: &Config, fonts: &CellFonts, area: &Rect) -> Vec<Badge> {
+    let right = area.x + area.width - BADGE_GAP;
+    let mut x = right;
+    let mut y = area.y + BADGE_GAP;
+    let mut badges = Vec::new();
+    for rule in config.reminders_for(lesson) {
+        let width = fonts.label.width(&rule.badge, BADGE_FONT_SIZE).ceil() as i32 + BADGE_HEIGHT;
+        if x - width < area.x + BADGE_GAP && x != right {
+            x = right;
+            y += BADGE_HEIGHT + BADGE_GAP;
+        }
+        x -= width;
+        badges.push(Badge {
+            text: rule.badge.clone(),
+            color: rule.color.clone(),
+            text_color: contrasting_text(&rule.color),
+            rect: Rect {
+                x,
+                y,
+                width,
+                height: BADGE_HEIGHT,
+            },
+        });
+        x -= BADGE_GAP;
+    }
+    badges
+}
+
+/// Resolve the colours, texts and rectangles for one lesson.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn lesson_cell(
+    lesson: &Lesson,
+    config: &Config,
+    fonts: &CellFonts,
+    icons: &IconSet,
+    x: i32,
+    y: i32,
+    col_width: i32,
+    row_height: i32,
+) -> LessonCell {
+    let theme = &config.theme;
+
+    // Handle Unknown room - use dark grey
+    let unknown_room = lesson.room == UNKNOWN || lesson.room == "DEFAULT";
+
+    // Get color mapping from config
+    let mapping = (!unknown_room)
+        .then(|| config.get_style_for_room(&lesson.room))
+        .flatten();
+    let (bg_color, fg_color) = if unknown_room {
+        (
+            theme.unknown_bg_color.clone(),
+            theme.unknown_fg_color.clone(),
+        )
+    } else {
+        mapping
+            .filter(|_| !theme.patterns)
+            .map(|m| (m.bg_color.clone(), m.fg_color.clone()))
+            .unwrap_or((theme.cell_color.clone(), theme.text_color.clone()))
+    };
+    // Mono themes tell departments apart by hatching the strip instead
+    let pattern = mapping
+        .filter(|_| theme.patterns)
+        .map(|m| hatch_index(config, m));
+
+    // Use class_code if available, otherwise use subject for Unknown rooms, otherwise room code
+    let label = if !lesson.class_code.is_empty() {
+        lesson.class_code.clone()
+    } else if unknown_room {
+        lesson.subject.clone()
+    } else {
+        lesson.room.clone()
+    };
+
+    let fill_color = if theme.cell_tint > 0.0 {
+        mix_colors(&theme.cell_color, &bg_color, theme.cell_tint)
+            .unwrap_or_else(|| theme.cell_color.clone())
+    } else {
+        theme.cell_color.clone()
+    };
+
+    let cell_padding = theme.cell_padding; // Space between cells
+    let inner_width = col_width - (cell_padding * 2);
+    let inner_height = row_height - (cell_padding * 2);
+    let strip = theme.label_width;
+    let (left, top) = (x + cell_padding, y + cell_padding);
+
+    // Main area and coloured strip, split according to the strip position
+    let (rect, label_rect) = match theme.label_position {
+        LabelPosition::Right => (
+            Rect {
+                x: left,
+                y: top,
+                width: inner_width - strip,
+                height: inner_height,
+            },
+            Rect {
+                x: left + inner_width - strip,
+                y: top,
+                width: strip,
+                height: inner_height,
+            },
+        ),
+        LabelPosition::Left => (
+            Rect {
+                x: left + strip,
+                y: top,
+                width: inner_width - strip,
+                height: inner_height,
+            },
+            Rect {
+                x: left,
+                y: top,
+                width: strip,
+                height: inner_height,
+            },
+        ),
+        LabelPosition::Top => (
+            Rect {
+                x: left,
+                y: top + strip,
+                width: inner_width,
+                height: inner_height - strip,
+            },
+            Rect {
+                x: left,
+                y: top,
+                width: inner_width,
+                height: strip,
+            },
+        ),
+    };
+
+    // Wrap the subject to the text area, shrinking it if it still overflows
+    let text_width = (rect.width - theme.text_padding * 2) as f64;
+    // An icon sits before the subject, leaving it less room
+    let icon = config
+        .icon_for_lesson(lesson)
+        .filter(|id| icons.contains(id))
+        .map(str::to_string);
+    let subject_indent = if icon.is_some() {
+        theme.subject_font_size.round() as i32 + ICON_GAP
+    } else {
+        0
+    };
+    let note = lesson.note.clone().filter(|note| !note.trim().is_empty());
+    let detail_height = match note {
+        Some(_) => DETAIL_LINES_HEIGHT + NOTE_LINE_HEIGHT,
+        None => DETAIL_LINES_HEIGHT,
+    };
+    let max_lines = ((rect.height - detail_height) as f64 / theme.subject_font_size)
+        .floor()
+        .max(1.0) as usize;
+    let subject = fonts.subject.fit(
+        &lesson.subject,
+        theme.subject_font_size,
+        theme.subject_font_size * MIN_SUBJECT_SCALE,
+        text_width - subject_indent as f64,
+        max_lines,
+    );
+
+    let teacher = Some(lesson.teacher.as_str())
+        .filter(|t| *t != UNKNOWN)
+        .and_then(|t| config.teacher_names.apply(t));
+    // The `.detail` stylesheet rule decides the size the teacher is drawn at
+    let teacher_line = teacher
+        .as_ref()
+        .map(|t| fonts.detail.truncate(t, theme.detail_font_size, text_width));
+    let note_line = note.as_ref().map(|n| {
+        fonts
+            .detail
+            .truncate(n, theme.teacher_font_size, text_width)
+    });
+
+    let room = (lesson.room != UNKNOWN).then(|| lesson.room.clone());
+    let label_room = room
+        .clone()
+        .filter(|_| theme.label_room && !lesson.class_code.is_empty() && !unknown_room);
+
+    // The class code runs along a side strip, or across a top strip
+    let label_length = match theme.label_position {
+        LabelPosition::Left | LabelPosition::Right => label_rect.height,
+        LabelPosition::Top => label_rect.width,
+    } as f64;
+    let label_size = label_font_size(theme, &label_rect, label_room.is_some());
+    let label_overflows = fonts.label.width(&label, label_size) > label_length
+        || label_room
+            .as_ref()
+            .is_some_and(|r| fonts.detail.width(r, theme.teacher_font_size) > label_length);
+
+    let description = describe_lesson(lesson, config, room.as_deref(), teacher.as_deref());
+
+    LessonCell {
+        day_index: lesson.day_index,
+        period_index: lesson.period_index,
+        rect,
+        label_rect,
+        bg_color,
+        fg_color,
+        pattern,
+        fill_color,
+        subject: lesson.subject.clone(),
+        subject_lines: subject.lines,
+        subject_font_size: subject.size,
+        subject_truncated: subject.truncated,
+        room,
+        teacher,
+        teacher_line,
+        class_code: (!lesson.class_code.is_empty()).then(|| lesson.class_code.clone()),
+        note,
+        note_line,
+        label,
+        label_room,
+        label_overflows,
+        unknown_room,
+        text_x: rect.x + theme.text_padding,
+        icon,
+        subject_x: rect.x + theme.text_padding + subject_indent,
+        badges: layout_badges(lesson, config, fonts, &rect),
+        description,
+    }
+}
+
+/// Height of a break row: [`GAP_HEIGHT`], or scaled to its duration but never
+/// too short for its label (px). Durations past [`MAX_BREAK_MINUTES`], which
+/// [`Config::validate`] rejects, are drawn at that length.
+pub(crate) fn gap_height(row: &BreakRow) -> i32 {
+    match row.duration {
+        Some(minutes) => {
+            let minutes = minutes.min(MAX_BREAK_MINUTES) as i32;
+            (GAP_HEIGHT * minutes / GAP_MINUTES).max(GAP_HEIGHT / 2)
+        }
+        None => GAP_HEIGHT,
+    }
+}
+
+/// Font size of the class code in `strip`, smaller when the room is stacked beneath it.
+pub(crate) fn label_font_size(theme: &Theme, strip: &Rect, stacked: bool) -> f64 {
+    let size = match theme.label_position {
+        LabelPosition::Left | LabelPosition::Right => theme.class_code_font_size,
+        LabelPosition::Top => theme.class_code_font_size.min(strip.height as f64 * 0.8),
+    };
+    if stacked {
+        size * STACKED_LABEL_SCALE
+    } else {
+        size
+    }
+}
+
+/// Blend two `#rrggbb`/`#rgb` colours, `amount` of the way from `base` to `color`.
+///
+/// Returns `None` if either colour is not a hex code.
+pub(crate) fn mix_colors(base: &str, color: &str, amount: f64) -> Option<String> {
+    let (base, color) = (rgb(base)?, rgb(color)?);
+    let amount = amount.clamp(0.0, 1.0);
+    let mix =
+        |i: usize| (base[i] as f64 + (color[i] as f64 - base[i] as f64) * amount).round() as u8;
+    Some(format!("#{:02x}{:02x}{:02x}", mix(0), mix(1), mix(2)))
+}
+
+/// Dark text on light colours and white text on dark ones (and on anything
+/// that is not a hex code).
+fn contrasting_text(color: &str) -> String {
+    let light = rgb(color)
+        .is_some_and(|[r, g, b]| 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64 > 150.0);
+    if light { "#231f20" } else { "#ffffff" }.to_string()
+}
+
+/// Parse a `#rrggbb` or `#rgb` colour into its channels.
+fn rgb(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim().strip_prefix('#')?;
+    let hex: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Mapping, Reminder, TeacherNames};
+
+    #[test]
+    fn lesson_cell_wraps_and_shrinks_subject_to_fit() {
+        let config = Config::default();
+        let fonts = CellFonts::heuristic();
+        let lesson = |subject: &str| Lesson {
+            subject: subject.into(),
+            room: "MA3".into(),
+            ..Default::default()
+        };
+
+        let cell = lesson_cell(
+            &lesson("Art and Design"),
+            &config,
+            &fonts,
+            &IconSet::default(),
+            0,
+            0,
+            140,
+            80,
+        );
+        assert_eq!(cell.subject_lines, vec!["Art and Design"]);
+        assert_eq!(cell.subject_font_size, 11.0);
+
+        let cell = lesson_cell(
+            &lesson("Sociolinguistics"),
+            &config,
+            &fonts,
+            &IconSet::default(),
+            0,
+            0,
+            140,
+            80,
+        );
+        assert_eq!(cell.subject_lines, vec!["Sociolinguistics"]);
+        assert!(cell.subject_font_size < 11.0);
+        assert!(!cell.subject_truncated);
+    }
+
+    #[test]
+    fn lesson_cell_truncates_text_that_cannot_fit() {
+        let config = Config::default();
+        let lesson = Lesson {
+            subject: "Internationalisation".into(),
+            room: "MA3".into(),
+            teacher: "Professor Bartholomew Fitzgerald".into(),
+            ..Default::default()
+        };
+
+        let cell = lesson_cell(
+            &lesson,
+            &config,
+            &CellFonts::heuristic(),
+            &IconSet::default(),
+            0,
+            0,
+            140,
+            80,
+        );
+        assert_eq!(cell.subject_lines, vec!["Internationalisat…"]);
+        assert!(cell.subject_truncated);
+        assert_eq!(cell.teacher_line.as_deref(), Some("Professor Bar…"));
+        assert_eq!(
+            cell.teacher.as_deref(),
+            Some("Professor Bartholomew Fitzgerald")
+        );
+    }
+
+    #[test]
+    fn overflows_lists_text_that_did_not_fit() {
+        let week = Week {
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Professor Bartholomew Fitzgerald".into(),
+                class_code: "10A/Ma1-Set3".into(),
+                day_index: 2,
+                period_index: 4,
+                note: None,
+            }],
+            ..Default::default()
+        };
+
+        let layout = SheetLayout::compute(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            &RenderOptions::default(),
+            794,
+            650,
+        );
+        let overflows = layout.overflows();
+
+        assert_eq!(overflows.len(), 2);
+        assert_eq!(overflows[0].kind, OverflowKind::Teacher);
+        assert_eq!((overflows[0].day_index, overflows[0].period_index), (2, 4));
+        assert_eq!(overflows[1].kind, OverflowKind::Label);
+        assert_eq!(overflows[1].text, "10A/Ma1-Set3");
+    }
+
+    #[test]
+    fn label_strip_follows_theme_position() {
+        let mut config = Config::default();
+        config.theme.label_width = 40;
+        config.theme.label_position = LabelPosition::Left;
+        let lesson = Lesson {
+            subject: "Maths".into(),
+            room: "MA3".into(),
+            ..Default::default()
+        };
+
+        let cell = lesson_cell(
+            &lesson,
+            &config,
+            &CellFonts::heuristic(),
+            &IconSet::default(),
+            100,
+            200,
+            140,
+            90,
+        );
+        assert_eq!(
+            cell.label_rect,
+            Rect {
+                x: 103,
+                y: 203,
+                width: 40,
+                height: 84
+            }
+        );
+        assert_eq!(cell.rect.x, 143);
+        assert_eq!(cell.text_x, 148);
+
+        config.theme.label_position = LabelPosition::Top;
+        let cell = lesson_cell(
+            &lesson,
+            &config,
+            &CellFonts::heuristic(),
+            &IconSet::default(),
+            100,
+            200,
+            140,
+            90,
+        );
+        assert_eq!(
+            cell.label_rect,
+            Rect {
+                x: 103,
+                y: 203,
+                width: 134,
+                height: 40
+            }
+        );
+        assert_eq!(
+            cell.rect,
+            Rect {
+                x: 103,
+                y: 243,
+                width: 134,
+                height: 44
+            }
+        );
+    }
+
+    #[test]
+    fn zebra_rows_shade_alternate_periods() {
+        let mut config = Config::default();
+        config.theme.zebra = Zebra::Rows;
+        let week = Week {
+            lessons: vec![
+                Lesson {
+                    subject: "Maths".into(),
+                    period_index: 0,
+                    ..Default::default()
+                },
+                Lesson {
+                    subject: "Science".into(),
+                    period_index: 1,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let layout = SheetLayout::compute(
+            &week,
+            &config,
+            &IconSet::default(),
+            &RenderOptions::default(),
+            794,
+            650,
+        );
+
+        // L1, L3 and L5 are shaded; PD and L2 are not
+        assert_eq!(layout.stripes.len(), 3);
+        assert_eq!(layout.stripes[0].y, layout.cells[1].rect.y - 3);
+        assert_eq!(layout.cells[0].fill_color, "#ffffff");
+        assert_eq!(layout.cells[1].fill_color, "#f2f2f2");
+    }
+
+    #[test]
+    fn mix_colors_blends_towards_department_colour() {
+        assert_eq!(
+            mix_colors("#ffffff", "#000000", 0.5).as_deref(),
+            Some("#808080")
+        );
+        assert_eq!(
+            mix_colors("#fff", "#f0503f", 0.0).as_deref(),
+            Some("#ffffff")
+        );
+        assert_eq!(mix_colors("white", "#f0503f", 0.5), None);
+    }
+
+    #[test]
+    fn break_rows_follow_the_config() {
+        let config = Config::builder()
+            .break_row(BreakRow::new("L2", "Break").duration(15))
+            .break_row(BreakRow::new("L4", "Lunch 1").duration(30))
+            .break_row(BreakRow::new("L4", "Lunch 2"))
+            .break_row(BreakRow::new("L9", "Nowhere"))
+            .build();
+        let week = Week {
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                period_index: 5,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let layout = SheetLayout::compute(
+            &week,
+            &config,
+            &IconSet::default(),
+            &RenderOptions::default(),
+            794,
+            650,
+        );
+
+        let gaps: Vec<_> = layout
+            .gaps
+            .iter()
+            .map(|g| (g.after_period, g.label.as_str(), g.rect.height))
+            .collect();
+        assert_eq!(
+            gaps,
+            vec![(2, "Break", 12), (4, "Lunch 1", 30), (4, "Lunch 2", 18)]
+        );
+        // The split lunch rows stack, and L5 starts below both
+        assert_eq!(layout.gaps[2].rect.y, layout.gaps[1].rect.y + 36);
+        assert_eq!(
+            layout.cells[0].rect.y,
+            80 + 5 * layout.row_height + 18 + 36 + 24 + 3
+        );
+
+        let config = Config {
+            breaks: Some(Vec::new()),
+            ..Default::default()
+        };
+        let layout = SheetLayout::compute(
+            &week,
+            &config,
+            &IconSet::default(),
+            &RenderOptions::default(),
+            794,
+            650,
+        );
+        assert!(layout.gaps.is_empty());
+        assert_eq!(layout.cells[0].rect.y, 80 + 5 * layout.row_height + 3);
+    }
+
+    #[test]
+    fn compute_places_cells_and_resolves_colours() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").fg_color("#f0503f")],
+            ..Default::default()
+        };
+        let week = Week {
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Unknown".into(),
+                class_code: String::new(),
+                day_index: 1,
+                period_index: 3,
+                note: None,
+            }],
+            ..Default::default()
+        };
+
+        let layout = SheetLayout::compute(
+            &week,
+            &config,
+            &IconSet::default(),
+            &RenderOptions::default(),
+            794,
+            650,
+        );
+        let cell = &layout.cells[0];
+
+        // Period 3 follows the break row
+        assert_eq!(cell.rect.y, 80 + 3 * layout.row_height + GAP_HEIGHT + 3);
+        assert_eq!(cell.rect.x, 60 + layout.col_width + 3);
+        assert_eq!(cell.bg_color, "#fcdcd8");
+        assert_eq!(cell.label, "MA3");
+        assert_eq!(cell.teacher, None);
+    }
+
+    #[test]
+    fn teacher_names_are_reduced_or_hidden() {
+        let week = Week {
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Dr Jane Smith".into(),
+                class_code: String::new(),
+                day_index: 0,
+                period_index: 1,
+                note: None,
+            }],
+            ..Default::default()
+        };
+        let teacher = |teacher_names| {
+            let config = Config {
+                teacher_names,
+                ..Default::default()
+            };
+            let layout = SheetLayout::compute(
+                &week,
+                &config,
+                &IconSet::default(),
+                &RenderOptions::default(),
+                794,
+                650,
+            );
+            layout.cells[0].teacher.clone()
+        };
+
+        assert_eq!(
+            teacher(TeacherNames::Full).as_deref(),
+            Some("Dr Jane Smith")
+        );
+        assert_eq!(teacher(TeacherNames::Initials).as_deref(), Some("Dr J S"));
+        assert_eq!(teacher(TeacherNames::Hidden), None);
+    }
+
+    #[test]
+    fn reminder_badges_line_up_from_the_corner_and_wrap() {
+        let config = Config::builder()
+            .reminder(Reminder::new("Kit"))
+            .reminder(Reminder::new("Homework").color("#fff3b0"))
+            .reminder(Reminder::new("Instrument"))
+            .build();
+        let lesson = Lesson {
+            subject: "PE".into(),
+            room: "PE1".into(),
+            ..Default::default()
+        };
+
+        // Heuristic text widths round up to 15, 39 and 48px, plus 13px for the rounded ends;
+        // "Instrument" no longer fits beside the others and starts a second row
+        let area = Rect {
+            x: 0,
+            y: 0,
+            width: 110,
+            height: 80,
+        };
+        let badges = layout_badges(&lesson, &config, &CellFonts::heuristic(), &area);
+
+        let rects: Vec<_> = badges
+            .iter()
+            .map(|b| (b.rect.x, b.rect.y, b.rect.width))
+            .collect();
+        assert_eq!(rects, [(79, 3, 28), (24, 3, 52), (46, 19, 61)]);
+        assert_eq!(badges[0].text_color, "#ffffff");
+        assert_eq!(badges[1].text_color, "#231f20");
+    }
+}
@@ -10,23 +10,26 @@
 //! - Apply per-week/day/period overrides to correct parsing errors
 //! - Highlight school map SVGs based on department locations
 //! - Render A4-sized SVG timetables with embedded maps
+//! - Render responsive HTML pages with hover tooltips and an inline map
 //!
 //! ## Example Usage
 //!
 //! ```no_run
-//! use timetable_core::{config::Config, parser::parse_pdf, renderer::render_timetable, processor::{process_map, MapHighlight}};
+//! use timetable_core::{config::Config, icons::IconSet, parser::parse_pdf, renderer::{render_timetable, RenderOptions}, processor::{process_map, MapHighlight}};
 //! use std::path::Path;
 //! use std::collections::HashSet;
 //!
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! // Load configuration
 //! let config = Config::load(Path::new("config.toml"))?;
+//! let icons = IconSet::load(&config)?;
 //!
 //! // Parse PDF timetable
 //! let mut weeks = parse_pdf(Path::new("input/timetable.pdf"))?;
 //!
-//! // Apply overrides from config
+//! // Apply overrides and alias normalisation from config
 //! config.apply_overrides(&mut weeks);
+//! config.apply_aliases(&mut weeks);
 //!
 //! // Render each week to SVG
 //! for (i, week) in weeks.iter().enumerate() {
@@ -37,10 +40,9 @@
 //!     for lesson in &week.lessons {
 //!         if let Some(mapping) = config.get_style_for_room(&lesson.room) {
 //!             if seen_ids.insert(mapping.map_id.clone()) {
-//!                 highlights.push(MapHighlight {
-//!                     id: mapping.map_id.clone(),
-//!                     color: mapping.bg_color.clone(),
-//!                 });
+//!                 highlights.push(
+//!                     MapHighlight::new(&mapping.map_id, &mapping.bg_color).style(mapping.highlight),
+//!                 );
 //!             }
 //!         }
 //!     }
@@ -50,7 +52,7 @@
 //!
 //!     // Render to output file
 //!     let output_path = format!("output/week_{}.svg", i + 1);
-//!     render_timetable(week, &config, &map_svg, Path::new(&output_path))?;
+//!     render_timetable(week, &config, &icons, &map_svg, &RenderOptions::default(), Path::new(&output_path))?;
 //! }
 //! # Ok(())
 //! # }
@@ -59,14 +61,26 @@
 //! ## Modules
 //!
 //! - [`config`]: Configuration loading and room-to-department mapping
+//! - [`html`]: Interactive HTML timetable pages
+//! - [`icons`]: Subject icon symbols, bundled and user-provided
+//! - [`latex`]: LaTeX `tabularx` export for typeset planners
+//! - [`layout`]: Serialisable model of the computed sheet layout
+//! - [`metrics`]: Text measurement for wrapping and shrinking text to fit cells
 //! - [`parser`]: PDF parsing and text extraction from Bromcom PDFs
 //! - [`processor`]: SVG map manipulation and department highlighting
 //! - [`renderer`]: Timetable SVG generation with embedded maps
+//! - [`typst`]: Typst source export for post-processing in Typst
 
 pub mod config;
+pub mod html;
+pub mod icons;
+pub mod latex;
+pub mod layout;
+pub mod metrics;
 pub mod parser;
 pub mod processor;
 pub mod renderer;
+pub mod typst;
 
 pub fn hello() {
     println!("Hello from core!");
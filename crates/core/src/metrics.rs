@@ -0,0 +1,223 @@
+//! Text measurement for fitting labels inside cells.
+//!
+//! Widths come from the font file itself when the theme's font is embedded
+//! through `[[fonts]]` as TrueType or OpenType. Otherwise an average glyph
+//! width is assumed, which is close enough for common sans-serif fonts.
+
+use crate::config::Config;
+use std::fs;
+
+/// Average glyph advance as a fraction of the font size, used without a font file.
+const AVERAGE_GLYPH_WIDTH: f64 = 0.6;
+
+/// Measures rendered text width for one font.
+pub struct TextMetrics {
+    font: Option<fontdue::Font>,
+}
+
+impl TextMetrics {
+    /// Metrics based on an average glyph width, for when no font file is available.
+    pub fn heuristic() -> Self {
+        Self { font: None }
+    }
+
+    /// Metrics for the first family in a CSS font stack that is embedded in `config`.
+    ///
+    /// Among the files embedded for that family, the one whose weight is closest
+    /// to `weight` is used. Falls back to [`TextMetrics::heuristic`] if no family
+    /// is embedded or the file cannot be read as TrueType or OpenType.
+    pub fn for_font_stack(config: &Config, stack: &str, weight: u16) -> Self {
+        let face = stack
+            .split(',')
+            .map(|f| f.trim().trim_matches(|c| c == '\'' || c == '"'))
+            .find_map(|family| {
+                config
+                    .fonts
+                    .iter()
+                    .filter(|f| f.family.eq_ignore_ascii_case(family))
+                    .min_by_key(|f| f.weight.abs_diff(weight))
+            });
+
+        let font = face
+            .and_then(|face| fs::read(&face.path).ok())
+            .and_then(|bytes| fontdue::Font::from_bytes(bytes, Default::default()).ok());
+        Self { font }
+    }
+
+    /// Width of `text` set at `size` px.
+    pub fn width(&self, text: &str, size: f64) -> f64 {
+        let Some(font) = &self.font else {
+            return text.chars().count() as f64 * size * AVERAGE_GLYPH_WIDTH;
+        };
+
+        let px = size as f32;
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            if let Some(kern) = previous.and_then(|p| font.horizontal_kern(p, c, px)) {
+                width += kern;
+            }
+            width += font.metrics(c, px).advance_width;
+            previous = Some(c);
+        }
+        width as f64
+    }
+
+    /// Wrap `text` at word boundaries into lines no wider than `max_width`.
+    ///
+    /// A single word wider than `max_width` is kept whole on its own line.
+    pub fn wrap(&self, text: &str, size: f64, max_width: f64) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut current_line = String::new();
+        for word in text.split_whitespace() {
+            if current_line.is_empty() {
+                current_line = word.to_string();
+                continue;
+            }
+            let candidate = format!("{} {}", current_line, word);
+            if self.width(&candidate, size) <= max_width {
+                current_line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut current_line, word.to_string()));
+            }
+        }
+        if !current_line.is_empty() || lines.is_empty() {
+            lines.push(current_line);
+        }
+        lines
+    }
+
+    /// Shorten `text` with a trailing ellipsis so it is no wider than `max_width`.
+    ///
+    /// Text that already fits is returned unchanged.
+    pub fn truncate(&self, text: &str, size: f64, max_width: f64) -> String {
+        if self.width(text, size) <= max_width {
+            return text.to_string();
+        }
+        let mut chars: Vec<char> = text.chars().collect();
+        while !chars.is_empty() {
+            chars.pop();
+            let candidate = format!("{}…", chars.iter().collect::<String>().trim_end());
+            if self.width(&candidate, size) <= max_width {
+                return candidate;
+            }
+        }
+        "…".to_string()
+    }
+
+    /// Wrap `text` into at most `max_lines` lines of `max_width`, shrinking the
+    /// font from `size` towards `min_size` until it fits.
+    ///
+    /// If the text does not fit even at `min_size`, the lines are cut short
+    /// with an ellipsis and the result is marked as truncated.
+    pub fn fit(
+        &self,
+        text: &str,
+        size: f64,
+        min_size: f64,
+        max_width: f64,
+        max_lines: usize,
+    ) -> FittedText {
+        let mut size = size;
+        loop {
+            let lines = self.wrap(text, size, max_width);
+            let fits = lines.len() <= max_lines
+                && lines.iter().all(|line| self.width(line, size) <= max_width);
+            if fits {
+                return FittedText {
+                    lines,
+                    size,
+                    truncated: false,
+                };
+            }
+            if size <= min_size {
+                break;
+            }
+            size = (size - 0.5).max(min_size);
+        }
+
+        // Keep the lines that fit and end the last one with an ellipsis
+        let mut lines = self.wrap(text, size, max_width);
+        if lines.len() > max_lines.max(1) {
+            let rest = lines.split_off(max_lines.max(1) - 1).join(" ");
+            lines.push(format!("{}…", rest));
+        }
+        let lines = lines
+            .iter()
+            .map(|line| self.truncate(line, size, max_width))
+            .collect();
+        FittedText {
+            lines,
+            size,
+            truncated: true,
+        }
+    }
+}
+
+/// Text wrapped, and if necessary shrunk or truncated, to fit a box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FittedText {
+    /// Lines to draw
+    pub lines: Vec<String>,
+    /// Font size the lines fit at (px)
+    pub size: f64,
+    /// Whether text was cut short with an ellipsis
+    pub truncated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FontFace;
+
+    #[test]
+    fn wrap_splits_long_names_by_width() {
+        let metrics = TextMetrics::heuristic();
+        assert_eq!(metrics.wrap("Maths", 11.0, 94.0), vec!["Maths"]);
+        assert_eq!(
+            metrics.wrap("Personal Development Intervention", 11.0, 94.0),
+            vec!["Personal", "Development", "Intervention"]
+        );
+        assert_eq!(
+            metrics.wrap("Art and Design", 11.0, 94.0),
+            vec!["Art and Design"]
+        );
+    }
+
+    #[test]
+    fn fit_shrinks_text_that_overflows() {
+        let metrics = TextMetrics::heuristic();
+
+        let fitted = metrics.fit("Electronics", 11.0, 8.0, 60.0, 3);
+        assert_eq!(fitted.lines, vec!["Electronics"]);
+        assert_eq!(fitted.size, 9.0);
+        assert!(!fitted.truncated);
+
+        let fitted = metrics.fit("Supercalifragilistic", 11.0, 8.0, 60.0, 3);
+        assert_eq!(fitted.size, 8.0);
+    }
+
+    #[test]
+    fn fit_truncates_text_that_cannot_fit() {
+        let metrics = TextMetrics::heuristic();
+
+        // 12 characters fit in 60px at 8px
+        let fitted = metrics.fit("Supercalifragilistic", 11.0, 8.0, 60.0, 3);
+        assert_eq!(fitted.lines, vec!["Supercalifr…"]);
+        assert!(fitted.truncated);
+
+        let fitted = metrics.fit("One Two Three Four Five Six", 8.0, 8.0, 30.0, 2);
+        assert_eq!(fitted.lines, vec!["One", "Two T…"]);
+        assert!(fitted.truncated);
+    }
+
+    #[test]
+    fn for_font_stack_falls_back_without_embedded_font() {
+        let config = Config::builder()
+            .font_face(FontFace::new("Lexend", "/nonexistent/Lexend.ttf"))
+            .build();
+        let metrics = TextMetrics::for_font_stack(&config, "'Lexend', sans-serif", 700);
+        assert!(metrics.font.is_none());
+        assert_eq!(metrics.width("abcd", 10.0), 24.0);
+    }
+}
@@ -3,6 +3,7 @@
 //! This module extracts text with coordinates from Bromcom PDF files and reconstructs
 //! the timetable grid structure using heuristics for day/period detection.
 
+use chrono::{Datelike, NaiveDate, Weekday};
 use lopdf::{Document, Object};
 use regex::Regex;
 use std::path::Path;
@@ -23,7 +24,7 @@ pub enum ParserError {
 }
 
 /// A single lesson entry in the timetable.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Lesson {
     /// Subject name (e.g., "Mathematics", "French")
     pub subject: String,
@@ -37,10 +38,12 @@ pub struct Lesson {
     pub day_index: usize,
     /// Period index (0 = PD, 1 = L1, 2 = L2, etc.)
     pub period_index: usize,
+    /// Free-text note printed beneath the subject (e.g., "Bring calculator"), set by overrides
+    pub note: Option<String>,
 }
 
 /// A week of timetable data containing multiple lessons.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Week {
     /// All lessons for this week
     pub lessons: Vec<Lesson>,
@@ -50,6 +53,38 @@ pub struct Week {
     pub student_name: Option<String>,
     /// Form/class code (e.g., "11XX")
     pub form: Option<String>,
+    /// Year group (e.g., "Year 11")
+    pub year_group: Option<String>,
+    /// House the student belongs to (e.g., "Austen")
+    pub house: Option<String>,
+    /// File name of the PDF this week was parsed from (e.g., "timetable.pdf")
+    pub source: Option<String>,
+    /// Monday of the teaching week, when known from the term calendar
+    pub starts_on: Option<NaiveDate>,
+}
+
+impl Week {
+    /// Day of this week that falls on `date`, if the week's dates are known
+    /// and `date` is a school day within it.
+    pub fn weekday_on(&self, date: NaiveDate) -> Option<Weekday> {
+        let starts = self.starts_on?;
+        let offset = (date - starts).num_days();
+        (0..5).contains(&offset).then(|| date.weekday())
+    }
+
+    /// This week with only the lessons of `day_index` (0 = Monday), e.g. to
+    /// highlight a map with just the rooms of one day.
+    pub fn day(&self, day_index: usize) -> Week {
+        Week {
+            lessons: self
+                .lessons
+                .iter()
+                .filter(|lesson| lesson.day_index == day_index)
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
 }
 
 /// Internal representation of text item with coordinates.
@@ -97,6 +132,9 @@ struct TextItem {
 /// ```
 pub fn parse_pdf(path: &Path) -> Result<Vec<Week>, ParserError> {
     let doc = Document::load(path)?;
+    let source = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
     let mut weeks = Vec::new();
 
     for (page_num, page_id) in doc.get_pages() {
@@ -109,9 +147,128 @@ pub fn parse_pdf(path: &Path) -> Result<Vec<Week>, ParserError> {
         weeks.extend(page_weeks);
     }
 
+    for week in &mut weeks {
+        week.source = source.clone();
+    }
+
     Ok(weeks)
 }
 
+/// Split the weeks parsed from a PDF holding several students' timetables,
+/// such as a year-group export, into each student's weeks, in the order they
+/// appear. A week without a student name of its own belongs to the student
+/// before it.
+///
+/// ```
+/// use timetable_core::parser::{split_students, Week};
+///
+/// let week = |name: &str| Week {
+///     student_name: Some(name.into()),
+///     ..Default::default()
+/// };
+/// let students = split_students(vec![week("Alex"), week("Alex"), week("Sam")]);
+/// assert_eq!(students.len(), 2);
+/// assert_eq!(students[0].len(), 2);
+/// ```
+pub fn split_students(weeks: Vec<Week>) -> Vec<Vec<Week>> {
+    let mut students: Vec<Vec<Week>> = Vec::new();
+    for week in weeks {
+        match students.last_mut() {
+            Some(student)
+                if week.student_name.is_none()
+                    || student.iter().any(|w| w.student_name == week.student_name) =>
+            {
+                student.push(week)
+            }
+            _ => students.push(vec![week]),
+        }
+    }
+    students
+}
+
+/// Room, teacher or subject given to a lesson whose cell in the PDF has none.
+pub const UNKNOWN: &str = "Unknown";
+
+/// Titles recognised at the start of a teacher's name (lowercase, without a trailing dot).
+pub(crate) const TEACHER_TITLES: [&str; 9] =
+    ["mr", "mrs", "ms", "miss", "mx", "dr", "prof", "sir", "rev"];
+
+/// Surnames given to teachers by [`anonymise`], in order of first appearance.
+const PLACEHOLDER_SURNAMES: [&str; 16] = [
+    "Adams", "Baker", "Clarke", "Davies", "Evans", "Fisher", "Green", "Hughes", "Jones", "King",
+    "Lewis", "Morgan", "Patel", "Roberts", "Taylor", "Walker",
+];
+
+/// Replace the student name, form, year group, house, source file name and
+/// teacher names in `weeks` with placeholders, so timetables can be shared when reporting bugs.
+///
+/// Each teacher keeps their title and is given the same placeholder surname
+/// in every week, so the shape of the timetable is preserved.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::parser::{anonymise, Lesson, Week};
+///
+/// let mut weeks = vec![Week {
+///     student_name: Some("Jamie Example".into()),
+///     lessons: vec![Lesson { teacher: "Mrs Hopkins".into(), ..Default::default() }],
+///     ..Default::default()
+/// }];
+/// anonymise(&mut weeks);
+/// assert_eq!(weeks[0].student_name.as_deref(), Some("Alex Testington"));
+/// assert_eq!(weeks[0].lessons[0].teacher, "Mrs Adams");
+/// ```
+pub fn anonymise(weeks: &mut [Week]) {
+    let mut teachers: Vec<String> = Vec::new();
+
+    for week in weeks.iter_mut() {
+        if week.student_name.is_some() {
+            week.student_name = Some("Alex Testington".to_string());
+        }
+        if week.form.is_some() {
+            week.form = Some("11XX".to_string());
+        }
+        if week.year_group.is_some() {
+            week.year_group = Some("Year 11".to_string());
+        }
+        if week.house.is_some() {
+            week.house = Some("Austen".to_string());
+        }
+        if week.source.is_some() {
+            week.source = Some("timetable.pdf".to_string());
+        }
+
+        for lesson in &mut week.lessons {
+            if lesson.teacher.is_empty() || lesson.teacher == UNKNOWN {
+                continue;
+            }
+            let index = match teachers.iter().position(|t| *t == lesson.teacher) {
+                Some(index) => index,
+                None => {
+                    teachers.push(lesson.teacher.clone());
+                    teachers.len() - 1
+                }
+            };
+
+            let title = lesson
+                .teacher
+                .split_whitespace()
+                .next()
+                .filter(|word| {
+                    let bare = word.trim_end_matches('.').to_ascii_lowercase();
+                    TEACHER_TITLES.contains(&bare.as_str())
+                })
+                .unwrap_or("Mx");
+            let surname = PLACEHOLDER_SURNAMES[index % PLACEHOLDER_SURNAMES.len()];
+            lesson.teacher = match index / PLACEHOLDER_SURNAMES.len() {
+                0 => format!("{} {}", title, surname),
+                n => format!("{} {}-{}", title, surname, n + 1),
+            };
+        }
+    }
+}
+
 fn extract_text_from_page(
     doc: &Document,
     page_id: (u32, u16),
@@ -129,26 +286,22 @@ fn extract_text_from_page(
                 current_x = 0.0;
                 current_y = 0.0;
             }
-            "Tm" => {
-                if operation.operands.len() == 6 {
-                    if let (Ok(e), Ok(f)) = (
-                        operation.operands[4].as_float(),
-                        operation.operands[5].as_float(),
-                    ) {
-                        current_x = e as f64;
-                        current_y = f as f64;
-                    }
+            "Tm" if operation.operands.len() == 6 => {
+                if let (Ok(e), Ok(f)) = (
+                    operation.operands[4].as_float(),
+                    operation.operands[5].as_float(),
+                ) {
+                    current_x = e as f64;
+                    current_y = f as f64;
                 }
             }
-            "Td" | "TD" => {
-                if operation.operands.len() == 2 {
-                    if let (Ok(tx), Ok(ty)) = (
-                        operation.operands[0].as_float(),
-                        operation.operands[1].as_float(),
-                    ) {
-                        current_x += tx as f64;
-                        current_y += ty as f64;
-                    }
+            "Td" | "TD" if operation.operands.len() == 2 => {
+                if let (Ok(tx), Ok(ty)) = (
+                    operation.operands[0].as_float(),
+                    operation.operands[1].as_float(),
+                ) {
+                    current_x += tx as f64;
+                    current_y += ty as f64;
                 }
             }
             "Tj" => {
@@ -282,6 +435,7 @@ fn process_page_text(items: Vec<TextItem>, _page_num: u32) -> Vec<Week> {
 
         // Try to extract student name and form from the page
         let (student_name, form) = extract_student_info(&week_items);
+        let (year_group, house) = extract_year_and_house(&week_items);
 
         if !lessons.is_empty() {
             weeks.push(Week {
@@ -289,6 +443,10 @@ fn process_page_text(items: Vec<TextItem>, _page_num: u32) -> Vec<Week> {
                 week_name,
                 student_name,
                 form,
+                year_group,
+                house,
+                source: None,
+                starts_on: None,
             });
         }
     }
@@ -425,8 +583,8 @@ fn parse_lesson_content(items: Vec<&&TextItem>, day_index: usize, period_index:
     });
 
     let mut subject_parts: Vec<String> = Vec::new();
-    let mut room = "Unknown".to_string();
-    let mut teacher = "Unknown".to_string();
+    let mut room = UNKNOWN.to_string();
+    let mut teacher = UNKNOWN.to_string();
     let mut class_code = String::new();
 
     let room_regex = Regex::new(r"^[A-Z]{2,3}\d+[A-Z]?$").unwrap(); // e.g. SC8, HU5, MA3 - strict format
@@ -453,7 +611,7 @@ fn parse_lesson_content(items: Vec<&&TextItem>, day_index: usize, period_index:
             continue;
         }
 
-        if room_regex.is_match(text) && room == "Unknown" {
+        if room_regex.is_match(text) && room == UNKNOWN {
             // Only capture first room code found, excluding common false positives
             room = text.to_string();
         } else if teacher_regex.is_match(text) {
@@ -468,7 +626,7 @@ fn parse_lesson_content(items: Vec<&&TextItem>, day_index: usize, period_index:
 
     // Join subject parts with spaces
     let subject = if subject_parts.is_empty() {
-        "Unknown".to_string()
+        UNKNOWN.to_string()
     } else {
         subject_parts.join(" ")
     };
@@ -480,6 +638,7 @@ fn parse_lesson_content(items: Vec<&&TextItem>, day_index: usize, period_index:
         class_code,
         day_index,
         period_index,
+        note: None,
     }
 }
 
@@ -580,10 +739,158 @@ fn extract_student_info(items: &[&TextItem]) -> (Option<String>, Option<String>)
     (student_name, form)
 }
 
+/// Find the year group ("Year 11", "Yr 9") and house ("House: Austen") printed
+/// near the top of the page, if the school includes them.
+fn extract_year_and_house(items: &[&TextItem]) -> (Option<String>, Option<String>) {
+    let year_regex = Regex::new(r"^(?i:year|yr)\.?\s*(\d{1,2})$").unwrap();
+    let house_regex = Regex::new(r"^(?i:house)\s*:?\s+(.+)$").unwrap();
+
+    let mut sorted = items.to_vec();
+    sorted.sort_by(|a, b| a.y.partial_cmp(&b.y).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut year_group = None;
+    let mut house = None;
+    for item in sorted.iter().take(50) {
+        let text = item.text.trim();
+        if year_group.is_none() {
+            year_group = year_regex
+                .captures(text)
+                .map(|cap| format!("Year {}", &cap[1]));
+        }
+        if house.is_none() {
+            house = house_regex
+                .captures(text)
+                .map(|cap| cap[1].trim().to_string());
+        }
+    }
+
+    (year_group, house)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn split_students_groups_consecutive_weeks_by_name() {
+        let week = |name: Option<&str>, week_name: &str| Week {
+            student_name: name.map(String::from),
+            week_name: week_name.into(),
+            ..Default::default()
+        };
+        let students = split_students(vec![
+            week(Some("Alex Testington"), "Week 1"),
+            week(None, "Week 2"),
+            week(Some("Sam Example"), "Week 1"),
+            week(Some("Sam Example"), "Week 2"),
+            week(Some("Alex Testington"), "Week 1"),
+        ]);
+
+        let names: Vec<(Option<&str>, usize)> = students
+            .iter()
+            .map(|weeks| (weeks[0].student_name.as_deref(), weeks.len()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("Alex Testington"), 2),
+                (Some("Sam Example"), 2),
+                (Some("Alex Testington"), 1)
+            ]
+        );
+        assert!(split_students(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn anonymise_keeps_titles_and_matches_teachers_across_weeks() {
+        let lesson = |teacher: &str| Lesson {
+            teacher: teacher.into(),
+            ..Default::default()
+        };
+        let week = Week {
+            lessons: vec![lesson("Mr Smith"), lesson("Dr Jones"), lesson("Unknown")],
+            student_name: Some("Jamie Example".into()),
+            form: Some("10JE".into()),
+            year_group: Some("Year 10".into()),
+            house: Some("Brontë".into()),
+            source: Some("Jamie Example.pdf".into()),
+            ..Default::default()
+        };
+        let mut weeks = vec![
+            week.clone(),
+            Week {
+                lessons: vec![lesson("Dr Jones"), lesson("Sam Brown")],
+                ..week
+            },
+        ];
+
+        anonymise(&mut weeks);
+
+        let teachers: Vec<&str> = weeks
+            .iter()
+            .flat_map(|w| w.lessons.iter().map(|l| l.teacher.as_str()))
+            .collect();
+        assert_eq!(
+            teachers,
+            ["Mr Adams", "Dr Baker", "Unknown", "Dr Baker", "Mx Clarke"]
+        );
+        assert_eq!(weeks[1].form.as_deref(), Some("11XX"));
+        assert_eq!(weeks[1].year_group.as_deref(), Some("Year 11"));
+        assert_eq!(weeks[1].house.as_deref(), Some("Austen"));
+        assert_eq!(weeks[1].source.as_deref(), Some("timetable.pdf"));
+    }
+
+    #[test]
+    fn extract_year_and_house_from_header_items() {
+        let src = [
+            make_item(10.0, 10.0, "Alex Testington"),
+            make_item(80.0, 10.0, "Yr 11"),
+            make_item(120.0, 10.0, "House: Austen"),
+            make_item(10.0, 40.0, "Year 7 Assembly"),
+        ];
+        let refs: Vec<&TextItem> = src.iter().collect();
+
+        assert_eq!(
+            extract_year_and_house(&refs),
+            (Some("Year 11".to_string()), Some("Austen".to_string()))
+        );
+        assert_eq!(extract_year_and_house(&refs[..1]), (None, None));
+    }
+
+    #[test]
+    fn weekday_on_matches_school_days_of_the_week() {
+        let date = |d| NaiveDate::from_ymd_opt(2025, 1, d).unwrap();
+        let week = Week {
+            starts_on: Some(date(13)),
+            ..Default::default()
+        };
+
+        assert_eq!(week.weekday_on(date(15)), Some(Weekday::Wed));
+        assert_eq!(week.weekday_on(date(18)), None);
+        assert_eq!(week.weekday_on(date(10)), None);
+        assert_eq!(Week::default().weekday_on(date(15)), None);
+    }
+
+    #[test]
+    fn day_keeps_only_that_days_lessons() {
+        let lesson = |day_index, room: &str| Lesson {
+            day_index,
+            room: room.into(),
+            ..Default::default()
+        };
+        let week = Week {
+            week_name: "Week 1".into(),
+            lessons: vec![lesson(0, "MA3"), lesson(1, "SC8"), lesson(0, "EN2")],
+            ..Default::default()
+        };
+
+        let monday = week.day(0);
+        assert_eq!(monday.week_name, "Week 1");
+        let rooms: Vec<&str> = monday.lessons.iter().map(|l| l.room.as_str()).collect();
+        assert_eq!(rooms, ["MA3", "EN2"]);
+        assert!(week.day(4).lessons.is_empty());
+    }
+
     fn make_item(x: f64, y: f64, text: &str) -> TextItem {
         TextItem {
             x,
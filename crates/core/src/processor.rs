@@ -1,12 +1,33 @@
 //! SVG map processing and department highlighting.
 //!
 //! This module manipulates school map SVG files by finding elements matching
-//! department IDs and applying color fills to highlight them.
+//! department IDs and applying color fills to highlight them. [`MapDocument`]
+//! parses a map once for runs that highlight it for many sheets. [`map_element`]
+//! turns a processed map into an element tree for embedding, [`raster_map`]
+//! wraps a bitmap map and the areas marked on it as a map SVG,
+//! [`sanitise_map`] makes maps from third parties safe to open, and
+//! [`optimise_svg`] shrinks finished SVG output.
+//!
+//! Centres, crops and label positions come from each element's geometry. With
+//! the `geometry` feature the map is measured by `usvg`, which resolves nested
+//! viewports, symbols and curve extents exactly; otherwise they are estimated
+//! from the shapes' points and `transform` attributes.
 
+use crate::layout::mix_colors;
+use base64::Engine;
 use regex::Regex;
-use roxmltree::Document;
+use roxmltree::{Document, Node, ParsingOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
+use svg::node::{Attributes, Children, NodeDefaultHash, Value};
+use svg::Node as _;
 use thiserror::Error;
 
 /// Errors that can occur during map processing.
@@ -21,21 +42,509 @@ pub enum ProcessorError {
     /// Regex compilation error
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
+    /// Raster map that is not a PNG or JPEG of readable size
+    #[error("Unsupported map image: expected a PNG or JPEG")]
+    UnsupportedImage,
+    /// Highlight whose ID matches no `id` or `data-name` in the map
+    #[error("Highlight \"{0}\" matches nothing in the map")]
+    HighlightNotFound(String),
 }
 
 /// Represents a department to highlight on the map.
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct MapHighlight {
     /// SVG element ID or data-name attribute to match
     pub id: String,
     /// Hex color code to apply (e.g., "#fcdcd8")
     pub color: String,
+    /// Whether the shapes are filled, outlined, tinted or hatched in `color`
+    pub style: HighlightStyle,
+    /// Text drawn over the centre of the element, one line per `\n` (e.g. a room code)
+    pub label: Option<String>,
+    /// Department name, given to the element as a `<title>` tooltip and listed
+    /// in the map's own legend (see [`MapDocument::with_legend`])
+    pub name: Option<String>,
+    /// Whether the colour is drawn faintly, e.g. for rooms used this week but
+    /// not today
+    pub muted: bool,
+    /// Colour of a border drawn around the shapes whatever the `style`, which
+    /// reads better on poor photocopies than a fill alone (`color` if only
+    /// `stroke_width` is set)
+    pub stroke: Option<String>,
+    /// Width of that border in map units (that of [`HighlightStyle::Stroke`]
+    /// if only `stroke` is set)
+    pub stroke_width: Option<f64>,
+}
+
+// Widths compare by their bits, as they are hashed, so equality is an
+// equivalence even for NaN and agrees with `Hash` for -0.0 and 0.0
+impl PartialEq for MapHighlight {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.color == other.color
+            && self.style == other.style
+            && self.label == other.label
+            && self.name == other.name
+            && self.muted == other.muted
+            && self.stroke == other.stroke
+            && self.stroke_width.map(f64::to_bits) == other.stroke_width.map(f64::to_bits)
+    }
+}
+
+impl Eq for MapHighlight {}
+
+impl Hash for MapHighlight {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.color.hash(state);
+        self.style.hash(state);
+        self.label.hash(state);
+        self.name.hash(state);
+        self.muted.hash(state);
+        self.stroke.hash(state);
+        self.stroke_width.map(f64::to_bits).hash(state);
+    }
+}
+
+impl MapHighlight {
+    /// Highlight the element `id` by filling its shapes with `color`.
+    pub fn new(id: impl Into<String>, color: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            color: color.into(),
+            style: HighlightStyle::default(),
+            label: None,
+            name: None,
+            muted: false,
+            stroke: None,
+            stroke_width: None,
+        }
+    }
+
+    /// Set how the shapes are marked.
+    pub fn style(mut self, style: HighlightStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the text drawn over the element.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the name listed in the map's legend.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the colour of a border drawn around the shapes.
+    pub fn stroke(mut self, color: impl Into<String>) -> Self {
+        self.stroke = Some(color.into());
+        self
+    }
+
+    /// Set the width of the border drawn around the shapes.
+    pub fn stroke_width(mut self, width: f64) -> Self {
+        self.stroke_width = Some(width);
+        self
+    }
+
+    /// Draw the colour at [`MUTED_HIGHLIGHT_OPACITY`], so that other highlights
+    /// stand out against it.
+    pub fn muted(mut self) -> Self {
+        self.muted = true;
+        self
+    }
+
+    /// Paint properties and values this highlight sets on each shape, for the
+    /// `index`th highlight in a list.
+    fn paint(&self, index: usize) -> Vec<(&'static str, String)> {
+        let fill = ("fill", self.color.clone());
+        let stroke = [
+            ("stroke", self.color.clone()),
+            ("stroke-width", HIGHLIGHT_STROKE_WIDTH.to_string()),
+        ];
+        let mut paint = match self.style {
+            HighlightStyle::Fill => vec![fill],
+            HighlightStyle::Stroke => stroke.to_vec(),
+            HighlightStyle::Both => [&[fill][..], &stroke[..]].concat(),
+            HighlightStyle::Overlay => {
+                vec![
+                    fill,
+                    ("fill-opacity", HIGHLIGHT_OVERLAY_OPACITY.to_string()),
+                ]
+            }
+            HighlightStyle::Hatch => vec![("fill", format!("url(#{})", hatch_id(index)))],
+        };
+        if self.stroke.is_some() || self.stroke_width.is_some() {
+            paint.retain(|(property, _)| !property.starts_with("stroke"));
+            paint.push(("stroke", self.stroke.clone().unwrap_or(self.color.clone())));
+            paint.push((
+                "stroke-width",
+                self.stroke_width
+                    .map_or(HIGHLIGHT_STROKE_WIDTH.to_string(), format_coordinate),
+            ));
+        }
+        if self.muted {
+            for (property, opacity) in [("fill", "fill-opacity"), ("stroke", "stroke-opacity")] {
+                if !paint.iter().any(|(p, _)| *p == property) {
+                    continue;
+                }
+                let own = paint
+                    .iter()
+                    .find(|(p, _)| *p == opacity)
+                    .and_then(|(_, value)| value.parse::<f64>().ok())
+                    .unwrap_or(1.0);
+                paint.retain(|(p, _)| *p != opacity);
+                paint.push((opacity, format_opacity(own * MUTED_HIGHLIGHT_OPACITY)));
+            }
+        }
+        paint
+    }
+
+    /// Colour this highlight fills shapes with, or `None` if it only outlines them.
+    fn fill_color(&self) -> Option<&str> {
+        (self.style != HighlightStyle::Stroke).then_some(self.color.as_str())
+    }
+}
+
+/// `paint` with its fill replaced by the `index`th split paint server, for
+/// shapes filled by several highlights.
+fn split_paint(paint: &[(&'static str, String)], index: usize) -> Vec<(&'static str, String)> {
+    paint
+        .iter()
+        .map(|(property, value)| match *property {
+            "fill" => (*property, format!("url(#{})", split_id(index))),
+            _ => (*property, value.clone()),
+        })
+        .collect()
+}
+
+/// `<pattern>` for the `index`th highlight in a list: black lines over
+/// `color`, at an angle that differs between neighbouring highlights so they
+/// stay apart in black and white.
+fn hatch_pattern(index: usize, color: &str) -> String {
+    format!(
+        concat!(
+            r#"<pattern id="{}" patternUnits="userSpaceOnUse" width="8" height="8" "#,
+            r#"patternTransform="rotate({})"><rect width="8" height="8" fill="{}"/>"#,
+            r##"<path d="M0 0V8" stroke="#000000" stroke-width="1.5"/></pattern>"##
+        ),
+        hatch_id(index),
+        HATCH_ANGLES[index % HATCH_ANGLES.len()],
+        escape_attribute(color)
+    )
+}
+
+/// Id of the hatch pattern added to the map for the `index`th highlight.
+fn hatch_id(index: usize) -> String {
+    format!("map-hatch-{}", index)
+}
+
+/// `<pattern>` or `<linearGradient>` sharing a shape between `colors`, the
+/// fills of the highlights that meet on it, as `shared` asks.
+fn split_paint_server(index: usize, colors: &[&str], shared: SharedFill) -> String {
+    match shared {
+        SharedFill::Stripes => {
+            let bands: String = colors
+                .iter()
+                .enumerate()
+                .map(|(band, color)| {
+                    format!(
+                        r#"<rect x="{}" width="{}" height="{}" fill="{}"/>"#,
+                        band * SPLIT_STRIPE_WIDTH,
+                        SPLIT_STRIPE_WIDTH,
+                        colors.len() * SPLIT_STRIPE_WIDTH,
+                        escape_attribute(color)
+                    )
+                })
+                .collect();
+            format!(
+                concat!(
+                    r#"<pattern id="{}" patternUnits="userSpaceOnUse" width="{}" height="{}" "#,
+                    r#"patternTransform="rotate(45)">{}</pattern>"#
+                ),
+                split_id(index),
+                colors.len() * SPLIT_STRIPE_WIDTH,
+                colors.len() * SPLIT_STRIPE_WIDTH,
+                bands
+            )
+        }
+        SharedFill::Gradient => {
+            let last = colors.len().saturating_sub(1).max(1) as f64;
+            let stops: String = colors
+                .iter()
+                .enumerate()
+                .map(|(stop, color)| {
+                    format!(
+                        r#"<stop offset="{}" stop-color="{}"/>"#,
+                        format_opacity(stop as f64 / last),
+                        escape_attribute(color)
+                    )
+                })
+                .collect();
+            format!(
+                r#"<linearGradient id="{}">{}</linearGradient>"#,
+                split_id(index),
+                stops
+            )
+        }
+    }
+}
+
+/// Id of the paint server added to the map for the `index`th set of shared colours.
+fn split_id(index: usize) -> String {
+    format!("map-split-{}", index)
+}
+
+/// `<defs>` holding the hatch patterns `highlights` need and the paint servers
+/// for `splits`, or nothing.
+fn paint_defs(highlights: &[MapHighlight], splits: &[Vec<&str>], shared: SharedFill) -> String {
+    let patterns: String = highlights
+        .iter()
+        .enumerate()
+        .filter(|(_, highlight)| highlight.style == HighlightStyle::Hatch)
+        .map(|(index, highlight)| hatch_pattern(index, &highlight.color))
+        .chain(
+            splits
+                .iter()
+                .enumerate()
+                .map(|(index, colors)| split_paint_server(index, colors, shared)),
+        )
+        .collect();
+    if patterns.is_empty() {
+        patterns
+    } else {
+        format!("<defs>{}</defs>", patterns)
+    }
+}
+
+/// How a department's shapes are marked on the map.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// Fill the shapes with the colour (default)
+    #[default]
+    Fill,
+    /// Draw a thick outline in the colour, keeping the map's own fills
+    Stroke,
+    /// Fill and outline the shapes
+    Both,
+    /// Fill the shapes with the colour at half opacity, so departments that
+    /// share a space blend and the map beneath shows through
+    Overlay,
+    /// Fill the shapes with black lines over the colour, which survive
+    /// black-and-white photocopying
+    Hatch,
+}
+
+/// A `<title>` element, shown as a tooltip over its parent by browsers.
+fn title_element(title: &str) -> String {
+    format!("<title>{}</title>", escape_text(title))
+}
+
+/// Width of the outline drawn by [`HighlightStyle::Stroke`], in map units.
+const HIGHLIGHT_STROKE_WIDTH: &str = "3";
+
+/// Fill opacity of [`HighlightStyle::Overlay`].
+const HIGHLIGHT_OVERLAY_OPACITY: &str = "0.5";
+
+/// Angles of the lines in successive [`HighlightStyle::Hatch`] patterns (degrees).
+const HATCH_ANGLES: [i32; 4] = [45, -45, 0, 90];
+
+/// Width of each colour's band in [`SharedFill::Stripes`], in map units.
+const SPLIT_STRIPE_WIDTH: usize = 6;
+
+/// Text drawn over a room highlighted through its own map element, by
+/// [`Config::map_highlights`](crate::config::Config::map_highlights).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapLabels {
+    /// The room code
+    Codes,
+    /// The room code and the lessons held there, e.g. "Mon L1, Wed L3"
+    Periods,
+}
+
+/// How shapes filled by more than one highlight, such as a hall used by two
+/// departments, share their colours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SharedFill {
+    /// Diagonal stripes of each colour (default)
+    #[default]
+    Stripes,
+    /// A left-to-right gradient through each colour
+    Gradient,
+}
+
+/// How [`MapDocument::highlight`] colours the highlighted shapes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HighlightMode {
+    /// Rewrite `fill` attributes and `style` declarations on each shape (default)
+    #[default]
+    Attributes,
+    /// Add a `<style>` block with `!important` fills for the highlighted
+    /// groups, leaving the shapes untouched; works for maps styled entirely
+    /// through classes, but needs a viewer that applies CSS
+    Stylesheet,
+}
+
+/// What [`MapDocument::highlight_checked`] does with highlights whose ID
+/// matches nothing in the map.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Fail with [`ProcessorError::HighlightNotFound`] for the first one
+    Strict,
+    /// Highlight the rest and list each one in [`CheckedMap::warnings`] (default)
+    #[default]
+    Lenient,
+}
+
+/// A highlighted map and the problems found applying the highlights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckedMap {
+    /// The map content with the highlights applied
+    pub svg: String,
+    /// One message per highlight ID that matches nothing in the map, in the
+    /// order the highlights were given
+    pub warnings: Vec<String>,
+}
+
+/// A path through the map, such as a student's walk between lessons.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapRoute {
+    /// Stops in order, in the map's user space
+    pub points: Vec<(f64, f64)>,
+    /// Line and stop marker colour (hex code)
+    pub color: String,
+    /// Whether a line joins the stops; without it only the numbered markers are drawn
+    pub line: bool,
+    /// Key entries, one per stop (e.g. "L1 → MA3"), listed in a corner of the map
+    pub key: Vec<String>,
+}
+
+/// Default colour of [`MapRoute`]s, chosen to stand out against pastel highlights.
+pub const ROUTE_COLOR: &str = "#d6004c";
+
+/// Height of a row in a route key, in map units.
+const ROUTE_KEY_ROW: f64 = 18.0;
+
+impl MapRoute {
+    /// A route through `points` in [`ROUTE_COLOR`].
+    pub fn new(points: Vec<(f64, f64)>) -> Self {
+        Self {
+            points,
+            color: ROUTE_COLOR.to_string(),
+            line: true,
+            key: Vec::new(),
+        }
+    }
+
+    /// Set the colour.
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Set whether a line joins the stops.
+    pub fn line(mut self, line: bool) -> Self {
+        self.line = line;
+        self
+    }
+
+    /// Set the key entries, one per stop.
+    pub fn key(mut self, key: Vec<String>) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// A dashed line through the stops, with each stop numbered in a disc, and
+    /// the key in the top-left corner of `extent`.
+    fn to_svg(&self, extent: Option<Bounds>) -> String {
+        if self.points.is_empty() {
+            return String::new();
+        }
+        let points: Vec<String> = self
+            .points
+            .iter()
+            .map(|(x, y)| format!("{},{}", format_coordinate(*x), format_coordinate(*y)))
+            .collect();
+        let mut svg = String::from(r#"<g class="map-route">"#);
+        if self.line && self.points.len() > 1 {
+            let _ = write!(
+                svg,
+                concat!(
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="3" "#,
+                    r#"stroke-dasharray="8 5" stroke-linecap="round" stroke-linejoin="round"/>"#
+                ),
+                points.join(" "),
+                escape_attribute(&self.color)
+            );
+        }
+        for (i, (x, y)) in self.points.iter().enumerate() {
+            svg.push_str(&self.marker(*x, *y, 7.0, i + 1));
+        }
+        if let (false, Some(extent)) = (self.key.is_empty(), extent) {
+            let longest = self
+                .key
+                .iter()
+                .map(|e| e.chars().count())
+                .max()
+                .unwrap_or(0);
+            let (x, y) = (extent.min_x + 8.0, extent.min_y + 8.0);
+            let _ = write!(
+                svg,
+                concat!(
+                    r#"<g class="map-route-key" font-family="Arial, sans-serif" font-size="10">"#,
+                    r##"<rect x="{}" y="{}" width="{}" height="{}" rx="3" fill="#ffffff" "##,
+                    r#"fill-opacity="0.9" stroke="{}"/>"#
+                ),
+                format_coordinate(x),
+                format_coordinate(y),
+                format_coordinate(30.0 + longest as f64 * 6.0),
+                format_coordinate(self.key.len() as f64 * ROUTE_KEY_ROW + 6.0),
+                escape_attribute(&self.color)
+            );
+            for (i, entry) in self.key.iter().enumerate() {
+                let row = y + 3.0 + (i as f64 + 0.5) * ROUTE_KEY_ROW;
+                svg.push_str(&self.marker(x + 12.0, row, 6.0, i + 1));
+                let _ = write!(
+                    svg,
+                    r#"<text x="{}" y="{}" dominant-baseline="central">{}</text>"#,
+                    format_coordinate(x + 24.0),
+                    format_coordinate(row),
+                    escape_text(entry)
+                );
+            }
+            svg.push_str("</g>");
+        }
+        svg.push_str("</g>");
+        svg
+    }
+
+    /// Stop number `n` in a disc of `radius` at `(x, y)`.
+    fn marker(&self, x: f64, y: f64, radius: f64, n: usize) -> String {
+        let (x, y) = (format_coordinate(x), format_coordinate(y));
+        format!(
+            concat!(
+                r##"<circle cx="{x}" cy="{y}" r="{r}" fill="{color}" stroke="#ffffff" stroke-width="1.5"/>"##,
+                r##"<text x="{x}" y="{y}" font-family="Arial, sans-serif" font-size="{size}" font-weight="bold" "##,
+                r##"text-anchor="middle" dominant-baseline="central" fill="#ffffff">{n}</text>"##
+            ),
+            x = x,
+            y = y,
+            r = format_coordinate(radius),
+            color = escape_attribute(&self.color),
+            size = format_coordinate(radius + 2.0),
+            n = n
+        )
+    }
 }
 
 /// Process a school map SVG file and apply department highlights.
 ///
-/// Loads an SVG map file, finds elements matching the provided highlight IDs,
-/// and injects fill attributes with the specified colors.
+/// Reads the file at `path` and applies the highlights with [`process_map_str`].
 ///
 /// # Arguments
 ///
@@ -51,7 +560,6 @@ pub struct MapHighlight {
 /// Returns [`ProcessorError`] if:
 /// - The map file cannot be read
 /// - The SVG XML is malformed
-/// - Regex patterns are invalid
 ///
 /// # Example
 ///
@@ -61,14 +569,8 @@ pub struct MapHighlight {
 ///
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// let highlights = vec![
-///     MapHighlight {
-///         id: "Maths_Rooms".to_string(),
-///         color: "#fcdcd8".to_string(),
-///     },
-///     MapHighlight {
-///         id: "Science_Rooms".to_string(),
-///         color: "#fad7e6".to_string(),
-///     },
+///     MapHighlight::new("Maths_Rooms", "#fcdcd8"),
+///     MapHighlight::new("Science_Rooms", "#fad7e6"),
 /// ];
 ///
 /// let map_svg = process_map(Path::new("resources/map.svg"), &highlights)?;
@@ -78,87 +580,3398 @@ pub struct MapHighlight {
 /// ```
 pub fn process_map(path: &Path, highlights: &[MapHighlight]) -> Result<String, ProcessorError> {
     let content = fs::read_to_string(path)?;
-    let doc = Document::parse(&content)?;
+    process_map_str(&content, highlights)
+}
 
-    // We will collect replacements: (start_index, end_index, new_text)
-    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
-    let fill_re = Regex::new(r#"fill\s*=\s*(?:"[^"]*"|'[^']*')"#)?;
+/// Apply department highlights to a school map SVG file, checking that each
+/// highlight ID is in the map.
+///
+/// [`process_map`] ignores highlights that match nothing, which hides typos in
+/// a config's `map_id`s; here they fail the call or come back as warnings, as
+/// `strictness` asks.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError`] if the map file cannot be read or is malformed,
+/// or [`ProcessorError::HighlightNotFound`] for an unknown ID when strict.
+///
+/// # Example
+///
+/// ```no_run
+/// use timetable_core::processor::{process_map_checked, MapHighlight, Strictness};
+/// use std::path::Path;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let highlights = vec![MapHighlight::new("Maths_Rooms", "#fcdcd8")];
+///
+/// let map = process_map_checked(Path::new("resources/map.svg"), &highlights, Strictness::Lenient)?;
+/// for warning in &map.warnings {
+///     eprintln!("Warning: {}", warning);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn process_map_checked(
+    path: &Path,
+    highlights: &[MapHighlight],
+    strictness: Strictness,
+) -> Result<CheckedMap, ProcessorError> {
+    MapDocument::load(path)?.highlight_checked(highlights, strictness)
+}
 
-    for highlight in highlights {
-        // Find the node by id or data-name
-        let node = doc.descendants().find(|n| {
-            n.attribute("id") == Some(&highlight.id)
-                || n.attribute("data-name") == Some(&highlight.id)
-        });
+/// Apply department highlights to school map SVG content.
+///
+/// For a legend inside the map, dimming or cropping, parse a [`MapDocument`]
+/// and set the options on it before highlighting.
+///
+/// Finds elements matching the provided highlight IDs (by `id` or
+/// `data-name`) and sets the fill (or, for [`HighlightStyle::Stroke`], the
+/// outline) of them and their descendants to the specified colors: existing
+/// attributes and declarations in `style` attributes are replaced, and shapes
+/// without either get an attribute added. Works on content already in memory,
+/// for servers and WASM callers without a filesystem.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError`] if the SVG XML is malformed.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::{process_map_str, MapHighlight};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = r##"<svg><g id="Maths_Rooms"><rect fill="#000000"/></g></svg>"##;
+/// let highlights = vec![MapHighlight::new("Maths_Rooms", "#fcdcd8")];
+///
+/// let map_svg = process_map_str(map, &highlights)?;
+/// assert!(map_svg.contains("fill=\"#fcdcd8\""));
+/// # Ok(())
+/// # }
+/// ```
+pub fn process_map_str(svg: &str, highlights: &[MapHighlight]) -> Result<String, ProcessorError> {
+    Ok(MapDocument::parse(svg)?.highlight(highlights))
+}
 
-        if let Some(group_node) = node {
-            // Iterate over all descendants to find shapes with fill attributes
-            for child in group_node.descendants() {
-                // We only care about elements that have a 'fill' attribute
-                if child.has_attribute("fill") {
-                    let range = child.range();
-                    // Find the end of the start tag.
-                    if let Some(start_tag_end) = content[range.start..].find('>') {
-                        let start_tag_str = &content[range.start..range.start + start_tag_end + 1];
-
-                        if let Some(mat) = fill_re.find(start_tag_str) {
-                            let absolute_start = range.start + mat.start();
-                            let absolute_end = range.start + mat.end();
-                            replacements.push((
-                                absolute_start,
-                                absolute_end,
-                                format!("fill=\"{}\"", highlight.color),
-                            ));
-                        }
-                    }
-                }
+/// A named region of a raster map, drawn over the image as a shape that
+/// highlights can fill.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapArea {
+    /// Name highlights refer to, as a department's `map_id` or a room code
+    pub id: String,
+    /// Rectangle as `[x, y, width, height]` in image pixels
+    #[serde(default)]
+    pub rect: Option<[f64; 4]>,
+    /// Polygon corners as `[x, y]` pairs in image pixels, used when there is no `rect`
+    #[serde(default)]
+    pub points: Vec<[f64; 2]>,
+}
+
+impl MapArea {
+    /// An area covering the rectangle at `(x, y)`.
+    pub fn rect(id: impl Into<String>, x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            id: id.into(),
+            rect: Some([x, y, width, height]),
+            points: Vec::new(),
+        }
+    }
+
+    /// An area covering the polygon through `points`.
+    pub fn polygon(id: impl Into<String>, points: Vec<[f64; 2]>) -> Self {
+        Self {
+            id: id.into(),
+            rect: None,
+            points,
+        }
+    }
+
+    /// The area as an unfilled SVG shape.
+    fn to_svg(&self) -> String {
+        let id = escape_attribute(&self.id);
+        match self.rect {
+            Some([x, y, width, height]) => format!(
+                r#"<rect id="{}" x="{}" y="{}" width="{}" height="{}" fill="none"/>"#,
+                id,
+                format_coordinate(x),
+                format_coordinate(y),
+                format_coordinate(width),
+                format_coordinate(height)
+            ),
+            None => {
+                let points: Vec<String> = self
+                    .points
+                    .iter()
+                    .map(|[x, y]| format!("{},{}", format_coordinate(*x), format_coordinate(*y)))
+                    .collect();
+                format!(
+                    r#"<polygon id="{}" points="{}" fill="none"/>"#,
+                    id,
+                    points.join(" ")
+                )
             }
         }
     }
+}
 
-    // Apply replacements in reverse order
-    replacements.sort_by(|a, b| b.0.cmp(&a.0));
+/// Opacity of highlights over a raster map, so the image shows through.
+const RASTER_AREA_OPACITY: &str = "0.6";
+
+/// Build a map SVG from a PNG or JPEG image and the `areas` marked on it.
+///
+/// The image is embedded as a data URI at its own pixel size, and each area
+/// becomes an unfilled shape with the area's id, so the result can be
+/// highlighted like any other map. Highlights are drawn partly transparent
+/// so the image's own labels stay readable.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError::UnsupportedImage`] if `image` is not a PNG or
+/// JPEG whose size can be read.
+pub fn raster_map(image: &[u8], areas: &[MapArea]) -> Result<String, ProcessorError> {
+    let (mime, (width, height)) = if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ("image/png", png_size(image))
+    } else if image.starts_with(&[0xff, 0xd8]) {
+        ("image/jpeg", jpeg_size(image))
+    } else {
+        return Err(ProcessorError::UnsupportedImage);
+    };
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(ProcessorError::UnsupportedImage);
+    };
+    let shapes: String = areas.iter().map(MapArea::to_svg).collect();
+    Ok(format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" "#,
+            r#"width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            r#"<image width="{w}" height="{h}" xlink:href="data:{mime};base64,{data}"/>"#,
+            r#"<g id="map-areas" fill-opacity="{opacity}">{shapes}</g></svg>"#
+        ),
+        w = width,
+        h = height,
+        mime = mime,
+        data = base64::engine::general_purpose::STANDARD.encode(image),
+        opacity = RASTER_AREA_OPACITY,
+        shapes = shapes
+    ))
+}
 
-    // Deduplicate based on start index to avoid conflicting writes if regions overlap
-    replacements.dedup_by_key(|k| k.0);
+/// Width and height of a PNG, from its `IHDR` chunk.
+fn png_size(image: &[u8]) -> (Option<u32>, Option<u32>) {
+    let field = |at: usize| {
+        image
+            .get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    (field(16), field(20))
+}
 
-    let mut result = content;
-    for (start, end, text) in replacements {
-        // Ensure we don't panic if indices are out of bounds
-        if start <= end && end <= result.len() {
-            result.replace_range(start..end, &text);
+/// Width and height of a JPEG, from its first start-of-frame segment.
+fn jpeg_size(image: &[u8]) -> (Option<u32>, Option<u32>) {
+    let field = |at: usize| {
+        image
+            .get(at..at + 2)
+            .map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])))
+    };
+    let mut at = 2;
+    while let (Some(0xff), Some(&marker)) = (image.get(at), image.get(at + 1)) {
+        match marker {
+            // Padding before a marker
+            0xff => at += 1,
+            // Start of frame, except the huffman, arithmetic coding and
+            // JPEG extension markers that share the range
+            0xc0..=0xcf if ![0xc4, 0xc8, 0xcc].contains(&marker) => {
+                return (field(at + 7), field(at + 5));
+            }
+            _ => match field(at + 2) {
+                Some(length) => at += 2 + length as usize,
+                None => break,
+            },
         }
     }
+    (None, None)
+}
+
+/// Colour of the most-visited areas in a [`heatmap`], dark enough for a
+/// range of tints to tell apart.
+pub const HEATMAP_COLOR: &str = "#b2182b";
 
-    Ok(result)
+/// Tint of the least-visited areas in a [`heatmap`], as a fraction of the way
+/// from white to its colour.
+const HEATMAP_MIN_TINT: f64 = 0.15;
+
+/// Recolour `highlights` as a heatmap of `visits`, the number of lessons held in
+/// each, from a pale tint of `color` for the fewest to `color` itself for the
+/// most, so the areas a student goes to most stand out.
+///
+/// Each highlight is filled, whatever its style, and keeps its id, label and
+/// name. A `color` that is not a hex code is used as it is.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::{heatmap, MapHighlight};
+///
+/// let highlights = [
+///     MapHighlight::new("Maths_Rooms", "#fcdcd8"),
+///     MapHighlight::new("Science_Rooms", "#fad7e6"),
+/// ];
+/// let heat = heatmap(&highlights, &[8, 2], "#000000");
+/// assert_eq!(heat[0].color, "#000000");
+/// assert_eq!(heat[1].color, "#a3a3a3");
+/// ```
+pub fn heatmap(highlights: &[MapHighlight], visits: &[usize], color: &str) -> Vec<MapHighlight> {
+    let most = visits.iter().copied().max().unwrap_or(0).max(1);
+    highlights
+        .iter()
+        .zip(visits)
+        .map(|(highlight, &count)| {
+            let amount = HEATMAP_MIN_TINT + (1.0 - HEATMAP_MIN_TINT) * count as f64 / most as f64;
+            MapHighlight {
+                color: mix_colors("#ffffff", color, amount).unwrap_or_else(|| color.to_string()),
+                style: HighlightStyle::Fill,
+                ..highlight.clone()
+            }
+        })
+        .collect()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::env;
+/// An element a [`MapHighlight`] can refer to, as listed by [`list_map_ids`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapElement {
+    /// The element's `id` attribute
+    pub id: Option<String>,
+    /// The element's `data-name` attribute, as set by Illustrator for layer names
+    pub data_name: Option<String>,
+    /// Tag name, such as `g` or `rect`
+    pub element: String,
+}
 
-    #[test]
-    fn process_map_replaces_fill() {
-        let temp_dir = env::temp_dir();
-        let file = temp_dir.join("test_map.svg");
-        let content = r###"<?xml version="1.0"?>
-<svg>
-    <g id="Maths_Rooms">
-        <path fill="#000000" d="M0" />
-    </g>
-    <g id="Other">
-        <rect fill="#ffffff" />
-    </g>
-</svg>"###;
+/// List the elements in the map SVG at `path` that a config `map_id` can name.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError`] if the file cannot be read or is malformed.
+pub fn list_map_ids(path: &Path) -> Result<Vec<MapElement>, ProcessorError> {
+    list_map_ids_str(&fs::read_to_string(path)?)
+}
 
-        std::fs::write(&file, content).unwrap();
+/// List the elements with an `id` or `data-name` in map SVG content, in
+/// document order.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError`] if the SVG XML is malformed.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::list_map_ids_str;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = r#"<svg><g id="Maths_Rooms"><rect data-name="MA1"/><rect/></g></svg>"#;
+/// let elements = list_map_ids_str(map)?;
+/// assert_eq!(elements.len(), 2);
+/// assert_eq!(elements[0].id.as_deref(), Some("Maths_Rooms"));
+/// assert_eq!(elements[1].data_name.as_deref(), Some("MA1"));
+/// assert_eq!(elements[1].element, "rect");
+/// # Ok(())
+/// # }
+/// ```
+pub fn list_map_ids_str(svg: &str) -> Result<Vec<MapElement>, ProcessorError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(svg, options)?;
+    Ok(doc
+        .descendants()
+        .filter(|n| n.is_element())
+        .filter_map(|node| {
+            let id = node.attribute("id").map(str::to_string);
+            let data_name = node.attribute("data-name").map(str::to_string);
+            (id.is_some() || data_name.is_some()).then(|| MapElement {
+                id,
+                data_name,
+                element: node.tag_name().name().to_string(),
+            })
+        })
+        .collect())
+}
 
-        let highlights = vec![MapHighlight {
-            id: "Maths_Rooms".into(),
-            color: "#ff0000".into(),
-        }];
-        let out = process_map(&file, &highlights).unwrap();
-        assert!(out.contains("fill=\"#ff0000\""));
+/// A school map parsed once, ready to be highlighted many times.
+///
+/// Multi-week and multi-student runs highlight the same map with different
+/// departments for every sheet. A `MapDocument` reads and parses the map
+/// once; each [`MapDocument::highlight`] then only splices colours into the text.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::{MapDocument, MapHighlight};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = MapDocument::parse(r##"<svg><g id="Maths_Rooms"><rect fill="#000"/></g></svg>"##)?;
+/// let highlight = |color: &str| MapHighlight::new("Maths_Rooms", color);
+///
+/// assert!(map.highlight(&[highlight("#fcdcd8")]).contains("#fcdcd8"));
+/// assert!(map.highlight(&[highlight("#fad7e6")]).contains("#fad7e6"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MapDocument {
+    /// The map's SVG text, shared with the [`MapCache`] keys made from it
+    content: Arc<str>,
+    /// Hash of `content`, taken once when parsed
+    content_hash: u64,
+    /// Where paint goes within every element, in document order
+    elements: Vec<PaintSites>,
+    /// Indices into `elements` of each element and its descendants, keyed by
+    /// the element's `id` and `data-name` (the first element in document order wins)
+    groups: HashMap<String, Range<usize>>,
+    /// End of the root `<svg>` start tag, where a highlight stylesheet goes
+    /// (`None` for an empty map)
+    root_tag_end: Option<usize>,
+    /// Start of the root `</svg>` end tag, where labels go so they are drawn last
+    root_close: Option<usize>,
+    mode: HighlightMode,
+    /// Opacity given to everything outside the highlights, if they are dimmed
+    dim: Option<f64>,
+    /// Padding around the highlights, if the map is cropped to them
+    crop: Option<f64>,
+    /// Whether a legend of the named highlights is drawn in the map
+    legend: bool,
+    /// How shapes filled by several highlights share their colours
+    shared: SharedFill,
+    /// Byte range of the value of the root's `viewBox` attribute
+    root_view_box: Option<Range<usize>>,
+    /// Extents of named elements measured by `usvg`, by `id` and `data-name`,
+    /// preferred to [`PaintSites::bounds`] (empty without the `geometry` feature)
+    measured: HashMap<String, Bounds>,
+    /// Extent of the whole map measured by `usvg`
+    measured_extent: Option<Bounds>,
+}
+
+/// Places in one element's start tag where highlight paint is written.
+#[derive(Debug, Clone, Default)]
+struct PaintSites {
+    /// Byte ranges of existing paint attributes, which are replaced, by property
+    attributes: Vec<(&'static str, Range<usize>)>,
+    /// Byte ranges of paint declaration values in a `style` attribute, by property
+    declarations: Vec<(&'static str, Range<usize>)>,
+    /// End of the tag name, where attributes the element lacks are inserted
+    insert_at: Option<usize>,
+    /// Whether the element is one of [`FILLED_SHAPES`]
+    shape: bool,
+    /// Whether the element is drawn, and so faded by dimming
+    drawn: bool,
+    /// Index of the innermost [`UNPAINTED_CONTAINERS`] element enclosing this one
+    container: Option<usize>,
+    /// Extent of the element's own geometry in the map's user space
+    bounds: Option<Bounds>,
+    /// Byte range of the element, or of a `<symbol>`'s contents: the text
+    /// copied when a `<use>` referencing it is expanded
+    copy: Range<usize>,
+    /// Byte range of the `id` attribute and the space before it, dropped from copies
+    id: Option<Range<usize>>,
+    /// Where a `<use>` element draws from and how it places it
+    reference: Option<UseSite>,
+    /// Byte range of the `>` or `/>` ending the start tag, where a first child
+    /// is written, with the end tag an empty element then needs
+    tag_close: Option<(Range<usize>, Option<String>)>,
+}
+
+/// A `<use>` element, which [`MapDocument::highlight`] replaces inside
+/// highlighted groups by a painted copy of the element it references, since
+/// paint on the `<use>` does not reach shapes that set their own.
+#[derive(Debug, Clone, Default)]
+struct UseSite {
+    /// `id` of the referenced element
+    target: String,
+    /// The `<use>`'s other attributes as written, for the `<g>` replacing it
+    attributes: String,
+    /// The `<use>`'s transform followed by its `x`/`y` offset
+    transform: String,
+}
+
+/// An axis-aligned box in map coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+impl Bounds {
+    /// The smallest box holding every point, or `None` without points.
+    fn around(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        points
+            .into_iter()
+            .fold(None, |bounds: Option<Bounds>, (x, y)| {
+                Some(match bounds {
+                    Some(b) => Bounds {
+                        min_x: b.min_x.min(x),
+                        min_y: b.min_y.min(y),
+                        max_x: b.max_x.max(x),
+                        max_y: b.max_y.max(y),
+                    },
+                    None => Bounds {
+                        min_x: x,
+                        min_y: y,
+                        max_x: x,
+                        max_y: y,
+                    },
+                })
+            })
+    }
+
+    /// The corners of the box.
+    fn corners(&self) -> [(f64, f64); 4] {
+        [
+            (self.min_x, self.min_y),
+            (self.max_x, self.min_y),
+            (self.min_x, self.max_y),
+            (self.max_x, self.max_y),
+        ]
+    }
+}
+
+/// Largest font size of map labels, in map units.
+const MAP_LABEL_MAX_SIZE: f64 = 14.0;
+
+/// Properties a highlight, or dimming, may set.
+const PAINT_PROPERTIES: [&str; 5] = ["fill", "fill-opacity", "stroke", "stroke-width", "opacity"];
+
+/// Elements faded by dimming besides [`FILLED_SHAPES`].
+const DIMMED_ELEMENTS: [&str; 4] = ["line", "text", "image", "use"];
+
+/// Padding around the highlights with `--crop-map`, as a fraction of their extent.
+pub const CROP_PADDING: f64 = 0.1;
+
+/// Opacity of [`MapHighlight::muted`] colours, such as the rest of the week's
+/// rooms beside today's with `--today`.
+pub const MUTED_HIGHLIGHT_OPACITY: f64 = 0.4;
+
+/// Opacity of the map outside the highlights with `--dim-map`.
+pub const DIM_OPACITY: f64 = 0.35;
+
+/// Elements that are painted with `fill`, and so get one inserted if they lack it.
+const FILLED_SHAPES: [&str; 6] = ["path", "rect", "circle", "ellipse", "polygon", "polyline"];
+
+/// Containers whose shapes are not drawn directly, or whose fill means something
+/// else (such as mask coverage), so are left alone.
+const UNPAINTED_CONTAINERS: [&str; 6] = ["defs", "clipPath", "mask", "pattern", "marker", "symbol"];
+
+impl MapDocument {
+    /// Read and parse the map SVG file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProcessorError`] if the file cannot be read or is malformed.
+    pub fn load(path: &Path) -> Result<Self, ProcessorError> {
+        Self::parse(fs::read_to_string(path)?)
+    }
+
+    /// Parse map SVG content held in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProcessorError`] if the SVG XML is malformed.
+    pub fn parse(content: impl Into<String>) -> Result<Self, ProcessorError> {
+        let content = content.into();
+        let mut elements: Vec<PaintSites> = Vec::new();
+        let mut groups: HashMap<String, Range<usize>> = HashMap::new();
+        let root_tag_end;
+        let root_close;
+        let root_view_box;
+        // `data-name`s of elements that also have an `id`, which `usvg` keeps
+        let mut aliases: Vec<(String, String)> = Vec::new();
+        {
+            // Illustrator exports declare their namespaces through DTD entities
+            let options = ParsingOptions {
+                allow_dtd: true,
+                ..ParsingOptions::default()
+            };
+            let doc = Document::parse_with_options(&content, options)?;
+            root_view_box = doc
+                .root_element()
+                .attributes()
+                .find(|a| a.name() == "viewBox" && a.namespace().is_none())
+                .map(|a| a.range_value());
+            root_tag_end = doc.root_element().first_child().map(|c| c.range().start);
+            let root = doc.root_element().range();
+            root_close = root_tag_end.and_then(|_| content[..root.end].rfind("</"));
+            let mut containers: Vec<(Node, usize)> = Vec::new();
+            // Transform from each element's coordinates to the map's user space
+            let mut transforms: HashMap<roxmltree::NodeId, Affine> = HashMap::new();
+            for (index, node) in doc.descendants().filter(|n| n.is_element()).enumerate() {
+                let keys: Vec<&str> = [node.attribute("id"), node.attribute("data-name")]
+                    .into_iter()
+                    .flatten()
+                    .filter(|key| !groups.contains_key(*key))
+                    .collect();
+                let count = node.descendants().filter(|n| n.is_element()).count();
+                for key in keys {
+                    groups.insert(key.to_string(), index..index + count);
+                }
+                if let (Some(id), Some(name)) = (node.attribute("id"), node.attribute("data-name"))
+                {
+                    aliases.push((name.to_string(), id.to_string()));
+                }
+
+                // Existing paint attributes, paint set in style attributes, and
+                // where to add paint to shapes that would otherwise inherit it or
+                // take it from CSS
+                containers.retain(|(container, _)| node.ancestors().any(|a| a == *container));
+                let name = node.tag_name().name();
+                let start = node.range().start;
+                let transform = match node.parent_element().and_then(|p| transforms.get(&p.id())) {
+                    Some(parent) => multiply(
+                        parent,
+                        &parse_transform(node.attribute("transform").unwrap_or("")),
+                    ),
+                    // Labels go inside the root, so its own transform is not counted
+                    None => IDENTITY,
+                };
+                transforms.insert(node.id(), transform);
+                let insert_at = content[start + 1..]
+                    .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                    .map(|name_len| start + 1 + name_len);
+                let mut sites = PaintSites {
+                    insert_at,
+                    shape: FILLED_SHAPES.contains(&name),
+                    drawn: FILLED_SHAPES.contains(&name) || DIMMED_ELEMENTS.contains(&name),
+                    container: containers.last().map(|(_, i)| *i),
+                    bounds: shape_bounds(node)
+                        .and_then(|b| Bounds::around(b.corners().map(|p| apply(&transform, p)))),
+                    copy: match (name, node.first_child(), node.last_child()) {
+                        ("symbol", Some(first), Some(last)) => {
+                            first.range().start..last.range().end
+                        }
+                        ("symbol", _, _) => start..start,
+                        _ => node.range(),
+                    },
+                    id: node
+                        .attributes()
+                        .find(|a| a.name() == "id" && a.namespace().is_none())
+                        .map(|a| content[..a.range().start].trim_end().len()..a.range().end),
+                    reference: (name == "use").then(|| use_site(node, &content)).flatten(),
+                    tag_close: match node.first_child() {
+                        Some(child) => Some((child.range().start - 1..child.range().start, None)),
+                        None if content[..node.range().end].ends_with("/>") => Some((
+                            node.range().end - 2..node.range().end,
+                            insert_at.map(|end| format!("</{}>", &content[start + 1..end])),
+                        )),
+                        None => content[node.range()]
+                            .rfind("</")
+                            .map(|at| (start + at - 1..start + at, None)),
+                    },
+                    ..PaintSites::default()
+                };
+                if UNPAINTED_CONTAINERS.contains(&name) {
+                    containers.push((node, index));
+                }
+                for attribute in node.attributes().filter(|a| a.namespace().is_none()) {
+                    if let Some(property) =
+                        PAINT_PROPERTIES.iter().find(|p| **p == attribute.name())
+                    {
+                        sites.attributes.push((property, attribute.range()));
+                    } else if attribute.name() == "style" {
+                        let value = attribute.range_value();
+                        for property in PAINT_PROPERTIES {
+                            sites.declarations.extend(
+                                declaration_values(&content[value.clone()], property)
+                                    .into_iter()
+                                    .map(|r| {
+                                        (property, value.start + r.start..value.start + r.end)
+                                    }),
+                            );
+                        }
+                    }
+                }
+                elements.push(sites);
+            }
+
+            // Copies of referenced elements replace each <use>, so they must
+            // declare the namespaces in scope at the original and on the <use>
+            let nodes: Vec<Node> = doc.descendants().filter(|n| n.is_element()).collect();
+            for (node, sites) in nodes.iter().zip(&mut elements) {
+                let Some(site) = sites.reference.as_mut() else {
+                    continue;
+                };
+                let Some(target) = groups.get(&site.target).map(|group| nodes[group.start]) else {
+                    continue;
+                };
+                let in_scope: Vec<_> = node
+                    .parent_element()
+                    .map(|parent| parent.namespaces().collect())
+                    .unwrap_or_default();
+                let mut declared: Vec<Option<&str>> = Vec::new();
+                for ns in target.namespaces().chain(node.namespaces()) {
+                    if in_scope.contains(&ns)
+                        || ns.name() == Some("xml")
+                        || declared.contains(&ns.name())
+                    {
+                        continue;
+                    }
+                    declared.push(ns.name());
+                    let uri = escape_attribute(ns.uri());
+                    let _ = match ns.name() {
+                        Some(prefix) => write!(site.attributes, r#" xmlns:{}="{}""#, prefix, uri),
+                        None => write!(site.attributes, r#" xmlns="{}""#, uri),
+                    };
+                }
+            }
+        }
+        #[cfg(feature = "geometry")]
+        let (mut measured, measured_extent) = measure(&content);
+        #[cfg(not(feature = "geometry"))]
+        let (mut measured, measured_extent) = (HashMap::new(), None);
+        for (name, id) in aliases {
+            if groups.get(&name) == groups.get(&id) {
+                if let Some(bounds) = measured.get(&id).copied() {
+                    measured.insert(name, bounds);
+                }
+            }
+        }
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(Self {
+            content: content.into(),
+            content_hash: hasher.finish(),
+            elements,
+            groups,
+            root_tag_end,
+            root_close,
+            mode: HighlightMode::default(),
+            dim: None,
+            crop: None,
+            legend: false,
+            shared: SharedFill::default(),
+            root_view_box,
+            measured,
+            measured_extent,
+        })
+    }
+
+    /// Use `mode` for every later [`MapDocument::highlight`].
+    pub fn with_mode(mut self, mode: HighlightMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Whether the map has an element with this `id` or `data-name`, such as a
+    /// single room.
+    pub fn contains(&self, id: &str) -> bool {
+        self.groups.contains_key(id)
+    }
+
+    /// Named groups of shapes that look like departments but that none of
+    /// `map_ids` refers to, sorted by name.
+    ///
+    /// A candidate is a named element other than the root that contains drawn
+    /// shapes. Candidates wrapping a mapped element (such as a layer) or inside
+    /// one (such as a room) are skipped, as are those inside another candidate.
+    /// An element listed under both its `id` and `data-name` is reported once.
+    pub fn unmapped_groups(&self, map_ids: &[&str]) -> Vec<String> {
+        let mapped: Vec<&Range<usize>> = map_ids
+            .iter()
+            .filter_map(|id| self.groups.get(*id))
+            .collect();
+        let within = |inner: &Range<usize>, outer: &Range<usize>| {
+            outer.start <= inner.start && inner.end <= outer.end
+        };
+        let mut candidates: Vec<(&Range<usize>, &String)> = self
+            .groups
+            .iter()
+            .map(|(key, range)| (range, key))
+            .filter(|(range, _)| {
+                range.start > 0
+                    && !self.elements[range.start].shape
+                    && self.elements[(*range).clone()]
+                        .iter()
+                        .any(|e| e.shape && e.container.is_none())
+                    && !mapped.iter().any(|m| within(range, m) || within(m, range))
+            })
+            .collect();
+        candidates.sort_by_key(|(range, key)| (range.start, *key));
+        candidates.dedup_by_key(|(range, _)| range.start);
+        let mut names: Vec<String> = candidates
+            .iter()
+            .filter(|(range, _)| {
+                !candidates
+                    .iter()
+                    .any(|(outer, _)| outer != range && within(range, outer))
+            })
+            .map(|(_, key)| (*key).clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Fade everything outside the highlights to `opacity` (e.g. [`DIM_OPACITY`]),
+    /// so the highlighted buildings stand out on busy whole-site maps.
+    pub fn with_dimming(mut self, opacity: f64) -> Self {
+        self.dim = Some(opacity);
+        self
+    }
+
+    /// Zoom to the highlighted elements and routes, padded on every side by
+    /// `padding` times the longer side of their bounding box (e.g.
+    /// [`CROP_PADDING`]), so the relevant part of a large campus map fills the
+    /// space it is given.
+    pub fn with_crop(mut self, padding: f64) -> Self {
+        self.crop = Some(padding);
+        self
+    }
+
+    /// Draw a legend in the bottom-left corner listing each highlight with a
+    /// [`MapHighlight::name`] beside a swatch of its colour, so the map explains
+    /// itself when used on its own.
+    pub fn with_legend(mut self) -> Self {
+        self.legend = true;
+        self
+    }
+
+    /// The map's content and options, identifying it in a [`MapCache`].
+    fn cache_key(&self) -> MapKey {
+        MapKey {
+            content: Arc::clone(&self.content),
+            content_hash: self.content_hash,
+            options: (
+                self.mode,
+                self.dim.map(f64::to_bits),
+                self.crop.map(f64::to_bits),
+                self.legend,
+                self.shared,
+            ),
+        }
+    }
+
+    /// Set how shapes filled by more than one highlight share their colours.
+    pub fn with_shared_fill(mut self, shared: SharedFill) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// The map content with `highlights` applied.
+    ///
+    /// Shapes filled by highlights of different colours are shared between
+    /// them as [`MapDocument::with_shared_fill`] sets; otherwise, where two
+    /// highlights name the same shapes, the first one listed wins.
+    pub fn highlight(&self, highlights: &[MapHighlight]) -> String {
+        self.highlight_with_routes(highlights, &[])
+    }
+
+    /// The map content with `highlights` applied, after checking that each
+    /// highlight's ID is in the map; see [`process_map_checked`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProcessorError::HighlightNotFound`] for the first unknown ID
+    /// when `strictness` is [`Strictness::Strict`].
+    pub fn highlight_checked(
+        &self,
+        highlights: &[MapHighlight],
+        strictness: Strictness,
+    ) -> Result<CheckedMap, ProcessorError> {
+        let mut warnings = Vec::new();
+        for highlight in highlights {
+            if self.contains(&highlight.id) {
+                continue;
+            }
+            let error = ProcessorError::HighlightNotFound(highlight.id.clone());
+            match strictness {
+                Strictness::Strict => return Err(error),
+                Strictness::Lenient => {
+                    let warning = error.to_string();
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+            }
+        }
+        Ok(CheckedMap {
+            svg: self.highlight(highlights),
+            warnings,
+        })
+    }
+
+    /// The map content with `highlights` applied and `routes` drawn over it.
+    pub fn highlight_with_routes(
+        &self,
+        highlights: &[MapHighlight],
+        routes: &[MapRoute],
+    ) -> String {
+        let crop = self.crop_bounds(highlights, routes);
+        if self.mode == HighlightMode::Stylesheet {
+            return self.highlight_stylesheet(highlights, routes, crop);
+        }
+
+        // We will collect replacements: (range, new_text)
+        let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+        let (shared, splits) = self.splits(highlights);
+        let mut titles = self.titles(highlights);
+        for (index, highlight) in highlights.iter().enumerate() {
+            let Some(group) = self.groups.get(&highlight.id) else {
+                continue;
+            };
+            let own_paint = highlight.paint(index);
+            for (offset, sites) in self.elements[group.clone()].iter().enumerate() {
+                let paint = match shared[group.start + offset] {
+                    Some(split) if highlight.fill_color().is_some() => {
+                        split_paint(&own_paint, split)
+                    }
+                    _ => own_paint.clone(),
+                };
+                let title = titles.get(&(group.start + offset)).copied();
+                if let Some(copy) = self.expand_use(group.start + offset, &paint, title) {
+                    replacements.push((sites.copy.clone(), copy));
+                    titles.remove(&(group.start + offset));
+                    continue;
+                }
+                let painted = sites.shape && sites.container.is_none_or(|c| c <= group.start);
+                paint_sites(sites, &paint, painted, &mut replacements);
+            }
+        }
+        replacements.extend(self.title_edits(&titles));
+
+        // Fade every drawn element outside the highlights, keeping any of its
+        // own transparency
+        if let Some(dim) = self.dim {
+            let mut highlighted = vec![false; self.elements.len()];
+            for group in highlights.iter().filter_map(|h| self.groups.get(&h.id)) {
+                highlighted[group.clone()].fill(true);
+            }
+            for (sites, _) in
+                self.elements
+                    .iter()
+                    .zip(highlighted)
+                    .filter(|(sites, highlighted)| {
+                        sites.drawn && sites.container.is_none() && !highlighted
+                    })
+            {
+                let own = sites
+                    .attributes
+                    .iter()
+                    .map(|(p, r)| (p, attribute_value(&self.content[r.clone()])))
+                    .chain(
+                        sites
+                            .declarations
+                            .iter()
+                            .map(|(p, r)| (p, &self.content[r.clone()])),
+                    )
+                    .filter(|(p, _)| **p == "opacity")
+                    .find_map(|(_, value)| value.trim().parse::<f64>().ok())
+                    .unwrap_or(1.0);
+                let paint = [("opacity", format_opacity(own * dim))];
+                paint_sites(sites, &paint, true, &mut replacements);
+            }
+        }
+        let defs = paint_defs(highlights, &splits, self.shared);
+        if let Some(at) = self.root_tag_end.filter(|_| !defs.is_empty()) {
+            replacements.push((at..at, defs));
+        }
+        let overlay = self.overlay(highlights, routes, crop);
+        if let Some(at) = self.root_close.filter(|_| !overlay.is_empty()) {
+            replacements.push((at..at, overlay));
+        }
+        replacements.extend(crop.and_then(|bounds| self.view_box(bounds)));
+
+        // Apply replacements in reverse order
+        replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+        // Deduplicate based on start index to avoid conflicting writes if regions overlap
+        replacements.dedup_by_key(|(range, _)| range.start);
+
+        let mut result = self.content.to_string();
+        for (range, text) in replacements {
+            result.replace_range(range, &text);
+        }
+        result
+    }
+
+    /// Names of the highlights, keyed by the index of the element each one
+    /// names, for tooltips; where several name one element, the first wins.
+    fn titles<'a>(&self, highlights: &'a [MapHighlight]) -> HashMap<usize, &'a str> {
+        let mut titles = HashMap::new();
+        for highlight in highlights {
+            if let (Some(group), Some(name)) = (self.groups.get(&highlight.id), &highlight.name) {
+                titles.entry(group.start).or_insert(name.as_str());
+            }
+        }
+        titles
+    }
+
+    /// Edits adding each of `titles` as the first child of its element.
+    fn title_edits(&self, titles: &HashMap<usize, &str>) -> Vec<(Range<usize>, String)> {
+        titles
+            .iter()
+            .filter_map(|(index, title)| {
+                let (range, end_tag) = self.elements[*index].tag_close.clone()?;
+                Some((
+                    range,
+                    format!(">{}{}", title_element(title), end_tag.unwrap_or_default()),
+                ))
+            })
+            .collect()
+    }
+
+    /// For each element, the index into the returned colour sets of the fills
+    /// of the highlights that meet on it, where more than one colour does.
+    fn splits<'a>(
+        &self,
+        highlights: &'a [MapHighlight],
+    ) -> (Vec<Option<usize>>, Vec<Vec<&'a str>>) {
+        let mut colors: Vec<Vec<&str>> = vec![Vec::new(); self.elements.len()];
+        for highlight in highlights {
+            let (Some(group), Some(color)) =
+                (self.groups.get(&highlight.id), highlight.fill_color())
+            else {
+                continue;
+            };
+            for element in &mut colors[group.clone()] {
+                if !element.contains(&color) {
+                    element.push(color);
+                }
+            }
+        }
+        let mut splits: Vec<Vec<&str>> = Vec::new();
+        let shared = colors
+            .into_iter()
+            .map(|element| {
+                (element.len() > 1).then(|| {
+                    splits
+                        .iter()
+                        .position(|s| *s == element)
+                        .unwrap_or_else(|| {
+                            splits.push(element);
+                            splits.len() - 1
+                        })
+                })
+            })
+            .collect();
+        (shared, splits)
+    }
+
+    /// A `<g>` standing in for the `<use>` element at `index`, holding a copy of
+    /// the element it references with `paint` applied and its ids dropped, or
+    /// `None` if the element is not a `<use>` of an element in the map.
+    fn expand_use(
+        &self,
+        index: usize,
+        paint: &[(&'static str, String)],
+        title: Option<&str>,
+    ) -> Option<String> {
+        let site = self.elements[index].reference.as_ref()?;
+        let group = self.groups.get(&site.target)?;
+        // A reference to itself or an ancestor would copy the <use> forever
+        if group.contains(&index) {
+            return None;
+        }
+        let copy = self.elements[group.start].copy.clone();
+
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for sites in &self.elements[group.clone()] {
+            edits.extend(sites.id.clone().map(|id| (id, String::new())));
+            let painted = sites.shape && sites.container.is_none_or(|c| c <= group.start);
+            paint_sites(sites, paint, painted, &mut edits);
+        }
+        edits.retain(|(range, _)| copy.start <= range.start && range.end <= copy.end);
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        edits.dedup_by_key(|(range, _)| range.start);
+        let mut text = self.content[copy.clone()].to_string();
+        for (range, replacement) in edits {
+            text.replace_range(
+                range.start - copy.start..range.end - copy.start,
+                &replacement,
+            );
+        }
+
+        let transform = if site.transform.is_empty() {
+            String::new()
+        } else {
+            format!(r#" transform="{}""#, site.transform)
+        };
+        Some(format!(
+            "<g{}{}>{}{}</g>",
+            site.attributes,
+            transform,
+            title.map(title_element).unwrap_or_default(),
+            text
+        ))
+    }
+
+    /// Centre of the bounding box of the element with this `id` or `data-name`,
+    /// in the map's user space.
+    pub fn centre(&self, id: &str) -> Option<(f64, f64)> {
+        let bounds = self.bounds(id)?;
+        Some((
+            (bounds.min_x + bounds.max_x) / 2.0,
+            (bounds.min_y + bounds.max_y) / 2.0,
+        ))
+    }
+
+    /// Extent of the element with this `id` or `data-name`, as measured if the
+    /// map was, or else from its shapes.
+    fn bounds(&self, id: &str) -> Option<Bounds> {
+        match self.measured.get(id) {
+            Some(bounds) => Some(*bounds),
+            None => self.group_bounds(self.groups.get(id)?),
+        }
+    }
+
+    /// Extent of the drawn geometry of an element and its descendants.
+    fn group_bounds(&self, group: &Range<usize>) -> Option<Bounds> {
+        Bounds::around(
+            self.elements[group.clone()]
+                .iter()
+                .filter(|sites| sites.container.is_none_or(|c| c <= group.start))
+                .filter_map(|sites| sites.bounds)
+                .flat_map(|b| b.corners()),
+        )
+    }
+
+    /// The area of `highlights` and `routes`, padded as [`MapDocument::with_crop`]
+    /// asks; `None` when not cropping or there is no area to crop to.
+    fn crop_bounds(&self, highlights: &[MapHighlight], routes: &[MapRoute]) -> Option<Bounds> {
+        let padding = self.crop?;
+        let bounds = Bounds::around(
+            highlights
+                .iter()
+                .filter_map(|h| self.bounds(&h.id))
+                .flat_map(|b| b.corners())
+                .chain(routes.iter().flat_map(|r| r.points.iter().copied())),
+        )?;
+        let pad = (bounds.max_x - bounds.min_x).max(bounds.max_y - bounds.min_y) * padding;
+        let padded = Bounds {
+            min_x: bounds.min_x - pad,
+            min_y: bounds.min_y - pad,
+            max_x: bounds.max_x + pad,
+            max_y: bounds.max_y + pad,
+        };
+        (padded.max_x > padded.min_x && padded.max_y > padded.min_y).then_some(padded)
+    }
+
+    /// The edit setting the root's `viewBox` to `bounds`.
+    fn view_box(&self, bounds: Bounds) -> Option<(Range<usize>, String)> {
+        let value = format!(
+            "{} {} {} {}",
+            format_coordinate(bounds.min_x),
+            format_coordinate(bounds.min_y),
+            format_coordinate(bounds.max_x - bounds.min_x),
+            format_coordinate(bounds.max_y - bounds.min_y)
+        );
+        match &self.root_view_box {
+            Some(range) => Some((range.clone(), value)),
+            None => {
+                let at = self.elements.first()?.insert_at?;
+                Some((at..at, format!(r#" viewBox="{}""#, value)))
+            }
+        }
+    }
+
+    /// Routes, labels and the legend drawn over the map, routes first so labels
+    /// stay readable. Route keys and the legend go in the corners of `crop`, or
+    /// else of the map.
+    fn overlay(
+        &self,
+        highlights: &[MapHighlight],
+        routes: &[MapRoute],
+        crop: Option<Bounds>,
+    ) -> String {
+        let extent = crop.or(self.measured_extent).or_else(|| {
+            Bounds::around(
+                self.elements
+                    .iter()
+                    .filter_map(|sites| sites.bounds)
+                    .flat_map(|b| b.corners()),
+            )
+        });
+        let mut overlay: String = routes.iter().map(|route| route.to_svg(extent)).collect();
+        overlay.push_str(&self.labels(highlights));
+        if let Some(extent) = extent.filter(|_| self.legend) {
+            overlay.push_str(&legend(highlights, extent));
+        }
+        overlay
+    }
+
+    /// Text for the highlights' labels, each centred on its element and sized
+    /// to fit inside it.
+    fn labels(&self, highlights: &[MapHighlight]) -> String {
+        let mut labels = String::new();
+        for highlight in highlights {
+            let (Some(label), Some(bounds)) = (&highlight.label, self.bounds(&highlight.id)) else {
+                continue;
+            };
+
+            let lines: Vec<&str> = label.lines().collect();
+            let longest = lines
+                .iter()
+                .map(|l| l.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(1);
+            let width = bounds.max_x - bounds.min_x;
+            let height = bounds.max_y - bounds.min_y;
+            let size = (height / (lines.len() as f64 * 1.4))
+                .min(width * 0.9 / (longest as f64 * 0.6))
+                .min(MAP_LABEL_MAX_SIZE);
+            let x = (bounds.min_x + bounds.max_x) / 2.0;
+            let middle = (bounds.min_y + bounds.max_y) / 2.0;
+            let _ = write!(
+                labels,
+                concat!(
+                    r#"<g class="map-label" font-family="Arial, sans-serif" font-size="{}" "#,
+                    r##"text-anchor="middle" dominant-baseline="central" fill="#231f20" "##,
+                    r##"stroke="#ffffff" stroke-width="{}" paint-order="stroke">"##
+                ),
+                format_coordinate(size),
+                format_coordinate(size / 5.0)
+            );
+            for (i, line) in lines.iter().enumerate() {
+                let y = middle + (i as f64 - (lines.len() - 1) as f64 / 2.0) * size * 1.2;
+                let _ = write!(
+                    labels,
+                    r#"<text x="{}" y="{}"{}>{}</text>"#,
+                    format_coordinate(x),
+                    format_coordinate(y),
+                    if i == 0 { r#" font-weight="bold""# } else { "" },
+                    escape_text(line)
+                );
+            }
+            labels.push_str("</g>");
+        }
+        labels
+    }
+
+    /// The map content with a `<style>` block colouring the shapes inside each
+    /// highlighted element, zoomed to `crop`.
+    fn highlight_stylesheet(
+        &self,
+        highlights: &[MapHighlight],
+        routes: &[MapRoute],
+        crop: Option<Bounds>,
+    ) -> String {
+        let Some(root_tag_end) = self
+            .root_tag_end
+            .filter(|_| !highlights.is_empty() || !routes.is_empty() || self.dim.is_some())
+        else {
+            return self.content.to_string();
+        };
+        let drawn: Vec<&str> = FILLED_SHAPES
+            .iter()
+            .chain(&DIMMED_ELEMENTS)
+            .copied()
+            .collect();
+        let mut css = String::new();
+        if let Some(dim) = self.dim {
+            let _ = writeln!(
+                css,
+                "{} {{ opacity: {} !important; }}",
+                drawn.join(", "),
+                format_opacity(dim)
+            );
+            let _ = writeln!(
+                css,
+                ".map-label text, .map-route *, .map-legend * {{ opacity: 1 !important; }}"
+            );
+        }
+        // Later rules win, so the first highlight listed goes last
+        for (index, highlight) in highlights.iter().enumerate().rev() {
+            if self.dim.is_some() {
+                let _ = writeln!(
+                    css,
+                    "{} {{ opacity: 1 !important; }}",
+                    group_selectors(&highlight.id, &drawn)
+                );
+            }
+            let declarations: Vec<String> = highlight
+                .paint(index)
+                .into_iter()
+                .map(|(property, value)| {
+                    format!("{}: {} !important;", property, escape_text(&value))
+                })
+                .collect();
+            let _ = writeln!(
+                css,
+                "{} {{ {} }}",
+                group_selectors(&highlight.id, &FILLED_SHAPES),
+                declarations.join(" ")
+            );
+        }
+        // Shared groups follow, so they win over the groups around them
+        let (shared, splits) = self.splits(highlights);
+        let mut split_ids: Vec<&str> = Vec::new();
+        for highlight in highlights.iter().filter(|h| h.fill_color().is_some()) {
+            let Some(split) = self.groups.get(&highlight.id).and_then(|g| shared[g.start]) else {
+                continue;
+            };
+            if split_ids.contains(&highlight.id.as_str()) {
+                continue;
+            }
+            split_ids.push(&highlight.id);
+            let _ = writeln!(
+                css,
+                "{} {{ fill: url(#{}) !important; }}",
+                group_selectors(&highlight.id, &FILLED_SHAPES),
+                split_id(split)
+            );
+        }
+
+        let mut result = self.content.to_string();
+        if let Some(root_close) = self.root_close {
+            result.insert_str(root_close, &self.overlay(highlights, routes, crop));
+        }
+        // Copies of referenced shapes inside the groups, for the rules to reach,
+        // and tooltips
+        let mut titles = self.titles(highlights);
+        let mut copies: Vec<(Range<usize>, String)> = highlights
+            .iter()
+            .filter_map(|h| self.groups.get(&h.id))
+            .flat_map(|group| group.clone())
+            .filter_map(|index| {
+                let copy = self.expand_use(index, &[], titles.get(&index).copied())?;
+                titles.remove(&index);
+                Some((self.elements[index].copy.clone(), copy))
+            })
+            .collect();
+        copies.extend(self.title_edits(&titles));
+        copies.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        copies.dedup_by_key(|(range, _)| range.start);
+        for (range, copy) in copies {
+            result.replace_range(range, &copy);
+        }
+        result.insert_str(
+            root_tag_end,
+            &format!(
+                "{}<style>\n{}</style>",
+                paint_defs(highlights, &splits, self.shared),
+                css
+            ),
+        );
+        if let Some((range, view_box)) = crop.and_then(|bounds| self.view_box(bounds)) {
+            result.replace_range(range, &view_box);
+        }
+        result
+    }
+}
+
+/// Processed maps remembered by map and highlight list, for batch runs where
+/// many students share the same departments.
+///
+/// A cached map is exactly what [`MapDocument::highlight`] gives. Since the
+/// order of the highlights decides which of two naming the same shapes wins
+/// and the order of the legend, the same highlights listed in another order
+/// are processed again.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::{MapCache, MapDocument, MapHighlight};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = MapDocument::parse(r##"<svg><g id="MA"><rect/></g><g id="SC"><rect/></g></svg>"##)?;
+/// let maths = MapHighlight::new("MA", "#fcdcd8");
+/// let science = MapHighlight::new("SC", "#fad7e6");
+///
+/// let mut cache = MapCache::new();
+/// cache.warm(&map, [vec![maths.clone(), science.clone()]]);
+/// let svg = cache.highlight(&map, &[maths, science]);
+/// assert!(svg.contains("#fcdcd8") && svg.contains("#fad7e6"));
+/// assert_eq!(cache.len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct MapCache {
+    maps: HashMap<(MapKey, Vec<MapHighlight>), String>,
+}
+
+/// A map's content and options as a [`MapCache`] key. It hashes the content
+/// hash taken when the map was parsed, and compares the content itself only
+/// when the hashes agree and the maps are not the same document.
+#[derive(Debug, Clone)]
+struct MapKey {
+    content: Arc<str>,
+    content_hash: u64,
+    /// Highlight mode, dimming, crop padding, legend and shared fill
+    options: (HighlightMode, Option<u64>, Option<u64>, bool, SharedFill),
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.content_hash == other.content_hash
+            && self.options == other.options
+            && (Arc::ptr_eq(&self.content, &other.content) || self.content == other.content)
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.content_hash.hash(state);
+        self.options.hash(state);
+    }
+}
+
+impl MapCache {
+    /// An empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `map` with `highlights` applied, processed only the first time the map
+    /// and highlight list are seen.
+    pub fn highlight(&mut self, map: &MapDocument, highlights: &[MapHighlight]) -> String {
+        let key = (map.cache_key(), highlights.to_vec());
+        self.maps
+            .entry(key)
+            .or_insert_with_key(|(_, highlights)| map.highlight(highlights))
+            .clone()
+    }
+
+    /// Process `map` with each of `highlight_sets` ahead of time, such as the
+    /// departments of every form group before a year group's sheets are made.
+    pub fn warm(
+        &mut self,
+        map: &MapDocument,
+        highlight_sets: impl IntoIterator<Item = Vec<MapHighlight>>,
+    ) {
+        for highlights in highlight_sets {
+            self.highlight(map, &highlights);
+        }
+    }
+
+    /// Number of processed maps held.
+    pub fn len(&self) -> usize {
+        self.maps.len()
+    }
+
+    /// Whether no maps have been processed yet.
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+}
+
+/// A swatch and name for each named highlight, listed once per name, boxed in
+/// the bottom-left corner of `extent`; nothing without named highlights.
+fn legend(highlights: &[MapHighlight], extent: Bounds) -> String {
+    let mut entries: Vec<(usize, &MapHighlight, &str)> = Vec::new();
+    for (index, highlight) in highlights.iter().enumerate() {
+        if let Some(name) = &highlight.name {
+            if !entries.iter().any(|(_, _, n)| n == name) {
+                entries.push((index, highlight, name));
+            }
+        }
+    }
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    let longest = entries
+        .iter()
+        .map(|(_, _, n)| n.chars().count())
+        .max()
+        .unwrap_or(0);
+    let height = entries.len() as f64 * LEGEND_ROW + 6.0;
+    let (x, y) = (extent.min_x + 8.0, extent.max_y - 8.0 - height);
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        concat!(
+            r#"<g class="map-legend" font-family="Arial, sans-serif" font-size="10">"#,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" rx="3" fill="#ffffff" "##,
+            r##"fill-opacity="0.9" stroke="#231f20" stroke-width="0.5"/>"##
+        ),
+        format_coordinate(x),
+        format_coordinate(y),
+        format_coordinate(30.0 + longest as f64 * 6.0),
+        format_coordinate(height)
+    );
+    for (row, (index, highlight, name)) in entries.into_iter().enumerate() {
+        let top = y + 3.0 + row as f64 * LEGEND_ROW;
+        // The highlight's own paint over a plain outlined swatch
+        let mut paint: Vec<(&str, String)> = vec![
+            ("fill", "#ffffff".to_string()),
+            ("stroke", "#231f20".to_string()),
+            ("stroke-width", "0.5".to_string()),
+        ];
+        for (property, value) in highlight.paint(index) {
+            match paint.iter_mut().find(|(p, _)| *p == property) {
+                Some((_, existing)) => *existing = value,
+                None => paint.push((property, value)),
+            }
+        }
+        let paint: String = paint
+            .iter()
+            .map(|(property, value)| format!(r#" {}="{}""#, property, escape_attribute(value)))
+            .collect();
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{}" width="14" height="10"{}/><text x="{}" y="{}" dominant-baseline="central">{}</text>"#,
+            format_coordinate(x + 6.0),
+            format_coordinate(top + 4.0),
+            paint,
+            format_coordinate(x + 26.0),
+            format_coordinate(top + LEGEND_ROW / 2.0),
+            escape_text(name)
+        );
+    }
+    svg.push_str("</g>");
+    svg
+}
+
+/// Height of a row in a map legend, in map units.
+const LEGEND_ROW: f64 = 18.0;
+
+/// CSS selectors for `elements` that are, or are inside, the element whose
+/// `id` or `data-name` is `id`.
+fn group_selectors(id: &str, elements: &[&str]) -> String {
+    let id = css_string(id);
+    let selectors: Vec<String> = ["id", "data-name"]
+        .iter()
+        .flat_map(|attribute| {
+            let group = format!("[{}=\"{}\"]", attribute, id);
+            elements
+                .iter()
+                .flat_map(move |element| {
+                    [
+                        format!("{}{}", element, group),
+                        format!("{} {}", group, element),
+                    ]
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    selectors.join(", ")
+}
+
+/// Where the `<use>` element `node` draws from, if it references an element
+/// in the same document.
+fn use_site(node: Node, content: &str) -> Option<UseSite> {
+    let target = node
+        .attributes()
+        .find(|a| a.name() == "href")?
+        .value()
+        .strip_prefix('#')?
+        .to_string();
+    // Attributes placing the reference, which the copy does without
+    let placement = ["href", "x", "y", "width", "height", "transform"];
+    let mut attributes = String::new();
+    for attribute in node.attributes() {
+        if !placement.contains(&attribute.name()) {
+            attributes.push(' ');
+            attributes.push_str(&content[attribute.range()]);
+        }
+    }
+    let offset = |name| {
+        node.attribute(name)
+            .and_then(|v: &str| v.trim().parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    let (x, y) = (offset("x"), offset("y"));
+    let mut transform = node.attribute("transform").unwrap_or("").trim().to_string();
+    if x != 0.0 || y != 0.0 {
+        if !transform.is_empty() {
+            transform.push(' ');
+        }
+        let _ = write!(
+            transform,
+            "translate({},{})",
+            format_coordinate(x),
+            format_coordinate(y)
+        );
+    }
+    Some(UseSite {
+        target,
+        attributes,
+        transform,
+    })
+}
+
+/// Queue the replacements setting `paint` on one element: existing attributes
+/// and `style` declarations are rewritten, and when `insert` is set the
+/// properties it lacks are added as attributes.
+fn paint_sites(
+    sites: &PaintSites,
+    paint: &[(&'static str, String)],
+    insert: bool,
+    replacements: &mut Vec<(Range<usize>, String)>,
+) {
+    let mut missing = String::new();
+    for (property, value) in paint {
+        let mut found = false;
+        for (_, range) in sites.attributes.iter().filter(|(p, _)| p == property) {
+            replacements.push((
+                range.clone(),
+                format!("{}=\"{}\"", property, escape_attribute(value)),
+            ));
+            found = true;
+        }
+        for (_, range) in sites.declarations.iter().filter(|(p, _)| p == property) {
+            replacements.push((range.clone(), escape_attribute(value)));
+            found = true;
+        }
+        if !found {
+            let _ = write!(missing, " {}=\"{}\"", property, escape_attribute(value));
+        }
+    }
+    if let Some(at) = sites.insert_at.filter(|_| insert && !missing.is_empty()) {
+        replacements.push((at..at, missing));
+    }
+}
+
+/// Value of an attribute written as `name="value"`, without its quotes.
+fn attribute_value(attribute: &str) -> &str {
+    attribute.split_once('=').map_or("", |(_, value)| {
+        value.trim().trim_matches(|c| c == '"' || c == '\'')
+    })
+}
+
+/// An opacity written with at most three decimal places.
+fn format_opacity(opacity: f64) -> String {
+    let rounded = (opacity.clamp(0.0, 1.0) * 1000.0).round() / 1000.0;
+    rounded.to_string()
+}
+
+/// A coordinate or length written with at most two decimal places.
+fn format_coordinate(value: f64) -> String {
+    ((value * 100.0).round() / 100.0).to_string()
+}
+
+/// Text with the characters that are special in XML escaped.
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Text escaped for a double-quoted XML attribute value.
+fn escape_attribute(value: &str) -> String {
+    escape_text(value).replace('"', "&quot;")
+}
+
+/// A 2D affine transform `[a, b, c, d, e, f]`, as in SVG's `matrix()`.
+type Affine = [f64; 6];
+
+const IDENTITY: Affine = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// The transform applying `inner` and then `outer`.
+fn multiply(outer: &Affine, inner: &Affine) -> Affine {
+    let [a, b, c, d, e, f] = *outer;
+    let [a2, b2, c2, d2, e2, f2] = *inner;
+    [
+        a * a2 + c * b2,
+        b * a2 + d * b2,
+        a * c2 + c * d2,
+        b * c2 + d * d2,
+        a * e2 + c * f2 + e,
+        b * e2 + d * f2 + f,
+    ]
+}
+
+/// `point` moved by `transform`.
+fn apply(transform: &Affine, (x, y): (f64, f64)) -> (f64, f64) {
+    let [a, b, c, d, e, f] = *transform;
+    (a * x + c * y + e, b * x + d * y + f)
+}
+
+/// Parse an SVG `transform` attribute; unknown functions are ignored.
+fn parse_transform(transform: &str) -> Affine {
+    let mut result = IDENTITY;
+    for function in transform.split(')') {
+        let Some((name, args)) = function.split_once('(') else {
+            continue;
+        };
+        let args = numbers(args);
+        let arg = |i: usize, default: f64| args.get(i).copied().unwrap_or(default);
+        let step = match name.trim().trim_start_matches(',').trim() {
+            "matrix" if args.len() == 6 => [args[0], args[1], args[2], args[3], args[4], args[5]],
+            "translate" => [1.0, 0.0, 0.0, 1.0, arg(0, 0.0), arg(1, 0.0)],
+            "scale" => [arg(0, 1.0), 0.0, 0.0, arg(1, arg(0, 1.0)), 0.0, 0.0],
+            "rotate" => {
+                let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                let rotate = [cos, sin, -sin, cos, 0.0, 0.0];
+                multiply(
+                    &[1.0, 0.0, 0.0, 1.0, cx, cy],
+                    &multiply(&rotate, &[1.0, 0.0, 0.0, 1.0, -cx, -cy]),
+                )
+            }
+            "skewX" => [1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0],
+            "skewY" => [1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+            _ => IDENTITY,
+        };
+        result = multiply(&result, &step);
+    }
+    result
+}
+
+/// The numbers in a list separated by whitespace and commas, as in `points`,
+/// `transform` arguments and path data (where `1-2` and `.5.5` are two numbers each).
+fn numbers(list: &str) -> Vec<f64> {
+    let mut numbers = Vec::new();
+    let mut rest = list;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        let mut end = 0;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        for (i, c) in rest.char_indices() {
+            let accepted = match c {
+                '+' | '-' => i == 0 || rest[..i].ends_with(['e', 'E']),
+                '.' if !seen_dot && !seen_exponent => {
+                    seen_dot = true;
+                    true
+                }
+                'e' | 'E' if i > 0 && !seen_exponent => {
+                    seen_exponent = true;
+                    true
+                }
+                c => c.is_ascii_digit(),
+            };
+            if !accepted {
+                break;
+            }
+            end = i + c.len_utf8();
+        }
+        match rest[..end].parse::<f64>() {
+            Ok(number) if end > 0 => numbers.push(number),
+            _ => break,
+        }
+        rest = &rest[end..];
+    }
+    numbers
+}
+
+/// Extent of a shape's geometry in its own coordinates.
+fn shape_bounds(node: Node) -> Option<Bounds> {
+    let number = |name: &str| {
+        node.attribute(name)
+            .and_then(|v| numbers(v).first().copied())
+            .unwrap_or(0.0)
+    };
+    match node.tag_name().name() {
+        "rect" | "image" | "use" if node.has_attribute("width") => {
+            let (x, y) = (number("x"), number("y"));
+            Bounds::around([(x, y), (x + number("width"), y + number("height"))])
+        }
+        "circle" => {
+            let (cx, cy, r) = (number("cx"), number("cy"), number("r"));
+            Bounds::around([(cx - r, cy - r), (cx + r, cy + r)])
+        }
+        "ellipse" => {
+            let (cx, cy, rx, ry) = (number("cx"), number("cy"), number("rx"), number("ry"));
+            Bounds::around([(cx - rx, cy - ry), (cx + rx, cy + ry)])
+        }
+        "line" => Bounds::around([(number("x1"), number("y1")), (number("x2"), number("y2"))]),
+        "polygon" | "polyline" => {
+            let points = numbers(node.attribute("points")?);
+            Bounds::around(points.chunks_exact(2).map(|p| (p[0], p[1])))
+        }
+        "path" => Bounds::around(path_points(node.attribute("d")?)),
+        _ => None,
+    }
+}
+
+/// Extents of the named elements in `content` and of the whole map, measured
+/// by `usvg` in the map's user space; empty if `usvg` cannot read the map
+/// (such as one missing the SVG namespace), leaving the estimates.
+/// Where an `id` is repeated, as in the copies `<use>` makes, the first wins.
+#[cfg(feature = "geometry")]
+fn measure(content: &str) -> (HashMap<String, Bounds>, Option<Bounds>) {
+    use usvg::TreeParsing;
+
+    fn bounds(rect: usvg::Rect) -> Bounds {
+        Bounds {
+            min_x: rect.left() as f64,
+            min_y: rect.top() as f64,
+            max_x: rect.right() as f64,
+            max_y: rect.bottom() as f64,
+        }
+    }
+    fn walk(group: &usvg::Group, measured: &mut HashMap<String, Bounds>) {
+        for node in &group.children {
+            if let Some(rect) = node.abs_bounding_box().filter(|_| !node.id().is_empty()) {
+                measured
+                    .entry(node.id().to_string())
+                    .or_insert(bounds(rect));
+            }
+            if let usvg::Node::Group(child) = node {
+                walk(child, measured);
+            }
+        }
+    }
+
+    let Ok(mut tree) = usvg::Tree::from_str(content, &usvg::Options::default()) else {
+        return (HashMap::new(), None);
+    };
+    tree.calculate_abs_transforms();
+    tree.calculate_bounding_boxes();
+    let mut measured = HashMap::new();
+    walk(&tree.root, &mut measured);
+    (measured, tree.root.abs_bounding_box().map(bounds))
+}
+
+/// End and control points of path data, in absolute coordinates. Control
+/// points can lie outside the curve, so the box they give may be a little large.
+fn path_points(d: &str) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    let (mut x, mut y) = (0.0, 0.0);
+    let (mut start_x, mut start_y) = (0.0, 0.0);
+    let is_command = |c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E';
+    let mut rest = d;
+    while let Some(start) = rest.find(is_command) {
+        let command = rest[start..].chars().next().unwrap_or(' ');
+        let after = &rest[start + 1..];
+        let end = after.find(is_command).unwrap_or(after.len());
+        let args = numbers(&after[..end]);
+        rest = &after[end..];
+
+        let relative = command.is_ascii_lowercase();
+        let arity = match command.to_ascii_uppercase() {
+            'M' | 'L' | 'T' => 2,
+            'H' | 'V' => 1,
+            'S' | 'Q' => 4,
+            'C' => 6,
+            'A' => 7,
+            _ => 0,
+        };
+        if arity == 0 {
+            (x, y) = (start_x, start_y);
+            continue;
+        }
+        for (i, group) in args.chunks_exact(arity).enumerate() {
+            let (ox, oy) = if relative { (x, y) } else { (0.0, 0.0) };
+            match command.to_ascii_uppercase() {
+                'H' => x = group[0] + ox,
+                'V' => y = group[0] + oy,
+                'A' => (x, y) = (group[5] + ox, group[6] + oy),
+                _ => {
+                    for pair in group.chunks_exact(2) {
+                        points.push((pair[0] + ox, pair[1] + oy));
+                    }
+                    (x, y) = *points.last().unwrap_or(&(x, y));
+                }
+            }
+            points.push((x, y));
+            if command.eq_ignore_ascii_case(&'M') && i == 0 {
+                (start_x, start_y) = (x, y);
+            }
+        }
+    }
+    points
+}
+
+/// Quote-safe text for a CSS string, with characters that would end the
+/// string or break the surrounding XML escaped.
+fn css_string(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '<' | '&' | '\n' => {
+                let _ = write!(escaped, "\\{:x} ", c as u32);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Byte ranges, within a `style` attribute value, of the values of its
+/// declarations of `property` (without any `!important`).
+fn declaration_values(style: &str, property: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for declaration in style.split(';') {
+        let start = offset;
+        offset += declaration.len() + 1;
+        let Some((name, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        if name.trim() != property {
+            continue;
+        }
+        let value = value.split('!').next().unwrap_or(value);
+        let value_start = start + name.len() + 1 + (value.len() - value.trim_start().len());
+        ranges.push(value_start..value_start + value.trim().len());
+    }
+    ranges
+}
+
+/// Elements removed from untrusted maps: scripts, and elements that embed HTML
+/// or other documents.
+const UNSAFE_ELEMENTS: [&str; 5] = ["script", "foreignObject", "iframe", "embed", "object"];
+
+/// Remove anything from a map SVG that could run code or fetch other content
+/// when the map is opened in a browser.
+///
+/// Maps from third parties are sanitised before processing. This removes:
+/// - `<script>`, `<foreignObject>`, `<iframe>`, `<embed>` and `<object>` elements
+/// - `on*` event handler attributes
+/// - animations that set an event handler or a link
+/// - `href`s that do not point into the map itself (or at an embedded raster image)
+/// - external CSS `url(...)`s, which become `none`
+/// - `@import` rules
+/// - `xml-stylesheet` processing instructions
+///
+/// Everything else is copied through byte for byte.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError`] if the SVG XML is malformed.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::sanitise_map;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = r##"<svg xmlns="http://www.w3.org/2000/svg"><script>alert(1)</script><rect id="hall" onclick="alert(2)" fill="url(#tiles)"/></svg>"##;
+/// assert_eq!(
+///     sanitise_map(map)?,
+///     r##"<svg xmlns="http://www.w3.org/2000/svg"><rect id="hall" fill="url(#tiles)"/></svg>"##
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn sanitise_map(svg: &str) -> Result<String, ProcessorError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(svg, options)?;
+    let url_re = Regex::new(r#"(?i)url\(\s*("[^"]*"|'[^']*'|[^)]*)\)"#)?;
+    let import_re = Regex::new(r"(?i)@import[^;]*;?")?;
+    let clean_css = |css: &str| -> Option<String> {
+        let cleaned = import_re.replace_all(css, "");
+        let cleaned = url_re.replace_all(&cleaned, |caps: &regex::Captures| {
+            let target = caps[1].trim_matches(|c: char| c == '"' || c == '\'' || c.is_whitespace());
+            if is_local_reference(target) {
+                caps[0].to_string()
+            } else {
+                "none".to_string()
+            }
+        });
+        (cleaned != css).then(|| cleaned.into_owned())
+    };
+
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for node in doc.descendants() {
+        if node.pi().is_some_and(|pi| pi.target == "xml-stylesheet") {
+            edits.push((node.range(), String::new()));
+            continue;
+        }
+        if !node.is_element() {
+            continue;
+        }
+        let name = node.tag_name().name();
+        let animated = node.attribute("attributeName").unwrap_or("");
+        if UNSAFE_ELEMENTS.contains(&name)
+            || (["set", "animate"].contains(&name)
+                && (is_event_handler(animated) || animated.ends_with("href")))
+        {
+            edits.push((node.range(), String::new()));
+            continue;
+        }
+        for attribute in node.attributes() {
+            let unsafe_attribute = (attribute.namespace().is_none()
+                && is_event_handler(attribute.name()))
+                || (attribute.name() == "href" && !is_local_reference(attribute.value()));
+            if unsafe_attribute {
+                let start = svg[..attribute.range().start].trim_end().len();
+                edits.push((start..attribute.range().end, String::new()));
+            } else if let Some(cleaned) = clean_css(attribute.value()) {
+                edits.push((attribute.range_value(), escape_attribute(&cleaned)));
+            }
+        }
+        if name == "style" {
+            if let (Some(first), Some(last)) = (node.first_child(), node.last_child()) {
+                let css: String = node.children().filter_map(|c| c.text()).collect();
+                if let Some(cleaned) = clean_css(&css) {
+                    edits.push((first.range().start..last.range().end, escape_text(&cleaned)));
+                }
+            }
+        }
+    }
+
+    // Edits come in document order; those inside a removed element are dropped
+    let mut output = String::with_capacity(svg.len());
+    let mut last = 0;
+    for (range, replacement) in edits {
+        if range.start < last {
+            continue;
+        }
+        output.push_str(&svg[last..range.start]);
+        output.push_str(&replacement);
+        last = range.end;
+    }
+    output.push_str(&svg[last..]);
+    Ok(output)
+}
+
+/// Whether an attribute name is an event handler such as `onclick`.
+fn is_event_handler(name: &str) -> bool {
+    name.get(..2)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("on"))
+}
+
+/// Whether a link stays inside the map: a fragment, or an embedded raster image.
+fn is_local_reference(target: &str) -> bool {
+    let target = target.trim();
+    let lower = target.to_ascii_lowercase();
+    target.starts_with('#')
+        || ["png", "jpeg", "jpg", "gif", "webp"].iter().any(|kind| {
+            lower.starts_with(&format!("data:image/{};", kind))
+                || lower.starts_with(&format!("data:image/{},", kind))
+        })
+}
+
+/// Parse a map document into an element tree that can be added to a sheet.
+///
+/// Splicing the map's text into a sheet breaks on a DOCTYPE or an XML
+/// declaration in the wrong place. Instead the map is parsed, with entities
+/// expanded and comments and processing instructions dropped, and rebuilt
+/// element by element. Each element declares the namespaces it introduces,
+/// so prefixed names such as `xlink:href` keep their meaning once nested.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError::Xml`] if `map_content` is not well-formed XML.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::map_element;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let map = r#"<?xml version="1.0"?>
+/// <!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+/// <svg xmlns="http://www.w3.org/2000/svg"><rect id="hall"/></svg>"#;
+/// let element = map_element(map)?;
+/// assert_eq!(
+///     element.to_string(),
+///     "<svg xmlns=\"http://www.w3.org/2000/svg\">\n<rect id=\"hall\"/>\n</svg>"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn map_element(map_content: &str) -> Result<CopiedElement, ProcessorError> {
+    map_element_with_id_prefix(map_content, "")
+}
+
+/// Like [`map_element`], but with `id_prefix` added to every id in the map
+/// and to everything that refers to one: `href`s, `url(#...)` references,
+/// `aria-labelledby` and `aria-describedby`, and `#id` and `[id=...]`
+/// selectors in stylesheets.
+///
+/// Maps placed in one document this way keep their ids, patterns and
+/// stylesheet rules apart, even when they use the same names.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError::Xml`] if `map_content` is not well-formed XML.
+pub fn map_element_with_id_prefix(
+    map_content: &str,
+    id_prefix: &str,
+) -> Result<CopiedElement, ProcessorError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(map_content, options)?;
+    Ok(copy_element(doc.root_element(), id_prefix))
+}
+
+/// An element copied from a parsed document, which nests in `svg` crate
+/// documents like an [`Element`](svg::node::element::Element).
+///
+/// The `svg` crate writes attribute values with `&`, `<` and `>` escaped but
+/// leaves quotes as they are, so a copied value such as
+/// `font-family='"Open Sans"'` would end the attribute early. This element
+/// writes them as `&quot;` instead.
+#[derive(Clone, Debug)]
+pub struct CopiedElement {
+    name: String,
+    attributes: Attributes,
+    children: Children,
+}
+
+impl CopiedElement {
+    /// An element named `name`, with no attributes or children.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attributes: Attributes::new(),
+            children: Children::new(),
+        }
+    }
+
+    /// The element's attributes.
+    pub fn get_attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+}
+
+impl fmt::Display for CopiedElement {
+    /// Written as the `svg` crate writes an element, attributes sorted by
+    /// name, but with quotes in their values escaped.
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "<{}", self.name)?;
+        let mut attributes: Vec<_> = self.attributes.iter().collect();
+        attributes.sort_by_key(|(name, _)| name.as_str());
+        for (name, value) in attributes {
+            write!(formatter, " {}=\"{}\"", name, escape_attribute(value))?;
+        }
+        if self.children.is_empty() {
+            return write!(formatter, "/>");
+        }
+        write!(formatter, ">")?;
+        let mut bare = false;
+        for child in &self.children {
+            bare = child.is_bare() && !formatter.alternate();
+            if !bare {
+                writeln!(formatter)?;
+            }
+            write!(formatter, "{}", child)?;
+        }
+        if !bare {
+            writeln!(formatter)?;
+        }
+        write!(formatter, "</{}>", self.name)
+    }
+}
+
+impl svg::Node for CopiedElement {
+    fn append<T>(&mut self, node: T)
+    where
+        T: Into<Box<dyn svg::Node>>,
+    {
+        self.children.push(node.into());
+    }
+
+    fn assign<T, U>(&mut self, name: T, value: U)
+    where
+        T: Into<String>,
+        U: Into<Value>,
+    {
+        self.attributes.insert(name.into(), value.into());
+    }
+
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_attributes(&self) -> Option<&Attributes> {
+        Some(&self.attributes)
+    }
+
+    fn get_attributes_mut(&mut self) -> Option<&mut Attributes> {
+        Some(&mut self.attributes)
+    }
+
+    fn get_children(&self) -> Option<&Children> {
+        Some(&self.children)
+    }
+
+    fn get_children_mut(&mut self) -> Option<&mut Children> {
+        Some(&mut self.children)
+    }
+}
+
+impl NodeDefaultHash for CopiedElement {
+    fn default_hash(&self, state: &mut DefaultHasher) {
+        self.name.hash(state);
+        let mut attributes: Vec<_> = self.attributes.iter().collect();
+        attributes.sort_by_key(|(name, _)| name.as_str());
+        for (name, value) in attributes {
+            name.hash(state);
+            value.hash(state);
+        }
+        for child in &self.children {
+            child.default_hash(state);
+        }
+    }
+}
+
+/// Copy a parsed element and its descendants into a [`CopiedElement`], adding
+/// `id_prefix` to their ids and the references to them.
+pub(crate) fn copy_element(node: Node, id_prefix: &str) -> CopiedElement {
+    let name = qualified_name(node, node.tag_name().namespace(), node.tag_name().name());
+    let mut element = CopiedElement::new(name);
+
+    let inherited: Vec<_> = node
+        .parent_element()
+        .map(|p| p.namespaces().collect())
+        .unwrap_or_default();
+    for ns in node.namespaces().filter(|ns| !inherited.contains(ns)) {
+        match ns.name() {
+            Some(prefix) => element.assign(format!("xmlns:{}", prefix), ns.uri()),
+            None => element.assign("xmlns", ns.uri()),
+        }
+    }
+    for attribute in node.attributes() {
+        let name = qualified_name(node, attribute.namespace(), attribute.name());
+        if id_prefix.is_empty() {
+            element.assign(name, attribute.value());
+            continue;
+        }
+        let value = attribute.value();
+        let value = match attribute.name() {
+            "id" if attribute.namespace().is_none() => format!("{}{}", id_prefix, value),
+            "href" if value.starts_with('#') => format!("#{}{}", id_prefix, &value[1..]),
+            "aria-labelledby" | "aria-describedby" => value
+                .split_whitespace()
+                .map(|id| format!("{}{}", id_prefix, id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => prefix_url_refs(value, id_prefix),
+        };
+        element.assign(name, value);
+    }
+
+    let in_text = matches!(
+        node.tag_name().name(),
+        "text" | "tspan" | "textPath" | "title" | "desc" | "style"
+    );
+    for child in node.children() {
+        if child.is_element() {
+            element.append(copy_element(child, id_prefix));
+        } else if child.is_text() && (in_text || !child.text().unwrap_or("").trim().is_empty()) {
+            let text = child.text().unwrap_or("");
+            if node.has_tag_name("style") && !id_prefix.is_empty() {
+                element.append(svg::node::Text::new(prefix_css_ids(text, id_prefix)));
+            } else {
+                element.append(svg::node::Text::new(text));
+            }
+        }
+    }
+    element
+}
+
+/// `value` with `id_prefix` added to the id in each `url(#id)` reference,
+/// quoted or not.
+fn prefix_url_refs(value: &str, id_prefix: &str) -> String {
+    ["url(#", "url('#", "url(\"#"]
+        .into_iter()
+        .fold(value.to_string(), |value, reference| {
+            value.replace(reference, &format!("{}{}", reference, id_prefix))
+        })
+}
+
+/// A stylesheet with `id_prefix` added to the ids its selectors match, as
+/// `#id` or `[id=...]`, and to its `url(#id)` references.
+///
+/// Only the text before each `{` is read as a selector, so colours such as
+/// `#fff` in declarations are left alone.
+fn prefix_css_ids(css: &str, id_prefix: &str) -> String {
+    let id_selector = Regex::new(r"#(-?[A-Za-z_])").expect("valid regex");
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(end) = rest.find(['{', '}', ';']) {
+        let (text, delimiter) = (&rest[..end], &rest[end..=end]);
+        if delimiter == "{" && !text.trim_start().starts_with('@') {
+            let selector = id_selector.replace_all(text, format!("#{}$1", id_prefix));
+            let selector = ["[id=\"", "[id='"].into_iter().fold(
+                selector.into_owned(),
+                |selector, attribute| {
+                    selector.replace(attribute, &format!("{}{}", attribute, id_prefix))
+                },
+            );
+            result.push_str(&selector);
+        } else {
+            result.push_str(&prefix_url_refs(text, id_prefix));
+        }
+        result.push_str(delimiter);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(&prefix_url_refs(rest, id_prefix));
+    result
+}
+
+/// Namespaces of editor bookkeeping (Inkscape, Sodipodi, Illustrator and RDF
+/// metadata) that viewers ignore.
+const EDITOR_NAMESPACES: [&str; 7] = [
+    "http://www.inkscape.org/namespaces/inkscape",
+    "http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
+    "http://creativecommons.org/ns#",
+    "http://purl.org/dc/elements/1.1/",
+    "http://ns.adobe.com/AdobeIllustrator/10.0/",
+    "http://ns.adobe.com/Extensibility/1.0/",
+];
+
+/// Attributes holding coordinates and lengths, whose numbers are rounded.
+const NUMERIC_ATTRIBUTES: [&str; 24] = [
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "dx",
+    "dy",
+    "width",
+    "height",
+    "d",
+    "points",
+    "transform",
+    "viewBox",
+    "stroke-width",
+    "font-size",
+    "opacity",
+    "fill-opacity",
+    "offset",
+];
+
+/// Shrink an SVG document for web embedding.
+///
+/// - Drops comments, processing instructions, `<metadata>`, and editor
+///   elements and attributes (Inkscape, Sodipodi, Illustrator)
+/// - Drops whitespace between elements and collapses it inside text
+/// - Rounds coordinates and lengths to `precision` decimal places
+/// - Merges every `<style>` element into the first, dropping repeated rules
+///   (a map embedded twice, for example) and whitespace
+///
+/// Titles, descriptions and ARIA attributes are kept.
+///
+/// # Errors
+///
+/// Returns [`ProcessorError::Xml`] if `svg` is not well-formed XML.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::processor::optimise_svg;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+///     <!-- Exported from an editor -->
+///     <rect x="10.123456" width="20"/>
+/// </svg>"#;
+/// assert_eq!(
+///     optimise_svg(svg, 2)?,
+///     r#"<svg xmlns="http://www.w3.org/2000/svg"><rect x="10.12" width="20"/></svg>"#
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn optimise_svg(svg: &str, precision: usize) -> Result<String, ProcessorError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(svg, options)?;
+    let number_re = Regex::new(r"-?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?")?;
+
+    // Every stylesheet applies to the whole document, so they can share one element
+    let mut rules: Vec<String> = Vec::new();
+    for style in doc.descendants().filter(|n| n.has_tag_name("style")) {
+        for rule in css_rules(&minify_css(style.text().unwrap_or(""))) {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+    }
+    let mut optimiser = Optimiser {
+        output: String::with_capacity(svg.len()),
+        number_re,
+        precision,
+        styles: Some(rules.concat()),
+    };
+    optimiser.element(doc.root_element());
+    Ok(optimiser.output)
+}
+
+/// State for writing an optimised copy of a parsed document.
+struct Optimiser {
+    output: String,
+    number_re: Regex,
+    precision: usize,
+    /// Merged stylesheet, taken by the first `<style>` element written
+    styles: Option<String>,
+}
+
+impl Optimiser {
+    fn element(&mut self, node: Node) {
+        if self.skipped(node) {
+            return;
+        }
+        let name = qualified_name(node, node.tag_name().namespace(), node.tag_name().name());
+        let _ = write!(self.output, "<{}", name);
+
+        // Declare only the namespaces this element adds
+        let inherited: Vec<_> = node
+            .parent_element()
+            .map(|p| p.namespaces().collect())
+            .unwrap_or_default();
+        for ns in node.namespaces() {
+            if inherited.contains(&ns) || EDITOR_NAMESPACES.contains(&ns.uri()) {
+                continue;
+            }
+            match ns.name() {
+                Some(prefix) => {
+                    let _ = write!(self.output, " xmlns:{}=\"", prefix);
+                }
+                None => self.output.push_str(" xmlns=\""),
+            }
+            escape_into(&mut self.output, ns.uri(), true);
+            self.output.push('"');
+        }
+
+        for attribute in node.attributes() {
+            if attribute
+                .namespace()
+                .is_some_and(|uri| EDITOR_NAMESPACES.contains(&uri))
+            {
+                continue;
+            }
+            let name = qualified_name(node, attribute.namespace(), attribute.name());
+            let _ = write!(self.output, " {}=\"", name);
+            if attribute.namespace().is_none() && NUMERIC_ATTRIBUTES.contains(&attribute.name()) {
+                let value = self.round_numbers(attribute.value());
+                escape_into(&mut self.output, &value, true);
+            } else {
+                escape_into(&mut self.output, attribute.value(), true);
+            }
+            self.output.push('"');
+        }
+
+        if node.has_tag_name("style") {
+            let css = std::mem::take(&mut self.styles).unwrap_or_default();
+            if css.contains('<') || css.contains('&') {
+                let _ = write!(self.output, "><![CDATA[{}]]></{}>", css, name);
+            } else {
+                let _ = write!(self.output, ">{}</{}>", css, name);
+            }
+            return;
+        }
+
+        let in_text = matches!(
+            node.tag_name().name(),
+            "text" | "tspan" | "textPath" | "title" | "desc"
+        );
+        let children: Vec<Node> = node
+            .children()
+            .filter(|c| (c.is_element() && !self.skipped(*c)) || (c.is_text() && in_text))
+            .collect();
+        if children.is_empty() {
+            self.output.push_str("/>");
+            return;
+        }
+        self.output.push('>');
+        let last = children.len() - 1;
+        for (i, child) in children.into_iter().enumerate() {
+            if child.is_element() {
+                self.element(child);
+                continue;
+            }
+            let mut text = child
+                .text()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let raw = child.text().unwrap_or("");
+            if i > 0 && raw.starts_with(char::is_whitespace) && !text.is_empty() {
+                text.insert(0, ' ');
+            }
+            if i < last && raw.ends_with(char::is_whitespace) {
+                text.push(' ');
+            }
+            escape_into(&mut self.output, &text, false);
+        }
+        let _ = write!(self.output, "</{}>", name);
+    }
+
+    /// Whether `node` is left out: metadata, editor elements, and every
+    /// `<style>` after the one holding the merged stylesheet.
+    fn skipped(&self, node: Node) -> bool {
+        is_editor_node(node)
+            || node.has_tag_name("metadata")
+            || (node.has_tag_name("style")
+                && self.styles.as_deref().is_none_or(|css| css.is_empty()))
+    }
+
+    /// Round every fractional number in `value` to the configured precision.
+    fn round_numbers(&self, value: &str) -> String {
+        let mut result = String::with_capacity(value.len());
+        let mut last = 0;
+        for number in self.number_re.find_iter(value) {
+            result.push_str(&value[last..number.start()]);
+            let text = number.as_str();
+            let rounded = match text.parse::<f64>() {
+                Ok(n) if text.contains(['.', 'e', 'E']) => {
+                    let fixed = format!("{:.*}", self.precision, n);
+                    let trimmed = if fixed.contains('.') {
+                        fixed.trim_end_matches('0').trim_end_matches('.')
+                    } else {
+                        fixed.as_str()
+                    };
+                    match trimmed {
+                        "-0" => "0".to_string(),
+                        _ if trimmed.starts_with("0.") => trimmed[1..].to_string(),
+                        _ if trimmed.starts_with("-0.") => format!("-{}", &trimmed[2..]),
+                        _ => trimmed.to_string(),
+                    }
+                }
+                _ => text.to_string(),
+            };
+            // "1.5.5" in path data is two numbers; keep them apart once rounded
+            if !rounded.contains('.') && value[number.end()..].starts_with('.') {
+                result.push_str(&rounded);
+                result.push(' ');
+            } else {
+                result.push_str(&rounded);
+            }
+            last = number.end();
+        }
+        result.push_str(&value[last..]);
+        result
+    }
+}
+
+/// Whether `node` is an element from an editor's private namespace.
+fn is_editor_node(node: Node) -> bool {
+    node.tag_name()
+        .namespace()
+        .is_some_and(|uri| EDITOR_NAMESPACES.contains(&uri))
+}
+
+/// Name of an element or attribute with the prefix its namespace has at `node`.
+fn qualified_name(node: Node, namespace: Option<&str>, name: &str) -> String {
+    let prefix = namespace.and_then(|uri| {
+        node.namespaces()
+            .find(|ns| ns.uri() == uri)
+            .and_then(|ns| ns.name())
+    });
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, name),
+        None => name.to_string(),
+    }
+}
+
+/// Append `text` with XML special characters escaped.
+fn escape_into(output: &mut String, text: &str, attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' if !attribute => output.push_str("&gt;"),
+            '"' if attribute => output.push_str("&quot;"),
+            _ => output.push(c),
+        }
+    }
+}
+
+/// Remove comments and unneeded whitespace from a stylesheet.
+fn minify_css(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+    stripped.push_str(rest);
+
+    let collapsed = stripped.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut minified = String::with_capacity(collapsed.len());
+    for c in collapsed.chars() {
+        if matches!(c, '{' | '}' | ';') && minified.ends_with(' ') {
+            minified.pop();
+        }
+        if c == ' ' && minified.ends_with(['{', '}', ';', ':', ',']) {
+            continue;
+        }
+        minified.push(c);
+    }
+    minified.replace(";}", "}")
+}
+
+/// Split a minified stylesheet into its top-level rules.
+fn css_rules(css: &str) -> Vec<String> {
+    let mut rules = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in css.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    rules.push(css[start..=i].to_string());
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn process_map_replaces_fill() {
+        let temp_dir = env::temp_dir();
+        let file = temp_dir.join("test_map.svg");
+        let content = r###"<?xml version="1.0"?>
+<svg>
+    <g id="Maths_Rooms">
+        <path fill="#000000" d="M0" />
+    </g>
+    <g id="Other">
+        <rect fill="#ffffff" />
+    </g>
+</svg>"###;
+
+        std::fs::write(&file, content).unwrap();
+
+        let highlights = vec![MapHighlight::new("Maths_Rooms", "#ff0000")];
+        let out = process_map(&file, &highlights).unwrap();
+        assert!(out.contains("fill=\"#ff0000\""));
+    }
+
+    #[test]
+    fn process_map_str_matches_data_name_without_a_file() {
+        let content = r###"<svg>
+    <g data-name="Science_Rooms"><path fill="#000000" d="M0"/></g>
+    <rect fill="#ffffff"/>
+</svg>"###;
+        let highlights = vec![MapHighlight::new("Science_Rooms", "#fad7e6")];
+
+        let out = process_map_str(content, &highlights).unwrap();
+        assert!(out.contains("<path fill=\"#fad7e6\" d=\"M0\"/>"));
+        assert!(out.contains("<rect fill=\"#ffffff\"/>"));
+        assert!(process_map_str("<svg>", &highlights).is_err());
+    }
+
+    #[test]
+    fn map_document_applies_each_highlight_set_to_the_original() {
+        let content = r###"<svg>
+    <g id="Maths_Rooms"><path fill="#000000" d="M0"/></g>
+    <g data-name="Science_Rooms"><rect fill='#000000'/></g>
+</svg>"###;
+        let map = MapDocument::parse(content).unwrap();
+        let maths = vec![MapHighlight::new("Maths_Rooms", "#fcdcd8")];
+        let science = vec![MapHighlight::new("Science_Rooms", "#fad7e6")];
+
+        let first = map.highlight(&maths);
+        let second = map.highlight(&science);
+        assert_eq!(first, process_map_str(content, &maths).unwrap());
+        assert!(first.contains("<rect fill='#000000'/>"));
+        assert!(second.contains("<path fill=\"#000000\" d=\"M0\"/>"));
+        assert!(second.contains("<rect fill=\"#fad7e6\"/>"));
+        assert_eq!(map.highlight(&[]), content);
+    }
+
+    #[test]
+    fn highlight_adds_fill_to_shapes_without_one() {
+        let content = r###"<svg xmlns:svg="http://www.w3.org/2000/svg">
+    <g id="Maths_Rooms" class="room">
+        <path d="M0"/>
+        <rect fill="#000000"/>
+        <text>MA1</text>
+        <clipPath id="clip"><rect width="5"/></clipPath>
+        <svg:circle r="2"></svg:circle>
+    </g>
+</svg>"###;
+        let highlights = vec![MapHighlight::new("Maths_Rooms", "#fcdcd8")];
+
+        let out = process_map_str(content, &highlights).unwrap();
+        assert!(out.contains("<path fill=\"#fcdcd8\" d=\"M0\"/>"));
+        assert!(out.contains("<rect fill=\"#fcdcd8\"/>"));
+        assert!(out.contains("<text>MA1</text>"));
+        assert!(out.contains("<rect width=\"5\"/>"));
+        assert!(out.contains("<svg:circle fill=\"#fcdcd8\" r=\"2\">"));
+    }
+
+    #[test]
+    fn highlight_rewrites_fill_in_style_attributes() {
+        let content = r###"<svg>
+    <g id="Maths_Rooms">
+        <path style="fill:#d0d0d0;fill-opacity:0.5;stroke:#000"/>
+        <rect style="stroke:#000; fill : #d0d0d0 !important"/>
+    </g>
+</svg>"###;
+        let highlights = vec![MapHighlight::new("Maths_Rooms", "#fcdcd8")];
+
+        let out = process_map_str(content, &highlights).unwrap();
+        assert!(out.contains(r#"<path style="fill:#fcdcd8;fill-opacity:0.5;stroke:#000"/>"#));
+        assert!(out.contains(r#"<rect style="stroke:#000; fill : #fcdcd8 !important"/>"#));
+    }
+
+    #[test]
+    fn stylesheet_mode_injects_rules_instead_of_editing_shapes() {
+        let content = r###"<svg xmlns="http://www.w3.org/2000/svg"><style>.room { fill: #d0d0d0; }</style>
+    <g data-name="Maths_Rooms"><path class="room" d="M0"/></g>
+</svg>"###;
+        let map = MapDocument::parse(content)
+            .unwrap()
+            .with_mode(HighlightMode::Stylesheet);
+
+        let out = map.highlight(&[
+            MapHighlight::new("Maths_Rooms", "#fcdcd8"),
+            MapHighlight::new("Sci\"Rooms", "#e0f0d8"),
+        ]);
+        assert!(out.contains(r#"<path class="room" d="M0"/>"#));
+        let maths = out.find("[data-name=\"Maths_Rooms\"] path").unwrap();
+        let science = out.find(r#"[id="Sci\"Rooms"] path"#).unwrap();
+        assert!(
+            science < maths,
+            "first highlight listed comes last and wins"
+        );
+        assert!(out.contains("fill: #fcdcd8 !important;"));
+        assert!(map_element(&out).is_ok());
+        assert_eq!(map.highlight(&[]), content);
+    }
+
+    #[test]
+    fn stroke_highlight_outlines_shapes_and_keeps_fills() {
+        let content = r###"<svg>
+    <g id="Maths_Rooms">
+        <rect fill="#a0c0e0" stroke="#000"/>
+        <path style="fill:#a0c0e0;stroke-width:0.5"/>
+    </g>
+</svg>"###;
+        let map = MapDocument::parse(content).unwrap();
+
+        let stroke = [MapHighlight::new("Maths_Rooms", "#e05040").style(HighlightStyle::Stroke)];
+        let out = map.highlight(&stroke);
+        assert!(out.contains(r##"<rect stroke-width="3" fill="#a0c0e0" stroke="#e05040"/>"##));
+        assert!(out.contains(r##"<path stroke="#e05040" style="fill:#a0c0e0;stroke-width:3"/>"##));
+
+        let both = [MapHighlight::new("Maths_Rooms", "#e05040").style(HighlightStyle::Both)];
+        assert!(map
+            .highlight(&both)
+            .contains(r##"<path stroke="#e05040" style="fill:#e05040;stroke-width:3"/>"##));
+    }
+
+    #[test]
+    fn highlight_borders_add_to_any_style() {
+        let content = r###"<svg><g id="Maths_Rooms"><rect fill="#a0c0e0"/></g></svg>"###;
+        let map = MapDocument::parse(content).unwrap();
+
+        let bordered = [MapHighlight::new("Maths_Rooms", "#fcdcd8")
+            .stroke("#231f20")
+            .stroke_width(4.5)];
+        assert!(map
+            .highlight(&bordered)
+            .contains(r##"<rect stroke="#231f20" stroke-width="4.5" fill="#fcdcd8"/>"##));
+
+        let wider = [MapHighlight::new("Maths_Rooms", "#e05040")
+            .style(HighlightStyle::Stroke)
+            .stroke_width(6.0)];
+        assert!(map
+            .highlight(&wider)
+            .contains(r##"<rect stroke="#e05040" stroke-width="6" fill="#a0c0e0"/>"##));
+    }
+
+    #[test]
+    fn highlight_equality_agrees_with_hash() {
+        let hash = |highlight: &MapHighlight| {
+            let mut hasher = DefaultHasher::new();
+            highlight.hash(&mut hasher);
+            hasher.finish()
+        };
+        let width = |width: f64| MapHighlight::new("Maths_Rooms", "#fcdcd8").stroke_width(width);
+
+        assert_eq!(width(2.0), width(2.0));
+        assert_eq!(hash(&width(2.0)), hash(&width(2.0)));
+        assert_eq!(width(f64::NAN), width(f64::NAN));
+        assert_ne!(width(0.0), width(-0.0));
+    }
+
+    #[test]
+    fn overlay_and_hatch_highlights() {
+        let content = r###"<svg>
+    <g id="Maths_Rooms"><rect fill="#d0d0d0"/></g>
+    <g id="Hall"><rect fill="#d0d0d0"/></g>
+</svg>"###;
+        let map = MapDocument::parse(content).unwrap();
+
+        let out = map.highlight(&[
+            MapHighlight::new("Maths_Rooms", "#fcdcd8").style(HighlightStyle::Overlay),
+            MapHighlight::new("Hall", "#fad7e6").style(HighlightStyle::Hatch),
+        ]);
+        assert!(out.contains(r##"<rect fill-opacity="0.5" fill="#fcdcd8"/>"##));
+        assert!(out.contains(r##"<rect fill="url(#map-hatch-1)"/>"##));
+        assert!(out.starts_with(r#"<svg><defs><pattern id="map-hatch-1""#));
+        assert!(out.contains(r##"rotate(-45)"><rect width="8" height="8" fill="#fad7e6"/>"##));
+        assert!(map_element(&out).is_ok());
+    }
+
+    #[test]
+    fn dimming_fades_everything_outside_the_highlights() {
+        let content = r###"<svg>
+    <defs><rect id="tile" width="5"/></defs>
+    <g id="Maths_Rooms"><rect fill="#d0d0d0"/><text>MA1</text></g>
+    <rect id="Hall" opacity="0.5"/>
+    <text style="opacity:1">Car park</text>
+</svg>"###;
+        let map = MapDocument::parse(content).unwrap().with_dimming(0.4);
+
+        let out = map.highlight(&[MapHighlight::new("Maths_Rooms", "#fcdcd8")]);
+        assert!(out.contains(r##"<g id="Maths_Rooms"><rect fill="#fcdcd8"/><text>MA1</text></g>"##));
+        assert!(out.contains(r#"<rect id="Hall" opacity="0.2"/>"#));
+        assert!(out.contains(r#"<text style="opacity:0.4">Car park</text>"#));
+        assert!(out.contains(r#"<rect id="tile" width="5"/>"#));
+
+        let css = map
+            .with_mode(HighlightMode::Stylesheet)
+            .highlight(&[MapHighlight::new("Maths_Rooms", "#fcdcd8")]);
+        assert!(css.contains("use { opacity: 0.4 !important; }"));
+        assert!(css.contains("[id=\"Maths_Rooms\"] text, "));
+    }
+
+    #[test]
+    fn highlight_checked_fails_or_warns_on_unknown_ids() {
+        let map = MapDocument::parse(r#"<svg><g id="Maths_Rooms"><rect/></g></svg>"#).unwrap();
+        let highlights = [
+            MapHighlight::new("Maths_Rooms", "#fcdcd8"),
+            MapHighlight::new("Sciense_Rooms", "#fad7e6"),
+            MapHighlight::new("Sciense_Rooms", "#d4e8d4"),
+        ];
+
+        let lenient = map
+            .highlight_checked(&highlights, Strictness::Lenient)
+            .unwrap();
+        assert_eq!(lenient.svg, map.highlight(&highlights));
+        assert_eq!(
+            lenient.warnings,
+            [r#"Highlight "Sciense_Rooms" matches nothing in the map"#]
+        );
+
+        assert!(matches!(
+            map.highlight_checked(&highlights, Strictness::Strict),
+            Err(ProcessorError::HighlightNotFound(id)) if id == "Sciense_Rooms"
+        ));
+        assert!(map
+            .highlight_checked(&highlights[..1], Strictness::Strict)
+            .unwrap()
+            .warnings
+            .is_empty());
+    }
+
+    #[test]
+    fn contains_finds_ids_and_data_names() {
+        let map = MapDocument::parse(
+            r#"<svg><g id="Maths_Rooms"><rect id="MA3"/><rect data-name="MA4"/></g></svg>"#,
+        )
+        .unwrap();
+        assert!(map.contains("Maths_Rooms"));
+        assert!(map.contains("MA3"));
+        assert!(map.contains("MA4"));
+        assert!(!map.contains("SC8"));
+    }
+
+    #[test]
+    fn labels_are_centred_on_their_elements() {
+        let content = r###"<svg>
+    <g id="MA3" transform="translate(100,50)"><rect width="60" height="40"/></g>
+    <path id="SC8" d="M0 0h40v20h-40z"/>
+</svg>"###;
+        let map = MapDocument::parse(content).unwrap();
+
+        let out = map.highlight(&[
+            MapHighlight::new("MA3", "#fcdcd8").label("MA3\nMon L1"),
+            MapHighlight::new("SC8", "#fad7e6").label("SC8"),
+        ]);
+        assert!(out.contains(r#"<text x="130" y="61.6" font-weight="bold">MA3</text>"#));
+        assert!(out.contains(r#"<text x="130" y="78.4">Mon L1</text>"#));
+        assert!(out.contains(r#"<text x="20" y="10" font-weight="bold">SC8</text></g></svg>"#));
+        assert!(map_element(&out).is_ok());
+    }
+
+    #[cfg(feature = "geometry")]
+    #[test]
+    fn measured_geometry_follows_nested_viewports_and_curves() {
+        let content = r#"<svg xmlns="http://www.w3.org/2000/svg">
+    <svg x="100" width="50" height="50" viewBox="0 0 10 10"><rect id="Gym" width="10" height="10"/></svg>
+    <path id="Hall" data-name="Main Hall" d="M0 0C0 100 100 100 100 0z"/>
+</svg>"#;
+        let map = MapDocument::parse(content).unwrap();
+
+        assert_eq!(map.centre("Gym"), Some((125.0, 25.0)));
+        assert_eq!(map.centre("Hall"), Some((50.0, 37.5)));
+        assert_eq!(map.centre("Main Hall"), Some((50.0, 37.5)));
+    }
+
+    #[test]
+    fn routes_join_stops_in_order() {
+        let content = r#"<svg>
+    <rect id="MA3" x="0" y="0" width="40" height="20"/>
+    <rect id="SC8" x="100" y="50" width="20" height="20"/>
+</svg>"#;
+        let map = MapDocument::parse(content).unwrap();
+        assert_eq!(map.centre("SC8"), Some((110.0, 60.0)));
+        assert_eq!(map.centre("XX1"), None);
+
+        let route = MapRoute::new(vec![
+            map.centre("MA3").unwrap(),
+            map.centre("SC8").unwrap(),
+            (5.5, 80.0),
+        ]);
+        let out = map.highlight_with_routes(&[], &[route]);
+        assert!(out.contains(r#"<polyline points="20,10 110,60 5.5,80" fill="none""#));
+        assert!(out.contains(r#"<circle cx="110" cy="60" r="7""#));
+        assert!(out.contains(r##"fill="#ffffff">3</text></g></svg>"##));
+        assert!(map_element(&out).is_ok());
+    }
+
+    #[test]
+    fn marker_routes_omit_the_line_and_list_stops_in_a_key() {
+        let content = r#"<svg>
+    <rect id="MA3" x="10" y="20" width="40" height="20"/>
+    <rect id="SC8" x="100" y="50" width="20" height="20"/>
+</svg>"#;
+        let map = MapDocument::parse(content).unwrap();
+
+        let route = MapRoute::new(vec![(30.0, 30.0), (110.0, 60.0)])
+            .line(false)
+            .key(vec!["L1, L2 → MA3".into(), "L3 → SC8 & co".into()]);
+        let out = map.highlight_with_routes(&[], &[route]);
+        assert!(!out.contains("<polyline"));
+        assert!(out.contains(r#"<circle cx="110" cy="60" r="7""#));
+        assert!(out.contains(r#"<rect x="18" y="28" width="108" height="42" rx="3""#));
+        assert!(out.contains(r#"<circle cx="30" cy="40" r="6""#));
+        assert!(out.contains(
+            r#"<text x="42" y="58" dominant-baseline="central">L3 → SC8 &amp; co</text>"#
+        ));
+        assert!(map_element(&out).is_ok());
+    }
+
+    #[test]
+    fn crop_zooms_the_view_box_to_the_highlights() {
+        let content = r#"<svg width="800" height="600">
+    <rect id="MA3" x="100" y="100" width="40" height="20"/>
+    <rect id="SC8" x="160" y="100" width="40" height="40"/>
+    <rect id="Hall" x="600" y="500" width="100" height="50"/>
+</svg>"#;
+        let map = MapDocument::parse(content).unwrap().with_crop(CROP_PADDING);
+
+        let out = map.highlight(&[
+            MapHighlight::new("MA3", "#fcdcd8"),
+            MapHighlight::new("SC8", "#fad7e6"),
+        ]);
+        assert!(out.starts_with(r#"<svg viewBox="90 90 120 60" width="800""#));
+        // Nothing highlighted leaves the whole map in view
+        assert_eq!(map.highlight(&[]), content);
+
+        let map = MapDocument::parse(content.replace("<svg ", r#"<svg viewBox="0 0 800 600" "#))
+            .unwrap()
+            .with_mode(HighlightMode::Stylesheet)
+            .with_crop(0.0);
+        let out = map.highlight(&[MapHighlight::new("Hall", "#fad7e6")]);
+        assert!(out.starts_with(r#"<svg viewBox="600 500 100 50" width="800""#));
+        assert!(map_element(&out).is_ok());
+    }
+
+    #[test]
+    fn legend_lists_each_named_highlight_once() {
+        let content = r#"<svg>
+    <rect id="MA3" x="0" y="0" width="200" height="100"/>
+    <rect id="MA4" x="0" y="100" width="200" height="100"/>
+    <rect id="Hall" x="200" y="0" width="100" height="100"/>
+</svg>"#;
+        let map = MapDocument::parse(content).unwrap().with_legend();
+
+        let out = map.highlight(&[
+            MapHighlight::new("MA3", "#fcdcd8").name("Maths"),
+            MapHighlight::new("MA4", "#fcdcd8").name("Maths"),
+            MapHighlight::new("Hall", "#fad7e6")
+                .style(HighlightStyle::Stroke)
+                .name("Drama & Dance"),
+        ]);
+        assert_eq!(out.matches("Maths</text>").count(), 1);
+        assert!(out.contains(r#"<rect x="8" y="150" width="108" height="42" rx="3""#));
+        assert!(out.contains(r##"<rect x="14" y="157" width="14" height="10" fill="#fcdcd8" stroke="#231f20" stroke-width="0.5"/>"##));
+        assert!(out.contains(r##"height="10" fill="#ffffff" stroke="#fad7e6" stroke-width="3"/><text x="34" y="180" dominant-baseline="central">Drama &amp; Dance</text></g></svg>"##));
+        assert!(map_element(&out).is_ok());
+
+        // Unnamed highlights leave the map without a legend
+        let out = map.highlight(&[MapHighlight::new("MA3", "#fcdcd8")]);
+        assert!(!out.contains("map-legend"));
+    }
+
+    #[test]
+    fn highlights_reach_shapes_drawn_through_use() {
+        let content = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+    <defs><rect id="room" width="10" height="10" fill="#cccccc"/><symbol id="desk"><path d="M0 0h2v2z" style="fill:#999999"/></symbol></defs>
+    <g id="Maths_Rooms"><use href="#room" x="5" class="r"/><use xlink:href="#desk" transform="scale(2)"/></g>
+    <use id="Hall" href="#room" x="50"/>
+</svg>"##;
+        let map = MapDocument::parse(content).unwrap();
+
+        let out = map.highlight(&[MapHighlight::new("Maths_Rooms", "#fcdcd8")]);
+        assert!(out.contains(concat!(
+            r##"<g id="Maths_Rooms"><g class="r" transform="translate(5,0)"><rect width="10" height="10" fill="#fcdcd8"/></g>"##,
+            r##"<g transform="scale(2)"><path d="M0 0h2v2z" style="fill:#fcdcd8"/></g></g>"##
+        )));
+        // The shared shapes and other references to them are untouched
+        assert!(out.contains(r##"<rect id="room" width="10" height="10" fill="#cccccc"/>"##));
+        assert!(out.contains(r##"<use id="Hall" href="#room" x="50"/>"##));
+        assert!(map_element(&out).is_ok());
+
+        let map = MapDocument::parse(content)
+            .unwrap()
+            .with_mode(HighlightMode::Stylesheet);
+        let out = map.highlight(&[MapHighlight::new("Hall", "#fad7e6")]);
+        assert!(out.contains(r#"<g id="Hall" transform="translate(50,0)"><rect width="10""#));
+    }
+
+    /// Assert `output` is well-formed and declares every namespace `input` does.
+    fn assert_namespaces_preserved(input: &str, output: &str) {
+        let parse = |svg| {
+            let options = ParsingOptions {
+                allow_dtd: true,
+                ..ParsingOptions::default()
+            };
+            Document::parse_with_options(svg, options)
+        };
+        let declared = |doc: &Document| {
+            let mut namespaces: Vec<(Option<String>, String)> = doc
+                .descendants()
+                .filter(|n| n.is_element())
+                .flat_map(|n| n.namespaces())
+                .map(|ns| (ns.name().map(String::from), ns.uri().to_string()))
+                .collect();
+            namespaces.sort();
+            namespaces.dedup();
+            namespaces
+        };
+        let input = parse(input).unwrap();
+        let output = parse(output).expect("output is well-formed");
+        assert_eq!(
+            input.root_element().namespaces().collect::<Vec<_>>(),
+            output.root_element().namespaces().collect::<Vec<_>>()
+        );
+        let kept = declared(&output);
+        for namespace in declared(&input) {
+            assert!(kept.contains(&namespace), "{:?} was lost", namespace);
+        }
+    }
+
+    #[test]
+    fn output_keeps_namespaces_and_stays_well_formed() {
+        let content = r##"<?xml version="1.0"?>
+<!DOCTYPE svg [
+    <!ENTITY ns_svg "http://www.w3.org/2000/svg">
+    <!ENTITY ns_xlink "http://www.w3.org/1999/xlink">
+]>
+<svg xmlns="&ns_svg;" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" viewBox="0 0 100 100">
+    <defs xmlns:xlink="&ns_xlink;"><g id="room"><image xlink:href="tile.png" width="5" height="5"/><rect width="10" height="10" fill="#cccccc"/></g></defs>
+    <g id="Maths_Rooms" inkscape:label="Maths"><use xmlns:xlink="&ns_xlink;" xlink:href="#room" xlink:title="MA1" x="20"/></g>
+    <rect id="Hall" x="50" y="50" width="20" height="20"/>
+</svg>"##;
+        let highlights = [
+            MapHighlight::new("Maths_Rooms", "#fcdcd8")
+                .label("MA1 & <2>")
+                .name("Maths & Stats"),
+            MapHighlight::new("Hall", r#"#fad7e6" onload="x"#)
+                .style(HighlightStyle::Hatch)
+                .name("Hall"),
+        ];
+        let routes = [MapRoute::new(vec![(25.0, 5.0), (60.0, 60.0)])
+            .color("a&b")
+            .key(vec!["L1 → <Hall>".into()])];
+
+        for mode in [HighlightMode::Attributes, HighlightMode::Stylesheet] {
+            let map = MapDocument::parse(content)
+                .unwrap()
+                .with_mode(mode)
+                .with_dimming(DIM_OPACITY)
+                .with_crop(CROP_PADDING)
+                .with_legend();
+            let out = map.highlight_with_routes(&highlights, &routes);
+            assert_namespaces_preserved(content, &out);
+            assert!(out.contains(r#"<g xlink:title="MA1" xmlns:xlink="http://www.w3.org/1999/xlink" transform="translate(20,0)">"#));
+            assert!(map_element(&out).is_ok());
+        }
+    }
+
+    #[test]
+    fn list_map_ids_reports_ids_and_data_names_in_document_order() {
+        let map = r#"<!DOCTYPE svg [<!ENTITY ns "http://www.w3.org/2000/svg">]>
+<svg xmlns="&ns;" id="map">
+    <g id="Science_Rooms" data-name="Science"><path id="SC1"/><path/></g>
+    <rect data-name="Hall"/>
+</svg>"#;
+        let element = |id: Option<&str>, data_name: Option<&str>, element: &str| MapElement {
+            id: id.map(str::to_string),
+            data_name: data_name.map(str::to_string),
+            element: element.to_string(),
+        };
+        assert_eq!(
+            list_map_ids_str(map).unwrap(),
+            vec![
+                element(Some("map"), None, "svg"),
+                element(Some("Science_Rooms"), Some("Science"), "g"),
+                element(Some("SC1"), None, "path"),
+                element(None, Some("Hall"), "rect"),
+            ]
+        );
+    }
+
+    #[test]
+    fn shapes_shared_between_highlights_are_split_between_their_colours() {
+        let content = r##"<svg>
+    <g id="Sports"><rect id="Gym"/><g id="Hall"><rect fill="#d0d0d0"/></g></g>
+    <g id="Studio"><rect/></g>
+</svg>"##;
+        let highlights = [
+            MapHighlight::new("Sports", "#b8dfc8"),
+            MapHighlight::new("Hall", "#fad7e6"),
+            MapHighlight::new("Studio", "#fad7e6"),
+            MapHighlight::new("Studio", "#c6d9f1").style(HighlightStyle::Overlay),
+            MapHighlight::new("Studio", "#000000").style(HighlightStyle::Stroke),
+        ];
+
+        let out = process_map_str(content, &highlights).unwrap();
+        assert!(out.contains(r##"<rect fill="#b8dfc8" id="Gym"/>"##));
+        assert!(out.contains(r##"<g id="Hall"><rect fill="url(#map-split-0)"/></g>"##));
+        assert!(out.contains(r##"<g id="Studio"><rect fill="url(#map-split-1)"/></g>"##));
+        assert!(out.contains(concat!(
+            r##"<pattern id="map-split-0" patternUnits="userSpaceOnUse" width="12" height="12" "##,
+            r##"patternTransform="rotate(45)"><rect x="0" width="6" height="12" fill="#b8dfc8"/>"##,
+            r##"<rect x="6" width="6" height="12" fill="#fad7e6"/></pattern>"##
+        )));
+
+        let map = MapDocument::parse(content)
+            .unwrap()
+            .with_shared_fill(SharedFill::Gradient);
+        let out = map.highlight(&highlights[..2]);
+        assert!(out.contains(concat!(
+            r##"<linearGradient id="map-split-0"><stop offset="0" stop-color="#b8dfc8"/>"##,
+            r##"<stop offset="1" stop-color="#fad7e6"/></linearGradient>"##
+        )));
+        assert!(map_element(&out).is_ok());
+
+        let out = map
+            .with_mode(HighlightMode::Stylesheet)
+            .highlight(&highlights[..2]);
+        let sports = out.find(r#"[id="Sports"] rect"#).unwrap();
+        let hall = out.rfind(r#"[id="Hall"] rect"#).unwrap();
+        assert!(
+            sports < hall,
+            "shared group comes after the group around it"
+        );
+        assert!(out.contains("fill: url(#map-split-0) !important;"));
+        assert!(out.contains("<linearGradient id=\"map-split-0\">"));
+    }
+
+    #[test]
+    fn raster_maps_embed_the_image_under_highlightable_areas() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(400u32.to_be_bytes());
+        png.extend(300u32.to_be_bytes());
+        let areas = [
+            MapArea::rect("Maths_Rooms", 10.0, 20.0, 100.0, 50.5),
+            MapArea::polygon("Hall", vec![[200.0, 0.0], [300.0, 0.0], [250.0, 80.0]]),
+        ];
+
+        let svg = raster_map(&png, &areas).unwrap();
+        assert!(svg.contains(r#"viewBox="0 0 400 300""#));
+        assert!(svg.contains(
+            r#"<image width="400" height="300" xlink:href="data:image/png;base64,iVBORw0KGgo"#
+        ));
+        let map = MapDocument::parse(svg).unwrap();
+        let out = map.highlight(&[MapHighlight::new("Maths_Rooms", "#fcdcd8")]);
+        assert!(out.contains(
+            r##"<rect id="Maths_Rooms" x="10" y="20" width="100" height="50.5" fill="#fcdcd8"/>"##
+        ));
+        assert!(out.contains(r#"<polygon id="Hall" points="200,0 300,0 250,80" fill="none"/>"#));
+        assert_eq!(map.centre("Hall"), Some((250.0, 40.0)));
+
+        // JFIF header, then a baseline frame 120 wide and 90 high
+        let jpeg = [
+            &[0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x4a, 0x46][..],
+            &[0xff, 0xc0, 0x00, 0x11, 0x08, 0x00, 0x5a, 0x00, 0x78],
+        ]
+        .concat();
+        assert!(raster_map(&jpeg, &[])
+            .unwrap()
+            .contains(r#"viewBox="0 0 120 90""#));
+
+        assert!(matches!(
+            raster_map(b"GIF89a", &areas),
+            Err(ProcessorError::UnsupportedImage)
+        ));
+        assert!(matches!(
+            raster_map(&png[..20], &areas),
+            Err(ProcessorError::UnsupportedImage)
+        ));
+    }
+
+    #[test]
+    fn map_cache_reuses_maps_with_the_same_highlight_set() {
+        let map = MapDocument::parse(
+            r#"<svg><g id="Maths_Rooms"><rect/></g><g id="Science_Rooms"><rect/></g></svg>"#,
+        )
+        .unwrap();
+        let maths = MapHighlight::new("Maths_Rooms", "#fcdcd8");
+        let mut cache = MapCache::new();
+        assert!(cache.is_empty());
+
+        let just_maths = vec![maths.clone()];
+        cache.warm(&map, [just_maths.clone()]);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.highlight(&map, &just_maths),
+            map.highlight(&just_maths)
+        );
+        assert_eq!(cache.len(), 1);
+
+        // Order decides which highlight wins, so each order is its own entry
+        let overlapping = MapHighlight::new("Maths_Rooms", "#fad7e6");
+        let first = vec![maths.clone(), overlapping.clone()];
+        let second = vec![overlapping, maths];
+        assert_eq!(cache.highlight(&map, &first), map.highlight(&first));
+        assert_eq!(cache.highlight(&map, &second), map.highlight(&second));
+        assert_ne!(map.highlight(&first), map.highlight(&second));
+        assert_eq!(cache.len(), 3);
+
+        // An identical map parsed separately shares entries; other options do not
+        let reparsed = MapDocument::parse(map.content.to_string()).unwrap();
+        cache.highlight(&reparsed, &just_maths);
+        assert_eq!(cache.len(), 3);
+        let dimmed = map.clone().with_dimming(0.3);
+        assert_ne!(
+            cache.highlight(&dimmed, &just_maths),
+            map.highlight(&just_maths)
+        );
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn heatmap_darkens_with_visits() {
+        let highlights = [
+            MapHighlight::new("Maths_Rooms", "#fcdcd8")
+                .style(HighlightStyle::Hatch)
+                .name("Maths"),
+            MapHighlight::new("Science_Rooms", "#fad7e6"),
+            MapHighlight::new("Hall", "#fad7e6"),
+        ];
+        let heat = heatmap(&highlights, &[4, 2, 0], "#b2182b");
+        let colors: Vec<&str> = heat.iter().map(|h| h.color.as_str()).collect();
+        assert_eq!(colors, ["#b2182b", "#d37a85", "#f3dcdf"]);
+        assert_eq!(heat[0].style, HighlightStyle::Fill);
+        assert_eq!(heat[0].name.as_deref(), Some("Maths"));
+
+        assert_eq!(heatmap(&highlights[..1], &[0], "red")[0].color, "red");
+    }
+
+    #[test]
+    fn muted_highlights_are_drawn_faintly() {
+        let content = r#"<svg><g id="Maths_Rooms"><rect/></g><g id="Hall"><rect/></g><g id="Lab"><rect/></g></svg>"#;
+        let out = process_map_str(
+            content,
+            &[
+                MapHighlight::new("Maths_Rooms", "#fcdcd8").muted(),
+                MapHighlight::new("Hall", "#fad7e6")
+                    .style(HighlightStyle::Overlay)
+                    .muted(),
+                MapHighlight::new("Lab", "#c6d9f1").style(HighlightStyle::Both),
+            ],
+        )
+        .unwrap();
+        assert!(out.contains(r##"<g id="Maths_Rooms"><rect fill="#fcdcd8" fill-opacity="0.4"/>"##));
+        assert!(out.contains(r##"<g id="Hall"><rect fill="#fad7e6" fill-opacity="0.2"/>"##));
+        assert!(out
+            .contains(r##"<g id="Lab"><rect fill="#c6d9f1" stroke="#c6d9f1" stroke-width="3"/>"##));
+
+        let both = MapHighlight::new("Lab", "#c6d9f1")
+            .style(HighlightStyle::Both)
+            .muted();
+        assert_eq!(
+            both.paint(0),
+            [
+                ("fill", "#c6d9f1".to_string()),
+                ("stroke", "#c6d9f1".to_string()),
+                ("stroke-width", "3".to_string()),
+                ("fill-opacity", "0.4".to_string()),
+                ("stroke-opacity", "0.4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_highlights_get_a_title_tooltip() {
+        let content = r##"<svg>
+    <defs><rect id="room" width="10" height="10"/></defs>
+    <g id="Maths_Rooms"><rect/></g>
+    <rect id="Hall"/>
+    <g id="Empty"></g>
+    <use id="Studio" href="#room"/>
+</svg>"##;
+        let highlights = [
+            MapHighlight::new("Maths_Rooms", "#fcdcd8").name("Maths & Stats"),
+            MapHighlight::new("Hall", "#fad7e6").name("PE"),
+            MapHighlight::new("Hall", "#c6d9f1").name("Drama"),
+            MapHighlight::new("Empty", "#fad7e6").name("Empty"),
+            MapHighlight::new("Studio", "#fad7e6").name("Drama"),
+            MapHighlight::new("room", "#fad7e6"),
+        ];
+        for mode in [HighlightMode::Attributes, HighlightMode::Stylesheet] {
+            let out = MapDocument::parse(content)
+                .unwrap()
+                .with_mode(mode)
+                .highlight(&highlights);
+            assert!(out.contains(r#"<g id="Maths_Rooms"><title>Maths &amp; Stats</title><rect"#));
+            assert!(out.contains("><title>PE</title></rect>"));
+            assert!(!out.contains("<title>Drama</title></rect>"));
+            assert!(out.contains(r#"<g id="Empty"><title>Empty</title></g>"#));
+            assert!(out.contains("><title>Drama</title><rect"));
+            assert_eq!(out.matches("<title>").count(), 4);
+            assert!(map_element(&out).is_ok());
+        }
+    }
+
+    #[test]
+    fn unmapped_groups_lists_outermost_named_groups_without_a_mapping() {
+        let map = MapDocument::parse(
+            r#"<svg id="map">
+    <g id="Layer_1">
+        <g id="Maths_Rooms"><rect id="MA1"/><g id="MA2"><rect/></g></g>
+        <g id="Drama_Rooms" data-name="Drama"><g id="DR1"><rect/></g></g>
+        <g id="Art_Rooms"><rect/></g>
+        <g id="Labels"><text>Hall</text></g>
+    </g>
+    <defs><g id="Tree"><circle r="2"/></g></defs>
+</svg>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            map.unmapped_groups(&["Maths_Rooms", "Typo_Rooms"]),
+            vec!["Art_Rooms", "Drama"]
+        );
+        assert!(map
+            .unmapped_groups(&["Maths_Rooms", "Drama_Rooms", "Art_Rooms"])
+            .is_empty());
+    }
+
+    #[test]
+    fn sanitise_map_removes_scripts_handlers_and_external_references() {
+        let map = r##"<?xml-stylesheet href="https://example.com/map.css"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" onload="steal()">
+    <style>@import url(https://example.com/fonts.css); .room { fill: url( 'https://example.com/tile.png' ) }</style>
+    <script><![CDATA[steal()]]></script>
+    <foreignObject><div xmlns="http://www.w3.org/1999/xhtml" onclick="steal()"/></foreignObject>
+    <a xlink:href="javascript:steal()"><rect id="MA3" ONMOUSEOVER="steal()" fill="url(#tiles)"/></a>
+    <a href="https://example.com"><set attributeName="href" to="javascript:steal()"/></a>
+    <image href="data:image/png;base64,AAAA"/>
+    <image href="data:image/svg+xml;base64,AAAA"/>
+    <use href="#MA3" style="filter: url(&quot;https://example.com/f.svg#x&quot;)"/>
+</svg>"##;
+        let output = sanitise_map(map).unwrap();
+
+        for unsafe_text in [
+            "steal",
+            "example.com",
+            "script",
+            "foreignObject",
+            "svg+xml",
+            "@import",
+            "<set",
+        ] {
+            assert!(
+                !output.contains(unsafe_text),
+                "{unsafe_text} kept in {output}"
+            );
+        }
+        assert!(output.starts_with("\n<svg xmlns="));
+        assert!(output.contains(r##"<rect id="MA3" fill="url(#tiles)"/>"##));
+        assert!(output.contains(r##"<image href="data:image/png;base64,AAAA"/>"##));
+        assert!(output.contains(r##"<use href="#MA3" style="filter: none"/>"##));
+        assert!(output.contains(".room { fill: none }"));
+        assert!(MapDocument::parse(output).is_ok());
+    }
+
+    #[test]
+    fn optimise_svg_strips_metadata_rounds_and_merges_styles() {
+        let svg = r###"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg"
+     xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+     viewBox="0 0 100.000001 50">
+    <metadata><title>Exported</title></metadata>
+    <style>
+        .a { fill: red; }
+    </style>
+    <g inkscape:label="Layer 1" id="layer">
+        <path d="M1.2345.5L-0.0001 2" class="a"/>
+        <text x="3.14159">
+            Maths  &amp; Stats
+        </text>
+    </g>
+    <svg><style>.a { fill: red; } .b { stroke: #000; }</style></svg>
+</svg>"###;
+
+        let out = optimise_svg(svg, 2).unwrap();
+        assert_eq!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 100 50\">\
+             <style>.a{fill:red}.b{stroke:#000}</style>\
+             <g id=\"layer\"><path d=\"M1.23.5L0 2\" class=\"a\"/>\
+             <text x=\"3.14\">Maths &amp; Stats</text></g><svg/></svg>"
+        );
+    }
+
+    #[test]
+    fn map_element_keeps_quotes_in_attribute_values() {
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" font-family='"Open Sans", sans-serif'><text aria-label='Say "hi" &amp; wave'>Hi</text></svg>"#;
+        let element = map_element(map).expect("well-formed map").to_string();
+
+        assert!(element.contains(r#"font-family="&quot;Open Sans&quot;, sans-serif""#));
+        assert!(element.contains(r#"aria-label="Say &quot;hi&quot; &amp; wave""#));
+        let reparsed = Document::parse(&element).expect("copy stays well-formed");
+        assert_eq!(
+            reparsed.root_element().attribute("font-family"),
+            Some(r#""Open Sans", sans-serif"#)
+        );
+    }
+
+    #[test]
+    fn id_prefix_reaches_every_reference() {
+        let map = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" aria-labelledby="t"><title id="t">North</title><style>#Hall, [id="Gym"] rect { fill: #fff; stroke: url(#lines) } @media print { .a { fill: url('#lines') } }</style><pattern id="lines"/><g id="Hall"><rect fill="url(#lines)"/></g><use xlink:href="#Hall"/><use href="http://example.com/#x"/></svg>"##;
+        let element = map_element_with_id_prefix(map, "site-2-")
+            .expect("well-formed map")
+            .to_string();
+
+        assert!(element.contains(r#"<svg aria-labelledby="site-2-t""#));
+        assert!(element.contains(r#"<title id="site-2-t">"#));
+        assert!(element.contains(
+            r#"<style>#site-2-Hall, [id="site-2-Gym"] rect { fill: #fff; stroke: url(#site-2-lines) }"#
+        ));
+        assert!(element.contains("@media print { .a { fill: url('#site-2-lines') } }"));
+        assert!(element.contains("<g id=\"site-2-Hall\">\n<rect fill=\"url(#site-2-lines)\"/>"));
+        assert!(element.contains(r##"xlink:href="#site-2-Hall""##));
+        assert!(element.contains(r##"href="http://example.com/#x""##));
+        assert_eq!(
+            map_element(map)
+                .unwrap()
+                .to_string()
+                .matches("site-")
+                .count(),
+            0
+        );
     }
 }
@@ -3,12 +3,30 @@
 //! This module generates A4-sized SVG documents containing a formatted weekly
 //! timetable grid with color-coded cells and an embedded school map.
 
-use crate::config::Config;
+use crate::config::{
+    Config, FontFace, LabelPosition, LabelRotation, LogoPosition, Mapping, Strings, Theme,
+};
+use crate::icons::{icon_ref, IconError, IconSet};
+use crate::layout::{
+    gap_height, label_font_size, lesson_cell, Badge, CellFonts, CellOverflow, LessonCell,
+    OverflowKind, Rect, SheetLayout, BADGE_FONT_SIZE, PERIOD_LABELS,
+};
 use crate::parser::Week;
-use std::fs;
+use crate::processor::{
+    map_element, map_element_with_id_prefix, optimise_svg, CopiedElement, ProcessorError,
+};
+use base64::Engine;
+use chrono::{Datelike, Weekday};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
 use std::path::Path;
-use svg::node::element::{Group, Rectangle, Text};
+use svg::node::element::path::Data;
+use svg::node::element::{Definitions, Description, Group, Image, Rectangle, Text, Title, Use};
 use svg::Document;
+use svg::Node;
 use thiserror::Error;
 
 /// Errors that can occur during SVG rendering.
@@ -17,11 +35,530 @@ pub enum RenderError {
     /// SVG file writing error
     #[error("SVG generation error: {0}")]
     Svg(#[from] std::io::Error),
+    /// Image file with an extension that cannot be embedded
+    #[error("Unsupported image format: {0}")]
+    UnsupportedImage(String),
+    /// Font file with an extension that cannot be embedded
+    #[error("Unsupported font format: {0}")]
+    UnsupportedFont(String),
+    /// Generated SVG could not be converted to PDF
+    #[cfg(feature = "pdf")]
+    #[error("PDF conversion error: {0}")]
+    Pdf(String),
+    /// Generated SVG could not be rasterised to PNG
+    #[cfg(feature = "png")]
+    #[error("PNG rasterisation error: {0}")]
+    Png(String),
+    /// Map could not be embedded, or finished SVG could not be optimised
+    #[error("SVG processing error: {0}")]
+    Map(#[from] ProcessorError),
+    /// The `icon_set` file could not be loaded
+    #[error("Icon error: {0}")]
+    Icon(#[from] IconError),
+    /// User-supplied template failed to parse or render
+    #[cfg(feature = "templates")]
+    #[error("Template error: {0}")]
+    Template(String),
+}
+
+/// Decimal places kept by [`RenderOptions::optimise`].
+pub const OPTIMISE_PRECISION: usize = 2;
+
+/// Pixels per millimetre at the 96 DPI used for SVG user units.
+const PX_PER_MM: f64 = 96.0 / 25.4;
+
+/// Day keys used in element ids, whatever the language of the sheet.
+const DAY_IDS: [&str; 5] = ["mon", "tue", "wed", "thu", "fri"];
+
+/// Black hatch patterns used for departments by mono themes, as
+/// (tile size in px, SVG path, whether the path is filled, CSS background).
+pub(crate) const HATCHES: [(i32, &str, bool, &str); 8] = [
+    (
+        8,
+        "M-2 2L2-2M0 8L8 0M6 10L10 6",
+        false,
+        "repeating-linear-gradient(45deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        8,
+        "M-2 6L2 10M0 0L8 8M6-2L10 2",
+        false,
+        "repeating-linear-gradient(-45deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        8,
+        "M0 4H8",
+        false,
+        "repeating-linear-gradient(0deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        8,
+        "M4 0V8",
+        false,
+        "repeating-linear-gradient(90deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        8,
+        "M0 4H8M4 0V8",
+        false,
+        "repeating-linear-gradient(0deg, #000 0 1px, transparent 1px 6px), repeating-linear-gradient(90deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        8,
+        "M-2 2L2-2M0 8L8 0M6 10L10 6M-2 6L2 10M0 0L8 8M6-2L10 2",
+        false,
+        "repeating-linear-gradient(45deg, #000 0 1px, transparent 1px 6px), repeating-linear-gradient(-45deg, #000 0 1px, transparent 1px 6px)",
+    ),
+    (
+        6,
+        "M2 3a1 1 0 1 0 2 0a1 1 0 1 0-2 0",
+        true,
+        "radial-gradient(#000 1px, transparent 1.5px) 0 0 / 6px 6px",
+    ),
+    (
+        4,
+        "M-1 1L1-1M0 4L4 0M3 5L5 3",
+        false,
+        "repeating-linear-gradient(45deg, #000 0 1px, transparent 1px 3px)",
+    ),
+];
+
+/// Space reserved around the timetable grid, in px.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Margins {
+    /// Space above the grid for the student name and week title
+    pub top: i32,
+    /// Space right of the grid
+    pub right: i32,
+    /// Space below the grid for the footer
+    pub bottom: i32,
+    /// Space left of the grid for period labels
+    pub left: i32,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Self {
+            top: 80,
+            right: 30,
+            bottom: 40,
+            left: 60,
+        }
+    }
+}
+
+/// Standard paper sizes for rendered sheets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperSize {
+    /// ISO A3, 297mm × 420mm (classroom walls)
+    A3,
+    /// ISO A4, 210mm × 297mm (default)
+    A4,
+    /// ISO A5, 148mm × 210mm
+    A5,
+    /// US Letter, 8.5in × 11in
+    Letter,
+}
+
+impl PaperSize {
+    /// Portrait `(width, height)` of the paper in millimetres.
+    pub fn dimensions_mm(self) -> (f64, f64) {
+        match self {
+            PaperSize::A3 => (297.0, 420.0),
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+/// Page orientation of a rendered sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// Timetable at the top with the map below (default)
+    #[default]
+    Portrait,
+    /// Timetable on the left stretched to the full page height, map on the right
+    /// (unless [`RenderOptions::map_placement`] says otherwise)
+    Landscape,
+}
+
+/// Where the school map goes relative to the timetable grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapPlacement {
+    /// Below the grid in portrait, beside it in landscape (default)
+    #[default]
+    Auto,
+    /// Beneath the grid, which keeps [`RenderOptions::timetable_height`]
+    Below,
+    /// Above the grid, which moves to the bottom of the sheet
+    Above,
+    /// Right of the grid, which keeps [`RenderOptions::timetable_width`]
+    Beside,
+    /// On a page of its own after each sheet in PDF output, with the grid
+    /// filling the whole sheet; single-page outputs leave the map out
+    Page,
+}
+
+/// Layout parameters for a rendered sheet.
+///
+/// The defaults reproduce the A4 portrait sheet: a 650px timetable area at the
+/// top with the map filling the rest of the page. Build customised options
+/// with [`RenderOptions::builder`].
+///
+/// Pixel values (margins, areas, and theme font sizes) are laid out on a sheet
+/// whose short side is always 794 units wide, the width of A4 at 96 DPI, and
+/// then scaled to the physical paper size. Other ISO sizes therefore come out
+/// as enlargements or reductions of the A4 sheet, while sizes with a
+/// different aspect ratio, such as US Letter, adjust the long side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Physical page width in millimetres, measured in portrait orientation
+    pub page_width_mm: f64,
+    /// Physical page height in millimetres, measured in portrait orientation
+    pub page_height_mm: f64,
+    /// Portrait or landscape layout
+    pub orientation: Orientation,
+    /// Margins around the timetable grid
+    pub margins: Margins,
+    /// Height of the timetable area (px) when the map is below or above it
+    pub timetable_height: i32,
+    /// Width of the timetable area (px) when the map is beside it
+    pub timetable_width: i32,
+    /// Gap around the embedded map (px)
+    pub map_margin: i32,
+    /// Whether to embed the map at all
+    pub show_map: bool,
+    /// Where the map goes when shown
+    pub map_placement: MapPlacement,
+    /// Whether to draw a legend of the week's departments above the map
+    pub show_legend: bool,
+    /// Multiplier applied to every theme font size
+    pub font_scale: f64,
+    /// Compact cells showing only the (abbreviated) subject and room code
+    pub compact: bool,
+    /// Day column to shade, e.g. today's when regenerating daily
+    pub highlight_day: Option<Weekday>,
+    /// Draw empty slots as labelled free-period cells instead of blank space
+    pub show_free_periods: bool,
+    /// Write styling as presentation attributes on each element instead of a
+    /// `<style>` element, for viewers that ignore embedded CSS (fonts embedded
+    /// through `[[fonts]]` still need one for their `@font-face` rules)
+    pub inline_styles: bool,
+    /// Shrink the output with [`optimise_svg`], for embedding in web pages
+    pub optimise: bool,
+    /// Lay out a staff timetable: the header names the staff member and their
+    /// department, and the class code takes the place of the teacher line
+    pub teacher_sheet: bool,
+    /// Time used for `{date}` and `{time}` in the templates, or the current
+    /// time if `None`; fix it for output that is identical on every run
+    pub generated_at: Option<chrono::DateTime<chrono::Local>>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            page_width_mm: 210.0,
+            page_height_mm: 297.0,
+            orientation: Orientation::Portrait,
+            margins: Margins::default(),
+            timetable_height: 650,
+            timetable_width: 780,
+            map_margin: 20,
+            show_map: true,
+            map_placement: MapPlacement::Auto,
+            show_legend: false,
+            font_scale: 1.0,
+            compact: false,
+            highlight_day: None,
+            show_free_periods: false,
+            inline_styles: false,
+            optimise: false,
+            teacher_sheet: false,
+            generated_at: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Start building render options from the A4 portrait defaults.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::renderer::RenderOptions;
+    ///
+    /// let options = RenderOptions::builder()
+    ///     .timetable_height(700)
+    ///     .map_margin(10)
+    ///     .build();
+    /// assert_eq!(options.width_px(), 794);
+    /// ```
+    pub fn builder() -> RenderOptionsBuilder {
+        RenderOptionsBuilder::default()
+    }
+
+    /// Preset for a pocket/wallet card printed on A7 (74mm × 105mm).
+    ///
+    /// The grid fills the card, cells show only an abbreviated subject and the
+    /// room code, fonts are enlarged to stay legible, and no map is embedded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::renderer::RenderOptions;
+    ///
+    /// let card = RenderOptions::pocket_card();
+    /// assert!(card.compact && !card.show_map);
+    /// ```
+    pub fn pocket_card() -> Self {
+        let mut options = RenderOptions::builder()
+            .page_size_mm(74.0, 105.0)
+            .margins(Margins {
+                top: 110,
+                right: 20,
+                bottom: 20,
+                left: 70,
+            })
+            .show_map(false)
+            .font_scale(2.5)
+            .compact(true)
+            .build();
+        options.timetable_height = options.height_px();
+        options
+    }
+
+    /// Preset for a 9:19.5 phone lock screen (72mm × 156mm).
+    ///
+    /// The grid sits below the space taken by the lock screen clock, fonts are
+    /// enlarged, cells use the compact layout, and no map is embedded. Render
+    /// with [`render_timetable_png`] at about 413 DPI for a 1170 × 2532 image,
+    /// and combine with [`Theme::with_dark_colors`](crate::config::Theme::with_dark_colors)
+    /// for a dark background.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use timetable_core::renderer::RenderOptions;
+    ///
+    /// let phone = RenderOptions::phone_wallpaper();
+    /// assert_eq!((phone.width_px(), phone.height_px()), (794, 1720));
+    /// ```
+    pub fn phone_wallpaper() -> Self {
+        let mut options = RenderOptions::builder()
+            .page_size_mm(72.0, 156.0)
+            .margins(Margins {
+                top: 420,
+                right: 20,
+                bottom: 60,
+                left: 70,
+            })
+            .show_map(false)
+            .font_scale(2.5)
+            .compact(true)
+            .build();
+        options.timetable_height = options.height_px();
+        options
+    }
+
+    /// Options for the reverse side of a duplex-printed sheet.
+    ///
+    /// Swaps the left and right margins so that, after a long-edge flip, the
+    /// grid on the back lies exactly behind the grid on the front.
+    pub fn mirrored(&self) -> Self {
+        RenderOptions {
+            margins: Margins {
+                left: self.margins.right,
+                right: self.margins.left,
+                ..self.margins
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Sheet width in millimetres after applying the orientation.
+    pub fn sheet_width_mm(&self) -> f64 {
+        match self.orientation {
+            Orientation::Portrait => self.page_width_mm,
+            Orientation::Landscape => self.page_height_mm,
+        }
+    }
+
+    /// Sheet height in millimetres after applying the orientation.
+    pub fn sheet_height_mm(&self) -> f64 {
+        match self.orientation {
+            Orientation::Portrait => self.page_height_mm,
+            Orientation::Landscape => self.page_width_mm,
+        }
+    }
+
+    /// Factor converting layout units to physical px at 96 DPI.
+    ///
+    /// 1.0 for A4, about 1.41 for A3, and about 0.70 for A5.
+    pub fn layout_scale(&self) -> f64 {
+        self.page_width_mm.min(self.page_height_mm) / 210.0
+    }
+
+    /// Sheet width in layout units (the SVG `viewBox` width).
+    pub fn width_px(&self) -> i32 {
+        (self.sheet_width_mm() * PX_PER_MM / self.layout_scale()).round() as i32
+    }
+
+    /// Sheet height in layout units (the SVG `viewBox` height).
+    pub fn height_px(&self) -> i32 {
+        (self.sheet_height_mm() * PX_PER_MM / self.layout_scale()).round() as i32
+    }
+
+    /// Width and height of the timetable area; the map fills the rest of the sheet.
+    pub fn timetable_size(&self) -> (i32, i32) {
+        match self.resolved_map_placement() {
+            MapPlacement::Beside => (self.timetable_width, self.height_px()),
+            MapPlacement::Page => (self.width_px(), self.height_px()),
+            _ => (self.width_px(), self.timetable_height),
+        }
+    }
+
+    /// [`RenderOptions::generated_at`], or the current time if it is not set.
+    pub fn generation_time(&self) -> chrono::DateTime<chrono::Local> {
+        self.generated_at.unwrap_or_else(chrono::Local::now)
+    }
+
+    /// [`RenderOptions::map_placement`] with [`MapPlacement::Auto`] resolved
+    /// from the orientation.
+    pub fn resolved_map_placement(&self) -> MapPlacement {
+        match (self.map_placement, self.orientation) {
+            (MapPlacement::Auto, Orientation::Portrait) => MapPlacement::Below,
+            (MapPlacement::Auto, Orientation::Landscape) => MapPlacement::Beside,
+            (placement, _) => placement,
+        }
+    }
+}
+
+/// Builder for [`RenderOptions`], created with [`RenderOptions::builder`].
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptionsBuilder {
+    options: RenderOptions,
+}
+
+impl RenderOptionsBuilder {
+    /// Set the physical page size in millimetres.
+    pub fn page_size_mm(mut self, width: f64, height: f64) -> Self {
+        self.options.page_width_mm = width;
+        self.options.page_height_mm = height;
+        self
+    }
+
+    /// Set the page size from a standard paper size.
+    pub fn paper_size(self, paper: PaperSize) -> Self {
+        let (width, height) = paper.dimensions_mm();
+        self.page_size_mm(width, height)
+    }
+
+    /// Set the page orientation.
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.options.orientation = orientation;
+        self
+    }
+
+    /// Set the margins around the timetable grid.
+    pub fn margins(mut self, margins: Margins) -> Self {
+        self.options.margins = margins;
+        self
+    }
+
+    /// Set the height of the timetable area (px).
+    pub fn timetable_height(mut self, height: i32) -> Self {
+        self.options.timetable_height = height;
+        self
+    }
+
+    /// Set the width of the timetable area in landscape orientation (px).
+    pub fn timetable_width(mut self, width: i32) -> Self {
+        self.options.timetable_width = width;
+        self
+    }
+
+    /// Set the gap around the embedded map (px).
+    pub fn map_margin(mut self, margin: i32) -> Self {
+        self.options.map_margin = margin;
+        self
+    }
+
+    /// Set whether the map is embedded.
+    pub fn show_map(mut self, show: bool) -> Self {
+        self.options.show_map = show;
+        self
+    }
+
+    /// Set where the map goes relative to the grid.
+    pub fn map_placement(mut self, placement: MapPlacement) -> Self {
+        self.options.map_placement = placement;
+        self
+    }
+
+    /// Set whether a department colour legend is drawn above the map.
+    pub fn show_legend(mut self, show: bool) -> Self {
+        self.options.show_legend = show;
+        self
+    }
+
+    /// Set the multiplier applied to every theme font size.
+    pub fn font_scale(mut self, scale: f64) -> Self {
+        self.options.font_scale = scale;
+        self
+    }
+
+    /// Set whether cells use the compact subject-and-room layout.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.options.compact = compact;
+        self
+    }
+
+    /// Set whether empty slots are drawn as labelled free periods.
+    pub fn show_free_periods(mut self, show: bool) -> Self {
+        self.options.show_free_periods = show;
+        self
+    }
+
+    /// Set whether styling is written inline instead of in a `<style>` element.
+    pub fn inline_styles(mut self, inline: bool) -> Self {
+        self.options.inline_styles = inline;
+        self
+    }
+
+    /// Set whether the output is shrunk with [`optimise_svg`].
+    pub fn optimise(mut self, optimise: bool) -> Self {
+        self.options.optimise = optimise;
+        self
+    }
+
+    /// Set whether the sheet is laid out for a staff timetable.
+    pub fn teacher_sheet(mut self, teacher_sheet: bool) -> Self {
+        self.options.teacher_sheet = teacher_sheet;
+        self
+    }
+
+    /// Fix the time shown by `{date}` and `{time}` instead of using the current time.
+    pub fn generated_at(mut self, time: chrono::DateTime<chrono::Local>) -> Self {
+        self.options.generated_at = Some(time);
+        self
+    }
+
+    /// Shade the column of `day` (weekends are ignored).
+    pub fn highlight_day(mut self, day: Weekday) -> Self {
+        self.options.highlight_day = Some(day);
+        self
+    }
+
+    /// Finish building the options.
+    pub fn build(self) -> RenderOptions {
+        self.options
+    }
 }
 
 /// Render a timetable week to an SVG file.
 ///
-/// Generates an A4-sized (210mm × 297mm) SVG document containing:
+/// Generates an SVG document (A4, 210mm × 297mm, with default options) containing:
 /// - A formatted timetable grid with student name, week identifier, and lessons
 /// - Color-coded cells based on room-to-department mappings
 /// - Break and lunch period rows
@@ -32,8 +569,11 @@ pub enum RenderError {
 /// * `week` - The week data to render
 /// * `config` - Configuration for room mappings and styling
 /// * `map_content` - Processed SVG map content (from [`process_map`](crate::processor::process_map))
+/// * `options` - Page size and layout parameters ([`RenderOptions::default`] for A4 portrait)
 /// * `output_path` - Path where the SVG file will be written
 ///
+/// A path ending in `.svgz` is written gzip-compressed.
+///
 /// # Returns
 ///
 /// `Ok(())` if the SVG was successfully generated and written.
@@ -47,17 +587,19 @@ pub enum RenderError {
 /// # Example
 ///
 /// ```no_run
-/// use timetable_core::{config::Config, parser::{parse_pdf, Week}, renderer::render_timetable};
+/// use timetable_core::{config::Config, icons::IconSet, parser::{parse_pdf, Week}, renderer::{render_timetable, RenderOptions}};
 /// use std::path::Path;
 ///
 /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
 /// let config = Config::load(Path::new("config.toml"))?;
+/// let icons = IconSet::load(&config)?;
 /// let weeks = parse_pdf(Path::new("input/timetable.pdf"))?;
 /// let map_svg = "<svg></svg>"; // Processed map content
+/// let options = RenderOptions::default();
 ///
 /// for (i, week) in weeks.iter().enumerate() {
 ///     let output = format!("output/week_{}.svg", i + 1);
-///     render_timetable(week, &config, map_svg, Path::new(&output))?;
+///     render_timetable(week, &config, &icons, map_svg, &options, Path::new(&output))?;
 /// }
 /// # Ok(())
 /// # }
@@ -65,514 +607,3964 @@ pub enum RenderError {
 pub fn render_timetable(
     week: &Week,
     config: &Config,
+    icons: &IconSet,
     map_content: &str,
+    options: &RenderOptions,
     output_path: &Path,
 ) -> Result<(), RenderError> {
-    // A4 @ 96 DPI ~= 794 x 1123
-    let width = 794;
-    let height = 1123;
-
-    let timetable_height = 650;
-    let _map_height = height - timetable_height;
-
-    let mut document = Document::new()
-        .set("viewBox", (0, 0, width, height))
-        .set("width", "210mm")
-        .set("height", "297mm");
-
-    // Add white background rectangle for the entire page
-    let background = Rectangle::new()
-        .set("x", 0)
-        .set("y", 0)
-        .set("width", width)
-        .set("height", height)
-        .set("fill", "#ffffff");
-    document = document.add(background);
-
-    // Inject Styles matching the diagram
-    let styles = r#"
-        .detail {
-            font-family: 'Bahnschrift Light', Bahnschrift, Arial, sans-serif;
-            font-size: 11px;
-            font-weight: 300;
-            fill: #231f20;
-        }
+    let svg_string = render_timetable_to_string(week, config, icons, map_content, options)?;
+    save_svg(output_path, &svg_string)?;
 
-        .subject {
-            font-family: Bahnschrift, Arial, sans-serif;
-            font-size: 11px;
-            font-weight: 400;
-            fill: #231f20;
-        }
+    Ok(())
+}
 
-        .room {
-            font-family: 'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif;
-            font-size: 18px;
-            font-weight: 600;
-            fill: #231f20;
-            text-anchor: middle;
-            dominant-baseline: middle;
-        }
+/// Compress a rendered SVG document with gzip, as stored in `.svgz` files.
+///
+/// Sheets with an embedded map often shrink to a fifth of their size.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Svg`] if compression fails.
+pub fn svg_to_svgz(svg: &str) -> Result<Vec<u8>, RenderError> {
+    let mut bytes = Vec::new();
+    write_svg(&mut bytes, svg, true)?;
+    Ok(bytes)
+}
 
-        .label {
-            font-family: 'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif;
-            font-size: 11px;
-            font-weight: 600;
-            fill: #231f20;
-        }
+/// Write a rendered SVG document to `writer`, gzip-compressed if `compressed` is set.
+///
+/// This is the final step of every SVG renderer, so a document can go to any
+/// sink: a file, an in-memory buffer, stdout or an HTTP response body (served
+/// compressed with `Content-Encoding: gzip`).
+///
+/// # Errors
+///
+/// Returns [`RenderError::Svg`] if compression or writing fails.
+pub fn write_svg<W: Write>(mut writer: W, svg: &str, compressed: bool) -> Result<(), RenderError> {
+    if compressed {
+        let mut encoder = GzEncoder::new(writer, Compression::best());
+        encoder.write_all(svg.as_bytes())?;
+        encoder.finish()?;
+    } else {
+        writer.write_all(svg.as_bytes())?;
+    }
+    Ok(())
+}
 
-        .box {
-            fill: none;
-            stroke: #231f20;
-            stroke-width: 1;
-            stroke-miterlimit: 10;
-        }
+/// Save SVG text to `path`, gzip-compressed if the extension is `.svgz`.
+fn save_svg(path: &Path, svg: &str) -> Result<(), RenderError> {
+    let compressed = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("svgz"));
+    let mut file = BufWriter::new(File::create(path)?);
+    write_svg(&mut file, svg, compressed)?;
+    file.flush()?;
+    Ok(())
+}
 
-        .period-label {
-            font-family: 'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif;
-            font-size: 12px;
-            font-weight: 600;
-            fill: #231f20;
-            text-anchor: middle;
-        }
+/// Render a timetable week to a print-ready PDF file.
+///
+/// Renders the same sheet as [`render_timetable`] and converts it to a single
+/// page PDF at the physical size given by `options`, so it prints at 100%
+/// without browser scaling. Text is converted to outlines using the fonts
+/// installed on the system. With [`MapPlacement::Page`] the map follows on a
+/// second page.
+///
+/// Requires the `pdf` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if rendering or PDF conversion fails, or the file
+/// cannot be written.
+#[cfg(feature = "pdf")]
+pub fn render_timetable_pdf(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let pdf = svgs_to_pdf(&sheet_pages(week, config, icons, map_content, options)?)?;
+    fs::write(output_path, pdf)?;
 
-        .header-text {
-            font-family: Bahnschrift, Arial, sans-serif;
-            font-size: 14px;
-            font-weight: 400;
-            fill: #231f20;
-        }
+    Ok(())
+}
 
-        .week-label {
-            font-family: 'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif;
-            font-size: 16px;
-            font-weight: 600;
-            fill: #231f20;
-        }
-    "#;
+/// Convert a rendered SVG document into PDF bytes.
+///
+/// One SVG pixel is treated as 1/96 inch, matching the size of the sheet
+/// produced by [`render_timetable_to_string`].
+///
+/// Requires the `pdf` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Pdf`] if the SVG cannot be parsed.
+#[cfg(feature = "pdf")]
+pub fn svg_to_pdf(svg: &str) -> Result<Vec<u8>, RenderError> {
+    let tree = parse_svg_tree(svg).map_err(|e| RenderError::Pdf(e.to_string()))?;
 
-    let style_element = svg::node::element::Style::new(styles);
-    let defs = svg::node::element::Definitions::new().add(style_element);
-    document = document.add(defs);
+    let options = svg2pdf::Options {
+        dpi: 96.0,
+        ..Default::default()
+    };
+    Ok(svg2pdf::convert_tree(&tree, options))
+}
 
-    // 1. Draw Timetable
-    let timetable_group = draw_timetable_grid(week, config, width, timetable_height);
-    document = document.add(timetable_group);
+/// Render several timetable weeks into a single multi-page PDF file.
+///
+/// Each `(week, map_content)` pair becomes one page, in order, followed by a
+/// page for its map with [`MapPlacement::Page`]. When `map_page` is given, a
+/// final page containing just that map is appended (see
+/// [`render_map_page_to_string`]).
+///
+/// Requires the `pdf` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if rendering or PDF conversion fails, or the file
+/// cannot be written.
+#[cfg(feature = "pdf")]
+pub fn render_timetables_pdf(
+    sheets: &[(&Week, &str)],
+    config: &Config,
+    icons: &IconSet,
+    options: &RenderOptions,
+    map_page: Option<&str>,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let mut pages = Vec::with_capacity(sheets.len() + 1);
+    for (week, map_content) in sheets {
+        pages.extend(sheet_pages(week, config, icons, map_content, options)?);
+    }
+    if let Some(map_content) = map_page {
+        pages.push(render_map_page_to_string(config, map_content, options)?);
+    }
 
-    // 2. Embed Map
-    // We wrap the map content in a nested <svg> to handle positioning
-    // The map_content is a full <svg> string. We need to strip the xml declaration if present,
-    // and maybe wrap it in a <g> with transform.
-    // Or better: use <svg x="..." y="..." width="..." height="..."> ... </svg>
-    // But we have the content as a string.
+    let pdf = svgs_to_pdf(&pages)?;
+    fs::write(output_path, pdf)?;
 
-    // We can't easily add a raw string to `svg::Document`.
-    // So we will serialize the document so far, and then inject the map string.
+    Ok(())
+}
 
-    let mut svg_string = document.to_string();
+/// The sheet for one week, followed by its map when that goes on its own page.
+#[cfg(feature = "pdf")]
+fn sheet_pages(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+) -> Result<Vec<String>, RenderError> {
+    let mut pages = vec![render_timetable_to_string(
+        week,
+        config,
+        icons,
+        map_content,
+        options,
+    )?];
+    if options.show_map
+        && options.resolved_map_placement() == MapPlacement::Page
+        && !map_content.trim().is_empty()
+    {
+        pages.push(render_map_page_to_string(config, map_content, options)?);
+    }
+    Ok(pages)
+}
 
-    // Remove the closing </svg>
-    if svg_string.ends_with("</svg>") {
-        svg_string.truncate(svg_string.len() - 6);
+/// Render weeks into a PDF for double-sided printing.
+///
+/// Pages alternate front and back (Week 1 front, Week 2 back, and so on), and
+/// every back page uses [`RenderOptions::mirrored`] margins so the grids line
+/// up when the sheet is printed with a long-edge flip. Maps placed with
+/// [`MapPlacement::Page`] are left out to keep the pairs together.
+///
+/// Requires the `pdf` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if rendering or PDF conversion fails, or the file
+/// cannot be written.
+#[cfg(feature = "pdf")]
+pub fn render_duplex_pdf(
+    sheets: &[(&Week, &str)],
+    config: &Config,
+    icons: &IconSet,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let back_options = options.mirrored();
+    let mut pages = Vec::with_capacity(sheets.len());
+    for (i, (week, map_content)) in sheets.iter().enumerate() {
+        let page_options = if i % 2 == 0 { options } else { &back_options };
+        pages.push(render_timetable_to_string(
+            week,
+            config,
+            icons,
+            map_content,
+            page_options,
+        )?);
     }
 
-    // Inject the map if provided (map_content non-empty). If empty, skip embedding.
-    if !map_content.trim().is_empty() {
-        // We place it at the bottom.
-        let map_y = timetable_height + 20;
-        let map_area_height = height - map_y - 20; // Leave 20px margin at bottom
+    let pdf = svgs_to_pdf(&pages)?;
+    fs::write(output_path, pdf)?;
 
-        svg_string.push_str(&format!(
-            "<svg x=\"0\" y=\"{}\" width=\"{}\" height=\"{}\">",
-            map_y, width, map_area_height
-        ));
+    Ok(())
+}
 
-        // Strip <?xml ... ?> if exists
-        let clean_map = map_content.trim_start_matches(|c| c != '<');
-        let clean_map = if clean_map.starts_with("<?xml") {
-            if let Some(idx) = clean_map.find("?>") {
-                &clean_map[idx + 2..]
-            } else {
-                clean_map
-            }
-        } else {
-            clean_map
-        };
+/// Convert several SVG documents into one PDF with a page per document.
+///
+/// Requires the `pdf` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Pdf`] if any SVG cannot be parsed.
+#[cfg(feature = "pdf")]
+pub fn svgs_to_pdf(svgs: &[String]) -> Result<Vec<u8>, RenderError> {
+    use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
 
-        svg_string.push_str(clean_map);
-        svg_string.push_str("</svg>");
-    }
+    let options = svg2pdf::Options {
+        dpi: 96.0,
+        ..Default::default()
+    };
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let mut next_id = Ref::new(3);
+    let svg_name = Name(b"S1");
 
-    // Close the root svg
-    svg_string.push_str("</svg>");
+    let mut pdf = Pdf::new();
+    let mut page_ids = Vec::with_capacity(svgs.len());
 
-    fs::write(output_path, svg_string)?;
+    for svg in svgs {
+        let tree = parse_svg_tree(svg).map_err(|e| RenderError::Pdf(e.to_string()))?;
+        // SVG pixels are 1/96 inch; PDF points are 1/72 inch
+        let width = tree.size.width() * 72.0 / 96.0;
+        let height = tree.size.height() * 72.0 / 96.0;
 
-    Ok(())
-}
+        let page_id = next_id.bump();
+        let content_id = next_id.bump();
+        let svg_id = next_id.bump();
+        page_ids.push(page_id);
 
-fn draw_timetable_grid(week: &Week, config: &Config, width: i32, height: i32) -> Group {
-    let mut group = Group::new().set("id", "timetable");
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, width, height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().x_objects().pair(svg_name, svg_id);
+        page.finish();
 
-    // Grid dimensions
-    let cols = 5; // Mon-Fri
-    let periods = 6; // PD + L1-L5
+        next_id = svg2pdf::convert_tree_into(&tree, options, &mut pdf, svg_id);
 
-    let left_margin = 60; // Space for period labels
-    let top_margin = 80; // Space for student name and week
-    let right_margin = 30;
-    let bottom_margin = 40; // Space for update date
+        let mut content = Content::new();
+        content
+            .transform([width, 0.0, 0.0, height, 0.0, 0.0])
+            .x_object(svg_name);
+        pdf.stream(content_id, &content.finish());
+    }
 
-    let grid_width = width - left_margin - right_margin;
-    let grid_height = height - top_margin - bottom_margin;
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    let count = page_ids.len() as i32;
+    pdf.pages(page_tree_id).kids(page_ids).count(count);
 
-    let break_height = 24;
-    let lunch_height = 24;
+    Ok(pdf.finish())
+}
 
-    let total_gap_height = break_height + lunch_height;
-    let row_height = (grid_height - total_gap_height) / periods;
-    let col_width = grid_width / cols;
+/// Render a timetable week to a PNG image at the given resolution.
+///
+/// `dpi` sets the pixel density of the output: 96 gives one image pixel per
+/// SVG pixel (794 × 1123 for A4), 300 gives a print-quality image.
+///
+/// Requires the `png` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if rendering or rasterisation fails, or the file
+/// cannot be written.
+#[cfg(feature = "png")]
+pub fn render_timetable_png(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    dpi: f32,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let svg_string = render_timetable_to_string(week, config, icons, map_content, options)?;
+    let png = svg_to_png(&svg_string, dpi)?;
+    fs::write(output_path, png)?;
 
-    // Add student name and form at top left
-    let student_info = if let (Some(name), Some(form)) = (&week.student_name, &week.form) {
-        format!("{} ({})", name, form)
-    } else if let Some(name) = &week.student_name {
-        name.clone()
-    } else {
-        String::from("Student Timetable")
-    };
+    Ok(())
+}
 
-    let text_student = Text::new(student_info.as_str())
-        .set("x", left_margin)
-        .set("y", 30)
-        .set("class", "header-text");
-    group = group.add(text_student);
+/// Rasterise a rendered SVG document into PNG bytes at `dpi`.
+///
+/// Requires the `png` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Png`] if the SVG cannot be parsed, the resulting
+/// image would be empty, or encoding fails.
+#[cfg(feature = "png")]
+pub fn svg_to_png(svg: &str, dpi: f32) -> Result<Vec<u8>, RenderError> {
+    use resvg::tiny_skia::{Pixmap, Transform};
 
-    // Add week label at top center
-    let text_week = Text::new(week.week_name.as_str())
-        .set("x", width / 2)
-        .set("y", 30)
-        .set("text-anchor", "middle")
-        .set("class", "week-label");
-    group = group.add(text_week);
+    let tree = parse_svg_tree(svg).map_err(|e| RenderError::Png(e.to_string()))?;
 
-    // Draw day headers (Monday-Friday)
-    let days = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"];
-    for (i, day) in days.iter().enumerate() {
-        let x = left_margin + (i as i32 * col_width) + (col_width / 2);
-        let y = top_margin - 15;
-        let text = Text::new(*day)
-            .set("x", x)
-            .set("y", y)
-            .set("text-anchor", "middle")
-            .set("class", "header-text");
-        group = group.add(text);
-    }
+    let scale = dpi / 96.0;
+    let width = (tree.size.width() * scale).round() as u32;
+    let height = (tree.size.height() * scale).round() as u32;
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| RenderError::Png(format!("invalid image size {width}x{height}")))?;
 
-    // Period labels and rows
-    let period_labels = ["PD", "L1", "L2", "L3", "L4", "L5"];
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
 
-    for (period_idx, label) in period_labels.iter().enumerate() {
-        let mut y = top_margin + (period_idx as i32 * row_height);
+    pixmap
+        .encode_png()
+        .map_err(|e| RenderError::Png(e.to_string()))
+}
 
-        // Adjust for breaks - break comes after L2 (index 2)
-        if period_idx > 2 {
-            y += break_height;
-        }
-        // Lunch comes after L4 (index 4)
-        if period_idx > 4 {
-            y += lunch_height;
-        }
+/// Parse SVG into a usvg tree with text converted to paths using system fonts
+/// and any fonts embedded with `@font-face`.
+#[cfg(any(feature = "pdf", feature = "png"))]
+fn parse_svg_tree(svg: &str) -> Result<usvg::Tree, usvg::Error> {
+    use usvg::{TreeParsing, TreePostProc};
 
-        // Draw period label on left
-        let text_period = Text::new(*label)
-            .set("x", 30)
-            .set("y", y + (row_height / 2))
-            .set("dominant-baseline", "middle")
-            .set("class", "period-label");
-        group = group.add(text_period);
+    let mut tree = usvg::Tree::from_str(svg, &usvg::Options::default())?;
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
 
-        // Draw break after L2 (period_idx 2)
-        if period_idx == 2 {
-            let cell_padding = 3;
-            let break_y = y + row_height + cell_padding;
-            // Calculate actual content width (5 columns worth of cells)
-            let total_content_width = col_width * cols;
-            let rect_break = Rectangle::new()
-                .set("x", left_margin + cell_padding)
-                .set("y", break_y)
-                .set("width", total_content_width - (cell_padding * 2))
-                .set("height", break_height - (cell_padding * 2))
-                .set("fill", "#eeeeee")
-                .set("stroke", "#231f20")
-                .set("stroke-width", 1);
-            group = group.add(rect_break);
-
-            let text_break = Text::new("Break (11:00 - 11:30)")
-                .set("x", left_margin + (total_content_width / 2))
-                .set("y", break_y + ((break_height - (cell_padding * 2)) / 2) + 1)
-                .set("text-anchor", "middle")
-                .set("dominant-baseline", "middle")
-                .set("class", "detail");
-            group = group.add(text_break);
-        }
-
-        // Draw lunch after L4 (period_idx 4)
-        if period_idx == 4 {
-            let cell_padding = 3;
-            let lunch_y = y + row_height + cell_padding;
-            // Calculate actual content width (5 columns worth of cells)
-            let total_content_width = col_width * cols;
-            let rect_lunch = Rectangle::new()
-                .set("x", left_margin + cell_padding)
-                .set("y", lunch_y)
-                .set("width", total_content_width - (cell_padding * 2))
-                .set("height", lunch_height - (cell_padding * 2))
-                .set("fill", "#eeeeee")
-                .set("stroke", "#231f20")
-                .set("stroke-width", 1);
-            group = group.add(rect_lunch);
-
-            let text_lunch = Text::new("Lunch (13:30 - 14:10)")
-                .set("x", left_margin + (total_content_width / 2))
-                .set("y", lunch_y + (lunch_height / 2) - 2)
-                .set("text-anchor", "middle")
-                .set("dominant-baseline", "middle")
-                .set("class", "detail");
-            group = group.add(text_lunch);
+    // usvg ignores `@font-face`, so register embedded fonts directly
+    let embedded = regex::Regex::new(r#"url\("data:font/[\w-]+;base64,([A-Za-z0-9+/=]+)"\)"#)
+        .expect("valid regex");
+    for capture in embedded.captures_iter(svg) {
+        if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(&capture[1]) {
+            fontdb.load_font_data(bytes);
         }
     }
+    tree.postprocess(usvg::PostProcessingSteps::default(), &fontdb);
 
-    // Draw lessons
-    for lesson in &week.lessons {
-        let x = left_margin + (lesson.day_index as i32 * col_width);
+    Ok(tree)
+}
 
-        // Calculate Y based on period and gaps
-        let mut y = top_margin + (lesson.period_index as i32 * row_height);
-        if lesson.period_index > 2 {
-            y += break_height;
-        }
-        if lesson.period_index > 4 {
-            y += lunch_height;
-        }
+/// Render a timetable week into any [`Write`] sink.
+///
+/// Produces the same document as [`render_timetable`], written to `writer`
+/// instead of a file (e.g., an HTTP response body or stdout).
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if an embedded asset cannot be read or writing fails.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::Week};
+/// use timetable_core::renderer::{render_timetable_to_writer, RenderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut buffer = Vec::new();
+/// let options = RenderOptions::default();
+/// let (week, config) = (Week::default(), Config::default());
+/// render_timetable_to_writer(&week, &config, &IconSet::default(), "", &options, &mut buffer)?;
+/// assert!(buffer.starts_with(b"<svg"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_timetable_to_writer<W: Write>(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<(), RenderError> {
+    let svg_string = render_timetable_to_string(week, config, icons, map_content, options)?;
+    write_svg(writer, &svg_string, false)
+}
 
-        // Handle Unknown room - use dark grey
-        let is_unknown_room = lesson.room == "Unknown" || lesson.room == "DEFAULT";
+/// List the lesson cells whose text will not fit when `week` is rendered with
+/// `options` and the subject icons in `icons`.
+///
+/// Use this before printing to find subjects that need an alias, or layouts
+/// that need larger cells. Overflowing subjects and teachers are drawn cut
+/// short with an ellipsis; overflowing class codes run past their strip.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::{Lesson, Week}};
+/// use timetable_core::renderer::{find_overflows, RenderOptions};
+///
+/// let week = Week {
+///     lessons: vec![Lesson {
+///         subject: "Maths".into(),
+///         room: "MA3".into(),
+///         class_code: "10A/Ma1-Set3".into(),
+///         ..Default::default()
+///     }],
+///     ..Default::default()
+/// };
+/// let overflows = find_overflows(
+///     &week,
+///     &Config::default(),
+///     &IconSet::default(),
+///     &RenderOptions::default(),
+/// );
+/// assert_eq!(overflows[0].text, "10A/Ma1-Set3");
+/// ```
+pub fn find_overflows(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    options: &RenderOptions,
+) -> Vec<CellOverflow> {
+    let (width, height) = options.timetable_size();
+    let config = Config {
+        theme: config.theme.with_font_scale(options.font_scale),
+        ..config.clone()
+    };
+    SheetLayout::compute(week, &config, icons, options, width, height)
+        .overflows()
+        .into_iter()
+        // Compact cells show an abbreviated subject and no teacher
+        .filter(|overflow| !options.compact || overflow.kind == OverflowKind::Label)
+        .collect()
+}
 
-        // Get color mapping from config
-        let (bg_color, fg_color) = if is_unknown_room {
-            ("#e0e0e0", "#4a4a4a") // Light grey bg, dark grey fg for unknown
-        } else {
-            config
-                .get_style_for_room(&lesson.room)
-                .map(|m| (m.bg_color.as_str(), m.fg_color.as_str()))
-                .unwrap_or(("#ffffff", "#231f20"))
+/// Render a timetable week to an SVG string.
+///
+/// Produces the same document as [`render_timetable`] without touching the
+/// filesystem, for web services, tests and WASM callers.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if an embedded asset (such as the logo) cannot be read.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::Week};
+/// use timetable_core::renderer::{render_timetable_to_string, RenderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let week = Week {
+///     week_name: "Week 1".into(),
+///     ..Default::default()
+/// };
+/// let config = Config::default();
+/// let svg = render_timetable_to_string(
+///     &week,
+///     &config,
+///     &IconSet::default(),
+///     "",
+///     &RenderOptions::default(),
+/// )?;
+/// assert!(svg.contains("Week 1"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_timetable_to_string(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+) -> Result<String, RenderError> {
+    let width = options.width_px();
+    let height = options.height_px();
+
+    // Area given to the timetable grid; the map fills the remaining space
+    let (timetable_width, timetable_height) = options.timetable_size();
+
+    // Scale fonts for small-format layouts without touching the caller's config
+    let scaled_config;
+    let config = if options.font_scale != 1.0 {
+        scaled_config = Config {
+            theme: config.theme.with_font_scale(options.font_scale),
+            ..config.clone()
         };
+        &scaled_config
+    } else {
+        config
+    };
 
-        let cell_padding = 3; // Space between cells
-        let label_width = 30; // Width of the vertical label section on right
+    let mut document = start_document(config, options)?;
+    if let Some(defs) = icon_definitions([week], config, icons) {
+        document = document.add(defs);
+    }
 
-        // Main cell area (white background)
-        let main_width = col_width - label_width - (cell_padding * 2);
-        let rect_main = Rectangle::new()
-            .set("x", x + cell_padding)
-            .set("y", y + cell_padding)
-            .set("width", main_width)
-            .set("height", row_height - (cell_padding * 2))
-            .set("fill", "#ffffff")
-            .set("stroke", "#231f20")
-            .set("stroke-width", 1);
-        group = group.add(rect_main);
-
-        // Right label area (colored background)
-        let label_x = x + col_width - label_width - cell_padding;
-        let rect_label = Rectangle::new()
-            .set("x", label_x)
-            .set("y", y + cell_padding)
-            .set("width", label_width)
-            .set("height", row_height - (cell_padding * 2))
-            .set("fill", bg_color)
-            .set("stroke", "#231f20")
-            .set("stroke-width", 1);
-        group = group.add(rect_label);
-
-        // Text: Subject (top left, bold)
-        // Split long subjects into multiple lines if needed
-        let subject_words: Vec<&str> = lesson.subject.split_whitespace().collect();
-        let max_chars_per_line = 18;
-
-        if lesson.subject.len() > max_chars_per_line && subject_words.len() > 1 {
-            // Multi-line subject
-            let mut lines = Vec::new();
-            let mut current_line = String::new();
-
-            for word in subject_words {
-                if current_line.is_empty() {
-                    current_line = word.to_string();
-                } else if current_line.len() + word.len() < max_chars_per_line {
-                    current_line.push(' ');
-                    current_line.push_str(word);
-                } else {
-                    lines.push(current_line.clone());
-                    current_line = word.to_string();
-                }
-            }
-            if !current_line.is_empty() {
-                lines.push(current_line);
-            }
+    // With the map above, the grid and logo move down to the bottom of the sheet
+    let placement = options.resolved_map_placement();
+    let grid_offset = match placement {
+        MapPlacement::Above => height - timetable_height,
+        _ => 0,
+    };
 
-            // Render each line
-            for (line_idx, line) in lines.iter().enumerate() {
-                let text_subject_line = Text::new(line.as_str())
-                    .set("x", x + cell_padding + 5)
-                    .set("y", y + cell_padding + 12 + (line_idx as i32 * 11))
-                    .set("class", "subject")
-                    .set("font-weight", "bold");
-                group = group.add(text_subject_line);
-            }
-        } else {
-            // Single line subject
-            let text_subject = Text::new(lesson.subject.as_str())
-                .set("x", x + cell_padding + 5)
-                .set("y", y + cell_padding + 14)
-                .set("class", "subject")
-                .set("font-weight", "bold");
-            group = group.add(text_subject);
+    // 1. Draw Timetable
+    let mut timetable_group = draw_timetable_grid(
+        week,
+        config,
+        icons,
+        options,
+        timetable_width,
+        timetable_height,
+        "",
+    );
+    if grid_offset != 0 {
+        timetable_group = timetable_group.set("transform", format!("translate(0 {})", grid_offset));
+    }
+    document = document.add(timetable_group);
+
+    // School logo in the header corner
+    if let Some(logo) = &config.logo {
+        let x = match logo.position {
+            LogoPosition::Left => 6,
+            LogoPosition::Right => timetable_width - options.margins.right - logo.size,
+        };
+        let image = Image::new()
+            .set("id", "logo")
+            .set("x", x)
+            .set("y", 8 + grid_offset)
+            .set("width", logo.size)
+            .set("height", logo.size)
+            .set("preserveAspectRatio", "xMidYMid meet")
+            .set("href", image_data_uri(&logo.path)?);
+        document = document.add(image);
+    }
+
+    // The map takes whatever the grid leaves, with a margin around it
+    let (map_x, mut map_y, map_area_width, mut map_area_height) = match placement {
+        MapPlacement::Beside => {
+            let map_x = timetable_width + options.map_margin;
+            let map_y = options.margins.top;
+            (
+                map_x,
+                map_y,
+                width - map_x - options.map_margin,
+                height - map_y - options.margins.bottom,
+            )
         }
+        MapPlacement::Above => (
+            0,
+            options.map_margin,
+            width,
+            grid_offset - 2 * options.map_margin,
+        ),
+        _ => {
+            let map_y = timetable_height + options.map_margin;
+            // Leave margin at bottom
+            (0, map_y, width, height - map_y - options.map_margin)
+        }
+    };
+    let map_on_sheet = options.show_map && placement != MapPlacement::Page;
 
-        // Text: Room code (above teacher) - only if not Unknown
-        if lesson.room != "Unknown" {
-            let text_room = Text::new(lesson.room.as_str())
-                .set("x", x + cell_padding + 5)
-                .set("y", y + row_height - cell_padding - 22)
-                .set("class", "detail");
-            group = group.add(text_room);
+    // Department legend takes the top of the map area
+    if options.show_legend && placement != MapPlacement::Page {
+        let (legend_x, legend_max_x) = match placement {
+            MapPlacement::Beside => (map_x, map_x + map_area_width),
+            _ => (options.margins.left, width - options.margins.right),
+        };
+        if let Some((legend, legend_height)) = draw_legend(
+            std::slice::from_ref(week),
+            config,
+            legend_x,
+            map_y,
+            legend_max_x,
+        ) {
+            document = document.add(legend);
+            map_y += legend_height;
+            map_area_height -= legend_height;
         }
+    }
 
-        // Text: Teacher (bottom, smaller text) - only if not Unknown
-        if lesson.teacher != "Unknown" {
-            let text_teacher = Text::new(lesson.teacher.as_str())
-                .set("x", x + cell_padding + 5)
-                .set("y", y + row_height - cell_padding - 8)
-                .set("class", "detail")
-                .set("font-size", "9px");
-            group = group.add(text_teacher);
+    if options.inline_styles {
+        inline_styles(&mut document, &config.theme);
+    }
+
+    // 2. Embed the map, if provided, in the area the grid leaves
+    if map_on_sheet && !map_content.trim().is_empty() {
+        document = document.add(map_group(
+            map_content,
+            (map_x, map_y, map_area_width, map_area_height),
+            &config.strings.school_map,
+        )?);
+    }
+
+    let mut svg_string = document.to_string();
+
+    if options.optimise {
+        svg_string = optimise_svg(&svg_string, OPTIMISE_PRECISION)?;
+    }
+    Ok(svg_string)
+}
+
+/// Render a timetable week through a user-supplied minijinja template.
+///
+/// The template receives:
+/// - `sheet`: the computed [`SheetLayout`] (cells, colours, texts, positions)
+/// - `theme`: the configured [`Theme`]
+/// - `page`: `width` and `height` in layout units, `width_mm` and `height_mm`
+/// - `map`: the processed map SVG (insert it with `{{ map | safe }}`)
+///
+/// Values are XML/HTML-escaped automatically, so the same template syntax
+/// works for SVG and HTML output. Requires the `templates` feature.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Template`] if the template is invalid or fails to render.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::Week};
+/// use timetable_core::renderer::{render_timetable_with_template, RenderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let week = Week { week_name: "Week 1".into(), ..Default::default() };
+/// let out = render_timetable_with_template(
+///     &week,
+///     &Config::default(),
+///     &IconSet::default(),
+///     "",
+///     &RenderOptions::default(),
+///     "<h1>{{ sheet.title.text }}</h1>{% for day in sheet.days %}<p>{{ day.text }}</p>{% endfor %}",
+/// )?;
+/// assert!(out.starts_with("<h1>Week 1</h1><p>Monday</p>"));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "templates")]
+pub fn render_timetable_with_template(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    template: &str,
+) -> Result<String, RenderError> {
+    // Lay the grid out exactly as the SVG sheet would for the same options
+    let (timetable_width, timetable_height) = options.timetable_size();
+    let config = &Config {
+        theme: config.theme.with_font_scale(options.font_scale),
+        ..config.clone()
+    };
+    let sheet = SheetLayout::compute(
+        week,
+        config,
+        icons,
+        options,
+        timetable_width,
+        timetable_height,
+    );
+
+    let mut env = minijinja::Environment::new();
+    env.set_auto_escape_callback(|_| minijinja::AutoEscape::Html);
+    env.render_str(
+        template,
+        minijinja::context! {
+            sheet => sheet,
+            theme => &config.theme,
+            page => minijinja::context! {
+                width => options.width_px(),
+                height => options.height_px(),
+                width_mm => options.sheet_width_mm(),
+                height_mm => options.sheet_height_mm(),
+            },
+            map => strip_xml_prolog(map_content),
+        },
+    )
+    .map_err(|e| RenderError::Template(e.to_string()))
+}
+
+/// How [`render_weeks_to_string`] arranges several weeks on one page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultiWeekLayout {
+    /// Weeks above one another (suits portrait pages)
+    #[default]
+    Stacked,
+    /// Weeks next to one another (suits landscape pages)
+    SideBySide,
+}
+
+/// Render several weeks onto a single page with a shared map and legend.
+///
+/// Stacked, the weeks share a timetable area four thirds the height of
+/// [`RenderOptions::timetable_height`], each taking an equal slice of it;
+/// side by side, each takes an equal share of the width at the full
+/// `timetable_height`. Both use the compact cell layout to stay legible. A
+/// legend of the departments used across all weeks sits between the
+/// timetables and the map, which should be highlighted for every week.
+///
+/// The weeks need not belong to one student: side by side, the same week
+/// from several students (say, siblings) makes a comparison sheet, and each
+/// grid's header names its own student.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if an embedded asset cannot be read.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::Week};
+/// use timetable_core::renderer::{render_weeks_to_string, MultiWeekLayout, RenderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let weeks = vec![
+///     Week { week_name: "Week 1".into(), ..Default::default() },
+///     Week { week_name: "Week 2".into(), ..Default::default() },
+/// ];
+/// let svg = render_weeks_to_string(
+///     &weeks,
+///     &Config::default(),
+///     &IconSet::default(),
+///     "",
+///     &RenderOptions::default(),
+///     MultiWeekLayout::Stacked,
+/// )?;
+/// assert!(svg.contains("Week 2"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_weeks_to_string(
+    weeks: &[Week],
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    layout: MultiWeekLayout,
+) -> Result<String, RenderError> {
+    let width = options.width_px();
+    let height = options.height_px();
+    let count = weeks.len().max(1) as i32;
+
+    let mut document = start_document(config, options)?;
+    if let Some(defs) = icon_definitions(weeks, config, icons) {
+        document = document.add(defs);
+    }
+
+    // Each week is laid out in its own slot with compact cells
+    let slot_options = RenderOptions {
+        compact: true,
+        ..options.clone()
+    };
+    let (slot_width, slot_height, timetables_height) = match layout {
+        MultiWeekLayout::Stacked => {
+            // A third more height than one sheet's grid, leaving room for the map
+            let total = options.timetable_height * 4 / 3;
+            (width, total / count, total)
         }
+        MultiWeekLayout::SideBySide => (
+            width / count,
+            options.timetable_height,
+            options.timetable_height,
+        ),
+    };
 
-        // Text: Class code as vertical label on right (rotated 90°, large font, saturated color)
-        // Use class_code if available, otherwise use subject for Unknown rooms, otherwise room code
-        let label_text = if !lesson.class_code.is_empty() {
-            &lesson.class_code
-        } else if is_unknown_room {
-            &lesson.subject // Use subject for unknown rooms
-        } else {
-            &lesson.room
+    for (i, week) in weeks.iter().enumerate() {
+        let (x, y) = match layout {
+            MultiWeekLayout::Stacked => (0, i as i32 * slot_height),
+            MultiWeekLayout::SideBySide => (i as i32 * slot_width, 0),
         };
+        let prefix = format!("week-{}-", i + 1);
+        let grid = draw_timetable_grid(
+            week,
+            config,
+            icons,
+            &slot_options,
+            slot_width,
+            slot_height,
+            &prefix,
+        )
+        .set("id", format!("timetable-{}", i + 1))
+        .set("transform", format!("translate({} {})", x, y));
+        document = document.add(grid);
+    }
 
-        let class_x = label_x + (label_width / 2) - 2;
-        let class_y = y + (row_height / 2);
+    let mut map_y = timetables_height + options.map_margin;
+    if let Some((legend, legend_height)) =
+        draw_legend(weeks, config, options.margins.left, map_y, width)
+    {
+        document = document.add(legend);
+        map_y += legend_height;
+    }
 
-        let text_class = Text::new(label_text)
-            .set("x", class_x)
-            .set("y", class_y)
-            .set("transform", format!("rotate(90 {} {})", class_x, class_y))
+    if options.inline_styles {
+        inline_styles(&mut document, &config.theme);
+    }
+
+    if options.show_map && !map_content.trim().is_empty() {
+        document = document.add(map_group(
+            map_content,
+            (0, map_y, width, height - map_y - options.map_margin),
+            &config.strings.school_map,
+        )?);
+    }
+
+    let mut svg_string = document.to_string();
+
+    if options.optimise {
+        svg_string = optimise_svg(&svg_string, OPTIMISE_PRECISION)?;
+    }
+    Ok(svg_string)
+}
+
+/// Render several students' weeks merged into one grid, e.g. a household of siblings.
+///
+/// Every lesson slot is split into compact sub-rows, one per student in the
+/// order given, while the day, period, break and lunch rows are shared. Each
+/// student is keyed by the initial of their name (or their position, if two
+/// initials clash), shown in the cell strip and listed in the header. A
+/// legend of the departments used by anyone sits above the map, which should
+/// be highlighted for every week.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if an embedded asset cannot be read.
+pub fn render_household_to_string(
+    weeks: &[Week],
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+) -> Result<String, RenderError> {
+    let width = options.width_px();
+    let height = options.height_px();
+    let theme = &config.theme;
+
+    let mut document = start_document(config, options)?;
+    if let Some(defs) = icon_definitions(weeks, config, icons) {
+        document = document.add(defs);
+    }
+
+    let slot_options = RenderOptions {
+        compact: true,
+        show_free_periods: false,
+        ..options.clone()
+    };
+    let grid_height = options.timetable_height;
+    let first = weeks.first().cloned().unwrap_or_default();
+    let mut layout = SheetLayout::compute(&first, config, icons, &slot_options, width, grid_height);
+
+    let keys = household_keys(weeks);
+    let names: Vec<String> = weeks
+        .iter()
+        .zip(&keys)
+        .map(|(week, key)| match &week.student_name {
+            Some(name) => format!("{}: {}", key, name),
+            None => key.clone(),
+        })
+        .collect();
+    layout.header.text = names.join(" · ");
+    layout.subheader.text.clear();
+
+    // Each student's cells shrink into their own sub-row of the slot
+    let count = weeks.len().max(1) as i32;
+    let mut owners = Vec::new();
+    layout.cells = weeks
+        .iter()
+        .enumerate()
+        .flat_map(|(i, week)| {
+            let cells =
+                SheetLayout::compute(week, config, icons, &slot_options, width, grid_height).cells;
+            owners.extend(std::iter::repeat_n(i, cells.len()));
+            cells.into_iter().map(move |mut cell| {
+                sub_row(&mut cell, i as i32, count, theme.cell_padding);
+                if let Some(name) = &week.student_name {
+                    cell.description = format!("{}: {}", name, cell.description);
+                }
+                cell
+            })
+        })
+        .collect();
+
+    let mut grid = draw_layout(&layout, config, &slot_options, "");
+    for (cell, &owner) in layout.cells.iter().zip(&owners) {
+        let strip = &cell.label_rect;
+        let size = theme
+            .class_code_font_size
+            .min(strip.width.min(strip.height) as f64 * 0.8);
+        let key = Text::new(keys[owner].as_str())
+            .set("x", strip.x + strip.width / 2)
+            .set("y", strip.y + strip.height / 2)
             .set("text-anchor", "middle")
             .set("dominant-baseline", "middle")
-            .set(
-                "font-family",
-                "'Bahnschrift SemiBold', Bahnschrift, Arial, sans-serif",
-            )
-            .set("font-size", "20px")
+            .set("font-family", theme.bold_font_family.as_str())
             .set("font-weight", "600")
-            .set("fill", fg_color);
-        group = group.add(text_class);
+            .set("font-size", format!("{}px", size))
+            .set("fill", cell.fg_color.as_str())
+            .set("aria-hidden", "true");
+        grid = grid.add(halo(key, cell, theme));
     }
+    document = document.add(grid.set("id", "household"));
 
-    // Add update date footer
-    let update_date = chrono::Local::now().format("%d %B %Y").to_string();
-    let text_update = Text::new(format!("Updated: {}", update_date).as_str())
-        .set("x", width - right_margin)
-        .set("y", height - 10)
-        .set("text-anchor", "end")
-        .set("class", "detail");
-    group = group.add(text_update);
+    let mut map_y = grid_height + options.map_margin;
+    if let Some((legend, legend_height)) =
+        draw_legend(weeks, config, options.margins.left, map_y, width)
+    {
+        document = document.add(legend);
+        map_y += legend_height;
+    }
 
-    group
+    if options.inline_styles {
+        inline_styles(&mut document, theme);
+    }
+
+    if options.show_map && !map_content.trim().is_empty() {
+        document = document.add(map_group(
+            map_content,
+            (0, map_y, width, height - map_y - options.map_margin),
+            &config.strings.school_map,
+        )?);
+    }
+
+    let mut svg_string = document.to_string();
+
+    if options.optimise {
+        svg_string = optimise_svg(&svg_string, OPTIMISE_PRECISION)?;
+    }
+    Ok(svg_string)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{Config, Mapping};
-    use crate::parser::Lesson;
-    use std::env;
+/// One-letter keys for the students in a household view.
+///
+/// The initial of each student's name, or "1", "2" and so on for everyone if
+/// a name is missing or two initials are the same.
+fn household_keys(weeks: &[Week]) -> Vec<String> {
+    let initials: Vec<Option<String>> = weeks
+        .iter()
+        .map(|week| {
+            week.student_name
+                .as_deref()
+                .and_then(|name| name.trim().chars().next())
+                .map(|c| c.to_uppercase().collect())
+        })
+        .collect();
+    let distinct = initials.iter().enumerate().all(|(i, initial)| {
+        initial
+            .as_ref()
+            .is_some_and(|initial| !initials[..i].contains(&Some(initial.clone())))
+    });
+    if distinct {
+        initials.into_iter().flatten().collect()
+    } else {
+        (1..=weeks.len()).map(|i| i.to_string()).collect()
+    }
+}
 
-    fn sample_week() -> Week {
-        let lessons = vec![
-            Lesson {
-                subject: "Maths".into(),
-                room: "MA3".into(),
-                teacher: "Ms Test A".into(),
-                class_code: "MA3".into(),
-                day_index: 0,
-                period_index: 1,
-            },
-            Lesson {
-                subject: "Science".into(),
-                room: "SC8".into(),
-                teacher: "Mr Test B".into(),
-                class_code: "SC8".into(),
-                day_index: 1,
-                period_index: 2,
-            },
-        ];
+/// Shrink a cell into sub-row `index` of `count`, keeping its strip in proportion.
+fn sub_row(cell: &mut LessonCell, index: i32, count: i32, spacing: i32) {
+    let top = cell.rect.y.min(cell.label_rect.y);
+    let total =
+        (cell.rect.y + cell.rect.height).max(cell.label_rect.y + cell.label_rect.height) - top;
+    let height = (total - spacing * (count - 1)) / count;
+    for rect in [&mut cell.rect, &mut cell.label_rect] {
+        rect.y = top + index * (height + spacing) + (rect.y - top) * height / total;
+        rect.height = rect.height * height / total;
+    }
+}
 
-        Week {
-            lessons,
-            week_name: "Week Test".into(),
-            student_name: Some("Test Student".into()),
-            form: Some("9X1".into()),
+/// `<defs>` holding the symbol of every available icon used in `weeks`, if any.
+fn icon_definitions<'a>(
+    weeks: impl IntoIterator<Item = &'a Week>,
+    config: &Config,
+    icons: &IconSet,
+) -> Option<Definitions> {
+    let mut used: Vec<&str> = Vec::new();
+    for lesson in weeks.into_iter().flat_map(|week| &week.lessons) {
+        if let Some(id) = config.icon_for_lesson(lesson) {
+            if icons.contains(id) && !used.contains(&id) {
+                used.push(id);
+            }
         }
     }
+    if used.is_empty() {
+        return None;
+    }
+    let mut defs = Definitions::new();
+    for symbol in used.into_iter().filter_map(|id| icons.symbol(id)) {
+        defs = defs.add(symbol);
+    }
+    Some(defs)
+}
 
-    #[test]
-    fn render_timetable_generates_svg_with_expected_content() {
-        let cfg = Config {
-            mappings: vec![
-                Mapping {
-                    prefix: "MA".into(),
-                    bg_color: "#fcdcd8".into(),
-                    fg_color: "#e8a490".into(),
-                    map_id: "Maths_Rooms".into(),
-                    label: Some("Maths".into()),
-                },
-                Mapping {
-                    prefix: "SC".into(),
-                    bg_color: "#fad7e6".into(),
-                    fg_color: "#e68cb8".into(),
-                    map_id: "Science_Rooms".into(),
-                    label: Some("Science".into()),
-                },
-            ],
-            overrides: vec![],
-        };
+/// Icon drawn before the subject, with its top at `y` and sized to the subject font.
+fn subject_icon(id: &str, x: i32, y: i32, theme: &Theme) -> Use {
+    let size = theme.subject_font_size.round() as i32;
+    Use::new()
+        .set("href", format!("#{}", icon_ref(id)))
+        .set("x", x)
+        .set("y", y)
+        .set("width", size)
+        .set("height", size)
+        .set("fill", theme.text_color.as_str())
+        .set("aria-hidden", "true")
+}
 
-        let map_svg = "<svg><g id=\"Maths_Rooms\"><path d=\"M0\"/></g><g id=\"Science_Rooms\"><path d=\"M0\"/></g></svg>";
-        let week = sample_week();
+/// Create the root document with the page background and theme styles.
+fn start_document(config: &Config, options: &RenderOptions) -> Result<Document, RenderError> {
+    let theme = &config.theme;
+    let width = options.width_px();
+    let height = options.height_px();
 
-        let mut out_path = env::temp_dir();
-        out_path.push("timetable_test_output.svg");
+    let document = Document::new()
+        .set("viewBox", (0, 0, width, height))
+        .set("width", format!("{}mm", options.sheet_width_mm()))
+        .set("height", format!("{}mm", options.sheet_height_mm()));
 
-        let res = render_timetable(&week, &cfg, map_svg, &out_path);
-        assert!(res.is_ok());
+    // Add background rectangle for the entire page
+    let background = Rectangle::new()
+        .set("x", 0)
+        .set("y", 0)
+        .set("width", width)
+        .set("height", height)
+        .set("fill", theme.page_color.as_str());
 
-        let content = std::fs::read_to_string(&out_path).expect("output svg exists");
-        // basic checks: student name, one subject, and a room label
-        assert!(content.contains("Test Student"));
-        assert!(content.contains("Maths"));
-        assert!(content.contains("MA3"));
+    // Inject styles built from the configured theme, after any embedded fonts
+    let mut styles = String::new();
+    for font in &config.fonts {
+        styles.push_str(&font_face_rule(font)?);
+    }
+    if !options.inline_styles {
+        styles.push_str(&build_styles(theme));
+    }
+    let mut defs = svg::node::element::Definitions::new();
+    if !styles.is_empty() {
+        defs = defs.add(svg::node::element::Style::new(styles));
+    }
 
-        // cleanup
-        let _ = std::fs::remove_file(&out_path);
+    // Hatch patterns standing in for department colours in mono themes
+    if theme.patterns {
+        for hatch in hatch_patterns() {
+            defs = defs.add(hatch);
+        }
+    }
+
+    // Faint branding image over the page colour, beneath everything else
+    let mut layer: Option<Box<dyn Node>> = None;
+    if let Some(image) = &config.background {
+        let href = image_data_uri(&image.path)?;
+        layer = Some(match image.tile {
+            Some(tile) => {
+                defs = defs.add(
+                    svg::node::element::Pattern::new()
+                        .set("id", "background-pattern")
+                        .set("patternUnits", "userSpaceOnUse")
+                        .set("width", tile)
+                        .set("height", tile)
+                        .add(
+                            Image::new()
+                                .set("width", tile)
+                                .set("height", tile)
+                                .set("href", href),
+                        ),
+                );
+                Rectangle::new()
+                    .set("id", "background")
+                    .set("aria-hidden", "true")
+                    .set("width", width)
+                    .set("height", height)
+                    .set("fill", "url(#background-pattern)")
+                    .set("opacity", image.opacity)
+                    .into()
+            }
+            None => Image::new()
+                .set("id", "background")
+                .set("aria-hidden", "true")
+                .set("width", width)
+                .set("height", height)
+                .set("preserveAspectRatio", "xMidYMid slice")
+                .set("opacity", image.opacity)
+                .set("href", href)
+                .into(),
+        });
+    }
+
+    let mut document = document.add(background).add(defs);
+    if let Some(layer) = layer {
+        document = document.add(layer);
+    }
+    Ok(document)
+}
+
+/// Read a font file and build an `@font-face` rule embedding it as a data URI.
+fn font_face_rule(font: &FontFace) -> Result<String, RenderError> {
+    let extension = font
+        .path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let (mime, format) = match extension.as_str() {
+        "ttf" => ("font/ttf", "truetype"),
+        "otf" => ("font/otf", "opentype"),
+        "woff" => ("font/woff", "woff"),
+        "woff2" => ("font/woff2", "woff2"),
+        _ => {
+            return Err(RenderError::UnsupportedFont(
+                font.path.display().to_string(),
+            ))
+        }
+    };
+
+    let bytes = fs::read(&font.path)?;
+    Ok(format!(
+        "\n        @font-face {{ font-family: '{}'; font-weight: {}; src: url(\"data:{};base64,{}\") format(\"{}\"); }}",
+        font.family,
+        font.weight,
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes),
+        format
+    ))
+}
+
+/// Position the map in `area` as `(x, y, width, height)`, labelled for screen readers.
+fn map_group(
+    map_content: &str,
+    area: (i32, i32, i32, i32),
+    label: &str,
+) -> Result<Group, RenderError> {
+    let (x, y, width, height) = area;
+    let mut viewport = svg::node::element::Element::new("svg");
+    viewport.assign("x", x);
+    viewport.assign("y", y);
+    viewport.assign("width", width);
+    viewport.assign("height", height);
+    let mut map = map_element(map_content)?;
+    fit_map(&mut map, width as f64, height as f64);
+    viewport.append(map);
+    Ok(Group::new()
+        .set("role", "img")
+        .set("aria-label", label)
+        .add(viewport))
+}
+
+/// Size the map's root element to fill `width` × `height` at its own aspect
+/// ratio, centred, so it is neither stretched nor clipped.
+///
+/// The aspect ratio comes from the `viewBox`, or from `width` and `height`
+/// when there is none, in which case a matching `viewBox` is added so the
+/// drawing scales. A map with `preserveAspectRatio="none"` is stretched to
+/// fill the area, and one with neither is left at its natural size.
+fn fit_map(map: &mut CopiedElement, width: f64, height: f64) {
+    let attributes = map.get_attributes();
+    let has_view_box = attributes.contains_key("viewBox");
+    let stretch = attributes
+        .get("preserveAspectRatio")
+        .is_some_and(|p| p.trim() == "none");
+
+    let Some((map_width, map_height)) = map_size(map) else {
+        return;
+    };
+    if !has_view_box {
+        map.assign("viewBox", format!("0 0 {} {}", map_width, map_height));
+    }
+
+    let (fitted_width, fitted_height) = if stretch {
+        (width, height)
+    } else {
+        let scale = (width / map_width).min(height / map_height);
+        (map_width * scale, map_height * scale)
+    };
+    let round = |n: f64| (n * 100.0).round() / 100.0;
+    map.assign("x", round((width - fitted_width) / 2.0));
+    map.assign("y", round((height - fitted_height) / 2.0));
+    map.assign("width", round(fitted_width));
+    map.assign("height", round(fitted_height));
+}
+
+/// Natural size of a map's root element, from its `viewBox` or else its
+/// `width` and `height`.
+fn map_size(map: &CopiedElement) -> Option<(f64, f64)> {
+    let attributes = map.get_attributes();
+    let view_box = attributes.get("viewBox").and_then(|v| {
+        let numbers: Vec<f64> = v
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|n| !n.is_empty())
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        match numbers[..] {
+            [_, _, w, h] if w > 0.0 && h > 0.0 => Some((w, h)),
+            _ => None,
+        }
+    });
+    let declared = attributes
+        .get("width")
+        .and_then(|w| parse_length(w))
+        .zip(attributes.get("height").and_then(|h| parse_length(h)));
+    view_box.or(declared)
+}
+
+/// Place the maps of a multi-site school side by side in one SVG, each scaled
+/// to the height of the tallest, with a small gap between them.
+///
+/// The result can be passed to the renderers like a single map. One map is
+/// returned unchanged and no maps give an empty string, which renders no map.
+/// Maps without a size are given a square. Each site's ids start with
+/// `site-1-`, `site-2-`… (see [`map_element_with_id_prefix`]), so one site's
+/// stylesheet rules and patterns never reach another's shapes.
+///
+/// # Errors
+///
+/// Returns [`RenderError::Map`] if a map is not well-formed XML.
+pub fn combine_maps(maps: &[String]) -> Result<String, RenderError> {
+    match maps {
+        [] => return Ok(String::new()),
+        [map] => return Ok(map.clone()),
+        _ => {}
+    }
+    let elements = maps
+        .iter()
+        .enumerate()
+        .map(|(i, map)| map_element_with_id_prefix(map, &format!("site-{}-", i + 1)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sizes: Vec<Option<(f64, f64)>> = elements.iter().map(map_size).collect();
+    let height = sizes
+        .iter()
+        .flatten()
+        .map(|&(_, h)| h)
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let round = |n: f64| (n * 100.0).round() / 100.0;
+
+    let mut combined = svg::node::element::Element::new("svg");
+    combined.assign("xmlns", "http://www.w3.org/2000/svg");
+    let mut x = 0.0;
+    for (mut map, size) in elements.into_iter().zip(sizes) {
+        let width = size.map_or(height, |(w, h)| w * height / h);
+        fit_map(&mut map, width, height);
+        let mut viewport = svg::node::element::Element::new("svg");
+        viewport.assign("x", round(x));
+        viewport.assign("width", round(width));
+        viewport.assign("height", round(height));
+        viewport.append(map);
+        combined.append(viewport);
+        x += width + height * MAP_GAP;
+    }
+    let width = x - height * MAP_GAP;
+    combined.assign("viewBox", format!("0 0 {} {}", round(width), round(height)));
+    Ok(combined.to_string())
+}
+
+/// Gap between combined maps, as a fraction of their height.
+const MAP_GAP: f64 = 0.05;
+
+/// Parse an SVG length such as `800`, `800px` or `210mm` in user units (px).
+///
+/// Percentages have no intrinsic size and give `None`.
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%');
+    let per_unit = match &value[number.len()..] {
+        "" | "px" => 1.0,
+        "mm" => PX_PER_MM,
+        "cm" => PX_PER_MM * 10.0,
+        "in" => 96.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| *n > 0.0)
+        .map(|n| n * per_unit)
+}
+
+/// `<pattern>` definitions for [`HATCHES`], with IDs `hatch-0` to `hatch-7`.
+pub(crate) fn hatch_patterns() -> Vec<svg::node::element::Pattern> {
+    HATCHES
+        .iter()
+        .enumerate()
+        .map(|(i, (size, path, filled, _))| {
+            let hatch = svg::node::element::Path::new()
+                .set("d", *path)
+                .set("stroke", "#000000")
+                .set("stroke-width", 1)
+                .set("fill", if *filled { "#000000" } else { "none" });
+            svg::node::element::Pattern::new()
+                .set("id", format!("hatch-{}", i))
+                .set("patternUnits", "userSpaceOnUse")
+                .set("width", *size)
+                .set("height", *size)
+                .add(hatch)
+        })
+        .collect()
+}
+
+/// Index into [`HATCHES`] of the pattern standing in for `mapping`'s department.
+///
+/// Departments are numbered by the first appearance of their map ID in the
+/// configuration, so every sheet uses the same pattern for a department.
+pub(crate) fn hatch_index(config: &Config, mapping: &Mapping) -> usize {
+    let mut departments: Vec<&str> = Vec::new();
+    for m in &config.mappings {
+        if !departments.contains(&m.map_id.as_str()) {
+            departments.push(&m.map_id);
+        }
+    }
+    let index = departments
+        .iter()
+        .position(|id| *id == mapping.map_id)
+        .unwrap_or(0);
+    index % HATCHES.len()
+}
+
+/// Fill for `mapping`'s department: its colour, or a hatch pattern reference
+/// (e.g. `url(#hatch-2)`) when the theme uses patterns.
+///
+/// Use it for map highlights so the map matches the sheet.
+pub fn department_fill(config: &Config, mapping: &Mapping) -> String {
+    if config.theme.patterns {
+        format!("url(#hatch-{})", hatch_index(config, mapping))
+    } else {
+        mapping.bg_color.clone()
+    }
+}
+
+/// Fill for a lesson's department strip: its hatch pattern or its colour.
+fn strip_fill(cell: &LessonCell) -> String {
+    match cell.pattern {
+        Some(i) => format!("url(#hatch-{})", i),
+        None => cell.bg_color.clone(),
+    }
+}
+
+/// Outline `text` in the cell colour when it sits on a hatch pattern, so the
+/// lines do not run through the letters.
+fn halo(text: Text, cell: &LessonCell, theme: &Theme) -> Text {
+    if cell.pattern.is_none() {
+        return text;
+    }
+    text.set("stroke", theme.cell_color.as_str())
+        .set("stroke-width", 4)
+        .set("paint-order", "stroke")
+}
+
+/// Draw a swatch-and-label legend for the departments used in `weeks`.
+///
+/// Entries flow left to right from `(x, y)` and wrap before `max_x`. Returns
+/// the legend group and its height, or `None` when no lesson has a mapping.
+fn draw_legend(
+    weeks: &[Week],
+    config: &Config,
+    x: i32,
+    y: i32,
+    max_x: i32,
+) -> Option<(Group, i32)> {
+    let mut used: Vec<&Mapping> = Vec::new();
+    for lesson in weeks.iter().flat_map(|w| &w.lessons) {
+        if let Some(mapping) = config.get_style_for_room(&lesson.room) {
+            if !used.iter().any(|m| m.map_id == mapping.map_id) {
+                used.push(mapping);
+            }
+        }
+    }
+    if used.is_empty() {
+        return None;
+    }
+
+    let theme = &config.theme;
+    let swatch = 12;
+    let item_width = 120;
+    let line_height = 20;
+
+    let mut group = Group::new().set("id", "legend");
+    let (mut item_x, mut item_y) = (x, y);
+    for mapping in used {
+        if item_x + item_width > max_x && item_x > x {
+            item_x = x;
+            item_y += line_height;
+        }
+        let rect = Rectangle::new()
+            .set("x", item_x)
+            .set("y", item_y)
+            .set("width", swatch)
+            .set("height", swatch)
+            .set("fill", department_fill(config, mapping))
+            .set("stroke", theme.stroke_color.as_str())
+            .set("stroke-width", theme.stroke_width);
+        let label = mapping.label.as_deref().unwrap_or(&mapping.prefix);
+        let text = Text::new(label)
+            .set("x", item_x + swatch + 6)
+            .set("y", item_y + swatch - 2)
+            .set("class", "detail");
+        group = group.add(rect).add(text);
+        item_x += item_width;
+    }
+
+    Some((group, item_y - y + line_height))
+}
+
+/// Render a page containing only the school map, scaled to fit inside the margins.
+///
+/// Used as the optional map page of a combined PDF so the map can be read at a
+/// larger size than on each week's sheet.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, renderer::{render_map_page_to_string, RenderOptions}};
+///
+/// let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"></svg>"#;
+/// let svg = render_map_page_to_string(&Config::default(), map, &RenderOptions::default())?;
+/// assert!(svg.contains("id=\"map-page\""));
+/// # Ok::<(), timetable_core::renderer::RenderError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`RenderError::Map`] if the map is not well-formed XML.
+pub fn render_map_page_to_string(
+    config: &Config,
+    map_content: &str,
+    options: &RenderOptions,
+) -> Result<String, RenderError> {
+    let width = options.width_px();
+    let height = options.height_px();
+    let margins = &options.margins;
+
+    let background = Rectangle::new()
+        .set("x", 0)
+        .set("y", 0)
+        .set("width", width)
+        .set("height", height)
+        .set("fill", config.theme.page_color.as_str());
+    let map = map_group(
+        map_content,
+        (
+            margins.left,
+            margins.top,
+            width - margins.left - margins.right,
+            height - margins.top - margins.bottom,
+        ),
+        &config.strings.school_map,
+    )?;
+    let document = Document::new()
+        .set("viewBox", (0, 0, width, height))
+        .set("width", format!("{}mm", options.sheet_width_mm()))
+        .set("height", format!("{}mm", options.sheet_height_mm()))
+        .add(background)
+        .add(map.set("id", "map-page"));
+
+    Ok(document.to_string())
+}
+
+/// Render a single day of a timetable week to an SVG file.
+///
+/// See [`render_day_to_string`] for the layout. A path ending in `.svgz` is
+/// written gzip-compressed.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if the file cannot be written.
+pub fn render_day(
+    week: &Week,
+    day_index: usize,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let svg_string = render_day_to_string(week, day_index, config, icons, map_content, options)?;
+    save_svg(output_path, &svg_string)?;
+
+    Ok(())
+}
+
+/// Render a single day of a timetable week into any [`Write`] sink.
+///
+/// Produces the same document as [`render_day`], written to `writer` instead
+/// of a file.
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if writing fails.
+pub fn render_day_to_writer<W: Write>(
+    week: &Week,
+    day_index: usize,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<(), RenderError> {
+    let svg_string = render_day_to_string(week, day_index, config, icons, map_content, options)?;
+    write_svg(writer, &svg_string, false)
+}
+
+/// Render one day (0 = Monday) as a large-format strip of periods from top to
+/// bottom, with the map beneath.
+///
+/// Each lesson is a full-width card showing the subject, teacher and room in
+/// large type, so the sheet can be read from across a kitchen or shown on an
+/// e-ink display. Pass a map highlighted with only that day's departments so
+/// the map shows where the day takes the student.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, icons::IconSet, parser::Week};
+/// use timetable_core::renderer::{render_day_to_string, RenderOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let week = Week { week_name: "Week 1".into(), ..Default::default() };
+/// let options = RenderOptions::default();
+/// let svg = render_day_to_string(&week, 0, &Config::default(), &IconSet::default(), "", &options)?;
+/// assert!(svg.contains("Monday"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn render_day_to_string(
+    week: &Week,
+    day_index: usize,
+    config: &Config,
+    icons: &IconSet,
+    map_content: &str,
+    options: &RenderOptions,
+) -> Result<String, RenderError> {
+    let width = options.width_px();
+    let height = options.height_px();
+
+    // Double-size type for reading at a distance
+    let config = Config {
+        theme: config.theme.with_font_scale(2.0 * options.font_scale),
+        ..config.clone()
+    };
+    let theme = &config.theme;
+    let margins = options.margins;
+    let fonts = CellFonts::new(&config);
+
+    let mut document = start_document(&config, options)?;
+    if let Some(defs) = icon_definitions([&week.day(day_index)], &config, icons) {
+        document = document.add(defs);
+    }
+    let mut group = Group::new().set("id", "day").set("role", "group");
+
+    let vars = template_vars(week, &config, options.generation_time());
+    let header = fill_template(&config.templates.header, &vars);
+    if !header.is_empty() {
+        group = group.add(
+            Text::new(header)
+                .set("x", margins.left)
+                .set("y", 40)
+                .set("class", "header-text"),
+        );
+    }
+    let subheader = fill_template(&config.templates.subheader, &vars);
+    if !subheader.is_empty() {
+        group = group.add(
+            Text::new(subheader)
+                .set("x", margins.left)
+                .set("y", 60)
+                .set("class", "detail"),
+        );
+    }
+    let title = fill_template(&config.templates.title, &vars);
+    let day_title = if title.is_empty() {
+        config.strings.day(day_index)
+    } else {
+        format!("{} · {}", config.strings.day(day_index), title)
+    };
+    group = group
+        .set("aria-label", day_title.as_str())
+        .add(Title::new(day_title.as_str()));
+    group = group.add(
+        Text::new(day_title)
+            .set("x", width - margins.right)
+            .set("y", 40)
+            .set("text-anchor", "end")
+            .set("class", "week-label"),
+    );
+
+    let top = margins.top;
+    let bottom = options.timetable_height - margins.bottom;
+    let breaks = config.break_rows();
+    let gaps_height: i32 = breaks.iter().map(|(_, row)| gap_height(row)).sum();
+    let row_height = (bottom - top - gaps_height) / PERIOD_LABELS.len() as i32;
+    let card_width = width - margins.left - margins.right;
+    let strip_width = 90;
+    let padding = theme.cell_padding;
+
+    let mut y = top;
+    for period_idx in 0..PERIOD_LABELS.len() {
+        group = group.add(
+            Text::new(config.strings.period(period_idx))
+                .set("x", margins.left / 2)
+                .set("y", y + row_height / 2)
+                .set("dominant-baseline", "middle")
+                .set("class", "period-label"),
+        );
+
+        let lesson = week
+            .lessons
+            .iter()
+            .find(|l| l.day_index == day_index && l.period_index == period_idx);
+        if let Some(lesson) = lesson {
+            let cell = lesson_cell(
+                lesson,
+                &config,
+                &fonts,
+                icons,
+                margins.left,
+                y,
+                card_width,
+                row_height,
+            );
+            let strip_x = margins.left + card_width - padding - strip_width;
+            let (_, strip_corners) = cell_corners(LabelPosition::Right);
+            let card = cell_box(
+                &Rect {
+                    x: margins.left + padding,
+                    y: y + padding,
+                    width: card_width - padding * 2,
+                    height: row_height - padding * 2,
+                },
+                [true; 4],
+                &cell.fill_color,
+                theme,
+            );
+            let strip = cell_box(
+                &Rect {
+                    x: strip_x,
+                    y: y + padding,
+                    width: strip_width,
+                    height: row_height - padding * 2,
+                },
+                strip_corners,
+                &strip_fill(&cell),
+                theme,
+            );
+            let text_x = margins.left + padding + theme.text_padding * 2;
+            let baseline = y + row_height * 9 / 20;
+            // Any icon sits before the subject, which moves right to make room
+            let subject_x = text_x + cell.subject_x - cell.rect.x - theme.text_padding;
+            let subject = Text::new(cell.subject.as_str())
+                .set("x", subject_x)
+                .set("y", baseline)
+                .set("class", "subject");
+            let mut card_group = Group::new()
+                .set("id", cell_id(day_index, period_idx))
+                .set("role", "img")
+                .set("aria-label", cell.description.as_str())
+                .add(Title::new(cell.description.as_str()))
+                .add(card)
+                .add(strip)
+                .add(subject);
+            if let Some(icon) = &cell.icon {
+                let top = baseline - theme.subject_font_size.round() as i32 + 2;
+                card_group = card_group.add(subject_icon(icon, text_x, top, theme));
+            }
+            if let Some(teacher) = &cell.teacher {
+                card_group = card_group.add(
+                    Text::new(teacher.as_str())
+                        .set("x", text_x)
+                        .set("y", y + row_height * 4 / 5)
+                        .set("class", "detail"),
+                );
+            }
+            let room = cell.room.as_deref().unwrap_or(&cell.label);
+            card_group = card_group.add(halo(
+                Text::new(room)
+                    .set("x", strip_x + strip_width / 2)
+                    .set("y", y + row_height / 2)
+                    .set("text-anchor", "middle")
+                    .set("dominant-baseline", "middle")
+                    .set("font-family", theme.bold_font_family.as_str())
+                    .set("font-size", format!("{}px", theme.subject_font_size * 1.2))
+                    .set("font-weight", "600")
+                    .set("fill", cell.fg_color.as_str()),
+                &cell,
+                theme,
+            ));
+            group = group.add(card_group);
+        } else if options.show_free_periods {
+            let rect = Rect {
+                x: margins.left + padding,
+                y: y + padding,
+                width: card_width - padding * 2,
+                height: row_height - padding * 2,
+            };
+            group = group.add(free_period(&rect, &config.theme.cell_color, &config));
+        }
+        y += row_height;
+
+        for (_, row) in breaks.iter().filter(|(after, _)| *after == period_idx) {
+            let height = gap_height(row);
+            let gap = cell_box(
+                &Rect {
+                    x: margins.left + padding,
+                    y: y + padding,
+                    width: card_width - padding * 2,
+                    height: height - padding * 2,
+                },
+                [true; 4],
+                &theme.break_color,
+                theme,
+            );
+            let text = Text::new(row.label.as_str())
+                .set("x", margins.left + card_width / 2)
+                .set("y", y + height / 2)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-size", format!("{}px", theme.detail_font_size / 2.0))
+                .set("class", "detail");
+            group = group.add(gap).add(text);
+            y += height;
+        }
+    }
+    document = document.add(group);
+
+    if options.inline_styles {
+        inline_styles(&mut document, &config.theme);
+    }
+
+    if options.show_map && !map_content.trim().is_empty() {
+        let map_y = options.timetable_height + options.map_margin;
+        document = document.add(map_group(
+            map_content,
+            (0, map_y, width, height - map_y - options.map_margin),
+            &config.strings.school_map,
+        )?);
+    }
+
+    let mut svg_string = document.to_string();
+
+    if options.optimise {
+        svg_string = optimise_svg(&svg_string, OPTIMISE_PRECISION)?;
+    }
+    Ok(svg_string)
+}
+
+/// Strip leading whitespace and any `<?xml ... ?>` declaration from SVG content.
+#[cfg(feature = "templates")]
+fn strip_xml_prolog(content: &str) -> &str {
+    let content = content.trim_start_matches(|c| c != '<');
+    if content.starts_with("<?xml") {
+        if let Some(idx) = content.find("?>") {
+            return &content[idx + 2..];
+        }
+    }
+    content
+}
+
+/// Read an image file and encode it as a base64 `data:` URI.
+fn image_data_uri(path: &Path) -> Result<String, RenderError> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mime = match extension.as_str() {
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        _ => return Err(RenderError::UnsupportedImage(path.display().to_string())),
+    };
+
+    let bytes = fs::read(path)?;
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Build the embedded stylesheet from the theme.
+fn build_styles(theme: &Theme) -> String {
+    format!(
+        r#"
+        .detail {{
+            font-family: {light_font};
+            font-size: {detail_size}px;
+            font-weight: 300;
+            fill: {text};
+        }}
+
+        .subject {{
+            font-family: {font};
+            font-size: {subject_size}px;
+            font-weight: 400;
+            fill: {text};
+        }}
+
+        .room {{
+            font-family: {bold_font};
+            font-size: 18px;
+            font-weight: 600;
+            fill: {text};
+            text-anchor: middle;
+            dominant-baseline: middle;
+        }}
+
+        .label {{
+            font-family: {bold_font};
+            font-size: {detail_size}px;
+            font-weight: 600;
+            fill: {text};
+        }}
+
+        .box {{
+            fill: none;
+            stroke: {stroke};
+            stroke-width: {stroke_width};
+            stroke-miterlimit: 10;
+        }}
+
+        .period-label {{
+            font-family: {bold_font};
+            font-size: {period_size}px;
+            font-weight: 600;
+            fill: {text};
+            text-anchor: middle;
+        }}
+
+        .header-text {{
+            font-family: {font};
+            font-size: {header_size}px;
+            font-weight: 400;
+            fill: {text};
+        }}
+
+        .week-label {{
+            font-family: {bold_font};
+            font-size: {week_size}px;
+            font-weight: 600;
+            fill: {text};
+        }}
+    "#,
+        font = theme.font_family,
+        light_font = theme.light_font_family,
+        bold_font = theme.bold_font_family,
+        detail_size = theme.detail_font_size,
+        subject_size = theme.subject_font_size,
+        period_size = theme.period_font_size,
+        header_size = theme.header_font_size,
+        week_size = theme.week_font_size,
+        text = theme.text_color,
+        stroke = theme.stroke_color,
+        stroke_width = theme.stroke_width,
+    )
+}
+
+/// Write the theme's class rules, and any inline `style` declarations, onto
+/// each element of `document` as presentation attributes.
+///
+/// Class rules replace attributes of the same name, as they would in a
+/// stylesheet, so the result looks the same without the `<style>` element.
+fn inline_styles(document: &mut Document, theme: &Theme) {
+    let css = build_styles(theme);
+    let rules: Vec<(&str, Vec<(&str, &str)>)> = css
+        .split('}')
+        .filter_map(|rule| rule.split_once('{'))
+        .map(|(selector, body)| {
+            let class = selector.trim().trim_start_matches('.');
+            (class, declarations(body))
+        })
+        .collect();
+    inline_node(document, &rules);
+}
+
+/// Split a CSS declaration block into `(property, value)` pairs.
+fn declarations(body: &str) -> Vec<(&str, &str)> {
+    body.split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .map(|(property, value)| (property.trim(), value.trim()))
+        .collect()
+}
+
+/// Apply [`inline_styles`] to `node` and its descendants.
+fn inline_node(node: &mut dyn Node, rules: &[(&str, Vec<(&str, &str)>)]) {
+    if let Some(attributes) = node.get_attributes_mut() {
+        let classes = attributes
+            .get("class")
+            .map(|c| c.to_string())
+            .unwrap_or_default();
+        for class in classes.split_whitespace() {
+            if let Some((_, properties)) = rules.iter().find(|(name, _)| *name == class) {
+                for (property, value) in properties {
+                    attributes.insert(property.to_string(), (*value).into());
+                }
+            }
+        }
+        if let Some(style) = attributes.remove("style") {
+            for (property, value) in declarations(&style) {
+                attributes.insert(property.to_string(), value.into());
+            }
+        }
+    }
+    if let Some(children) = node.get_children_mut() {
+        for child in children {
+            inline_node(child.as_mut(), rules);
+        }
+    }
+}
+
+/// Corners of the main cell area and the label strip to round, as
+/// `[top-left, top-right, bottom-right, bottom-left]`, so the pair reads as one card.
+fn cell_corners(position: LabelPosition) -> ([bool; 4], [bool; 4]) {
+    match position {
+        LabelPosition::Right => ([true, false, false, true], [false, true, true, false]),
+        LabelPosition::Left => ([false, true, true, false], [true, false, false, true]),
+        LabelPosition::Top => ([false, false, true, true], [true, true, false, false]),
+    }
+}
+
+/// Draw a filled, stroked box, rounding the given corners by `theme.corner_radius`.
+fn cell_box(rect: &Rect, corners: [bool; 4], fill: &str, theme: &Theme) -> Box<dyn Node> {
+    let radius = theme
+        .corner_radius
+        .min(rect.width as f64 / 2.0)
+        .min(rect.height as f64 / 2.0);
+    if radius <= 0.0 || corners.iter().all(|&c| c) {
+        let mut rectangle = Rectangle::new()
+            .set("x", rect.x)
+            .set("y", rect.y)
+            .set("width", rect.width)
+            .set("height", rect.height)
+            .set("fill", fill)
+            .set("stroke", theme.stroke_color.as_str())
+            .set("stroke-width", theme.stroke_width);
+        if radius > 0.0 {
+            rectangle = rectangle.set("rx", radius);
+        }
+        return Box::new(rectangle);
+    }
+
+    let r = |corner: usize| if corners[corner] { radius } else { 0.0 };
+    let (x, y) = (rect.x as f64, rect.y as f64);
+    let (right, bottom) = (x + rect.width as f64, y + rect.height as f64);
+    let data = Data::new()
+        .move_to((x + r(0), y))
+        .line_to((right - r(1), y))
+        .elliptical_arc_to((r(1), r(1), 0, 0, 1, right, y + r(1)))
+        .line_to((right, bottom - r(2)))
+        .elliptical_arc_to((r(2), r(2), 0, 0, 1, right - r(2), bottom))
+        .line_to((x + r(3), bottom))
+        .elliptical_arc_to((r(3), r(3), 0, 0, 1, x, bottom - r(3)))
+        .line_to((x, y + r(0)))
+        .elliptical_arc_to((r(0), r(0), 0, 0, 1, x + r(0), y))
+        .close();
+    Box::new(
+        svg::node::element::Path::new()
+            .set("d", data)
+            .set("fill", fill)
+            .set("stroke", theme.stroke_color.as_str())
+            .set("stroke-width", theme.stroke_width),
+    )
+}
+
+/// Draw an empty slot as a dashed cell labelled with the free-period text.
+fn free_period(rect: &Rect, fill: &str, config: &Config) -> Group {
+    let theme = &config.theme;
+    let cell = Rectangle::new()
+        .set("x", rect.x)
+        .set("y", rect.y)
+        .set("width", rect.width)
+        .set("height", rect.height)
+        .set("fill", fill)
+        .set("stroke", theme.stroke_color.as_str())
+        .set("stroke-width", theme.stroke_width)
+        .set("stroke-dasharray", "4 3");
+    let cell = if theme.corner_radius > 0.0 {
+        cell.set("rx", theme.corner_radius)
+    } else {
+        cell
+    };
+    let label = Text::new(config.strings.free_period.as_str())
+        .set("x", rect.x + rect.width / 2)
+        .set("y", rect.y + rect.height / 2)
+        .set("text-anchor", "middle")
+        .set("dominant-baseline", "middle")
+        .set("class", "detail");
+    Group::new()
+        .set("class", "free-period")
+        .add(cell)
+        .add(label)
+}
+
+fn draw_timetable_grid(
+    week: &Week,
+    config: &Config,
+    icons: &IconSet,
+    options: &RenderOptions,
+    width: i32,
+    height: i32,
+    id_prefix: &str,
+) -> Group {
+    let layout = SheetLayout::compute(week, config, icons, options, width, height);
+    draw_layout(&layout, config, options, id_prefix)
+}
+
+/// Id of the lesson drawn at `day_index` (0 = Monday) and `period_index`, e.g. "cell-mon-l3".
+///
+/// Ids stay the same across regenerations, so scripts and tests can find a
+/// lesson in any rendered sheet. In multi-week documents they are prefixed
+/// with the week, e.g. "week-2-cell-mon-l3".
+pub fn cell_id(day_index: usize, period_index: usize) -> String {
+    let day = DAY_IDS
+        .get(day_index)
+        .map_or_else(|| format!("day{}", day_index + 1), |day| day.to_string());
+    format!("cell-{}-{}", day, period_id(period_index))
+}
+
+/// Period key used in element ids, e.g. "l3".
+fn period_id(period_index: usize) -> String {
+    PERIOD_LABELS.get(period_index).map_or_else(
+        || format!("p{}", period_index),
+        |label| label.to_lowercase(),
+    )
+}
+
+/// Draw a computed [`SheetLayout`] as an SVG group.
+///
+/// Texts, break rows and lessons get stable ids such as `header`, `period-l3`,
+/// `break-1` and `cell-mon-l3`, each starting with `id_prefix` so several
+/// grids can share a document (e.g. `week-2-header`). A lesson id already
+/// used in the grid, as in the household view, gets a `-2`, `-3`… suffix.
+fn draw_layout(
+    layout: &SheetLayout,
+    config: &Config,
+    options: &RenderOptions,
+    id_prefix: &str,
+) -> Group {
+    let theme = &config.theme;
+    let id = |name: &str| format!("{}{}", id_prefix, name);
+
+    // Name the grid for screen readers, e.g. "Test Student (9X1), Week 1"
+    let label = [&layout.header.text, &layout.title.text]
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut group = Group::new().set("id", "timetable").set("role", "group");
+    if !label.is_empty() {
+        group = group
+            .set("aria-label", label.as_str())
+            .add(Title::new(label.as_str()));
+    }
+    let description = match layout.cells.len() {
+        1 => config.strings.sheet_description_one.clone(),
+        n => config
+            .strings
+            .sheet_description
+            .replace("{count}", &n.to_string()),
+    };
+    group = group.add(Description::new().add(svg::node::Text::new(description)));
+
+    // Add student name and form at top left
+    if !layout.header.text.is_empty() {
+        let text_student = Text::new(layout.header.text.as_str())
+            .set("x", layout.header.x)
+            .set("y", layout.header.y)
+            .set("id", id("header"))
+            .set("class", "header-text");
+        group = group.add(text_student);
+    }
+
+    // Year group and house beneath the student name
+    if !layout.subheader.text.is_empty() {
+        let text_details = Text::new(layout.subheader.text.as_str())
+            .set("x", layout.subheader.x)
+            .set("y", layout.subheader.y)
+            .set("id", id("subheader"))
+            .set("class", "detail");
+        group = group.add(text_details);
+    }
+
+    // Add week label at top center
+    if !layout.title.text.is_empty() {
+        let text_week = Text::new(layout.title.text.as_str())
+            .set("x", layout.title.x)
+            .set("y", layout.title.y)
+            .set("id", id("title"))
+            .set("text-anchor", "middle")
+            .set("class", "week-label");
+        group = group.add(text_week);
+    }
+
+    // Week date range beneath the week label
+    if !layout.dates.text.is_empty() {
+        let text_dates = Text::new(layout.dates.text.as_str())
+            .set("x", layout.dates.x)
+            .set("y", layout.dates.y)
+            .set("id", id("dates"))
+            .set("text-anchor", "middle")
+            .set("class", "detail");
+        group = group.add(text_dates);
+    }
+
+    // Draw day headers (Monday-Friday)
+    for (day, key) in layout.days.iter().zip(DAY_IDS) {
+        let text = Text::new(day.text.as_str())
+            .set("x", day.x)
+            .set("y", day.y)
+            .set("id", id(&format!("day-{}", key)))
+            .set("text-anchor", "middle")
+            .set("class", "header-text");
+        group = group.add(text);
+    }
+
+    // Zebra stripes behind alternate rows or columns
+    for rect in &layout.stripes {
+        let stripe = Rectangle::new()
+            .set("x", rect.x)
+            .set("y", rect.y)
+            .set("width", rect.width)
+            .set("height", rect.height)
+            .set("fill", theme.zebra_color.as_str())
+            .set("class", "stripe")
+            .set("aria-hidden", "true");
+        group = group.add(stripe);
+    }
+
+    // Period labels on the left, each followed by any break or lunch row
+    for (period_idx, period) in layout.periods.iter().enumerate() {
+        let text_period = Text::new(period.text.as_str())
+            .set("x", period.x)
+            .set("y", period.y)
+            .set("id", id(&format!("period-{}", period_id(period_idx))))
+            .set("dominant-baseline", "middle")
+            .set("class", "period-label");
+        group = group.add(text_period);
+
+        for (i, gap) in layout
+            .gaps
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.after_period == period_idx)
+        {
+            let mut gap_group = Group::new()
+                .set("id", id(&format!("break-{}", i + 1)))
+                .add(cell_box(&gap.rect, [true; 4], &theme.break_color, theme));
+
+            // Every label is centred in its row, a pixel low so it looks centred
+            let text_y = gap.rect.y + gap.rect.height / 2 + 1;
+            let text_gap = Text::new(gap.label.as_str())
+                .set(
+                    "x",
+                    gap.rect.x - theme.cell_padding + (layout.col_width * 5 / 2),
+                )
+                .set("y", text_y)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("class", "detail");
+            gap_group = gap_group.add(text_gap);
+            group = group.add(gap_group);
+        }
+    }
+
+    // Free periods as dashed, labelled cells
+    for rect in &layout.free_periods {
+        let fill = if layout.stripes.iter().any(|stripe| stripe.contains(rect)) {
+            &theme.zebra_color
+        } else {
+            &theme.cell_color
+        };
+        group = group.add(free_period(rect, fill, config));
+    }
+
+    // Draw lessons
+    let mut cell_ids = std::collections::HashSet::new();
+    for cell in &layout.cells {
+        // Top and height of the text area, as if it filled the row
+        let y = cell.rect.y - theme.cell_padding;
+        let row_height = cell.rect.height + theme.cell_padding * 2;
+        let cell_padding = theme.cell_padding;
+        let text_x = cell.text_x;
+
+        let base_id = id(&cell_id(cell.day_index, cell.period_index));
+        let cell_id = (1..)
+            .map(|n| match n {
+                1 => base_id.clone(),
+                n => format!("{}-{}", base_id, n),
+            })
+            .find(|candidate| cell_ids.insert(candidate.clone()))
+            .unwrap_or(base_id);
+
+        // Each lesson is announced as one image by screen readers
+        let mut cell_group = Group::new()
+            .set("id", cell_id)
+            .set("role", "img")
+            .set("aria-label", cell.description.as_str())
+            .add(Title::new(cell.description.as_str()));
+
+        // Main cell area (white background)
+        let (main_corners, label_corners) = cell_corners(theme.label_position);
+        cell_group = cell_group.add(cell_box(&cell.rect, main_corners, &cell.fill_color, theme));
+
+        // Label strip (colored background)
+        cell_group = cell_group.add(cell_box(
+            &cell.label_rect,
+            label_corners,
+            &strip_fill(cell),
+            theme,
+        ));
+
+        if options.compact {
+            // Abbreviated subject above the room code, centred vertically
+            let subject = abbreviate_subject(&cell.subject);
+            if let Some(icon) = &cell.icon {
+                let top = y + (row_height / 2) - theme.subject_font_size.round() as i32 + 2;
+                cell_group = cell_group.add(subject_icon(icon, text_x, top, theme));
+            }
+            let text_subject = Text::new(subject)
+                .set("x", cell.subject_x)
+                .set("y", y + (row_height / 2))
+                .set("class", "subject")
+                .set("font-weight", "bold");
+            cell_group = cell_group.add(text_subject);
+
+            if let Some(room) = &cell.room {
+                let text_room = Text::new(room.as_str())
+                    .set("x", text_x)
+                    .set(
+                        "y",
+                        y + (row_height / 2) + (theme.detail_font_size * 1.3) as i32,
+                    )
+                    .set("class", "detail");
+                cell_group = cell_group.add(text_room);
+            }
+            for badge in &cell.badges {
+                cell_group = cell_group.add(draw_badge(badge, theme));
+            }
+            group = group.add(cell_group);
+            continue;
+        }
+
+        // Text: Subject (top left, bold), wrapped onto several lines if long
+        let mut subject_texts = Vec::new();
+        let first_baseline = if cell.subject_lines.len() > 1 {
+            y + cell_padding + 12
+        } else {
+            y + cell_padding + 14
+        };
+        if cell.subject_lines.len() > 1 {
+            for (line_idx, line) in cell.subject_lines.iter().enumerate() {
+                subject_texts.push(Text::new(line.as_str()).set("x", cell.subject_x).set(
+                    "y",
+                    first_baseline + (line_idx as f64 * cell.subject_font_size) as i32,
+                ));
+            }
+        } else {
+            subject_texts.push(
+                Text::new(cell.subject.as_str())
+                    .set("x", cell.subject_x)
+                    .set("y", first_baseline),
+            );
+        }
+        if let Some(icon) = &cell.icon {
+            let top = first_baseline - theme.subject_font_size.round() as i32 + 2;
+            cell_group = cell_group.add(subject_icon(icon, text_x, top, theme));
+        }
+        for text_subject in subject_texts {
+            let mut text_subject = text_subject
+                .set("class", "subject")
+                .set("font-weight", "bold");
+            // Subjects shrunk to fit the cell override the stylesheet size inline,
+            // as class rules take precedence over presentation attributes
+            if cell.subject_font_size < theme.subject_font_size {
+                text_subject =
+                    text_subject.set("style", format!("font-size: {}px", cell.subject_font_size));
+            }
+            if cell.subject_truncated {
+                text_subject = text_subject.add(Title::new(cell.subject.as_str()));
+            }
+            cell_group = cell_group.add(text_subject);
+        }
+
+        // Text: Note (above room, small italic) - only if an override added one
+        if let (Some(note), Some(note_line)) = (&cell.note, &cell.note_line) {
+            let mut text_note = Text::new(note_line.as_str())
+                .set("x", text_x)
+                .set("y", y + row_height - cell_padding - 36)
+                .set("class", "detail")
+                .set("font-size", format!("{}px", theme.teacher_font_size))
+                .set("font-style", "italic");
+            if note_line != note {
+                text_note = text_note.add(Title::new(note.as_str()));
+            }
+            cell_group = cell_group.add(text_note);
+        }
+
+        // Text: Room code (above teacher) - only if not Unknown
+        if let Some(room) = &cell.room {
+            let text_room = Text::new(room.as_str())
+                .set("x", text_x)
+                .set("y", y + row_height - cell_padding - 22)
+                .set("class", "detail");
+            cell_group = cell_group.add(text_room);
+        }
+
+        // Text: Teacher (bottom, smaller text) - only if not Unknown
+        if let Some(teacher_line) = &cell.teacher_line {
+            let mut text_teacher = Text::new(teacher_line.as_str())
+                .set("x", text_x)
+                .set("y", y + row_height - cell_padding - 8)
+                .set("class", "detail")
+                .set("font-size", format!("{}px", theme.teacher_font_size));
+            // Hovering a shortened name in a browser shows it in full
+            if let Some(teacher) = cell.teacher.as_ref().filter(|t| *t != teacher_line) {
+                text_teacher = text_teacher.add(Title::new(teacher.as_str()));
+            }
+            cell_group = cell_group.add(text_teacher);
+        }
+
+        // Text: Class code in the strip (large font, saturated color), rotated in side strips
+        let (class_x, class_y, angle) = match theme.label_position {
+            LabelPosition::Top => (
+                cell.label_rect.x + (cell.label_rect.width / 2),
+                cell.label_rect.y + (cell.label_rect.height / 2),
+                0,
+            ),
+            LabelPosition::Left | LabelPosition::Right => {
+                let (angle, nudge) = match theme.label_rotation {
+                    LabelRotation::Clockwise => (90, -2),
+                    LabelRotation::Counterclockwise => (-90, 2),
+                };
+                (
+                    cell.label_rect.x + (cell.label_rect.width / 2) + nudge,
+                    cell.label_rect.y + (cell.label_rect.height / 2),
+                    angle,
+                )
+            }
+        };
+        let label_size = label_font_size(theme, &cell.label_rect, cell.label_room.is_some());
+        // With a room stacked beneath, the pair is centred as a block, then
+        // rotated together so "beneath" follows the reading direction
+        let room_size = theme.teacher_font_size;
+        let class_offset = match cell.label_room {
+            Some(_) => -((room_size + 1.0) / 2.0).round() as i32,
+            None => 0,
+        };
+        let mut text_class = Text::new(cell.label.as_str())
+            .set("x", class_x)
+            .set("y", class_y + class_offset);
+        if angle != 0 {
+            text_class = text_class.set(
+                "transform",
+                format!("rotate({} {} {})", angle, class_x, class_y),
+            );
+        }
+        let text_class = text_class
+            .set("font-size", format!("{}px", label_size))
+            .set("text-anchor", "middle")
+            .set("dominant-baseline", "middle")
+            .set("font-family", theme.bold_font_family.as_str())
+            .set("font-weight", "600")
+            .set("fill", cell.fg_color.as_str());
+        cell_group = cell_group.add(halo(text_class, cell, theme));
+        if let Some(room) = &cell.label_room {
+            let room_y = class_y + ((label_size + 1.0) / 2.0).round() as i32;
+            let mut text_room = Text::new(room.as_str()).set("x", class_x).set("y", room_y);
+            if angle != 0 {
+                text_room = text_room.set(
+                    "transform",
+                    format!("rotate({} {} {})", angle, class_x, class_y),
+                );
+            }
+            let text_room = text_room
+                .set("font-size", format!("{}px", room_size))
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-family", theme.light_font_family.as_str())
+                .set("fill", cell.fg_color.as_str());
+            cell_group = cell_group.add(halo(text_room, cell, theme));
+        }
+        for badge in &cell.badges {
+            cell_group = cell_group.add(draw_badge(badge, theme));
+        }
+        group = group.add(cell_group);
+    }
+
+    // Tint the highlighted day over its lessons
+    if let Some(rect) = &layout.highlight {
+        let highlight = Rectangle::new()
+            .set("id", "highlight-day")
+            .set("x", rect.x)
+            .set("y", rect.y)
+            .set("width", rect.width)
+            .set("height", rect.height)
+            .set("fill", theme.highlight_color.as_str())
+            .set("fill-opacity", 0.3);
+        group = group.add(highlight);
+    }
+
+    // Add footer (update date by default)
+    if !layout.footer.text.is_empty() {
+        let text_update = Text::new(layout.footer.text.as_str())
+            .set("x", layout.footer.x)
+            .set("y", layout.footer.y)
+            .set("id", id("footer"))
+            .set("text-anchor", "end")
+            .set("class", "detail");
+        group = group.add(text_update);
+    }
+
+    group
+}
+
+/// Draw a reminder badge as a rounded pill with centred text.
+fn draw_badge(badge: &Badge, theme: &Theme) -> Group {
+    let rect = &badge.rect;
+    Group::new()
+        .set("class", "badge")
+        .set("aria-hidden", "true")
+        .add(
+            Rectangle::new()
+                .set("x", rect.x)
+                .set("y", rect.y)
+                .set("width", rect.width)
+                .set("height", rect.height)
+                .set("rx", rect.height as f64 / 2.0)
+                .set("fill", badge.color.as_str()),
+        )
+        .add(
+            Text::new(badge.text.as_str())
+                .set("x", rect.x + rect.width / 2)
+                .set("y", rect.y + rect.height / 2)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-family", theme.bold_font_family.as_str())
+                .set("font-weight", "600")
+                .set("font-size", format!("{}px", BADGE_FONT_SIZE))
+                .set("fill", badge.text_color.as_str()),
+        )
+}
+
+/// Shorten a subject name for compact cells.
+///
+/// Multi-word subjects become their capitalised initials ("Design Technology"
+/// becomes "DT"); single words longer than five characters are cut to four
+/// ("Mathematics" becomes "Math").
+fn abbreviate_subject(subject: &str) -> String {
+    let words: Vec<&str> = subject.split_whitespace().collect();
+    match words.as_slice() {
+        [] => String::new(),
+        [word] if word.chars().count() > 5 => word.chars().take(4).collect(),
+        [word] => word.to_string(),
+        _ => words
+            .iter()
+            .filter_map(|w| w.chars().next())
+            .flat_map(char::to_uppercase)
+            .collect(),
+    }
+}
+
+/// Collect the template variables describing a week generated at `now`.
+pub(crate) fn template_vars(
+    week: &Week,
+    config: &Config,
+    now: chrono::DateTime<chrono::Local>,
+) -> Vec<(&'static str, String)> {
+    let student = if let (Some(name), Some(form)) = (&week.student_name, &week.form) {
+        format!("{} ({})", name, form)
+    } else if let Some(name) = &week.student_name {
+        name.clone()
+    } else {
+        config.strings.student_timetable.clone()
+    };
+    let details = [&week.year_group, &week.house]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" · ");
+    // The department with the most lessons, e.g. for the header of a teacher sheet
+    let mut departments: Vec<(&str, usize)> = Vec::new();
+    for mapping in week
+        .lessons
+        .iter()
+        .filter_map(|lesson| config.get_style_for_room(&lesson.room))
+    {
+        let name = mapping.label.as_deref().unwrap_or(&mapping.prefix);
+        match departments.iter_mut().find(|(d, _)| *d == name) {
+            Some((_, count)) => *count += 1,
+            None => departments.push((name, 1)),
+        }
+    }
+    let department = departments
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default();
+    let week_number: String = week
+        .week_name
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect();
+
+    vec![
+        ("student", student),
+        ("name", week.student_name.clone().unwrap_or_default()),
+        ("form", week.form.clone().unwrap_or_default()),
+        ("year", week.year_group.clone().unwrap_or_default()),
+        ("house", week.house.clone().unwrap_or_default()),
+        ("details", details),
+        ("department", department),
+        ("week", week_number),
+        ("week_name", week.week_name.clone()),
+        (
+            "dates",
+            week.starts_on
+                .map(|starts| config.strings.week_range(starts))
+                .unwrap_or_default(),
+        ),
+        (
+            "date",
+            format_date(now, &config.templates.date_format, &config.strings),
+        ),
+        ("time", now.format("%H:%M").to_string()),
+        ("source", week.source.clone().unwrap_or_default()),
+        ("updated", config.strings.updated.clone()),
+    ]
+}
+
+/// Format a date, substituting `%B` with the localised month name.
+///
+/// A format chrono rejects (see [`Config::validate`]) is returned as it is, so
+/// the mistake shows on the sheet rather than aborting the render.
+fn format_date(date: chrono::DateTime<chrono::Local>, format: &str, strings: &Strings) -> String {
+    // Swap the name specifiers for the locale's words before chrono sees the
+    // format, escaping any `%` in them so they are printed as written.
+    let mut localised = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localised.push(c);
+            continue;
+        }
+        let name = match chars.next() {
+            Some('B') => strings.month(date.month()),
+            Some('b') | Some('h') => strings.short_month(date.month()),
+            Some('A') => strings.weekday(date.weekday()),
+            Some('a') => strings.short_weekday(date.weekday()),
+            Some(other) => {
+                localised.push('%');
+                localised.push(other);
+                continue;
+            }
+            None => {
+                localised.push('%');
+                continue;
+            }
+        };
+        localised.push_str(&name.replace('%', "%%"));
+    }
+    let mut formatted = String::new();
+    match write!(formatted, "{}", date.format(&localised)) {
+        Ok(()) => formatted,
+        Err(_) => format.to_string(),
+    }
+}
+
+/// Replace `{name}` placeholders in a template with their values.
+///
+/// Unknown placeholders are left untouched so typos remain visible on the sheet.
+pub(crate) fn fill_template(template: &str, vars: &[(&str, String)]) -> String {
+    let mut result = template.to_string();
+    for (name, value) in vars {
+        result = result.replace(&format!("{{{}}}", name), value);
+    }
+    result.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Mapping};
+    use crate::parser::Lesson;
+    use std::env;
+
+    fn sample_week() -> Week {
+        let lessons = vec![
+            Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Ms Test A".into(),
+                class_code: "MA3".into(),
+                day_index: 0,
+                period_index: 1,
+                note: None,
+            },
+            Lesson {
+                subject: "Science".into(),
+                room: "SC8".into(),
+                teacher: "Mr Test B".into(),
+                class_code: "SC8".into(),
+                day_index: 1,
+                period_index: 2,
+                note: None,
+            },
+        ];
+
+        Week {
+            lessons,
+            week_name: "Week Test".into(),
+            student_name: Some("Test Student".into()),
+            form: Some("9X1".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_timetable_generates_svg_with_expected_content() {
+        let cfg = Config {
+            mappings: vec![
+                Mapping {
+                    prefix: "MA".into(),
+                    bg_color: "#fcdcd8".into(),
+                    fg_color: "#e8a490".into(),
+                    map_id: "Maths_Rooms".into(),
+                    label: Some("Maths".into()),
+                    ..Default::default()
+                },
+                Mapping {
+                    prefix: "SC".into(),
+                    bg_color: "#fad7e6".into(),
+                    fg_color: "#e68cb8".into(),
+                    map_id: "Science_Rooms".into(),
+                    label: Some("Science".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let map_svg = "<svg><g id=\"Maths_Rooms\"><path d=\"M0\"/></g><g id=\"Science_Rooms\"><path d=\"M0\"/></g></svg>";
+        let week = sample_week();
+
+        let mut out_path = env::temp_dir();
+        out_path.push("timetable_test_output.svg");
+
+        let res = render_timetable(
+            &week,
+            &cfg,
+            &IconSet::default(),
+            map_svg,
+            &RenderOptions::default(),
+            &out_path,
+        );
+        assert!(res.is_ok());
+
+        let content = std::fs::read_to_string(&out_path).expect("output svg exists");
+        // basic checks: student name, one subject, and a room label
+        assert!(content.contains("Test Student"));
+        assert!(content.contains("Maths"));
+        assert!(content.contains("MA3"));
+
+        // cleanup
+        let _ = std::fs::remove_file(&out_path);
+    }
+
+    #[test]
+    fn render_timetable_applies_theme() {
+        let mut cfg = Config::default();
+        cfg.theme.font_family = "Comic Neue, sans-serif".into();
+        cfg.theme.stroke_color = "#123456".into();
+
+        let mut out_path = env::temp_dir();
+        out_path.push(format!("timetable_theme_test_{}.svg", std::process::id()));
+
+        render_timetable(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let content = std::fs::read_to_string(&out_path).expect("output svg exists");
+        assert!(content.contains("font-family: Comic Neue, sans-serif;"));
+        assert!(content.contains("stroke=\"#123456\""));
+        assert!(!content.contains("stroke=\"#231f20\""));
+
+        let _ = std::fs::remove_file(&out_path);
+    }
+
+    #[test]
+    fn fill_template_substitutes_week_variables() {
+        let mut week = sample_week();
+        week.source = Some("timetable.pdf".into());
+        let now = chrono::Local::now();
+        let vars = template_vars(&week, &Config::default(), now);
+
+        assert_eq!(
+            fill_template("{student} - {week_name} ({week}) from {source}", &vars),
+            "Test Student (9X1) - Week Test () from timetable.pdf"
+        );
+        assert_eq!(fill_template("{unknown}", &vars), "{unknown}");
+    }
+
+    #[test]
+    fn year_group_and_house_drawn_beneath_header_when_known() {
+        let mut week = sample_week();
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(!svg.contains("Year 9"));
+
+        week.year_group = Some("Year 9".into());
+        let vars = template_vars(&week, &Config::default(), chrono::Local::now());
+        assert_eq!(fill_template("{details}", &vars), "Year 9");
+
+        week.house = Some("Austen".into());
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(svg.contains("Year 9 · Austen"));
+    }
+
+    #[test]
+    fn background_image_drawn_beneath_timetable() {
+        let image_path =
+            env::temp_dir().join(format!("timetable_background_{}.svg", std::process::id()));
+        std::fs::write(&image_path, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+
+        let mut background = crate::config::Background::new(&image_path);
+        let cfg = Config::builder().background(background.clone()).build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        let layer = svg
+            .find("id=\"background\"")
+            .expect("background layer drawn");
+        assert!(layer < svg.find("id=\"timetable\"").unwrap());
+        assert!(svg.contains("opacity=\"0.08\""));
+
+        background.tile = Some(60);
+        let cfg = Config::builder().background(background).build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(svg.contains("<pattern height=\"60\" id=\"background-pattern\""));
+        assert!(svg.contains("fill=\"url(#background-pattern)\""));
+
+        let _ = std::fs::remove_file(&image_path);
+    }
+
+    #[test]
+    fn mono_theme_hatches_department_strips() {
+        let config = Config {
+            mappings: vec![
+                Mapping::new("MA", "#fcdcd8", "Maths_Rooms"),
+                Mapping::new("SC", "#d8f0dc", "Science_Rooms"),
+            ],
+            theme: Theme::default().with_mono_colors(),
+            ..Default::default()
+        };
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("id=\"hatch-7\""));
+        assert!(svg.contains("fill=\"url(#hatch-0)\""));
+        assert!(svg.contains("fill=\"url(#hatch-1)\""));
+        assert!(!svg.contains("#fcdcd8"));
+        assert_eq!(
+            department_fill(&config, &config.mappings[1]),
+            "url(#hatch-1)"
+        );
+    }
+
+    #[test]
+    fn inline_styles_replace_style_element_with_attributes() {
+        let options = RenderOptions::builder().inline_styles(true).build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &options,
+        )
+        .expect("render succeeds");
+
+        assert!(!svg.contains("<style"));
+        assert!(svg.contains("class=\"week-label\" fill=\"#231f20\""));
+        assert!(svg.contains("font-size=\"16px\""));
+        assert!(!svg.contains("style=\""));
+    }
+
+    #[test]
+    fn optimise_shrinks_output_and_keeps_labels() {
+        let plain = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "<svg><!-- map --><rect width=\"10.0001\"/></svg>",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        let options = RenderOptions::builder().optimise(true).build();
+        let optimised = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "<svg><!-- map --><rect width=\"10.0001\"/></svg>",
+            &options,
+        )
+        .expect("render succeeds");
+
+        assert!(optimised.len() < plain.len());
+        assert!(!optimised.contains("<!--"));
+        assert!(!optimised.contains('\n'));
+        assert!(optimised.contains("<rect width=\"10\"/>"));
+        assert!(optimised.contains("<title>Tuesday, L2, Science in SC8 with Mr Test B</title>"));
+        roxmltree::Document::parse(&optimised).expect("optimised output is well-formed");
+    }
+
+    #[test]
+    fn map_with_doctype_and_namespaces_is_nested_as_elements() {
+        let map = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+<!-- Exported from an editor -->
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <defs><rect id="room" width="10" height="10"/></defs>
+  <use xlink:href="#room" id="map-marker"/>
+</svg>"##;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        let doc = roxmltree::Document::parse(&svg).expect("sheet is well-formed");
+        let marker = doc
+            .descendants()
+            .find(|n| n.attribute("id") == Some("map-marker"))
+            .expect("map content nested");
+        assert_eq!(
+            marker.attribute(("http://www.w3.org/1999/xlink", "href")),
+            Some("#room")
+        );
+        assert!(!svg.contains("DOCTYPE"));
+        assert!(!svg.contains("<!--"));
+    }
+
+    #[test]
+    fn map_is_letterboxed_at_its_aspect_ratio() {
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200px" height="100px"/>"#;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(svg.contains(
+            "height=\"397\" viewBox=\"0 0 200 100\" width=\"794\" x=\"0\" xmlns=\"http://www.w3.org/2000/svg\" y=\"18\""
+        ));
+
+        // A tall viewBox with an origin offset is centred horizontally
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-50,-50 100,200"/>"#;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(
+            svg.contains("height=\"433\" viewBox=\"-50,-50 100,200\" width=\"216.5\" x=\"288.75\"")
+        );
+    }
+
+    #[test]
+    fn combine_maps_places_sites_side_by_side_at_one_height() {
+        let north = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 200 100"/>"#;
+        let south = r#"<svg xmlns="http://www.w3.org/2000/svg" width="50" height="50"/>"#;
+        assert_eq!(combine_maps(&[]).unwrap(), "");
+        assert_eq!(combine_maps(&[north.to_string()]).unwrap(), north);
+
+        let combined = combine_maps(&[north.to_string(), south.to_string()]).unwrap();
+        assert!(combined.starts_with(r#"<svg viewBox="0 0 305 100""#));
+        assert!(combined.contains(r#"<svg height="100" width="200" x="0">"#));
+        assert!(combined.contains(r#"<svg height="100" width="100" x="205">"#));
+        assert!(combined.contains(r#"viewBox="0 0 50 50" width="100""#));
+
+        // Each site's stylesheet rules only reach its own shapes
+        let site = |color: &str| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><style>[id="Hall"] rect {{ fill: {} !important; }}</style><g id="Hall"><rect/></g></svg>"#,
+                color
+            )
+        };
+        let combined = combine_maps(&[site("#f00"), site("#00f")]).unwrap();
+        assert!(combined.contains(r#"[id="site-1-Hall"] rect { fill: #f00"#));
+        assert!(combined.contains(r#"[id="site-2-Hall"] rect { fill: #00f"#));
+        assert!(combined.contains(r#"<g id="site-1-Hall">"#));
+        assert!(combined.contains(r#"<g id="site-2-Hall">"#));
+        assert!(!combined.contains(r#"id="Hall""#));
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            &combined,
+            &RenderOptions::default(),
+        );
+        assert!(svg.is_ok());
+    }
+
+    #[test]
+    fn lessons_carry_accessible_labels() {
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "<svg/>",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("aria-label=\"Test Student (9X1), Week Test\""));
+        assert!(svg.contains("<desc>2 lessons, each read out"));
+        assert!(svg.contains(
+            "<g aria-label=\"Tuesday, L2, Science in SC8 with Mr Test B\" id=\"cell-tue-l2\" role=\"img\">\n<title>"
+        ));
+        assert!(svg.contains("<g aria-label=\"School map\" role=\"img\">\n<svg"));
+    }
+
+    #[test]
+    fn accessible_labels_follow_the_locale() {
+        let cfg = Config {
+            strings: crate::config::Strings::for_locale("fr").unwrap(),
+            ..Config::default()
+        };
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "<svg/>",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("<desc>2 cours, chacun lu comme"));
+        assert!(svg.contains("aria-label=\"Mardi, L2, Science en salle SC8 avec Mr Test B\""));
+        assert!(svg.contains("<g aria-label=\"Plan de l&apos;établissement\" role=\"img\">"));
+        assert!(!svg.contains(" with "));
+
+        let mut week = sample_week();
+        week.lessons.truncate(1);
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(svg.contains("<desc>1 lesson, read out as day,"));
+    }
+
+    #[test]
+    fn render_timetable_embeds_logo_as_data_uri() {
+        let logo_path = env::temp_dir().join(format!("timetable_logo_{}.svg", std::process::id()));
+        std::fs::write(&logo_path, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+
+        let cfg = Config {
+            logo: Some(crate::config::Logo {
+                path: logo_path.clone(),
+                position: LogoPosition::Left,
+                size: 40,
+            }),
+            ..Default::default()
+        };
+
+        let out_path =
+            env::temp_dir().join(format!("timetable_logo_test_{}.svg", std::process::id()));
+        render_timetable(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let content = std::fs::read_to_string(&out_path).expect("output svg exists");
+        assert!(content.contains("href=\"data:image/svg+xml;base64,"));
+        assert!(content.contains("id=\"logo\""));
+
+        let _ = std::fs::remove_file(&out_path);
+        let _ = std::fs::remove_file(&logo_path);
+    }
+
+    #[test]
+    fn render_timetable_embeds_font_faces() {
+        let font_path =
+            env::temp_dir().join(format!("timetable_font_{}.woff2", std::process::id()));
+        std::fs::write(&font_path, b"wOF2").unwrap();
+
+        let cfg = Config::builder()
+            .font_face(crate::config::FontFace::new("School Sans", &font_path).weight(700))
+            .build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains(
+            "@font-face { font-family: 'School Sans'; font-weight: 700; src: url(\"data:font/woff2;base64,d09GMg==\") format(\"woff2\"); }"
+        ));
+
+        let cfg = Config::builder()
+            .font_face(crate::config::FontFace::new("School Sans", "school.pfb"))
+            .build();
+        assert!(matches!(
+            render_timetable_to_string(
+                &sample_week(),
+                &cfg,
+                &IconSet::default(),
+                "",
+                &RenderOptions::default()
+            ),
+            Err(RenderError::UnsupportedFont(_))
+        ));
+
+        let _ = std::fs::remove_file(&font_path);
+    }
+
+    #[test]
+    fn render_timetable_uses_localised_strings() {
+        let mut cfg = Config::default();
+        cfg.strings.days = ["Llun", "Mawrth", "Mercher", "Iau", "Gwener"]
+            .map(String::from)
+            .to_vec();
+        cfg.strings.lunch_label = "Cinio".into();
+        cfg.strings.updated = "Diweddarwyd".into();
+        cfg.strings.periods = ["Cof", "1", "2", "3", "4", "5"].map(String::from).to_vec();
+
+        let out_path =
+            env::temp_dir().join(format!("timetable_strings_test_{}.svg", std::process::id()));
+        render_timetable(
+            &sample_week(),
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let content = std::fs::read_to_string(&out_path).expect("output svg exists");
+        assert!(content.contains("Mercher"));
+        assert!(content.contains("Cinio"));
+        assert!(content.contains("Diweddarwyd: "));
+        assert!(content.contains("Cof"));
+        assert!(!content.contains("Wednesday"));
+
+        let _ = std::fs::remove_file(&out_path);
+    }
+
+    #[test]
+    fn render_timetable_to_string_matches_writer_output() {
+        let cfg = Config {
+            templates: crate::config::Templates {
+                footer: String::new(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let week = sample_week();
+
+        let svg = render_timetable_to_string(
+            &week,
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        let mut buffer = Vec::new();
+        render_timetable_to_writer(
+            &week,
+            &cfg,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &mut buffer,
+        )
+        .expect("render succeeds");
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Test Student"));
+        assert_eq!(svg.as_bytes(), buffer.as_slice());
+    }
+
+    #[test]
+    fn svgz_path_writes_gzip_compressed_svg() {
+        use std::io::Read;
+
+        let out_path =
+            env::temp_dir().join(format!("timetable_svgz_test_{}.svgz", std::process::id()));
+        render_timetable(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let bytes = std::fs::read(&out_path).unwrap();
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+        let mut svg = String::new();
+        flate2::read::GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut svg)
+            .unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Test Student"));
+
+        let _ = std::fs::remove_file(&out_path);
+    }
+
+    #[test]
+    fn day_renders_into_any_writer() {
+        use std::io::Read;
+
+        let week = sample_week();
+        let (config, options) = (Config::default(), RenderOptions::default());
+        let mut buffer = Vec::new();
+        render_day_to_writer(
+            &week,
+            1,
+            &config,
+            &IconSet::default(),
+            "",
+            &options,
+            &mut buffer,
+        )
+        .expect("render succeeds");
+        let svg =
+            render_day_to_string(&week, 1, &config, &IconSet::default(), "", &options).unwrap();
+        assert_eq!(buffer, svg.as_bytes());
+
+        let mut compressed = Vec::new();
+        write_svg(&mut compressed, &svg, true).expect("compression succeeds");
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, svg);
+    }
+
+    #[test]
+    fn render_options_control_page_size() {
+        let options = RenderOptions::builder()
+            .page_size_mm(148.0, 210.0)
+            .timetable_height(500)
+            .build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &options,
+        )
+        .expect("render succeeds");
+
+        // A5 is laid out like A4 and scaled down to the physical size
+        assert!(svg.contains("width=\"148mm\""));
+        assert!(svg.contains("viewBox=\"0 0 794 1126\""));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn render_timetable_pdf_writes_pdf_document() {
+        let out_path =
+            env::temp_dir().join(format!("timetable_pdf_test_{}.pdf", std::process::id()));
+
+        render_timetable_pdf(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let bytes = fs::read(&out_path).expect("read pdf");
+        assert!(bytes.starts_with(b"%PDF-"));
+        let _ = fs::remove_file(&out_path);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn svg_to_png_scales_with_dpi() {
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        let png = svg_to_png(&svg, 48.0).expect("rasterise");
+        assert!(png.starts_with(b"\x89PNG"));
+        // IHDR width and height follow the 8-byte signature and chunk header
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        assert_eq!((width, height), (397, 561));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn render_timetables_pdf_writes_page_per_week_and_map() {
+        let out_path = env::temp_dir().join(format!(
+            "timetable_combined_test_{}.pdf",
+            std::process::id()
+        ));
+        let week = sample_week();
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"></svg>"#;
+
+        render_timetables_pdf(
+            &[(&week, ""), (&week, map)],
+            &Config::default(),
+            &IconSet::default(),
+            &RenderOptions::default(),
+            Some(map),
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let bytes = fs::read(&out_path).expect("read pdf");
+        let _ = fs::remove_file(&out_path);
+        let doc = lopdf::Document::load_mem(&bytes).expect("valid pdf");
+        assert_eq!(doc.get_pages().len(), 3);
+    }
+
+    #[test]
+    fn map_placement_above_moves_grid_down() {
+        let options = RenderOptions::builder()
+            .map_placement(MapPlacement::Above)
+            .build();
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"></svg>"#;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &options,
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("<svg height=\"433\" width=\"794\" x=\"0\" y=\"20\">"));
+        assert!(svg.contains("transform=\"translate(0 473)\""));
+    }
+
+    #[cfg(feature = "pdf")]
+    #[test]
+    fn map_placement_page_follows_each_sheet_in_pdf() {
+        let out_path = env::temp_dir().join(format!(
+            "timetable_map_page_test_{}.pdf",
+            std::process::id()
+        ));
+        let week = sample_week();
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"></svg>"#;
+        let options = RenderOptions::builder()
+            .map_placement(MapPlacement::Page)
+            .build();
+        assert_eq!(options.timetable_size(), (794, 1123));
+
+        render_timetables_pdf(
+            &[(&week, map), (&week, map)],
+            &Config::default(),
+            &IconSet::default(),
+            &options,
+            None,
+            &out_path,
+        )
+        .expect("render succeeds");
+
+        let bytes = fs::read(&out_path).expect("read pdf");
+        let _ = fs::remove_file(&out_path);
+        let doc = lopdf::Document::load_mem(&bytes).expect("valid pdf");
+        assert_eq!(doc.get_pages().len(), 4);
+    }
+
+    #[test]
+    fn landscape_places_map_right_of_timetable() {
+        let options = RenderOptions::builder()
+            .orientation(Orientation::Landscape)
+            .build();
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"></svg>"#;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &options,
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("viewBox=\"0 0 1123 794\""));
+        assert!(svg.contains("width=\"297mm\""));
+        assert!(svg.contains("<svg height=\"674\" width=\"303\" x=\"800\" y=\"80\">"));
+    }
+
+    #[test]
+    fn paper_sizes_keep_layout_proportions() {
+        let a3 = RenderOptions::builder().paper_size(PaperSize::A3).build();
+        assert_eq!((a3.width_px(), a3.height_px()), (794, 1122));
+        assert_eq!(a3.sheet_width_mm(), 297.0);
+
+        let letter = RenderOptions::builder()
+            .paper_size(PaperSize::Letter)
+            .build();
+        assert_eq!((letter.width_px(), letter.height_px()), (794, 1027));
+    }
+
+    #[test]
+    fn abbreviate_subject_uses_initials_or_prefix() {
+        assert_eq!(abbreviate_subject("Design Technology"), "DT");
+        assert_eq!(abbreviate_subject("Mathematics"), "Math");
+        assert_eq!(abbreviate_subject("Art"), "Art");
+    }
+
+    #[test]
+    fn pocket_card_omits_map_and_teacher() {
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect id="map-marker"/></svg>"#;
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            map,
+            &RenderOptions::pocket_card(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("width=\"74mm\""));
+        assert!(!svg.contains("map-marker"));
+        assert!(!svg.contains("Ms Test A"));
+    }
+
+    #[test]
+    fn phone_wallpaper_supports_dark_theme() {
+        let config = Config {
+            theme: Theme::default().with_dark_colors(),
+            ..Default::default()
+        };
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::phone_wallpaper(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("viewBox=\"0 0 794 1720\""));
+        assert!(svg.contains("fill=\"#121212\""));
+    }
+
+    #[test]
+    fn render_weeks_stacks_grids_with_shared_legend() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths")],
+            ..Default::default()
+        };
+        let mut second = sample_week();
+        second.week_name = "Week 2".into();
+        let weeks = vec![sample_week(), second];
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect id="map-marker"/></svg>"#;
+
+        let svg = render_weeks_to_string(
+            &weeks,
+            &config,
+            &IconSet::default(),
+            map,
+            &RenderOptions::default(),
+            MultiWeekLayout::Stacked,
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("id=\"timetable-2\""));
+        assert!(svg.contains("translate(0 433)"));
+        assert!(svg.contains("id=\"legend\""));
+        assert_eq!(svg.matches("map-marker").count(), 1);
+    }
+
+    #[test]
+    fn render_weeks_side_by_side_compares_students() {
+        let mut sibling = sample_week();
+        sibling.student_name = Some("Sam Sibling".into());
+        let weeks = vec![sample_week(), sibling];
+
+        let svg = render_weeks_to_string(
+            &weeks,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+            MultiWeekLayout::SideBySide,
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("Test Student"));
+        assert!(svg.contains("Sam Sibling"));
+        assert!(svg.contains("translate(397 0)"));
+    }
+
+    #[test]
+    fn elements_have_stable_ids() {
+        let (config, options) = (Config::default(), RenderOptions::default());
+        let svg =
+            render_timetable_to_string(&sample_week(), &config, &IconSet::default(), "", &options)
+                .expect("render succeeds");
+        for id in [
+            "header",
+            "title",
+            "day-wed",
+            "period-pd",
+            "break-2",
+            "cell-mon-l1",
+            "cell-tue-l2",
+        ] {
+            assert!(svg.contains(&format!("id=\"{}\"", id)), "missing {}", id);
+        }
+        assert_eq!(cell_id(4, 5), "cell-fri-l5");
+
+        let weeks = vec![sample_week(), sample_week()];
+        let svg = render_weeks_to_string(
+            &weeks,
+            &config,
+            &IconSet::default(),
+            "",
+            &options,
+            MultiWeekLayout::Stacked,
+        )
+        .expect("render succeeds");
+        assert!(svg.contains("id=\"week-2-header\""));
+        assert!(svg.contains("id=\"week-2-cell-mon-l1\""));
+        assert!(!svg.contains("id=\"header\""));
+
+        // Each student's lesson in a shared slot keeps its own id
+        let svg = render_household_to_string(&weeks, &config, &IconSet::default(), "", &options)
+            .expect("render succeeds");
+        assert!(svg.contains("id=\"cell-mon-l1\""));
+        assert!(svg.contains("id=\"cell-mon-l1-2\""));
+    }
+
+    #[test]
+    fn subject_icons_are_drawn_before_the_subject() {
+        let path = env::temp_dir().join("timetable_render_icons_test.svg");
+        fs::write(
+            &path,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><symbol id="sum" viewBox="0 0 10 10"><path d="M0 0h10"/></symbol></svg>"#,
+        )
+        .expect("write icon set");
+        let config = Config::builder()
+            .icon_set(&path)
+            .subject_icon("Maths", "sum")
+            .subject_icon("Science", "missing")
+            .build();
+        let icons = IconSet::load(&config).expect("icons load");
+
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &icons,
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert_eq!(svg.matches("<symbol").count(), 1);
+        assert!(svg.contains("<symbol id=\"icon-sum\" viewBox=\"0 0 10 10\">"));
+        assert_eq!(svg.matches("href=\"#icon-sum\"").count(), 1);
+        // The subject moves right of the icon; Science has no icon to make room for
+        assert!(svg.contains("x=\"82\" y=\"177\">\nMaths"));
+        assert!(svg.contains("x=\"208\" y=\"257\">\nScience"));
+
+        // The day view draws the icon too, defining only that day's symbols
+        let options = RenderOptions::default();
+        let monday = render_day_to_string(&sample_week(), 0, &config, &icons, "", &options)
+            .expect("render succeeds");
+        assert!(monday.contains("<symbol id=\"icon-sum\""));
+        assert_eq!(monday.matches("href=\"#icon-sum\"").count(), 1);
+        let tuesday = render_day_to_string(&sample_week(), 1, &config, &icons, "", &options)
+            .expect("render succeeds");
+        assert!(!tuesday.contains("<symbol"));
+
+        // The set is read once up front, so rendering never goes back to the file
+        fs::remove_file(&path).ok();
+        let svg = render_day_to_string(&sample_week(), 0, &config, &icons, "", &options)
+            .expect("render succeeds");
+        assert!(svg.contains("<symbol id=\"icon-sum\""));
+    }
+
+    #[test]
+    fn label_room_stacks_the_room_beneath_the_class_code() {
+        let mut config = Config::default();
+        config.theme.label_room = true;
+        let mut week = sample_week();
+        week.lessons[0].class_code = "9X1/Ma".into();
+        week.lessons[1].class_code.clear();
+
+        let svg = render_timetable_to_string(
+            &week,
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        // Both rotate about the strip centre, the room a line below the code
+        assert!(svg.contains(
+            "font-size=\"14px\" font-weight=\"600\" text-anchor=\"middle\" \
+             transform=\"rotate(90 180 200)\" x=\"180\" y=\"195\">\n9X1/Ma\n"
+        ));
+        assert!(svg.contains(
+            "font-size=\"9px\" text-anchor=\"middle\" \
+             transform=\"rotate(90 180 200)\" x=\"180\" y=\"208\">\nMA3\n"
+        ));
+        // Without a class code the room is the strip label, as before
+        assert!(svg.contains("font-size=\"20px\" font-weight=\"600\""));
+    }
+
+    #[test]
+    fn lesson_notes_are_printed_and_truncated() {
+        let mut week = sample_week();
+        week.lessons[0].note = Some("Mock exam".into());
+        week.lessons[1].note = Some("Bring goggles, lab coat and the revision guide".into());
+
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains(
+            "font-size=\"9px\" font-style=\"italic\" x=\"68\" y=\"201\">\nMock exam\n</text>"
+        ));
+        assert!(svg.contains("\nBring goggles, l…\n"));
+        assert!(svg.contains("<title>Bring goggles, lab coat and the revision guide</title>"));
+        assert!(svg.contains("Maths in MA3 with Ms Test A. Mock exam"));
+        assert_eq!(
+            find_overflows(
+                &week,
+                &Config::default(),
+                &IconSet::default(),
+                &RenderOptions::default()
+            )
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn generated_at_fixes_the_footer_date() {
+        let time = chrono::NaiveDate::from_ymd_opt(2026, 1, 5)
+            .and_then(|date| date.and_hms_opt(9, 30, 0))
+            .and_then(|time| time.and_local_timezone(chrono::Local).single())
+            .expect("valid time");
+        let options = RenderOptions::builder().generated_at(time).build();
+
+        let render = || {
+            render_timetable_to_string(
+                &sample_week(),
+                &Config::default(),
+                &IconSet::default(),
+                "",
+                &options,
+            )
+            .expect("render succeeds")
+        };
+        let svg = render();
+
+        assert!(svg.contains("Updated: 05 January 2026"));
+        assert_eq!(svg, render());
+
+        let mut config = Config::default();
+        config.templates.date_format = "%d %Q".to_string();
+        let svg =
+            render_timetable_to_string(&sample_week(), &config, &IconSet::default(), "", &options)
+                .expect("a bad date format does not abort the render");
+        assert!(svg.contains("Updated: %d %Q"));
+
+        config.strings = Strings::for_locale("fr").expect("French is built in");
+        config.templates.date_format = "%a %A %d %b %B %%a".to_string();
+        let svg =
+            render_timetable_to_string(&sample_week(), &config, &IconSet::default(), "", &options)
+                .expect("render succeeds");
+        assert!(svg.contains("lun. lundi 05 janv. janvier %a"));
+    }
+
+    #[test]
+    fn teacher_sheet_shows_department_and_class_instead_of_teacher() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths")],
+            ..Default::default()
+        };
+        let mut week = sample_week();
+        week.student_name = Some("Ms Test A".into());
+        week.lessons[0].class_code = "9X1/Ma".into();
+        let options = RenderOptions::builder().teacher_sheet(true).build();
+
+        let svg = render_timetable_to_string(&week, &config, &IconSet::default(), "", &options)
+            .expect("render succeeds");
+
+        assert!(svg.contains(">\nMs Test A\n</text>"));
+        assert!(svg.contains(
+            "<text class=\"detail\" id=\"subheader\" x=\"60\" y=\"50\">\nMaths\n</text>"
+        ));
+        assert!(svg.contains(">\n9X1/Ma\n</text>"));
+        assert!(svg.contains("aria-label=\"Monday, L1, Maths in MA3 with 9X1/Ma\""));
+        assert!(!svg.contains("Mr Test B"));
+    }
+
+    #[test]
+    fn household_splits_each_slot_into_one_row_per_student() {
+        let mut sibling = sample_week();
+        sibling.student_name = Some("Sam Sibling".into());
+        sibling.lessons[0].subject = "History".into();
+        let weeks = vec![sample_week(), sibling];
+
+        let svg = render_household_to_string(
+            &weeks,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("T: Test Student · S: Sam Sibling"));
+        assert!(svg.contains("aria-label=\"Sam Sibling: Monday, "));
+        assert_eq!(svg.matches("id=\"household\"").count(), 1);
+        // Both students have Science on Tuesday, one above the other
+        assert_eq!(svg.matches("\nScie\n").count(), 2);
+    }
+
+    #[test]
+    fn household_keys_fall_back_to_numbers_when_initials_clash() {
+        let mut twin = sample_week();
+        twin.student_name = Some("Tess Student".into());
+        assert_eq!(household_keys(&[sample_week(), twin]), vec!["1", "2"]);
+        let mut sibling = sample_week();
+        sibling.student_name = Some("sam".into());
+        assert_eq!(household_keys(&[sample_week(), sibling]), vec!["T", "S"]);
+    }
+
+    #[test]
+    fn render_day_lists_only_that_days_lessons() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms")],
+            ..Default::default()
+        };
+
+        let svg = render_day_to_string(
+            &sample_week(),
+            0,
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("Monday · Week Test"));
+        assert!(svg.contains("Maths"));
+        assert!(svg.contains("fill=\"#fcdcd8\""));
+        // Tuesday's Science lesson is not shown
+        assert!(!svg.contains("Science"));
+    }
+
+    #[test]
+    fn cell_tint_colours_whole_lesson_cell() {
+        let mut config = Config {
+            mappings: vec![Mapping::new("MA", "#000000", "Maths_Rooms")],
+            ..Default::default()
+        };
+        config.theme.cell_tint = 0.25;
+
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        // A quarter of the way from white to black
+        assert!(svg.contains("fill=\"#bfbfbf\""));
+    }
+
+    #[test]
+    fn truncated_text_keeps_full_text_in_title() {
+        let mut week = sample_week();
+        week.lessons[1].teacher = "Professor Bartholomew Fitzgerald".into();
+
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("Professor Bar…"));
+        assert!(svg.contains("<title>Professor Bartholomew Fitzgerald</title>"));
+        // Subjects that fit are not given a tooltip of their own
+        assert!(!svg.contains("<title>Maths</title>"));
+    }
+
+    #[test]
+    fn corner_radius_rounds_cells_and_rows() {
+        let square = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(!square.contains("rx="));
+
+        let mut config = Config::default();
+        config.theme.corner_radius = 8.0;
+        let rounded = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        // Break rows are fully rounded; lesson cells round only their outer corners
+        assert!(rounded.contains("rx=\"8\""));
+        assert_eq!(rounded.matches("<path").count(), 4);
+    }
+
+    #[test]
+    fn free_periods_fill_empty_slots_when_enabled() {
+        let blank = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(!blank.contains("Free / Study"));
+
+        let options = RenderOptions::builder().show_free_periods(true).build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &options,
+        )
+        .expect("render succeeds");
+
+        // 30 slots, two of which hold lessons
+        assert_eq!(svg.matches("class=\"free-period\"").count(), 28);
+        assert!(svg.contains("Free / Study"));
+    }
+
+    #[test]
+    fn highlight_day_tints_its_column() {
+        let options = RenderOptions::builder().highlight_day(Weekday::Wed).build();
+
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &options,
+        )
+        .expect("render succeeds");
+
+        // Wednesday is the third 140px column after the 60px left margin
+        assert!(svg.contains("id=\"highlight-day\""));
+        assert!(svg.contains("x=\"340\""));
+        assert!(svg.contains("fill=\"#ffd84d\""));
+
+        let weekend = RenderOptions::builder().highlight_day(Weekday::Sat).build();
+        let svg = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &weekend,
+        )
+        .expect("render succeeds");
+        assert!(!svg.contains("highlight-day"));
+    }
+
+    #[test]
+    fn week_dates_drawn_beneath_title() {
+        let mut week = sample_week();
+        week.starts_on = chrono::NaiveDate::from_ymd_opt(2025, 1, 13);
+
+        let svg = render_timetable_to_string(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+
+        assert!(svg.contains("13–17 January"));
+        let undated = render_timetable_to_string(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(!undated.contains("13–17"));
+    }
+
+    #[test]
+    fn legend_sits_above_map_when_enabled() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").label("Maths")],
+            ..Default::default()
+        };
+        let map = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect id="map-marker"/></svg>"#;
+
+        let plain = render_timetable_to_string(
+            &sample_week(),
+            &config,
+            &IconSet::default(),
+            map,
+            &RenderOptions::default(),
+        )
+        .expect("render succeeds");
+        assert!(!plain.contains("id=\"legend\""));
+
+        let options = RenderOptions::builder().show_legend(true).build();
+        let svg =
+            render_timetable_to_string(&sample_week(), &config, &IconSet::default(), map, &options)
+                .expect("render succeeds");
+
+        assert!(svg.contains("id=\"legend\""));
+        assert!(svg.contains("Maths"));
+        // The map moves down by one legend line
+        assert!(svg.contains("<svg height=\"413\" width=\"794\" x=\"0\" y=\"690\">"));
+    }
+
+    #[test]
+    fn mirrored_options_align_back_grid_with_front() {
+        let front = RenderOptions::default();
+        let back = front.mirrored();
+        assert_eq!((back.margins.left, back.margins.right), (30, 60));
+
+        // Cells on the back start where the front's right margin mirrors to
+        let week = sample_week();
+        let front_svg =
+            render_timetable_to_string(&week, &Config::default(), &IconSet::default(), "", &front)
+                .expect("render succeeds");
+        let back_svg =
+            render_timetable_to_string(&week, &Config::default(), &IconSet::default(), "", &back)
+                .expect("render succeeds");
+        assert!(front_svg.contains("x=\"63\""));
+        assert!(back_svg.contains("x=\"33\""));
+    }
+
+    #[cfg(feature = "templates")]
+    #[test]
+    fn template_receives_layout_and_escapes_text() {
+        let mut week = sample_week();
+        week.lessons[0].subject = "R&D".into();
+        let template = "{% for cell in sheet.cells %}{{ cell.subject }}@{{ cell.rect.x }};{% endfor %}{{ map | safe }}";
+
+        let out = render_timetable_with_template(
+            &week,
+            &Config::default(),
+            &IconSet::default(),
+            "<svg/>",
+            &RenderOptions::default(),
+            template,
+        )
+        .expect("template renders");
+
+        assert!(out.starts_with("R&amp;D@63;"));
+        assert!(out.ends_with("<svg/>"));
+    }
+
+    #[cfg(feature = "templates")]
+    #[test]
+    fn template_layout_follows_map_placement_and_font_scale() {
+        let options = RenderOptions::builder()
+            .map_placement(MapPlacement::Beside)
+            .font_scale(2.0)
+            .build();
+        let template = "{{ sheet.width }}x{{ sheet.height }} {{ theme.subject_font_size }}";
+
+        let out = render_timetable_with_template(
+            &sample_week(),
+            &Config::default(),
+            &IconSet::default(),
+            "",
+            &options,
+            template,
+        )
+        .expect("template renders");
+
+        let (width, height) = options.timetable_size();
+        let font_size = Config::default().theme.subject_font_size * 2.0;
+        assert_eq!(out, format!("{}x{} {:?}", width, height, font_size));
     }
 }
@@ -0,0 +1,291 @@
+//! Typst document export.
+//!
+//! This module emits a `.typ` source file describing the week as a Typst
+//! table with the same department colours as the SVG sheet, and the
+//! highlighted map embedded as an SVG image. Users can then add letterheads or
+//! merge the timetable into other Typst documents before compiling.
+
+use crate::config::{Config, Theme};
+use crate::icons::IconSet;
+use crate::layout::SheetLayout;
+use crate::parser::Week;
+use crate::renderer::{RenderError, RenderOptions};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Render a timetable week to a Typst source file.
+///
+/// # Arguments
+///
+/// * `week` - The parsed week containing lessons
+/// * `config` - Configuration with room mappings, theme, and strings
+/// * `map_content` - Processed SVG map content, or an empty string for no map
+/// * `options` - Page size (other layout values are left to Typst)
+/// * `output_path` - Path where the `.typ` file will be written
+///
+/// # Errors
+///
+/// Returns [`RenderError`] if the file cannot be written.
+pub fn render_timetable_typst(
+    week: &Week,
+    config: &Config,
+    map_content: &str,
+    options: &RenderOptions,
+    output_path: &Path,
+) -> Result<(), RenderError> {
+    let source = render_timetable_typst_to_string(week, config, map_content, options);
+    fs::write(output_path, source)?;
+
+    Ok(())
+}
+
+/// Render a timetable week to Typst source.
+///
+/// # Example
+///
+/// ```
+/// use timetable_core::{config::Config, parser::Week, renderer::RenderOptions};
+/// use timetable_core::typst::render_timetable_typst_to_string;
+///
+/// let week = Week { week_name: "Week 1".into(), ..Default::default() };
+/// let typ = render_timetable_typst_to_string(&week, &Config::default(), "", &RenderOptions::default());
+/// assert!(typ.contains("#set page(width: 210mm, height: 297mm"));
+/// ```
+pub fn render_timetable_typst_to_string(
+    week: &Week,
+    config: &Config,
+    map_content: &str,
+    options: &RenderOptions,
+) -> String {
+    let theme = &config.theme;
+    // Typst cells draw no subject icons, so none are loaded
+    let layout = SheetLayout::compute(
+        week,
+        config,
+        &IconSet::default(),
+        options,
+        options.width_px(),
+        options.timetable_height,
+    );
+
+    let mut typ = String::new();
+    let _ = writeln!(
+        typ,
+        "#set page(width: {}mm, height: {}mm, margin: 12mm, fill: rgb({}))",
+        options.sheet_width_mm(),
+        options.sheet_height_mm(),
+        typst_str(&theme.page_color)
+    );
+    let _ = writeln!(
+        typ,
+        "#set text(font: {}, size: {}pt, fill: rgb({}))",
+        typst_fonts(&theme.light_font_family),
+        theme.detail_font_size * 0.75,
+        typst_str(&theme.text_color)
+    );
+    typ.push_str(&lesson_function(theme));
+    typ.push('\n');
+
+    let mut header = format!(
+        "text(size: {}pt, {})",
+        theme.header_font_size * 0.75,
+        typst_str(&layout.header.text)
+    );
+    if !layout.subheader.text.is_empty() {
+        header = format!(
+            "stack(spacing: 4pt, {}, text(size: {}pt, {}))",
+            header,
+            theme.detail_font_size * 0.75,
+            typst_str(&layout.subheader.text)
+        );
+    }
+    let _ = writeln!(
+        typ,
+        "#grid(columns: (1fr, auto, 1fr), align: (left, center, right), {}, text(size: {}pt, weight: \"bold\", {}), [])",
+        header,
+        theme.week_font_size * 0.75,
+        typst_str(&layout.title.text)
+    );
+    typ.push('\n');
+
+    typ.push_str("#table(\n  columns: (auto, 1fr, 1fr, 1fr, 1fr, 1fr),\n  inset: 0pt,\n");
+    let _ = writeln!(
+        typ,
+        "  stroke: {}pt + rgb({}),",
+        theme.stroke_width * 0.75,
+        typst_str(&theme.stroke_color)
+    );
+    typ.push_str("  [],");
+    for day in &layout.days {
+        let _ = write!(
+            typ,
+            " table.cell(inset: 4pt, align: center, strong({})),",
+            typst_str(&day.text)
+        );
+    }
+    typ.push('\n');
+
+    for (period_idx, period) in layout.periods.iter().enumerate() {
+        let _ = write!(
+            typ,
+            "  table.cell(inset: 4pt, align: horizon, strong({})),",
+            typst_str(&period.text)
+        );
+        for day_idx in 0..layout.days.len() {
+            let cell = layout
+                .cells
+                .iter()
+                .find(|c| c.day_index == day_idx && c.period_index == period_idx);
+            match cell {
+                Some(cell) => {
+                    let _ = write!(
+                        typ,
+                        " lesson({}, {}, {}, {}, {}, {}),",
+                        typst_str(&cell.bg_color),
+                        typst_str(&cell.fg_color),
+                        typst_str(&cell.subject),
+                        typst_str(cell.room.as_deref().unwrap_or("")),
+                        typst_str(cell.teacher.as_deref().unwrap_or("")),
+                        typst_str(&cell.label)
+                    );
+                }
+                None => typ.push_str(" [],"),
+            }
+        }
+        typ.push('\n');
+
+        for gap in layout.gaps.iter().filter(|g| g.after_period == period_idx) {
+            let _ = writeln!(
+                typ,
+                "  [], table.cell(colspan: 5, inset: 3pt, fill: rgb({}), align: center, {}),",
+                typst_str(&theme.break_color),
+                typst_str(&gap.label)
+            );
+        }
+    }
+    typ.push_str(")\n");
+
+    if !layout.footer.text.is_empty() {
+        let _ = writeln!(typ, "\n#align(right, {})", typst_str(&layout.footer.text));
+    }
+
+    if !map_content.trim().is_empty() {
+        let svg = map_content
+            .find("<svg")
+            .map(|i| &map_content[i..])
+            .unwrap_or(map_content);
+        let _ = writeln!(
+            typ,
+            "\n#image(bytes({}), format: \"svg\", width: 100%)",
+            typst_str(svg)
+        );
+    }
+
+    typ
+}
+
+/// Typst function drawing one lesson: details on the left, coloured code strip on the right.
+fn lesson_function(theme: &Theme) -> String {
+    format!(
+        r#"
+#let lesson(bg, fg, subject, room, teacher, code) = grid(
+  columns: (1fr, 2.4em),
+  rows: 1fr,
+  grid.cell(inset: 4pt)[
+    #text(font: {font}, size: {subject_size}pt, weight: "bold", subject) \
+    #room \
+    #text(size: {teacher_size}pt, teacher)
+  ],
+  grid.cell(fill: rgb(bg), align: center + horizon, rotate(90deg, reflow: true,
+    text(font: {bold_font}, size: {code_size}pt, weight: "bold", fill: rgb(fg), code))),
+)
+"#,
+        font = typst_fonts(&theme.font_family),
+        bold_font = typst_fonts(&theme.bold_font_family),
+        subject_size = theme.subject_font_size * 0.75,
+        teacher_size = theme.teacher_font_size * 0.75,
+        code_size = theme.class_code_font_size * 0.5,
+    )
+}
+
+/// Quote text as a Typst string literal.
+fn typst_str(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Convert a CSS font-family list into a Typst font array, dropping generic families.
+///
+/// Typst has no generic families, so a list of only generics falls back to
+/// Typst's bundled default font.
+fn typst_fonts(css: &str) -> String {
+    let fonts: Vec<String> = css
+        .split(',')
+        .map(|f| f.trim().trim_matches(|c| c == '\'' || c == '"'))
+        .filter(|f| {
+            !f.is_empty() && !matches!(*f, "serif" | "sans-serif" | "monospace" | "cursive")
+        })
+        .map(typst_str)
+        .collect();
+    if fonts.is_empty() {
+        return r#"("New Computer Modern",)"#.to_string();
+    }
+    format!("({},)", fonts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Mapping;
+    use crate::parser::Lesson;
+
+    #[test]
+    fn typst_output_maps_lessons_and_breaks() {
+        let config = Config {
+            mappings: vec![Mapping::new("MA", "#fcdcd8", "Maths_Rooms").fg_color("#f0503f")],
+            ..Default::default()
+        };
+        let week = Week {
+            week_name: "Week 1".into(),
+            lessons: vec![Lesson {
+                subject: "Maths".into(),
+                room: "MA3".into(),
+                teacher: "Mr \"Sums\" Smith".into(),
+                class_code: "10A/Ma1".into(),
+                day_index: 0,
+                period_index: 0,
+                note: None,
+            }],
+            ..Default::default()
+        };
+
+        let typ =
+            render_timetable_typst_to_string(&week, &config, "<svg/>", &RenderOptions::default());
+
+        assert!(typ.contains(
+            r##"lesson("#fcdcd8", "#f0503f", "Maths", "MA3", "Mr \"Sums\" Smith", "10A/Ma1")"##
+        ));
+        assert!(typ.contains(r#"table.cell(colspan: 5"#));
+        assert!(typ.contains(r#"#image(bytes("<svg/>"), format: "svg""#));
+    }
+
+    #[test]
+    fn typst_fonts_drops_generic_families() {
+        assert_eq!(
+            typst_fonts("'Bahnschrift Light', Arial, sans-serif"),
+            r#"("Bahnschrift Light", "Arial",)"#
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use std::fs;
 use timetable_core::config::Config;
+use timetable_core::icons::IconSet;
 use timetable_core::parser::Week;
-use timetable_core::renderer::render_timetable;
+use timetable_core::renderer::{render_timetable, RenderOptions};
 
 #[test]
 fn render_timetable_without_map_produces_svg() {
@@ -11,12 +12,13 @@ fn render_timetable_without_map_produces_svg() {
         lessons: Vec::new(),
         student_name: None,
         form: None,
+        ..Default::default()
     };
 
     // Create a minimal Config (no mappings needed for this test)
     let config = Config {
         mappings: Vec::new(),
-        overrides: Vec::new(),
+        ..Default::default()
     };
 
     let mut out_path = std::env::temp_dir();
@@ -26,7 +28,15 @@ fn render_timetable_without_map_produces_svg() {
     ));
     let _ = fs::remove_file(&out_path);
 
-    render_timetable(&week, &config, "", &out_path).expect("render should succeed");
+    render_timetable(
+        &week,
+        &config,
+        &IconSet::default(),
+        "",
+        &RenderOptions::default(),
+        &out_path,
+    )
+    .expect("render should succeed");
 
     let svg = fs::read_to_string(&out_path).expect("read output");
 